@@ -38,6 +38,8 @@ async fn main() -> anyhow::Result<()> {
         quote_order_quantity: None,
         price: Some(Decimal::from_str("0.001")?),
         new_client_order_id: None,
+        stop_price: None,
+        trigger_direction: None,
     };
     let order_output = client
         .order(order_params)
@@ -0,0 +1,110 @@
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct Credentials {
+    pub api_key: String,
+    pub secret_key: String,
+}
+
+/// Keeps the secret key out of logs and error messages
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field(
+                "api_key",
+                &self.api_key,
+            )
+            .field(
+                "secret_key",
+                &"<redacted>",
+            )
+            .finish()
+    }
+}
+
+impl Credentials {
+    /// `MEXC_API_KEY` and `MEXC_SECRET_KEY` take precedence over the config
+    /// file, which is `~/.config/mexc/config.toml` unless a path is given
+    pub fn load(config_path: Option<&Path>) -> anyhow::Result<Option<Self>> {
+        dotenv::dotenv().ok();
+        if let (Ok(api_key), Ok(secret_key)) = (
+            std::env::var("MEXC_API_KEY"),
+            std::env::var("MEXC_SECRET_KEY"),
+        ) {
+            return Ok(
+                Some(
+                    Self {
+                        api_key,
+                        secret_key,
+                    },
+                ),
+            );
+        }
+
+        let path = match config_path {
+            Some(path) => path.to_path_buf(),
+            None => match default_config_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(None),
+            },
+        };
+        let contents = std::fs::read_to_string(&path).with_context(
+            || {
+                format!(
+                    "Failed to read config file {}",
+                    path.display()
+                )
+            },
+        )?;
+        Self::parse(&contents)
+            .with_context(
+                || {
+                    format!(
+                        "Invalid config file {}",
+                        path.display()
+                    )
+                },
+            )
+            .map(Some)
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(
+        PathBuf::from(home)
+            .join(".config")
+            .join("mexc")
+            .join("config.toml"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_config_file() {
+        let credentials = Credentials::parse(
+            r#"
+                api_key = "key"
+                secret_key = "secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            credentials,
+            Credentials {
+                api_key: "key".to_string(),
+                secret_key: "secret".to_string(),
+            }
+        );
+        assert!(!format!("{credentials:?}").contains("\"secret\""));
+        assert!(Credentials::parse("api_key = \"key\"").is_err());
+    }
+}
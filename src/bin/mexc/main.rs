@@ -0,0 +1,639 @@
+//! Command-line access to the spot and futures apis.
+//!
+//! Credentials for the private commands are read from `MEXC_API_KEY` and
+//! `MEXC_SECRET_KEY`, or from a TOML config file with `api_key` and
+//! `secret_key`. Results are printed as JSON, one record per line.
+
+mod config;
+mod output;
+mod tail;
+
+use crate::{
+    config::Credentials,
+    output::{print_json_line, write_candles, Format},
+};
+use anyhow::{bail, Context};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use mexc_rs::{
+    exchange::{
+        history::{CandleInterval, HistoryFetcher},
+        CancelOrderParams, Exchange, MarginMode, OrderType, PlaceOrderParams, PositionEffect, Side, Venue,
+    },
+    futures::{
+        v1::endpoints::{
+            get_contract_detail::{GetContractDetail, GetContractDetailParams},
+            get_server_time::GetServerTime,
+        },
+        MexcFuturesApiClient, MexcFuturesApiClientWithAuthentication, MexcFuturesApiEndpoint,
+    },
+    spot::{
+        v3::{
+            avg_price::{AvgEndpoint, AvgParams},
+            depth::{DepthEndpoint, DepthParams},
+            exchange_information::{ExchangeInformationEndpoint, ExchangeInformationParams},
+            time::TimeEndpoint,
+        },
+        MexcSpotApiClient, MexcSpotApiClientWithAuthentication, MexcSpotApiEndpoint,
+    },
+};
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "mexc",
+    version,
+    about = "Command-line client for the mexc.com spot and futures apis"
+)]
+struct Cli {
+    /// Config file with `api_key` and `secret_key`, defaults to
+    /// ~/.config/mexc/config.toml
+    #[arg(
+        long,
+        global = true,
+        env = "MEXC_CONFIG"
+    )]
+    config: Option<PathBuf>,
+
+    /// Use the futures api instead of spot
+    #[arg(
+        long,
+        global = true
+    )]
+    futures: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Server time
+    Time,
+    /// Symbols, or contracts with --futures, and their trading rules
+    ExchangeInfo {
+        /// All symbols when none are given
+        symbols: Vec<String>,
+    },
+    /// Average price and best bid and ask, spot only
+    Ticker {
+        #[arg(required = true)]
+        symbols: Vec<String>,
+    },
+    /// Order book, spot only
+    Depth {
+        symbol: String,
+        #[arg(
+            long,
+            default_value_t = 20
+        )]
+        limit: u32,
+    },
+    /// Export klines of any range, fetched in as many requests as needed
+    Klines(KlinesArgs),
+    /// Balances of the account
+    Balances,
+    /// Open orders of a symbol
+    OpenOrders { symbol: String },
+    /// Place or cancel an order
+    #[command(subcommand)]
+    Order(OrderCommand),
+    /// Open futures positions
+    Positions { symbol: Option<String> },
+    /// Websocket tools
+    #[command(subcommand)]
+    Ws(WsCommand),
+}
+
+#[derive(Debug, Args)]
+struct KlinesArgs {
+    symbol: String,
+    /// 1m, 5m, 15m, 30m, 1h, 4h, 8h (futures only), 1d, 1w or 1M
+    #[arg(long, short, value_parser = CandleInterval::from_str)]
+    interval: CandleInterval,
+    /// RFC 3339 time or date
+    #[arg(long, value_parser = parse_time)]
+    start: DateTime<Utc>,
+    /// RFC 3339 time or date, defaults to now
+    #[arg(long, value_parser = parse_time)]
+    end: Option<DateTime<Utc>>,
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Written to stdout when not given
+    #[arg(
+        long, short
+    )]
+    output: Option<PathBuf>,
+    /// Keeps fetched candles, so later exports only fetch what is new
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum OrderCommand {
+    Place(PlaceArgs),
+    Cancel { symbol: String, order_id: String },
+}
+
+#[derive(Debug, Args)]
+struct PlaceArgs {
+    symbol: String,
+    #[arg(value_enum)]
+    side: SideArg,
+    #[arg(long = "type", value_enum, default_value_t = OrderTypeArg::Limit)]
+    order_type: OrderTypeArg,
+    /// Base asset for spot, contracts for futures
+    #[arg(
+        long, short
+    )]
+    quantity: Option<Decimal>,
+    /// Quote asset to spend or receive, spot market orders only
+    #[arg(long)]
+    quote_quantity: Option<Decimal>,
+    #[arg(
+        long, short
+    )]
+    price: Option<Decimal>,
+    #[arg(long)]
+    client_order_id: Option<String>,
+    /// Spot stop-limit and take-profit orders only
+    #[arg(long)]
+    stop_price: Option<Decimal>,
+    /// Futures only
+    #[arg(
+        long, value_enum
+    )]
+    effect: Option<EffectArg>,
+    /// Futures only
+    #[arg(
+        long, value_enum
+    )]
+    margin_mode: Option<MarginModeArg>,
+    /// Futures only
+    #[arg(long)]
+    leverage: Option<u32>,
+}
+
+#[derive(Debug, Subcommand)]
+enum WsCommand {
+    /// Print the pushes of topics as JSON lines until interrupted.
+    ///
+    /// Spot topics: deals:SYMBOL, kline:SYMBOL:INTERVAL, depth:SYMBOL,
+    /// account-deals, account-orders, account-update.
+    ///
+    /// Futures topics: tickers, ticker:SYMBOL, deal:SYMBOL, depth:SYMBOL,
+    /// depth-full:SYMBOL:LIMIT, kline:SYMBOL:INTERVAL, funding-rate:SYMBOL,
+    /// index-price:SYMBOL, fair-price:SYMBOL, contract, personal.
+    Tail {
+        #[arg(required = true)]
+        topics: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SideArg {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OrderTypeArg {
+    Limit,
+    Market,
+    PostOnly,
+    Ioc,
+    Fok,
+    StopLimit,
+    TakeProfit,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EffectArg {
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum MarginModeArg {
+    Isolated,
+    Cross,
+}
+
+fn parse_time(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(
+        value, "%Y-%m-%d",
+    )
+    .with_context(|| format!("{value} is not an RFC 3339 time or date"))?;
+    Ok(
+        date.and_hms_opt(
+            0, 0, 0,
+        )
+        .expect("Midnight is a valid time")
+        .and_utc(),
+    )
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let venue = match cli.futures {
+        true => Venue::Futures,
+        false => Venue::Spot,
+    };
+    let credentials = || -> anyhow::Result<Credentials> {
+        Credentials::load(
+            cli.config
+                .as_deref(),
+        )?
+        .context("No credentials, set MEXC_API_KEY and MEXC_SECRET_KEY or create ~/.config/mexc/config.toml")
+    };
+
+    match cli.command {
+        Command::Time => {
+            let server_time = match venue {
+                Venue::Spot => {
+                    MexcSpotApiClient::default()
+                        .time()
+                        .await?
+                        .server_time
+                }
+                Venue::Futures => {
+                    MexcFuturesApiClient::default()
+                        .get_server_time()
+                        .await?
+                }
+            };
+            print_json_line(&json!({ "server_time": server_time }))?;
+        }
+        Command::ExchangeInfo {
+            symbols,
+        } => {
+            exchange_info(
+                venue, &symbols,
+            )
+            .await?
+        }
+        Command::Ticker {
+            symbols,
+        } => {
+            if venue == Venue::Futures {
+                bail!("Futures tickers are only pushed, use `mexc --futures ws tail ticker:SYMBOL`");
+            }
+            let client = MexcSpotApiClient::default();
+            for symbol in &symbols {
+                let average = client
+                    .avg_price(
+                        AvgParams {
+                            symbol,
+                        },
+                    )
+                    .await?;
+                let depth = client
+                    .depth(
+                        DepthParams {
+                            symbol,
+                            limit: Some(1),
+                        },
+                    )
+                    .await?;
+                print_json_line(
+                    &json!({
+                        "symbol": symbol,
+                        "average_price": average.price,
+                        "average_minutes": average.mins,
+                        "bid": depth.bids.first().map(|level| level.price),
+                        "bid_quantity": depth.bids.first().map(|level| level.quantity),
+                        "ask": depth.asks.first().map(|level| level.price),
+                        "ask_quantity": depth.asks.first().map(|level| level.quantity),
+                    }),
+                )?;
+            }
+        }
+        Command::Depth {
+            symbol,
+            limit,
+        } => {
+            if venue == Venue::Futures {
+                bail!("Futures depth is only pushed, use `mexc --futures ws tail depth-full:SYMBOL:20`");
+            }
+            let depth = MexcSpotApiClient::default()
+                .depth(
+                    DepthParams {
+                        symbol: &symbol,
+                        limit: Some(limit),
+                    },
+                )
+                .await?;
+            let levels = |levels: &[mexc_rs::spot::v3::depth::PriceAndQuantity]| {
+                levels
+                    .iter()
+                    .map(
+                        |level| {
+                            [
+                                level.price,
+                                level.quantity,
+                            ]
+                        },
+                    )
+                    .collect::<Vec<_>>()
+            };
+            print_json_line(
+                &json!({
+                    "symbol": symbol,
+                    "last_update_id": depth.last_update_id,
+                    "bids": levels(&depth.bids),
+                    "asks": levels(&depth.asks),
+                }),
+            )?;
+        }
+        Command::Klines(args) => {
+            klines(
+                venue, args,
+            )
+            .await?
+        }
+        Command::Balances => {
+            for balance in exchange(
+                venue,
+                credentials()?,
+            )
+            .balances()
+            .await?
+            {
+                print_json_line(&balance)?;
+            }
+        }
+        Command::OpenOrders {
+            symbol,
+        } => {
+            for order in exchange(
+                venue,
+                credentials()?,
+            )
+            .open_orders(&symbol)
+            .await?
+            {
+                print_json_line(&order)?;
+            }
+        }
+        Command::Order(OrderCommand::Place(args)) => {
+            let params = PlaceOrderParams {
+                symbol: &args.symbol,
+                side: match args.side {
+                    SideArg::Buy => Side::Buy,
+                    SideArg::Sell => Side::Sell,
+                },
+                order_type: match args.order_type {
+                    OrderTypeArg::Limit => OrderType::Limit,
+                    OrderTypeArg::Market => OrderType::Market,
+                    OrderTypeArg::PostOnly => OrderType::PostOnly,
+                    OrderTypeArg::Ioc => OrderType::ImmediateOrCancel,
+                    OrderTypeArg::Fok => OrderType::FillOrKill,
+                    OrderTypeArg::StopLimit => OrderType::StopLimit,
+                    OrderTypeArg::TakeProfit => OrderType::TakeProfit,
+                },
+                quantity: args.quantity,
+                quote_quantity: args.quote_quantity,
+                price: args.price,
+                client_order_id: args
+                    .client_order_id
+                    .as_deref(),
+                stop_price: args.stop_price,
+                position_effect: args
+                    .effect
+                    .map(
+                        |effect| match effect {
+                            EffectArg::Open => PositionEffect::Open,
+                            EffectArg::Close => PositionEffect::Close,
+                        },
+                    ),
+                margin_mode: args
+                    .margin_mode
+                    .map(
+                        |margin_mode| match margin_mode {
+                            MarginModeArg::Isolated => MarginMode::Isolated,
+                            MarginModeArg::Cross => MarginMode::Cross,
+                        },
+                    ),
+                leverage: args.leverage,
+            };
+            let output = exchange(
+                venue,
+                credentials()?,
+            )
+            .place_order(params)
+            .await?;
+            print_json_line(&output)?;
+        }
+        Command::Order(OrderCommand::Cancel {
+            symbol,
+            order_id,
+        }) => {
+            exchange(
+                venue,
+                credentials()?,
+            )
+            .cancel_order(
+                CancelOrderParams {
+                    symbol: &symbol,
+                    order_id: &order_id,
+                },
+            )
+            .await?;
+            print_json_line(&json!({ "symbol": symbol, "order_id": order_id, "canceled": true }))?;
+        }
+        Command::Positions {
+            symbol,
+        } => {
+            for position in exchange(
+                Venue::Futures,
+                credentials()?,
+            )
+            .positions(symbol.as_deref())
+            .await?
+            {
+                print_json_line(&position)?;
+            }
+        }
+        Command::Ws(WsCommand::Tail {
+            topics,
+        }) => {
+            let credentials = Credentials::load(
+                cli.config
+                    .as_deref(),
+            )?;
+            tail::tail(
+                venue,
+                &topics,
+                credentials,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+fn exchange(venue: Venue, credentials: Credentials) -> Box<dyn Exchange + Send + Sync> {
+    match venue {
+        Venue::Spot => Box::new(
+            MexcSpotApiClientWithAuthentication::new(
+                MexcSpotApiEndpoint::Base,
+                credentials.api_key,
+                credentials.secret_key,
+            ),
+        ),
+        Venue::Futures => Box::new(
+            MexcFuturesApiClientWithAuthentication::new(
+                MexcFuturesApiEndpoint::Base,
+                credentials.api_key,
+                credentials.secret_key,
+            ),
+        ),
+    }
+}
+
+async fn exchange_info(venue: Venue, symbols: &[String]) -> anyhow::Result<()> {
+    match venue {
+        Venue::Spot => {
+            let symbols = symbols
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            let params = match symbols.as_slice() {
+                [] => ExchangeInformationParams::None,
+                [symbol] => ExchangeInformationParams::Symbol(symbol),
+                symbols => ExchangeInformationParams::Symbols(symbols),
+            };
+            let output = MexcSpotApiClient::default()
+                .exchange_information(params)
+                .await?;
+            for symbol in output.symbols {
+                print_json_line(
+                    &json!({
+                        "symbol": symbol.symbol,
+                        "status": symbol.status,
+                        "base_asset": symbol.base_asset,
+                        "quote_asset": symbol.quote_asset,
+                        "base_size_precision": symbol.base_size_precision,
+                        "quote_amount_precision": symbol.quote_amount_precision,
+                        "max_quote_amount": symbol.max_quote_amount,
+                        "maker_commission": symbol.maker_commission,
+                        "taker_commission": symbol.taker_commission,
+                        "order_types": symbol.order_types,
+                        "is_spot_trading_allowed": symbol.is_spot_trading_allowed,
+                    }),
+                )?;
+            }
+        }
+        Venue::Futures => {
+            let client = MexcFuturesApiClient::default();
+            let mut contracts = vec![];
+            if symbols.is_empty() {
+                contracts = client
+                    .get_contract_detail(GetContractDetailParams::default())
+                    .await?
+                    .contracts;
+            }
+            for symbol in symbols {
+                contracts.extend(
+                    client
+                        .get_contract_detail(
+                            GetContractDetailParams {
+                                symbol: Some(symbol),
+                            },
+                        )
+                        .await?
+                        .contracts,
+                );
+            }
+            for contract in contracts {
+                print_json_line(
+                    &json!({
+                        "symbol": contract.symbol,
+                        "state": contract.state,
+                        "base_coin": contract.base_coin,
+                        "quote_coin": contract.quote_coin,
+                        "settle_coin": contract.settle_coin,
+                        "contract_size": contract.contract_size,
+                        "min_leverage": contract.min_leverage,
+                        "max_leverage": contract.max_leverage,
+                        "price_unit": contract.price_unit,
+                        "vol_unit": contract.vol_unit,
+                        "min_vol": contract.min_vol,
+                        "max_vol": contract.max_vol,
+                        "maker_fee_rate": contract.maker_fee_rate,
+                        "taker_fee_rate": contract.taker_fee_rate,
+                    }),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn klines(venue: Venue, args: KlinesArgs) -> anyhow::Result<()> {
+    let range = args.start
+        ..args
+            .end
+            .unwrap_or_else(Utc::now);
+    let history = match venue {
+        Venue::Spot => {
+            let mut fetcher = HistoryFetcher::new(Arc::new(MexcSpotApiClient::default()));
+            if let Some(cache_dir) = &args.cache_dir {
+                fetcher = fetcher.with_cache_dir(cache_dir);
+            }
+            fetcher
+                .fetch(
+                    &args.symbol,
+                    args.interval,
+                    range,
+                )
+                .await?
+        }
+        Venue::Futures => {
+            let mut fetcher = HistoryFetcher::new(Arc::new(MexcFuturesApiClient::default()));
+            if let Some(cache_dir) = &args.cache_dir {
+                fetcher = fetcher.with_cache_dir(cache_dir);
+            }
+            fetcher
+                .fetch(
+                    &args.symbol,
+                    args.interval,
+                    range,
+                )
+                .await?
+        }
+    };
+    for gap in &history.gaps {
+        eprintln!(
+            "No candles from {} to {}",
+            gap.start, gap.end
+        );
+    }
+    match &args.output {
+        Some(path) => write_candles(
+            std::io::BufWriter::new(
+                std::fs::File::create(path).with_context(
+                    || {
+                        format!(
+                            "Failed to create {}",
+                            path.display()
+                        )
+                    },
+                )?,
+            ),
+            &history.candles,
+            args.format,
+        ),
+        None => write_candles(
+            std::io::stdout().lock(),
+            &history.candles,
+            args.format,
+        ),
+    }
+}
@@ -0,0 +1,92 @@
+use mexc_rs::exchange::history::Candle;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+/// Prints a value as one line of JSON
+pub fn print_json_line(value: &impl serde::Serialize) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer(
+        &mut stdout,
+        value,
+    )?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+pub fn write_candles(mut writer: impl Write, candles: &[Candle], format: Format) -> anyhow::Result<()> {
+    match format {
+        Format::Csv => {
+            writeln!(
+                writer,
+                "open_time,open,high,low,close,volume,quote_volume"
+            )?;
+            for candle in candles {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    candle
+                        .open_time
+                        .to_rfc3339(),
+                    candle.open,
+                    candle.high,
+                    candle.low,
+                    candle.close,
+                    candle.volume,
+                    candle.quote_volume,
+                )?;
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(
+                &mut writer,
+                candles,
+            )?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn writes_candles_as_csv() {
+        let candle = Candle {
+            open_time: Utc
+                .timestamp_opt(
+                    1_700_000_000,
+                    0,
+                )
+                .unwrap(),
+            open: Decimal::new(
+                1005, 1,
+            ),
+            high: Decimal::from(101),
+            low: Decimal::from(99),
+            close: Decimal::from(100),
+            volume: Decimal::from(2),
+            quote_volume: Decimal::from(200),
+        };
+        let mut csv = Vec::new();
+        write_candles(
+            &mut csv,
+            &[candle],
+            Format::Csv,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "open_time,open,high,low,close,volume,quote_volume\n2023-11-14T22:13:20+00:00,100.5,101,99,100,2,200\n"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! `ws tail`: subscribes to topics and prints every push as a line of JSON.
+//!
+//! The pushes are taken from a [`Recorder`] attached to the websocket client,
+//! so they are printed as the server sent them rather than as parsed by the
+//! library.
+
+use crate::config::Credentials;
+use anyhow::{bail, Context};
+use flate2::read::GzDecoder;
+use mexc_rs::{
+    exchange::{history::CandleInterval, Venue},
+    futures::ws::{
+        auth::FuturesWebsocketAuth,
+        subscribe::{FuturesSubscribe, FuturesSubscribeParams},
+        topic::{DealTopic, DepthFullTopic, DepthLimit, DepthTopic as FuturesDepthTopic, FairPriceTopic, FundingRateTopic, FuturesTopic, IndexPriceTopic, KlineTopic as FuturesKlineTopic, TickerTopic},
+        MexcFuturesWebsocketClient,
+    },
+    proto::{push_data_v3_api_wrapper::Body, PushDataV3ApiWrapper},
+    recording::{RecordedFrame, RecordedPayload, Recorder, RecordingReader},
+    spot::ws::{
+        auth::WebsocketAuth,
+        message::kline::KlineIntervalTopic,
+        subscribe::{Subscribe, SubscribeParams},
+        topic::{DealsTopic, DepthTopic, KlineTopic, Topic},
+        MexcSpotWebsocketClient,
+    },
+};
+use prost::Message;
+use serde_json::{json, Value};
+use std::{io::Read, str::FromStr};
+
+pub async fn tail(venue: Venue, topics: &[String], credentials: Option<Credentials>) -> anyhow::Result<()> {
+    let (reader, writer) = std::io::pipe()?;
+    let recorder = Recorder::from_writer(writer)?;
+    // Reading blocks, the thread ends when the client drops the recorder
+    let printer = std::thread::spawn(
+        move || {
+            print_frames(
+                reader, venue,
+            )
+        },
+    );
+
+    match venue {
+        Venue::Spot => {
+            let mut params = SubscribeParams::default().with_topics(
+                topics
+                    .iter()
+                    .map(|topic| parse_spot_topic(topic))
+                    .collect::<anyhow::Result<_>>()?,
+            );
+            if params
+                .topics
+                .iter()
+                .any(Topic::requires_auth)
+            {
+                let credentials = credentials.context("Private topics require credentials")?;
+                params = params.with_auth(
+                    WebsocketAuth::new(
+                        credentials.api_key,
+                        credentials.secret_key,
+                    ),
+                );
+            }
+            let client = MexcSpotWebsocketClient::default()
+                .with_recorder(recorder)
+                .into_arc();
+            client
+                .clone()
+                .subscribe(params)
+                .await?;
+            wait(&printer).await?;
+        }
+        Venue::Futures => {
+            let mut params = FuturesSubscribeParams::default();
+            for topic in topics {
+                match parse_futures_topic(topic)? {
+                    Some(topic) => params = params.with_topic(topic),
+                    None => {
+                        let credentials = credentials
+                            .clone()
+                            .context("The personal topic requires credentials")?;
+                        params = params.with_auth(
+                            FuturesWebsocketAuth::new(
+                                credentials.api_key,
+                                credentials.secret_key,
+                            ),
+                        );
+                    }
+                }
+            }
+            let client = MexcFuturesWebsocketClient::default()
+                .with_recorder(recorder)
+                .into_arc();
+            client
+                .clone()
+                .subscribe(params)
+                .await?;
+            wait(&printer).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Until ctrl-c, or the printer stopped because stdout was closed
+async fn wait(printer: &std::thread::JoinHandle<anyhow::Result<()>>) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(200));
+    loop {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => return Ok(result?),
+            _ = interval.tick() => {
+                if printer.is_finished() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn print_frames(reader: impl Read, venue: Venue) -> anyhow::Result<()> {
+    for frame in RecordingReader::new(reader)? {
+        if let Some(line) = frame_to_json(
+            &frame?, venue,
+        ) {
+            crate::output::print_json_line(&line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pushes only, subscription replies and pongs are skipped
+fn frame_to_json(frame: &RecordedFrame, venue: Venue) -> Option<Value> {
+    let message = match (
+        venue,
+        &frame.payload,
+    ) {
+        (_, RecordedPayload::Text(text)) => serde_json::from_str::<Value>(text).ok()?,
+        (Venue::Spot, RecordedPayload::Binary(binary)) => spot_proto_to_json(binary)?,
+        (Venue::Futures, RecordedPayload::Binary(binary)) => {
+            let mut text = String::new();
+            GzDecoder::new(binary.as_slice())
+                .read_to_string(&mut text)
+                .ok()?;
+            serde_json::from_str::<Value>(&text).ok()?
+        }
+    };
+    let is_push = match venue {
+        Venue::Spot => message
+            .get("c")
+            .is_some(),
+        Venue::Futures => message
+            .get("channel")
+            .and_then(Value::as_str)
+            .is_some_and(|channel| channel.starts_with("push.")),
+    };
+    is_push.then(
+        || {
+            json!({
+                "received_at": frame.received_at,
+                "message": message,
+            })
+        },
+    )
+}
+
+/// Shaped like the JSON pushes, with the channel in `c` and the data in `d`
+fn spot_proto_to_json(binary: &[u8]) -> Option<Value> {
+    let wrapper = PushDataV3ApiWrapper::decode(binary).ok()?;
+    let data = match wrapper.body? {
+        Body::PublicAggreDepths(depth) => {
+            let levels = |levels: &[mexc_rs::proto::PublicAggreDepthV3ApiItem]| {
+                levels
+                    .iter()
+                    .map(
+                        |level| {
+                            json!({
+                                "p": level.price,
+                                "v": level.quantity,
+                            })
+                        },
+                    )
+                    .collect::<Vec<_>>()
+            };
+            json!({
+                "asks": levels(&depth.asks),
+                "bids": levels(&depth.bids),
+                "e": depth.event_type,
+                "fromVersion": depth.from_version,
+                "toVersion": depth.to_version,
+            })
+        }
+        _ => json!({ "raw": hex::encode(binary) }),
+    };
+    Some(
+        json!({
+            "c": wrapper.channel,
+            "s": wrapper.symbol,
+            "t": wrapper.send_time,
+            "d": data,
+        }),
+    )
+}
+
+/// `deals:SYMBOL`, `kline:SYMBOL:INTERVAL`, `depth:SYMBOL`, `account-deals`,
+/// `account-orders` or `account-update`
+fn parse_spot_topic(topic: &str) -> anyhow::Result<Topic> {
+    let parts = topic
+        .split(':')
+        .collect::<Vec<_>>();
+    Ok(
+        match parts.as_slice() {
+            ["deals", symbol] => Topic::Deals(DealsTopic::new(symbol.to_string())),
+            ["kline", symbol, interval] => Topic::Kline(
+                KlineTopic::new(
+                    symbol.to_string(),
+                    spot_kline_interval(CandleInterval::from_str(interval)?)?,
+                ),
+            ),
+            ["depth", symbol] => Topic::Depth(DepthTopic::new(symbol.to_string())),
+            ["account-deals"] => Topic::AccountDeals,
+            ["account-orders"] => Topic::AccountOrders,
+            ["account-update"] => Topic::AccountUpdate,
+            _ => bail!("Unknown spot topic {topic}"),
+        },
+    )
+}
+
+fn spot_kline_interval(interval: CandleInterval) -> anyhow::Result<KlineIntervalTopic> {
+    Ok(
+        match interval {
+            CandleInterval::OneMinute => KlineIntervalTopic::OneMinute,
+            CandleInterval::FiveMinutes => KlineIntervalTopic::FiveMinutes,
+            CandleInterval::FifteenMinutes => KlineIntervalTopic::FifteenMinutes,
+            CandleInterval::ThirtyMinutes => KlineIntervalTopic::ThirtyMinutes,
+            CandleInterval::OneHour => KlineIntervalTopic::OneHour,
+            CandleInterval::FourHours => KlineIntervalTopic::FourHours,
+            CandleInterval::OneDay => KlineIntervalTopic::OneDay,
+            CandleInterval::OneWeek => KlineIntervalTopic::OneWeek,
+            CandleInterval::OneMonth => KlineIntervalTopic::OneMonth,
+            CandleInterval::EightHours => bail!("Spot has no 8h klines"),
+        },
+    )
+}
+
+/// `tickers`, `ticker:SYMBOL`, `deal:SYMBOL`, `depth:SYMBOL`,
+/// `depth-full:SYMBOL:LIMIT`, `kline:SYMBOL:INTERVAL`, `funding-rate:SYMBOL`,
+/// `index-price:SYMBOL`, `fair-price:SYMBOL`, `contract`, or `personal` for
+/// the pushes of the logged in account, which is `None`
+fn parse_futures_topic(topic: &str) -> anyhow::Result<Option<FuturesTopic>> {
+    let parts = topic
+        .split(':')
+        .collect::<Vec<_>>();
+    Ok(
+        Some(
+            match parts.as_slice() {
+                ["tickers"] => FuturesTopic::Tickers,
+                ["ticker", symbol] => FuturesTopic::Ticker(TickerTopic::new(symbol.to_string())),
+                ["deal", symbol] => FuturesTopic::Deal(DealTopic::new(symbol.to_string())),
+                ["depth", symbol] => FuturesTopic::Depth(FuturesDepthTopic::new(symbol.to_string())),
+                ["depth-full", symbol, limit] => FuturesTopic::DepthFull(
+                    DepthFullTopic::new(
+                        symbol.to_string(),
+                        match *limit {
+                            "5" => DepthLimit::Five,
+                            "10" => DepthLimit::Ten,
+                            "20" => DepthLimit::Twenty,
+                            _ => bail!("Depth limit must be 5, 10 or 20"),
+                        },
+                    ),
+                ),
+                ["kline", symbol, interval] => FuturesTopic::Kline(
+                    FuturesKlineTopic::new(
+                        symbol.to_string(),
+                        CandleInterval::from_str(interval)?.into(),
+                    ),
+                ),
+                ["funding-rate", symbol] => FuturesTopic::FundingRate(FundingRateTopic::new(symbol.to_string())),
+                ["index-price", symbol] => FuturesTopic::IndexPrice(IndexPriceTopic::new(symbol.to_string())),
+                ["fair-price", symbol] => FuturesTopic::FairPrice(FairPriceTopic::new(symbol.to_string())),
+                ["contract"] => FuturesTopic::Contract,
+                ["personal"] => return Ok(None),
+                _ => bail!("Unknown futures topic {topic}"),
+            },
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use uuid::Uuid;
+
+    fn frame(payload: RecordedPayload) -> RecordedFrame {
+        RecordedFrame {
+            received_at: Utc::now(),
+            websocket_id: Uuid::nil(),
+            payload,
+        }
+    }
+
+    #[test]
+    fn prints_pushes_only() {
+        let ack = frame(RecordedPayload::Text(r#"{"id":0,"code":0,"msg":"spot@public.deals.v3.api@BTCUSDT"}"#.to_string()));
+        assert_eq!(
+            frame_to_json(
+                &ack,
+                Venue::Spot
+            ),
+            None
+        );
+
+        let mut gzip = GzEncoder::new(
+            Vec::new(),
+            Compression::default(),
+        );
+        gzip.write_all(br#"{"channel":"push.deal","symbol":"BTC_USDT","data":{"p":100}}"#)
+            .unwrap();
+        let push = frame(
+            RecordedPayload::Binary(
+                gzip.finish()
+                    .unwrap(),
+            ),
+        );
+        assert_eq!(
+            frame_to_json(
+                &push,
+                Venue::Futures
+            )
+            .unwrap()["message"]["data"]["p"],
+            100
+        );
+        let pong = frame(RecordedPayload::Text(r#"{"channel":"pong","data":1}"#.to_string()));
+        assert_eq!(
+            frame_to_json(
+                &pong,
+                Venue::Futures
+            ),
+            None
+        );
+    }
+}
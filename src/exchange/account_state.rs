@@ -0,0 +1,476 @@
+use crate::exchange::{Balance, Position, Venue};
+use chrono::{DateTime, Utc};
+use std::{collections::BTreeMap, sync::Mutex};
+use tokio::sync::watch;
+
+/// Balances and open positions at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    /// Sorted by venue and asset
+    pub balances: Vec<Balance>,
+    /// Sorted by position id
+    pub positions: Vec<Position>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl AccountSnapshot {
+    pub fn balance(&self, venue: Venue, asset: &str) -> Option<&Balance> {
+        self.balances
+            .iter()
+            .find(|balance| balance.venue == venue && balance.asset == asset)
+    }
+
+    pub fn positions_of<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item = &'a Position> + 'a {
+        self.positions
+            .iter()
+            .filter(
+                move |position| {
+                    position
+                        .instrument
+                        .symbol
+                        == symbol
+                },
+            )
+    }
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    /// `None` once a position closed, kept so older updates can't reopen it
+    value: Option<T>,
+    updated_at: DateTime<Utc>,
+    version: Option<i64>,
+}
+
+impl<T> Entry<T> {
+    /// Versions decide when both sides have one, otherwise the update time
+    fn is_older(&self, updated_at: DateTime<Utc>, version: Option<i64>) -> bool {
+        match (
+            self.version,
+            version,
+        ) {
+            (Some(current), Some(version)) => version <= current,
+            _ => updated_at < self.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    balances: BTreeMap<
+        (
+            Venue,
+            String,
+        ),
+        Entry<Balance>,
+    >,
+    positions: BTreeMap<String, Entry<Position>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl Entries {
+    fn snapshot(&self) -> AccountSnapshot {
+        AccountSnapshot {
+            balances: self
+                .balances
+                .values()
+                .filter_map(
+                    |entry| {
+                        entry
+                            .value
+                            .clone()
+                    },
+                )
+                .collect(),
+            positions: self
+                .positions
+                .values()
+                .filter_map(
+                    |entry| {
+                        entry
+                            .value
+                            .clone()
+                    },
+                )
+                .collect(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Balances and positions kept current from account pushes, with REST
+/// snapshots to seed it and to correct drift. Updates older than what is
+/// cached are ignored, as pushes and REST responses can arrive out of order.
+///
+/// All times are taken from the local clock: pushes at when they were
+/// received and REST snapshots at when they were requested. Exchange
+/// timestamps can't be compared with the local time of a request.
+pub struct AccountState {
+    entries: Mutex<Entries>,
+    sender: watch::Sender<AccountSnapshot>,
+}
+
+impl Default for AccountState {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            sender: watch::channel(AccountSnapshot::default()).0,
+        }
+    }
+}
+
+impl AccountState {
+    pub fn snapshot(&self) -> AccountSnapshot {
+        self.sender
+            .borrow()
+            .clone()
+    }
+
+    /// Sees a new snapshot after every change
+    pub fn watch(&self) -> watch::Receiver<AccountSnapshot> {
+        self.sender
+            .subscribe()
+    }
+
+    pub fn balance(&self, venue: Venue, asset: &str) -> Option<Balance> {
+        self.sender
+            .borrow()
+            .balance(
+                venue, asset,
+            )
+            .cloned()
+    }
+
+    /// Applies a pushed balance, returns whether it was newer than the cached
+    /// one
+    pub fn apply_balance(&self, balance: Balance, updated_at: DateTime<Utc>) -> bool {
+        self.update(
+            |entries| {
+                let key = (
+                    balance.venue,
+                    balance
+                        .asset
+                        .clone(),
+                );
+                if entries
+                    .balances
+                    .get(&key)
+                    .is_some_and(
+                        |entry| {
+                            entry.is_older(
+                                updated_at, None,
+                            )
+                        },
+                    )
+                {
+                    return false;
+                }
+                entries
+                    .balances
+                    .insert(
+                        key,
+                        Entry {
+                            value: Some(balance),
+                            updated_at,
+                            version: None,
+                        },
+                    );
+                true
+            },
+            updated_at,
+        )
+    }
+
+    /// Applies a pushed position, returns whether it was newer than the cached
+    /// one. A position without quantity is removed.
+    pub fn apply_position(&self, position: Position, version: Option<i64>, updated_at: DateTime<Utc>) -> bool {
+        self.update(
+            |entries| {
+                let key = position
+                    .position_id
+                    .clone();
+                if entries
+                    .positions
+                    .get(&key)
+                    .is_some_and(
+                        |entry| {
+                            entry.is_older(
+                                updated_at, version,
+                            )
+                        },
+                    )
+                {
+                    return false;
+                }
+                let value = match position
+                    .quantity
+                    .is_zero()
+                {
+                    true => None,
+                    false => Some(position),
+                };
+                entries
+                    .positions
+                    .insert(
+                        key,
+                        Entry {
+                            value,
+                            updated_at,
+                            version,
+                        },
+                    );
+                true
+            },
+            updated_at,
+        )
+    }
+
+    /// Replaces the balances of a venue with a REST snapshot requested at
+    /// `requested_at`. Balances pushed after that are newer and kept.
+    pub fn reconcile_balances(&self, venue: Venue, balances: Vec<Balance>, requested_at: DateTime<Utc>) {
+        self.update(
+            |entries| {
+                entries
+                    .balances
+                    .retain(|(balance_venue, _), entry| *balance_venue != venue || entry.updated_at > requested_at);
+                for balance in balances {
+                    let key = (
+                        venue,
+                        balance
+                            .asset
+                            .clone(),
+                    );
+                    if entries
+                        .balances
+                        .contains_key(&key)
+                    {
+                        continue;
+                    }
+                    entries
+                        .balances
+                        .insert(
+                            key,
+                            Entry {
+                                value: Some(balance),
+                                updated_at: requested_at,
+                                version: None,
+                            },
+                        );
+                }
+                true
+            },
+            requested_at,
+        );
+    }
+
+    /// Replaces all positions with a REST snapshot requested at
+    /// `requested_at`. Positions pushed after that are newer and kept.
+    pub fn reconcile_positions(&self, positions: Vec<Position>, requested_at: DateTime<Utc>) {
+        self.update(
+            |entries| {
+                entries
+                    .positions
+                    .retain(|_, entry| entry.updated_at > requested_at);
+                for position in positions {
+                    if entries
+                        .positions
+                        .contains_key(&position.position_id)
+                    {
+                        continue;
+                    }
+                    entries
+                        .positions
+                        .insert(
+                            position
+                                .position_id
+                                .clone(),
+                            Entry {
+                                value: Some(position),
+                                updated_at: requested_at,
+                                version: None,
+                            },
+                        );
+                }
+                true
+            },
+            requested_at,
+        );
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Entries) -> bool, updated_at: DateTime<Utc>) -> bool {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap();
+        if !update(&mut entries) {
+            return false;
+        }
+        entries.updated_at = entries
+            .updated_at
+            .max(Some(updated_at));
+        self.sender
+            .send_replace(entries.snapshot());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Instrument, MarginMode, PositionSide};
+    use chrono::TimeZone;
+    use rust_decimal::Decimal;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(
+            1_700_000_000 + seconds,
+            0,
+        )
+        .unwrap()
+    }
+
+    fn balance(asset: &str, free: i64) -> Balance {
+        Balance {
+            venue: Venue::Spot,
+            asset: asset.to_string(),
+            free: Decimal::from(free),
+            locked: Decimal::ZERO,
+            total: Decimal::from(free),
+        }
+    }
+
+    fn position(quantity: i64) -> Position {
+        Position {
+            instrument: Instrument {
+                venue: Venue::Futures,
+                symbol: "BTC_USDT".to_string(),
+            },
+            position_id: "1".to_string(),
+            side: PositionSide::Long,
+            quantity: Decimal::from(quantity),
+            entry_price: Decimal::from(100),
+            liquidation_price: None,
+            margin: Decimal::from(10),
+            margin_mode: MarginMode::Isolated,
+            realized_profit: Decimal::ZERO,
+        }
+    }
+
+    #[test]
+    fn ignores_out_of_order_updates() {
+        let state = AccountState::default();
+        let mut watch = state.watch();
+        state.reconcile_balances(
+            Venue::Spot,
+            vec![
+                balance(
+                    "USDT", 100,
+                ),
+                balance(
+                    "BTC", 1,
+                ),
+            ],
+            time(0),
+        );
+        assert!(
+            watch
+                .has_changed()
+                .unwrap()
+        );
+        watch.mark_unchanged();
+
+        assert!(
+            state.apply_balance(
+                balance("USDT", 80),
+                time(2),
+            )
+        );
+        assert!(
+            !state.apply_balance(
+                balance("USDT", 90),
+                time(1),
+            )
+        );
+        assert!(
+            state.apply_position(
+                position(3),
+                Some(5),
+                time(2),
+            )
+        );
+        assert!(
+            !state.apply_position(
+                position(2),
+                Some(4),
+                time(3),
+            )
+        );
+
+        // The REST snapshot was requested before the pushes, only BTC is stale
+        state.reconcile_balances(
+            Venue::Spot,
+            vec![
+                balance(
+                    "USDT", 100,
+                ),
+                balance(
+                    "BTC", 2,
+                ),
+            ],
+            time(1),
+        );
+        state.reconcile_positions(
+            vec![],
+            time(1),
+        );
+        let snapshot = watch
+            .borrow_and_update()
+            .clone();
+        assert_eq!(
+            snapshot
+                .balance(
+                    Venue::Spot,
+                    "USDT"
+                )
+                .map(|balance| balance.free),
+            Some(Decimal::from(80))
+        );
+        assert_eq!(
+            snapshot
+                .balance(
+                    Venue::Spot,
+                    "BTC"
+                )
+                .map(|balance| balance.free),
+            Some(Decimal::from(2))
+        );
+        assert_eq!(
+            snapshot
+                .positions_of("BTC_USDT")
+                .count(),
+            1
+        );
+
+        // A closed position stays closed when an older push arrives late
+        assert!(
+            state.apply_position(
+                position(0),
+                Some(6),
+                time(4),
+            )
+        );
+        assert!(
+            !state.apply_position(
+                position(3),
+                Some(5),
+                time(2),
+            )
+        );
+        assert!(
+            state
+                .snapshot()
+                .positions
+                .is_empty()
+        );
+    }
+}
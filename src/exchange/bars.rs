@@ -0,0 +1,607 @@
+//! Builds custom bars from public trades, for intervals the kline channels
+//! don't offer and for bars closed by volume or notional instead of time.
+
+use crate::exchange::{history::Candle, Side};
+#[cfg(
+    all(
+        feature = "futures",
+        feature = "ws"
+    )
+)]
+use crate::futures::ws::message::DealMessage;
+#[cfg(
+    all(
+        feature = "spot",
+        feature = "ws"
+    )
+)]
+use crate::spot::ws::message::deals::SpotDealsMessage;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+
+/// A public trade, `side` being the taker side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trade {
+    pub symbol: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub side: Side,
+    pub time: DateTime<Utc>,
+}
+
+#[cfg(
+    all(
+        feature = "spot",
+        feature = "ws"
+    )
+)]
+impl Trade {
+    pub fn from_spot_deals(message: &SpotDealsMessage) -> Vec<Self> {
+        message
+            .deals
+            .iter()
+            .map(
+                |deal| Self {
+                    symbol: deal
+                        .symbol
+                        .clone(),
+                    price: deal.price,
+                    quantity: deal.quantity,
+                    side: taker_side(deal.trade_type),
+                    time: deal.timestamp,
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(
+    all(
+        feature = "futures",
+        feature = "ws"
+    )
+)]
+impl Trade {
+    /// Quantities are in contracts
+    pub fn from_futures_deals(message: &DealMessage) -> Vec<Self> {
+        message
+            .data
+            .iter()
+            .map(
+                |deal| Self {
+                    symbol: message
+                        .symbol
+                        .clone(),
+                    price: deal.p,
+                    quantity: deal.v,
+                    side: taker_side(deal.trade_side),
+                    time: deal.trade_time,
+                },
+            )
+            .collect()
+    }
+}
+
+/// Both venues push 1 for a buying and 2 for a selling taker
+#[cfg(feature = "ws")]
+fn taker_side(value: i32) -> Side {
+    match value {
+        1 => Side::Buy,
+        _ => Side::Sell,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarKind {
+    /// Bars covering fixed periods aligned to the unix epoch, periods without
+    /// trades produce no bar
+    Time(Duration),
+    /// Bars closing once this quantity traded, trades crossing the threshold
+    /// are split between bars and counted in each
+    Volume(Decimal),
+    /// Bars closing once this quote amount traded, also known as dollar bars
+    Notional(Decimal),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bar {
+    pub open_time: DateTime<Utc>,
+    /// End of the period for time bars, time of the last trade otherwise
+    pub close_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub quote_volume: Decimal,
+    /// Zero for the part seeded from a kline, which doesn't carry these
+    pub trades: u64,
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+}
+
+impl Bar {
+    fn open(open_time: DateTime<Utc>, close_time: DateTime<Utc>, price: Decimal) -> Self {
+        Self {
+            open_time,
+            close_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::ZERO,
+            quote_volume: Decimal::ZERO,
+            trades: 0,
+            buy_volume: Decimal::ZERO,
+            sell_volume: Decimal::ZERO,
+        }
+    }
+
+    fn add(&mut self, price: Decimal, quantity: Decimal, side: Side) {
+        self.high = self
+            .high
+            .max(price);
+        self.low = self
+            .low
+            .min(price);
+        self.close = price;
+        self.volume += quantity;
+        self.quote_volume += price * quantity;
+        self.trades += 1;
+        match side {
+            Side::Buy => self.buy_volume += quantity,
+            Side::Sell => self.sell_volume += quantity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarEvent {
+    Completed(Bar),
+    /// The bar still being built, after the trades just applied
+    InProgress(Bar),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BarError {
+    #[error("The bar size must be positive: {0:?}")]
+    NonPositiveSize(BarKind),
+}
+
+#[derive(Debug, Clone)]
+pub struct BarAggregator {
+    symbol: String,
+    kind: BarKind,
+    current: Option<Bar>,
+    /// Trades up to this time are already part of the seeded kline
+    seeded_until: Option<DateTime<Utc>>,
+}
+
+impl BarAggregator {
+    pub fn new(symbol: &str, kind: BarKind) -> Result<Self, BarError> {
+        let positive = match kind {
+            BarKind::Time(period) => period > Duration::zero(),
+            BarKind::Volume(threshold) | BarKind::Notional(threshold) => threshold > Decimal::ZERO,
+        };
+        if !positive {
+            return Err(BarError::NonPositiveSize(kind));
+        }
+        Ok(
+            Self {
+                symbol: symbol.to_string(),
+                kind,
+                current: None,
+                seeded_until: None,
+            },
+        )
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn current(&self) -> Option<&Bar> {
+        self.current
+            .as_ref()
+    }
+
+    /// Starts the bar in progress from a REST kline fetched at `as_of`, so the
+    /// first bar includes trades from before subscribing. Trades up to `as_of`
+    /// are skipped afterwards. For time bars the kline has to open at the
+    /// start of the current period, and ideally span the same period.
+    pub fn seed(&mut self, candle: &Candle, as_of: DateTime<Utc>) {
+        let close_time = match self.kind {
+            BarKind::Time(period) => candle.open_time + period,
+            BarKind::Volume(_) | BarKind::Notional(_) => as_of,
+        };
+        self.current = Some(
+            Bar {
+                open_time: candle.open_time,
+                close_time,
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                volume: candle.volume,
+                quote_volume: candle.quote_volume,
+                trades: 0,
+                buy_volume: Decimal::ZERO,
+                sell_volume: Decimal::ZERO,
+            },
+        );
+        self.seeded_until = Some(as_of);
+    }
+
+    /// Applies trades of this aggregator's symbol, ignoring others. Emits
+    /// every bar completed by them, then the bar in progress if there is one.
+    pub fn apply<'a>(&mut self, trades: impl IntoIterator<Item = &'a Trade>) -> Vec<BarEvent> {
+        let mut events = vec![];
+        let mut applied = false;
+        for trade in trades {
+            if trade.symbol != self.symbol
+                || self
+                    .seeded_until
+                    .is_some_and(|seeded_until| trade.time <= seeded_until)
+            {
+                continue;
+            }
+            applied = true;
+            match self.kind {
+                BarKind::Time(period) => self.apply_timed(
+                    trade,
+                    period,
+                    &mut events,
+                ),
+                BarKind::Volume(threshold) => self.apply_sized(
+                    trade,
+                    threshold,
+                    |bar| bar.volume,
+                    &mut events,
+                ),
+                BarKind::Notional(threshold) => self.apply_sized(
+                    trade,
+                    threshold,
+                    |bar| bar.quote_volume,
+                    &mut events,
+                ),
+            }
+        }
+        if applied {
+            if let Some(current) = &self.current {
+                events.push(BarEvent::InProgress(current.clone()));
+            }
+        }
+        events
+    }
+
+    /// Completes the time bar in progress once its period ended, for when no
+    /// trade arrives to close it
+    pub fn close_until(&mut self, now: DateTime<Utc>) -> Option<Bar> {
+        match (
+            self.kind,
+            &self.current,
+        ) {
+            (BarKind::Time(_), Some(current)) if current.close_time <= now => self
+                .current
+                .take(),
+            _ => None,
+        }
+    }
+
+    fn apply_timed(&mut self, trade: &Trade, period: Duration, events: &mut Vec<BarEvent>) {
+        if let Some(current) = &self.current {
+            if trade.time < current.close_time {
+                self.current
+                    .as_mut()
+                    .unwrap()
+                    .add(
+                        trade.price,
+                        trade.quantity,
+                        trade.side,
+                    );
+                return;
+            }
+            events.push(
+                BarEvent::Completed(
+                    self.current
+                        .take()
+                        .unwrap(),
+                ),
+            );
+        }
+        let open_time = period_start(
+            trade.time, period,
+        );
+        let mut bar = Bar::open(
+            open_time,
+            open_time + period,
+            trade.price,
+        );
+        bar.add(
+            trade.price,
+            trade.quantity,
+            trade.side,
+        );
+        self.current = Some(bar);
+    }
+
+    /// Fills the bar in progress up to `threshold`, measured by `size` of the
+    /// bar, splitting the trade when it doesn't fit
+    fn apply_sized(&mut self, trade: &Trade, threshold: Decimal, size: fn(&Bar) -> Decimal, events: &mut Vec<BarEvent>) {
+        let mut remaining = trade.quantity;
+        loop {
+            let bar = self
+                .current
+                .get_or_insert_with(
+                    || {
+                        Bar::open(
+                            trade.time,
+                            trade.time,
+                            trade.price,
+                        )
+                    },
+                );
+            let room = threshold - size(bar);
+            let room = match self.kind {
+                BarKind::Notional(_) if trade.price > Decimal::ZERO => room / trade.price,
+                _ => room,
+            };
+            let quantity = remaining.min(room.max(Decimal::ZERO));
+            if quantity > Decimal::ZERO || room > Decimal::ZERO {
+                bar.add(
+                    trade.price,
+                    quantity,
+                    trade.side,
+                );
+                bar.close_time = trade.time;
+            }
+            remaining -= quantity;
+            if remaining > Decimal::ZERO || size(bar) >= threshold {
+                events.push(
+                    BarEvent::Completed(
+                        self.current
+                            .take()
+                            .unwrap(),
+                    ),
+                );
+            }
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+        }
+    }
+}
+
+fn period_start(time: DateTime<Utc>, period: Duration) -> DateTime<Utc> {
+    let period = period
+        .num_milliseconds()
+        .max(1);
+    let millis = time.timestamp_millis();
+    DateTime::from_timestamp_millis(millis - millis.rem_euclid(period)).unwrap_or(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn time(millis: i64) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(1_700_000_000_000 + millis)
+            .unwrap()
+    }
+
+    fn trade(millis: i64, price: &str, quantity: &str, side: Side) -> Trade {
+        Trade {
+            symbol: "BTCUSDT".to_string(),
+            price: dec(price),
+            quantity: dec(quantity),
+            side,
+            time: time(millis),
+        }
+    }
+
+    #[test]
+    fn builds_time_bars_from_seeded_kline() {
+        let mut aggregator = BarAggregator::new(
+            "BTCUSDT",
+            BarKind::Time(Duration::seconds(3)),
+        )
+        .unwrap();
+        aggregator.seed(
+            &Candle {
+                open_time: time(-2000),
+                open: dec("100"),
+                high: dec("102"),
+                low: dec("99"),
+                close: dec("101"),
+                volume: dec("5"),
+                quote_volume: dec("500"),
+            },
+            time(0),
+        );
+
+        let events = aggregator.apply(
+            &[
+                trade(
+                    0,
+                    "90",
+                    "9",
+                    Side::Sell,
+                ),
+                trade(
+                    500,
+                    "103",
+                    "1",
+                    Side::Buy,
+                ),
+                trade(
+                    1000,
+                    "98",
+                    "2",
+                    Side::Sell,
+                ),
+                trade(
+                    1200,
+                    "97",
+                    "3",
+                    Side::Buy,
+                ),
+            ],
+        );
+        let [BarEvent::Completed(first), BarEvent::InProgress(second)] = events.as_slice() else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert_eq!(
+            (
+                first.open_time,
+                first.close_time
+            ),
+            (
+                time(-2000),
+                time(1000)
+            )
+        );
+        assert_eq!(
+            (
+                first.open,
+                first.high,
+                first.low,
+                first.close
+            ),
+            (
+                dec("100"),
+                dec("103"),
+                dec("99"),
+                dec("103")
+            )
+        );
+        assert_eq!(
+            (
+                first.volume,
+                first.trades,
+                first.buy_volume
+            ),
+            (
+                dec("6"),
+                1,
+                dec("1")
+            )
+        );
+        assert_eq!(
+            (
+                second.open,
+                second.low,
+                second.trades,
+                second.buy_volume,
+                second.sell_volume
+            ),
+            (
+                dec("98"),
+                dec("97"),
+                2,
+                dec("3"),
+                dec("2")
+            )
+        );
+
+        assert_eq!(
+            aggregator.close_until(time(3999)),
+            None
+        );
+        assert_eq!(
+            aggregator
+                .close_until(time(4000))
+                .map(|bar| bar.volume),
+            Some(dec("5"))
+        );
+    }
+
+    #[test]
+    fn splits_trades_across_volume_bars() {
+        let mut aggregator = BarAggregator::new(
+            "BTCUSDT",
+            BarKind::Volume(dec("2")),
+        )
+        .unwrap();
+        let events = aggregator.apply(
+            &[
+                trade(
+                    0,
+                    "100",
+                    "1.5",
+                    Side::Buy,
+                ),
+                trade(
+                    1,
+                    "101",
+                    "3",
+                    Side::Sell,
+                ),
+            ],
+        );
+        let volumes = events
+            .iter()
+            .map(
+                |event| match event {
+                    BarEvent::Completed(bar) => (
+                        true, bar.volume,
+                    ),
+                    BarEvent::InProgress(bar) => (
+                        false, bar.volume,
+                    ),
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            volumes,
+            vec![
+                (
+                    true,
+                    dec("2")
+                ),
+                (
+                    true,
+                    dec("2")
+                ),
+                (
+                    false,
+                    dec("0.5")
+                )
+            ]
+        );
+        let BarEvent::Completed(first) = &events[0] else { unreachable!() };
+        assert_eq!(
+            (
+                first.buy_volume,
+                first.sell_volume,
+                first.close
+            ),
+            (
+                dec("1.5"),
+                dec("0.5"),
+                dec("101")
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_non_positive_sizes() {
+        for kind in [
+            BarKind::Time(Duration::zero()),
+            BarKind::Volume(Decimal::ZERO),
+            BarKind::Notional(dec("-1")),
+        ] {
+            assert!(
+                matches!(
+                    BarAggregator::new("BTCUSDT", kind),
+                    Err(BarError::NonPositiveSize(_))
+                )
+            );
+        }
+    }
+}
@@ -0,0 +1,1574 @@
+//! Executes a large parent order as a schedule of smaller child orders placed
+//! through any [`Exchange`], to limit market impact.
+//!
+//! Child fills are learned from the order and fill pushes of the venue, which
+//! have to be passed to [`Executor::apply_order`] and [`Executor::apply_fill`]
+//! while the executor runs. Without pushes, working children are queried
+//! every poll interval instead.
+
+use crate::exchange::{
+    history::{Candle, CandleInterval},
+    order_tracker::{OrderState, OrderTracker},
+    CancelOrderParams, Exchange, ExchangeError, ExchangeResult, Fill, MarginMode, Order, OrderType, PlaceOrderParams, PositionEffect, Side, Venue,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Timelike, Utc};
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{watch, Notify},
+    time::Instant,
+};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Precision and size limits of an instrument, implemented by the spot symbol
+/// rules and the futures contract spec
+pub trait InstrumentRules: Send + Sync {
+    /// Rounds to the tick size on the passive side: down for buys, up for
+    /// sells
+    fn round_price(&self, price: Decimal, side: Side) -> Decimal;
+
+    /// Rounds down to the quantity step
+    fn truncate_quantity(&self, quantity: Decimal) -> Decimal;
+
+    /// The smallest order, taking a minimum notional into account when the
+    /// price is known
+    fn min_quantity(&self, price: Option<Decimal>) -> Decimal;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketTrade {
+    pub time: DateTime<Utc>,
+    pub price: Decimal,
+    /// Base asset for spot, contracts for futures
+    pub quantity: Decimal,
+}
+
+/// A venue serving its most recent public trades
+#[async_trait]
+pub trait TradeSource {
+    fn venue(&self) -> Venue;
+
+    async fn recent_trades(&self, symbol: &str) -> ExchangeResult<Vec<MarketTrade>>;
+}
+
+/// Average traded volume by time of day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeProfile {
+    bucket_seconds: u32,
+    volumes: BTreeMap<u32, Decimal>,
+}
+
+impl VolumeProfile {
+    /// Averages the volume of candles sharing a time of day, e.g. a few days
+    /// of 5 minute klines. Intervals of a day or longer give a flat profile.
+    pub fn from_candles(candles: &[Candle], interval: CandleInterval) -> Self {
+        let bucket_seconds = interval
+            .duration()
+            .map(|duration| duration.num_seconds())
+            .unwrap_or(86_400)
+            .clamp(
+                1, 86_400,
+            ) as u32;
+        let mut sums = BTreeMap::<
+            u32,
+            (
+                Decimal,
+                u32,
+            ),
+        >::new();
+        for candle in candles {
+            let sum = sums
+                .entry(
+                    candle
+                        .open_time
+                        .num_seconds_from_midnight()
+                        / bucket_seconds,
+                )
+                .or_default();
+            sum.0 += candle.volume;
+            sum.1 += 1;
+        }
+        Self {
+            bucket_seconds,
+            volumes: sums
+                .into_iter()
+                .map(
+                    |(bucket, (volume, count))| {
+                        (
+                            bucket,
+                            volume / Decimal::from(count),
+                        )
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// The average volume of the bucket containing `time`
+    pub fn volume_at(&self, time: DateTime<Utc>) -> Decimal {
+        self.volumes
+            .get(&(time.num_seconds_from_midnight() / self.bucket_seconds))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The share of `slices` equal slices of `duration` from `start`, by the
+    /// volume at the middle of each slice. Equal shares without any volume.
+    pub fn slice_weights(&self, start: DateTime<Utc>, duration: Duration, slices: u32) -> Vec<Decimal> {
+        let slices = slices.max(1);
+        let step = duration / slices;
+        let volumes = (0..slices)
+            .map(
+                |slice| {
+                    let middle = step * slice + step / 2;
+                    self.volume_at(start + chrono::Duration::from_std(middle).unwrap_or_default())
+                },
+            )
+            .collect::<Vec<_>>();
+        let total = volumes
+            .iter()
+            .sum::<Decimal>();
+        match total.is_zero() {
+            true => equal_weights(slices),
+            false => volumes
+                .into_iter()
+                .map(|volume| volume / total)
+                .collect(),
+        }
+    }
+}
+
+fn equal_weights(slices: u32) -> Vec<Decimal> {
+    vec![Decimal::ONE / Decimal::from(slices.max(1)); slices.max(1) as usize]
+}
+
+#[derive(Debug, Clone)]
+pub enum Algorithm {
+    /// Equal slices spread evenly over `duration`, the first one right away
+    Twap { duration: Duration, slices: u32 },
+    /// Slices spread evenly over `duration`, sized by the expected volume
+    Vwap { duration: Duration, slices: u32, profile: VolumeProfile },
+    /// Follows the live market volume, trading `rate` of what the market
+    /// traded since the start. The volume comes from
+    /// [`Executor::record_market_volume`], e.g. fed from the deals stream, or
+    /// from polling a [`TradeSource`] every `interval`.
+    Participation { rate: Decimal, interval: Duration },
+    /// Rests `display_quantity` at the limit price, placing the next part
+    /// once it is filled
+    Iceberg { display_quantity: Decimal },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParentOrder {
+    pub symbol: String,
+    pub side: Side,
+    /// Base asset for spot, contracts for futures
+    pub quantity: Decimal,
+    /// Futures only, and required there
+    pub position_effect: Option<PositionEffect>,
+    /// Futures only
+    pub margin_mode: Option<MarginMode>,
+    /// Futures only
+    pub leverage: Option<u32>,
+}
+
+impl ParentOrder {
+    pub fn new(symbol: impl Into<String>, side: Side, quantity: Decimal) -> Self {
+        Self {
+            symbol: symbol.into(),
+            side,
+            quantity,
+            position_effect: None,
+            margin_mode: None,
+            leverage: None,
+        }
+    }
+
+    pub fn with_position_effect(mut self, position_effect: PositionEffect) -> Self {
+        self.position_effect = Some(position_effect);
+        self
+    }
+
+    pub fn with_margin_mode(mut self, margin_mode: MarginMode) -> Self {
+        self.margin_mode = Some(margin_mode);
+        self
+    }
+
+    pub fn with_leverage(mut self, leverage: u32) -> Self {
+        self.leverage = Some(leverage);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum ExecutionStatus {
+    Pending,
+    Running,
+    Paused,
+    /// Everything executed, up to a remainder below the minimum order size
+    Completed,
+    /// A time based schedule ended before everything executed, e.g. because
+    /// of the limit price
+    Expired,
+    Canceled,
+    Failed,
+}
+
+impl ExecutionStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ExecutionStatus::Completed | ExecutionStatus::Expired | ExecutionStatus::Canceled | ExecutionStatus::Failed
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExecutionProgress {
+    pub status: ExecutionStatus,
+    pub quantity: Decimal,
+    pub executed_quantity: Decimal,
+    /// Placed and not executed yet
+    pub working_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+    pub child_orders: usize,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ExecutionProgress {
+    pub fn remaining_quantity(&self) -> Decimal {
+        (self.quantity - self.executed_quantity).max(Decimal::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Run,
+    Pause,
+    Cancel,
+}
+
+/// What is due at some point of the schedule
+struct Due {
+    target: Decimal,
+    /// Time based schedules only, the slice the target belongs to
+    slice: Option<u32>,
+    last_slice: bool,
+    wake_in: Duration,
+}
+
+pub struct Executor<E> {
+    exchange: Arc<E>,
+    rules: Arc<dyn InstrumentRules>,
+    parent: ParentOrder,
+    algorithm: Algorithm,
+    limit_price: Option<Decimal>,
+    poll_interval: Duration,
+    trade_source: Option<Arc<dyn TradeSource + Send + Sync>>,
+    tracker: OrderTracker,
+    children: Mutex<Vec<String>>,
+    market_volume: Mutex<Decimal>,
+    changed: Notify,
+    control: watch::Sender<Control>,
+    progress: watch::Sender<ExecutionProgress>,
+}
+
+impl<E> Executor<E> {
+    pub fn new(exchange: Arc<E>, rules: Arc<dyn InstrumentRules>, parent: ParentOrder, algorithm: Algorithm) -> Self {
+        let progress = ExecutionProgress {
+            status: ExecutionStatus::Pending,
+            quantity: parent.quantity,
+            executed_quantity: Decimal::ZERO,
+            working_quantity: Decimal::ZERO,
+            average_price: None,
+            child_orders: 0,
+            updated_at: Utc::now(),
+        };
+        Self {
+            exchange,
+            rules,
+            parent,
+            algorithm,
+            limit_price: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            trade_source: None,
+            tracker: OrderTracker::default(),
+            children: Mutex::new(vec![]),
+            market_volume: Mutex::new(Decimal::ZERO),
+            changed: Notify::new(),
+            control: watch::channel(Control::Run).0,
+            progress: watch::channel(progress).0,
+        }
+    }
+
+    /// Children never buy above or sell below this price. They are limit
+    /// orders that do not rest, except for icebergs; without a limit price
+    /// they are market orders.
+    pub fn with_limit_price(mut self, limit_price: Decimal) -> Self {
+        self.limit_price = Some(limit_price);
+        self
+    }
+
+    /// How often to check on children between slices, defaults to a second.
+    /// Children nothing was heard about for that long are queried.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Polled for the market volume of [`Algorithm::Participation`], only
+    /// trades of complete seconds are counted. Use either this or
+    /// [`Executor::record_market_volume`].
+    pub fn with_trade_source(mut self, trade_source: Arc<dyn TradeSource + Send + Sync>) -> Self {
+        self.trade_source = Some(trade_source);
+        self
+    }
+
+    /// Client order IDs are the prefix followed by digits, MEXC accepts up to
+    /// 32 characters
+    pub fn with_client_order_id_prefix(mut self, prefix: &str) -> Self {
+        self.tracker = OrderTracker::new(prefix);
+        self
+    }
+
+    pub fn parent(&self) -> &ParentOrder {
+        &self.parent
+    }
+
+    pub fn tracker(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    pub fn progress(&self) -> ExecutionProgress {
+        self.progress
+            .borrow()
+            .clone()
+    }
+
+    /// Sees the progress after every change
+    pub fn watch(&self) -> watch::Receiver<ExecutionProgress> {
+        self.progress
+            .subscribe()
+    }
+
+    /// Cancels the working child and places no more until resumed. The time
+    /// spent paused does not count towards the schedule, and what the child
+    /// left is placed again once resumed.
+    pub fn pause(&self) {
+        self.control
+            .send_if_modified(
+                |control| {
+                    set_control(
+                        control,
+                        Control::Pause,
+                    )
+                },
+            );
+    }
+
+    pub fn resume(&self) {
+        self.control
+            .send_if_modified(
+                |control| {
+                    set_control(
+                        control,
+                        Control::Run,
+                    )
+                },
+            );
+    }
+
+    /// Cancels the working child and ends the execution
+    pub fn cancel(&self) {
+        self.control
+            .send_if_modified(
+                |control| {
+                    set_control(
+                        control,
+                        Control::Cancel,
+                    )
+                },
+            );
+    }
+
+    /// Applies an order update of a child, returning whether it was one
+    pub fn apply_order(&self, order: &Order) -> bool {
+        let applied = self
+            .tracker
+            .apply_order(order);
+        if applied {
+            self.changed
+                .notify_one();
+        }
+        applied
+    }
+
+    /// Applies a fill of a child, returning whether it was one
+    pub fn apply_fill(&self, client_order_id: Option<&str>, fill: &Fill) -> bool {
+        let applied = self
+            .tracker
+            .apply_fill(
+                client_order_id,
+                fill,
+            );
+        if applied {
+            self.changed
+                .notify_one();
+        }
+        applied
+    }
+
+    /// Adds public trades of the symbol to the market volume of
+    /// [`Algorithm::Participation`]
+    pub fn record_market_volume(&self, quantity: Decimal) {
+        *self
+            .market_volume
+            .lock()
+            .unwrap() += quantity;
+        self.changed
+            .notify_one();
+    }
+
+    fn children(&self) -> Vec<OrderState> {
+        self.children
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(
+                |client_order_id| {
+                    self.tracker
+                        .get(client_order_id)
+                },
+            )
+            .collect()
+    }
+
+    fn publish(&self, status: ExecutionStatus) -> ExecutionProgress {
+        let children = self.children();
+        let executed_quantity = children
+            .iter()
+            .map(|child| child.executed_quantity)
+            .sum::<Decimal>();
+        let executed_quote_quantity = children
+            .iter()
+            .filter_map(|child| Some(child.average_price()? * child.executed_quantity))
+            .sum::<Decimal>();
+        let progress = ExecutionProgress {
+            status,
+            quantity: self
+                .parent
+                .quantity,
+            executed_quantity,
+            working_quantity: children
+                .iter()
+                .filter(|child| !child.is_terminal())
+                .map(|child| (child.quantity - child.executed_quantity).max(Decimal::ZERO))
+                .sum(),
+            average_price: match executed_quantity.is_zero() {
+                true => None,
+                false => Some(executed_quote_quantity / executed_quantity),
+            },
+            child_orders: children.len(),
+            updated_at: Utc::now(),
+        };
+        // Watchers only wake up for changes
+        self.progress
+            .send_if_modified(
+                |current| {
+                    let changed = ExecutionProgress {
+                        updated_at: current.updated_at,
+                        ..progress.clone()
+                    } != *current;
+                    if changed {
+                        *current = progress.clone();
+                    }
+                    changed
+                },
+            );
+        progress
+    }
+
+    fn due(&self, elapsed: Duration, weights: &[Decimal]) -> Due {
+        let quantity = self
+            .parent
+            .quantity;
+        match &self.algorithm {
+            Algorithm::Twap {
+                duration,
+                ..
+            }
+            | Algorithm::Vwap {
+                duration,
+                ..
+            } => {
+                let slices = weights.len() as u32;
+                let step = *duration / slices;
+                let slice = match step.is_zero() {
+                    true => slices - 1,
+                    false => ((elapsed.as_nanos() / step.as_nanos()) as u32).min(slices - 1),
+                };
+                let share = weights[..=slice as usize]
+                    .iter()
+                    .sum::<Decimal>();
+                let last_slice = slice + 1 == slices;
+                Due {
+                    target: match last_slice {
+                        true => quantity,
+                        false => (quantity * share).min(quantity),
+                    },
+                    slice: Some(slice),
+                    last_slice,
+                    wake_in: match last_slice {
+                        true => self.poll_interval,
+                        false => (step * (slice + 1))
+                            .saturating_sub(elapsed)
+                            .min(self.poll_interval),
+                    },
+                }
+            }
+            Algorithm::Participation {
+                rate,
+                interval,
+            } => Due {
+                target: (*self
+                    .market_volume
+                    .lock()
+                    .unwrap()
+                    * rate)
+                    .min(quantity),
+                slice: None,
+                last_slice: false,
+                wake_in: *interval,
+            },
+            Algorithm::Iceberg {
+                ..
+            } => Due {
+                target: quantity,
+                slice: None,
+                last_slice: false,
+                wake_in: self.poll_interval,
+            },
+        }
+    }
+}
+
+fn set_control(control: &mut Control, new: Control) -> bool {
+    // Canceling is final
+    if *control == Control::Cancel || *control == new {
+        return false;
+    }
+    *control = new;
+    true
+}
+
+impl<E> Executor<E>
+where
+    E: Exchange + Send + Sync,
+{
+    /// Runs the schedule until everything executed, it expired or it was
+    /// canceled. Fails when a child could not be placed, after canceling the
+    /// working ones. A placement with an unknown outcome is looked up by its
+    /// client order ID first.
+    pub async fn run(&self) -> ExchangeResult<ExecutionProgress> {
+        let side = self
+            .parent
+            .side;
+        let limit_price = self
+            .limit_price
+            .map(
+                |price| {
+                    self.rules
+                        .round_price(
+                            price, side,
+                        )
+                },
+            );
+        let weights = match &self.algorithm {
+            Algorithm::Twap {
+                slices,
+                ..
+            } => equal_weights(*slices),
+            Algorithm::Vwap {
+                duration,
+                slices,
+                profile,
+            } => profile.slice_weights(
+                Utc::now(),
+                *duration,
+                *slices,
+            ),
+            Algorithm::Participation {
+                ..
+            } => vec![],
+            Algorithm::Iceberg {
+                display_quantity,
+            } => {
+                if limit_price.is_none() {
+                    return Err(ExchangeError::Missing("Limit price"));
+                }
+                if self
+                    .rules
+                    .truncate_quantity(*display_quantity)
+                    < self
+                        .rules
+                        .min_quantity(limit_price)
+                {
+                    return Err(ExchangeError::Rejected("The display quantity is below the minimum order size".to_string()));
+                }
+                vec![]
+            }
+        };
+        let min_quantity = self
+            .rules
+            .min_quantity(limit_price);
+        let mut control = self
+            .control
+            .subscribe();
+        let mut active = Duration::ZERO;
+        let mut resumed_at = None;
+        let mut placed_slice = None;
+        let mut trades_cursor = Utc::now();
+
+        loop {
+            let state = *control.borrow_and_update();
+            match state {
+                Control::Cancel => {
+                    self.cancel_children()
+                        .await;
+                    return Ok(self.publish(ExecutionStatus::Canceled));
+                }
+                Control::Pause => {
+                    if let Some(resumed_at) = resumed_at.take() {
+                        active += Instant::now() - resumed_at;
+                        // What the canceled child left of its slice is placed
+                        // again on resume, the last slice included
+                        if !self
+                            .children()
+                            .iter()
+                            .all(OrderState::is_terminal)
+                        {
+                            placed_slice = None;
+                        }
+                        self.cancel_children()
+                            .await;
+                    }
+                    // Fills of the canceled child still show up
+                    self.publish(ExecutionStatus::Paused);
+                    tokio::select! {
+                        _ = control.changed() => {}
+                        _ = self.changed.notified() => {}
+                    }
+                    continue;
+                }
+                Control::Run => {}
+            }
+            let resumed_at = *resumed_at.get_or_insert_with(Instant::now);
+            let elapsed = active + (Instant::now() - resumed_at);
+
+            if let (
+                Algorithm::Participation {
+                    ..
+                },
+                Some(source),
+            ) = (
+                &self.algorithm,
+                &self.trade_source,
+            ) {
+                self.poll_trades(
+                    source.as_ref(),
+                    &mut trades_cursor,
+                )
+                .await;
+            }
+
+            let progress = self.publish(ExecutionStatus::Running);
+            let due = self.due(
+                elapsed, &weights,
+            );
+            let idle = progress
+                .working_quantity
+                .is_zero()
+                && self
+                    .children()
+                    .iter()
+                    .all(OrderState::is_terminal);
+            let remaining = progress.remaining_quantity();
+            if idle && (remaining.is_zero() || remaining < min_quantity) {
+                return Ok(self.publish(ExecutionStatus::Completed));
+            }
+            if idle && due.last_slice && placed_slice == due.slice {
+                return Ok(self.publish(ExecutionStatus::Expired));
+            }
+
+            let mut quantity = self
+                .rules
+                .truncate_quantity(due.target - progress.executed_quantity);
+            if let Algorithm::Iceberg {
+                display_quantity,
+            } = &self.algorithm
+            {
+                quantity = quantity.min(
+                    self.rules
+                        .truncate_quantity(*display_quantity),
+                );
+            }
+            let slice_open = due
+                .slice
+                .is_none()
+                || placed_slice != due.slice;
+            if idle && slice_open && quantity >= min_quantity && !quantity.is_zero() {
+                if let Err(err) = self
+                    .place_child(
+                        quantity,
+                        limit_price,
+                    )
+                    .await
+                {
+                    self.cancel_children()
+                        .await;
+                    self.publish(ExecutionStatus::Failed);
+                    return Err(err);
+                }
+                placed_slice = due.slice;
+                continue;
+            }
+
+            let timed_out = tokio::select! {
+                _ = tokio::time::sleep(due.wake_in) => true,
+                _ = self.changed.notified() => false,
+                _ = control.changed() => false,
+            };
+            if timed_out {
+                self.refresh_children()
+                    .await;
+            }
+        }
+    }
+
+    async fn place_child(&self, quantity: Decimal, limit_price: Option<Decimal>) -> ExchangeResult<()> {
+        let parent = &self.parent;
+        let client_order_id = self
+            .tracker
+            .next_client_order_id();
+        let mut params = match limit_price {
+            Some(price) => PlaceOrderParams::limit(
+                &parent.symbol,
+                parent.side,
+                price,
+                quantity,
+            )
+            .with_order_type(
+                match self.algorithm {
+                    Algorithm::Iceberg {
+                        ..
+                    } => OrderType::Limit,
+                    _ => OrderType::ImmediateOrCancel,
+                },
+            ),
+            None => PlaceOrderParams::market(
+                &parent.symbol,
+                parent.side,
+                quantity,
+            ),
+        }
+        .with_client_order_id(&client_order_id);
+        params.position_effect = parent.position_effect;
+        params.margin_mode = parent.margin_mode;
+        params.leverage = parent.leverage;
+
+        // Tracked before sending, pushes can arrive before the response
+        let mut state = OrderState::new(
+            client_order_id.clone(),
+            parent
+                .symbol
+                .clone(),
+            parent.side,
+            limit_price.unwrap_or_default(),
+            quantity,
+        );
+        state.position_effect = parent.position_effect;
+        self.tracker
+            .track(state);
+        self.children
+            .lock()
+            .unwrap()
+            .push(client_order_id.clone());
+        match self
+            .exchange
+            .place_order(params)
+            .await
+        {
+            Ok(output) => {
+                self.tracker
+                    .acknowledge(
+                        &client_order_id,
+                        &output.order_id,
+                    );
+                Ok(())
+            }
+            Err(err) if err.is_ambiguous() => match self
+                .resolve(
+                    &parent.symbol,
+                    &client_order_id,
+                )
+                .await
+            {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err(err),
+                // Left unresolved, canceling looks it up again
+                Err(query_err) => {
+                    tracing::warn!(
+                        "Failed to look up child order {}: {}",
+                        client_order_id,
+                        query_err
+                    );
+                    Err(err)
+                }
+            },
+            Err(err) => {
+                self.tracker
+                    .reject(
+                        &client_order_id,
+                        err.to_string(),
+                    );
+                Err(err)
+            }
+        }
+    }
+
+    /// Queries a child by its client order ID and applies the result. A child
+    /// that was never acknowledged and is unknown to the exchange is rejected.
+    async fn resolve(&self, symbol: &str, client_order_id: &str) -> ExchangeResult<Option<Order>> {
+        let order = self
+            .exchange
+            .order(
+                symbol,
+                client_order_id,
+            )
+            .await?;
+        match &order {
+            Some(order) => {
+                self.tracker
+                    .apply_order(order);
+            }
+            None => {
+                let acknowledged = self
+                    .tracker
+                    .get(client_order_id)
+                    .is_some_and(
+                        |child| {
+                            child
+                                .order_id
+                                .is_some()
+                        },
+                    );
+                if !acknowledged {
+                    self.tracker
+                        .reject(
+                            client_order_id,
+                            "Unknown to the exchange".to_string(),
+                        );
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    async fn refresh_children(&self) {
+        for child in self
+            .children()
+            .into_iter()
+            .filter(|child| !child.is_terminal())
+        {
+            if let Err(err) = self
+                .resolve(
+                    &child.symbol,
+                    &child.client_order_id,
+                )
+                .await
+            {
+                tracing::warn!(
+                    "Failed to query child order {}: {}",
+                    child.client_order_id,
+                    err
+                );
+            }
+        }
+    }
+
+    async fn cancel_children(&self) {
+        for child in self
+            .children()
+            .into_iter()
+            .filter(|child| !child.is_terminal())
+        {
+            let order_id = match child.order_id {
+                Some(order_id) => order_id,
+                // The placement had an unknown outcome
+                None => match self
+                    .resolve(
+                        &child.symbol,
+                        &child.client_order_id,
+                    )
+                    .await
+                {
+                    Ok(Some(order))
+                        if order
+                            .status
+                            .is_open() =>
+                    {
+                        order.order_id
+                    }
+                    Ok(_) => continue,
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to look up child order {}: {}",
+                            child.client_order_id,
+                            err
+                        );
+                        continue;
+                    }
+                },
+            };
+            if let Err(err) = self
+                .exchange
+                .cancel_order(
+                    CancelOrderParams {
+                        symbol: &child.symbol,
+                        order_id: &order_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(
+                    "Failed to cancel child order {}: {}",
+                    child.client_order_id,
+                    err
+                );
+            }
+        }
+    }
+
+    /// Counts the trades of complete seconds after the cursor, the trades of
+    /// the newest second are counted by the next poll
+    async fn poll_trades(&self, source: &(dyn TradeSource + Send + Sync), cursor: &mut DateTime<Utc>) {
+        let trades = match source
+            .recent_trades(
+                &self
+                    .parent
+                    .symbol,
+            )
+            .await
+        {
+            Ok(trades) => trades,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to poll trades of {}: {}",
+                    self.parent
+                        .symbol,
+                    err
+                );
+                return;
+            }
+        };
+        let Some(newest) = trades
+            .iter()
+            .map(|trade| trade.time)
+            .max()
+        else {
+            return;
+        };
+        let complete_before = newest
+            .with_nanosecond(0)
+            .unwrap_or(newest);
+        let volume = trades
+            .iter()
+            .filter(|trade| trade.time > *cursor && trade.time < complete_before)
+            .map(|trade| trade.quantity)
+            .sum::<Decimal>();
+        *self
+            .market_volume
+            .lock()
+            .unwrap() += volume;
+        *cursor = (*cursor).max(complete_before - chrono::Duration::nanoseconds(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Balance, Instrument, OrderStatus, PlaceOrderOutput, Position};
+    use chrono::TimeZone;
+    use std::str::FromStr;
+    use tokio::sync::mpsc;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    struct Rules;
+
+    impl InstrumentRules for Rules {
+        fn round_price(&self, price: Decimal, _side: Side) -> Decimal {
+            price
+        }
+
+        fn truncate_quantity(&self, quantity: Decimal) -> Decimal {
+            quantity.round_dp_with_strategy(
+                1,
+                rust_decimal::RoundingStrategy::ToZero,
+            )
+        }
+
+        fn min_quantity(&self, _price: Option<Decimal>) -> Decimal {
+            dec("0.1")
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Request {
+        Place { client_order_id: String, order_type: OrderType, quantity: Decimal },
+        Cancel { order_id: String },
+    }
+
+    /// Every placed order stays open until a test changes it
+    struct TestExchange {
+        requests: mpsc::UnboundedSender<Request>,
+        orders: Mutex<BTreeMap<String, Order>>,
+        /// The response to the next placement is lost
+        lose_response: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl Exchange for TestExchange {
+        fn venue(&self) -> Venue {
+            Venue::Spot
+        }
+
+        async fn place_order(&self, params: PlaceOrderParams<'_>) -> ExchangeResult<PlaceOrderOutput> {
+            let client_order_id = params
+                .client_order_id
+                .unwrap()
+                .to_string();
+            let quantity = params
+                .quantity
+                .unwrap();
+            self.requests
+                .send(
+                    Request::Place {
+                        client_order_id: client_order_id.clone(),
+                        order_type: params.order_type,
+                        quantity,
+                    },
+                )
+                .unwrap();
+            self.orders
+                .lock()
+                .unwrap()
+                .insert(
+                    client_order_id.clone(),
+                    update(
+                        &client_order_id,
+                        quantity,
+                        "0",
+                        OrderStatus::Open,
+                    ),
+                );
+            #[cfg(feature = "spot")]
+            if std::mem::take(
+                &mut *self
+                    .lose_response
+                    .lock()
+                    .unwrap(),
+            ) {
+                return Err(ExchangeError::Spot(crate::spot::v3::ApiError::InternalServerError));
+            }
+            Ok(
+                PlaceOrderOutput {
+                    instrument: Instrument {
+                        venue: Venue::Spot,
+                        symbol: params
+                            .symbol
+                            .to_string(),
+                    },
+                    order_id: client_order_id,
+                },
+            )
+        }
+
+        async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ExchangeResult<()> {
+            self.requests
+                .send(
+                    Request::Cancel {
+                        order_id: params
+                            .order_id
+                            .to_string(),
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+
+        async fn open_orders(&self, _symbol: &str) -> ExchangeResult<Vec<Order>> {
+            Ok(vec![])
+        }
+
+        async fn order(&self, _symbol: &str, client_order_id: &str) -> ExchangeResult<Option<Order>> {
+            Ok(
+                self.orders
+                    .lock()
+                    .unwrap()
+                    .get(client_order_id)
+                    .cloned(),
+            )
+        }
+
+        async fn balances(&self) -> ExchangeResult<Vec<Balance>> {
+            Ok(vec![])
+        }
+
+        async fn positions(&self, _symbol: Option<&str>) -> ExchangeResult<Vec<Position>> {
+            Ok(vec![])
+        }
+    }
+
+    fn executor(
+        parent: ParentOrder,
+        algorithm: Algorithm,
+    ) -> (
+        Arc<Executor<TestExchange>>,
+        mpsc::UnboundedReceiver<Request>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let executor = Executor::new(
+            Arc::new(
+                TestExchange {
+                    requests: tx,
+                    orders: Mutex::new(BTreeMap::new()),
+                    lose_response: Mutex::new(false),
+                },
+            ),
+            Arc::new(Rules),
+            parent,
+            algorithm,
+        )
+        .with_limit_price(dec("100"))
+        .with_poll_interval(Duration::from_millis(5));
+        (
+            Arc::new(executor),
+            rx,
+        )
+    }
+
+    /// The update the venue pushes for a child, with order and client order
+    /// IDs alike
+    fn update(client_order_id: &str, quantity: Decimal, executed: &str, status: OrderStatus) -> Order {
+        Order {
+            instrument: Instrument {
+                venue: Venue::Spot,
+                symbol: "BTCUSDT".to_string(),
+            },
+            order_id: client_order_id.to_string(),
+            client_order_id: Some(client_order_id.to_string()),
+            side: Side::Buy,
+            position_effect: None,
+            order_type: OrderType::Limit,
+            price: dec("100"),
+            quantity,
+            executed_quantity: dec(executed),
+            average_price: Some(dec("100")),
+            stop_price: None,
+            status,
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    async fn next_placed(
+        requests: &mut mpsc::UnboundedReceiver<Request>,
+    ) -> (
+        String,
+        OrderType,
+        Decimal,
+    ) {
+        match requests
+            .recv()
+            .await
+            .unwrap()
+        {
+            Request::Place {
+                client_order_id,
+                order_type,
+                quantity,
+            } => (
+                client_order_id,
+                order_type,
+                quantity,
+            ),
+            request => panic!("Expected an order, got {request:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn twap_slices_respect_precision() {
+        let (executor, mut requests) = executor(
+            ParentOrder::new(
+                "BTCUSDT",
+                Side::Buy,
+                dec("1"),
+            ),
+            Algorithm::Twap {
+                duration: Duration::from_millis(60),
+                slices: 3,
+            },
+        );
+        let run = tokio::spawn(
+            {
+                let executor = executor.clone();
+                async move {
+                    executor
+                        .run()
+                        .await
+                }
+            },
+        );
+
+        let mut quantities = vec![];
+        for _ in 0..3 {
+            let (client_order_id, order_type, quantity) = next_placed(&mut requests).await;
+            assert_eq!(
+                order_type,
+                OrderType::ImmediateOrCancel
+            );
+            quantities.push(quantity);
+            assert!(
+                executor.apply_order(
+                    &update(
+                        &client_order_id,
+                        quantity,
+                        &quantity.to_string(),
+                        OrderStatus::Filled,
+                    ),
+                )
+            );
+        }
+        assert_eq!(
+            quantities,
+            [
+                dec("0.3"),
+                dec("0.3"),
+                dec("0.4")
+            ]
+        );
+        let progress = run
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            progress.status,
+            ExecutionStatus::Completed
+        );
+        assert_eq!(
+            progress.executed_quantity,
+            dec("1")
+        );
+        assert_eq!(
+            progress.average_price,
+            Some(dec("100"))
+        );
+
+        let midnight = Utc
+            .with_ymd_and_hms(
+                2024, 1, 1, 0, 0, 0,
+            )
+            .unwrap();
+        let candle = |minute: i64, volume: &str| Candle {
+            open_time: midnight + chrono::Duration::minutes(minute),
+            open: Decimal::ONE,
+            high: Decimal::ONE,
+            low: Decimal::ONE,
+            close: Decimal::ONE,
+            volume: dec(volume),
+            quote_volume: dec(volume),
+        };
+        let profile = VolumeProfile::from_candles(
+            &[
+                candle(
+                    0, "1",
+                ),
+                candle(
+                    1, "3",
+                ),
+                candle(
+                    1440, "3",
+                ),
+            ],
+            CandleInterval::OneMinute,
+        );
+        assert_eq!(
+            profile.slice_weights(
+                midnight,
+                Duration::from_secs(120),
+                2
+            ),
+            [
+                dec("0.4"),
+                dec("0.6")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn twap_places_the_paused_last_slice_again() {
+        let (executor, mut requests) = executor(
+            ParentOrder::new(
+                "BTCUSDT",
+                Side::Buy,
+                dec("1"),
+            ),
+            Algorithm::Twap {
+                duration: Duration::from_millis(40),
+                slices: 2,
+            },
+        );
+        let run = tokio::spawn(
+            {
+                let executor = executor.clone();
+                async move {
+                    executor
+                        .run()
+                        .await
+                }
+            },
+        );
+
+        let (first, _, quantity) = next_placed(&mut requests).await;
+        executor.apply_order(
+            &update(
+                &first,
+                quantity,
+                "0.5",
+                OrderStatus::Filled,
+            ),
+        );
+        let (last, _, _) = next_placed(&mut requests).await;
+        executor.pause();
+        assert_eq!(
+            requests
+                .recv()
+                .await,
+            Some(
+                Request::Cancel {
+                    order_id: last.clone(),
+                },
+            )
+        );
+        executor.apply_order(
+            &update(
+                &last,
+                dec("0.5"),
+                "0.1",
+                OrderStatus::PartiallyCanceled,
+            ),
+        );
+        let mut watch = executor.watch();
+        watch
+            .wait_for(|progress| progress.status == ExecutionStatus::Paused && progress.executed_quantity == dec("0.6"))
+            .await
+            .unwrap();
+        // Paused for longer than the whole schedule
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        executor.resume();
+        let (rest, _, quantity) = next_placed(&mut requests).await;
+        assert_eq!(
+            quantity,
+            dec("0.4")
+        );
+        executor.apply_order(
+            &update(
+                &rest,
+                quantity,
+                "0.4",
+                OrderStatus::Filled,
+            ),
+        );
+        let progress = run
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            progress.status,
+            ExecutionStatus::Completed
+        );
+    }
+
+    #[tokio::test]
+    async fn iceberg_pauses_and_cancels() {
+        let (executor, mut requests) = executor(
+            ParentOrder::new(
+                "BTCUSDT",
+                Side::Buy,
+                dec("2"),
+            ),
+            Algorithm::Iceberg {
+                display_quantity: dec("0.5"),
+            },
+        );
+        let run = tokio::spawn(
+            {
+                let executor = executor.clone();
+                async move {
+                    executor
+                        .run()
+                        .await
+                }
+            },
+        );
+
+        let (first, order_type, quantity) = next_placed(&mut requests).await;
+        assert_eq!(
+            (order_type, quantity),
+            (
+                OrderType::Limit,
+                dec("0.5")
+            )
+        );
+        executor.apply_order(
+            &update(
+                &first,
+                quantity,
+                "0.5",
+                OrderStatus::Filled,
+            ),
+        );
+        let (second, _, _) = next_placed(&mut requests).await;
+
+        // Pausing pulls the displayed child
+        executor.pause();
+        assert_eq!(
+            requests
+                .recv()
+                .await,
+            Some(
+                Request::Cancel {
+                    order_id: second.clone(),
+                },
+            )
+        );
+        executor.apply_order(
+            &update(
+                &second,
+                dec("0.5"),
+                "0.2",
+                OrderStatus::PartiallyCanceled,
+            ),
+        );
+        let mut watch = executor.watch();
+        watch
+            .wait_for(|progress| progress.status == ExecutionStatus::Paused && progress.executed_quantity == dec("0.7"))
+            .await
+            .unwrap();
+
+        executor.resume();
+        let (third, _, quantity) = next_placed(&mut requests).await;
+        assert_eq!(
+            quantity,
+            dec("0.5")
+        );
+        executor.cancel();
+        assert_eq!(
+            requests
+                .recv()
+                .await,
+            Some(
+                Request::Cancel {
+                    order_id: third,
+                },
+            )
+        );
+        let progress = run
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            progress.status,
+            ExecutionStatus::Canceled
+        );
+        assert_eq!(
+            progress.remaining_quantity(),
+            dec("1.3")
+        );
+    }
+
+    #[cfg(feature = "spot")]
+    #[tokio::test]
+    async fn queries_children_without_pushes() {
+        let (executor, mut requests) = executor(
+            ParentOrder::new(
+                "BTCUSDT",
+                Side::Buy,
+                dec("1"),
+            ),
+            Algorithm::Twap {
+                duration: Duration::ZERO,
+                slices: 1,
+            },
+        );
+        *executor
+            .exchange
+            .lose_response
+            .lock()
+            .unwrap() = true;
+        let run = tokio::spawn(
+            {
+                let executor = executor.clone();
+                async move {
+                    executor
+                        .run()
+                        .await
+                }
+            },
+        );
+
+        // The placement failed ambiguously, the order is found by its client
+        // order ID and its fill by polling
+        let (client_order_id, _, quantity) = next_placed(&mut requests).await;
+        executor
+            .exchange
+            .orders
+            .lock()
+            .unwrap()
+            .insert(
+                client_order_id.clone(),
+                update(
+                    &client_order_id,
+                    quantity,
+                    "1",
+                    OrderStatus::Filled,
+                ),
+            );
+        let progress = run
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            progress.status,
+            ExecutionStatus::Completed
+        );
+        assert_eq!(
+            progress.child_orders,
+            1
+        );
+        assert_eq!(
+            progress.executed_quantity,
+            dec("1")
+        );
+    }
+}
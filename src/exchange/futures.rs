@@ -0,0 +1,660 @@
+#[cfg(feature = "ws")]
+use crate::{
+    exchange::Fill,
+    futures::ws::message::{PersonalAssetData, PersonalOrderData, PersonalOrderDealData, PersonalPositionData},
+};
+use crate::{
+    exchange::{
+        execution::InstrumentRules,
+        history::{Candle, CandleInterval, CandleSource},
+        Balance, CancelOrderParams, Exchange, ExchangeError, ExchangeResult, Instrument, MarginMode, Order, OrderStatus, OrderType, PlaceOrderOutput, PlaceOrderParams, Position, PositionEffect, PositionSide, Side, Venue,
+    },
+    futures::{
+        error::{ApiError, ErrorCode},
+        v1::{
+            contract_spec::ContractSpec,
+            endpoints::{
+                cancel_order::{self, CancelOrder},
+                get_account_assets::GetAccountAssets,
+                get_kline::{GetKline, GetKlineParams},
+                get_open_orders::{GetOpenOrders, GetOpenOrdersParams},
+                get_open_positions::GetOpenPositions,
+                get_order::GetOrder,
+                order::{self, OrderParams},
+            },
+            models::{self, AccountAsset, OpenOrder, OpenPosition, OpenType, OrderState, PositionType},
+        },
+        MexcFuturesApiClient, MexcFuturesApiClientWithAuthentication,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+const OPEN_ORDERS_PAGE_SIZE: u32 = 100;
+
+fn instrument(symbol: &str) -> Instrument {
+    Instrument {
+        venue: Venue::Futures,
+        symbol: symbol.to_string(),
+    }
+}
+
+fn to_order_side(side: Side, position_effect: PositionEffect) -> models::OrderSide {
+    match (
+        side,
+        position_effect,
+    ) {
+        (Side::Buy, PositionEffect::Open) => models::OrderSide::OpenLong,
+        (Side::Buy, PositionEffect::Close) => models::OrderSide::CloseShort,
+        (Side::Sell, PositionEffect::Open) => models::OrderSide::OpenShort,
+        (Side::Sell, PositionEffect::Close) => models::OrderSide::CloseLong,
+    }
+}
+
+fn from_order_side(
+    side: models::OrderSide,
+) -> (
+    Side,
+    PositionEffect,
+) {
+    match side {
+        models::OrderSide::OpenLong => (
+            Side::Buy,
+            PositionEffect::Open,
+        ),
+        models::OrderSide::CloseShort => (
+            Side::Buy,
+            PositionEffect::Close,
+        ),
+        models::OrderSide::OpenShort => (
+            Side::Sell,
+            PositionEffect::Open,
+        ),
+        models::OrderSide::CloseLong => (
+            Side::Sell,
+            PositionEffect::Close,
+        ),
+    }
+}
+
+impl From<models::OrderSide> for Side {
+    fn from(side: models::OrderSide) -> Self {
+        from_order_side(side).0
+    }
+}
+
+impl From<models::OrderSide> for PositionEffect {
+    fn from(side: models::OrderSide) -> Self {
+        from_order_side(side).1
+    }
+}
+
+impl TryFrom<OrderType> for models::OrderType {
+    type Error = ExchangeError;
+
+    fn try_from(order_type: OrderType) -> Result<Self, Self::Error> {
+        match order_type {
+            OrderType::Limit => Ok(models::OrderType::PriceLimitedOrder),
+            OrderType::Market => Ok(models::OrderType::MarketOrders),
+            OrderType::PostOnly => Ok(models::OrderType::PostOnlyMaker),
+            OrderType::ImmediateOrCancel => Ok(models::OrderType::TransactOrCancelInstantly),
+            OrderType::FillOrKill => Ok(models::OrderType::TransactCompletelyOrCancelCompletely),
+            OrderType::StopLimit | OrderType::TakeProfit => Err(
+                ExchangeError::Unsupported {
+                    venue: Venue::Futures,
+                    concept: "Stop order",
+                },
+            ),
+        }
+    }
+}
+
+/// Market orders converted to limit orders at the current price are reported
+/// as market orders
+impl From<models::OrderType> for OrderType {
+    fn from(order_type: models::OrderType) -> Self {
+        match order_type {
+            models::OrderType::PriceLimitedOrder => OrderType::Limit,
+            models::OrderType::PostOnlyMaker => OrderType::PostOnly,
+            models::OrderType::TransactOrCancelInstantly => OrderType::ImmediateOrCancel,
+            models::OrderType::TransactCompletelyOrCancelCompletely => OrderType::FillOrKill,
+            models::OrderType::MarketOrders | models::OrderType::ConvertMarketPriceToCurrentPrice => OrderType::Market,
+        }
+    }
+}
+
+impl From<MarginMode> for OpenType {
+    fn from(margin_mode: MarginMode) -> Self {
+        match margin_mode {
+            MarginMode::Isolated => OpenType::Isolated,
+            MarginMode::Cross => OpenType::Cross,
+        }
+    }
+}
+
+impl From<OpenType> for MarginMode {
+    fn from(open_type: OpenType) -> Self {
+        match open_type {
+            OpenType::Isolated => MarginMode::Isolated,
+            OpenType::Cross => MarginMode::Cross,
+        }
+    }
+}
+
+impl<'a> TryFrom<PlaceOrderParams<'a>> for OrderParams<'a> {
+    type Error = ExchangeError;
+
+    fn try_from(params: PlaceOrderParams<'a>) -> Result<Self, Self::Error> {
+        if params
+            .quote_quantity
+            .is_some()
+        {
+            return Err(
+                ExchangeError::Unsupported {
+                    venue: Venue::Futures,
+                    concept: "Quote quantity",
+                },
+            );
+        }
+        if params
+            .stop_price
+            .is_some()
+        {
+            return Err(
+                ExchangeError::Unsupported {
+                    venue: Venue::Futures,
+                    concept: "Stop price",
+                },
+            );
+        }
+        let position_effect = params
+            .position_effect
+            .ok_or(ExchangeError::Missing("Position effect"))?;
+        let volume = params
+            .quantity
+            .ok_or(ExchangeError::Missing("Quantity"))?;
+        let price = match params.order_type {
+            OrderType::Market => params
+                .price
+                .unwrap_or_default(),
+            _ => params
+                .price
+                .ok_or(ExchangeError::Missing("Price"))?,
+        };
+
+        Ok(
+            OrderParams {
+                symbol: params.symbol,
+                price,
+                volume,
+                leverage: params.leverage,
+                side: to_order_side(
+                    params.side,
+                    position_effect,
+                ),
+                order_type: params
+                    .order_type
+                    .try_into()?,
+                open_type: params
+                    .margin_mode
+                    .unwrap_or(MarginMode::Isolated)
+                    .into(),
+                position_id: None,
+                external_order_id: params.client_order_id,
+                stop_loss_price: None,
+                take_profit_price: None,
+                position_mode: None,
+                reduce_only: None,
+            },
+        )
+    }
+}
+
+fn order_status(state: OrderState, has_deals: bool) -> OrderStatus {
+    match (
+        state, has_deals,
+    ) {
+        (OrderState::Uninformed, _) | (OrderState::Uncompleted, false) => OrderStatus::Open,
+        (OrderState::Uncompleted, true) => OrderStatus::PartiallyFilled,
+        (OrderState::Completed, _) => OrderStatus::Filled,
+        (OrderState::Cancelled, false) => OrderStatus::Canceled,
+        (OrderState::Cancelled, true) => OrderStatus::PartiallyCanceled,
+        (OrderState::Invalid, _) => OrderStatus::Rejected,
+    }
+}
+
+impl From<&OpenOrder> for Order {
+    fn from(order: &OpenOrder) -> Self {
+        let (side, position_effect) = from_order_side(order.side);
+        let has_deals = !order
+            .deal_volume
+            .is_zero();
+        Self {
+            instrument: instrument(&order.symbol),
+            order_id: order
+                .order_id
+                .to_string(),
+            client_order_id: order
+                .external_order_id
+                .clone(),
+            side,
+            position_effect: Some(position_effect),
+            order_type: order
+                .order_type
+                .into(),
+            price: order.trigger_price,
+            quantity: order.trigger_volume,
+            executed_quantity: order.deal_volume,
+            average_price: match has_deals {
+                true => Some(order.deal_average_price),
+                false => None,
+            },
+            stop_price: None,
+            status: order_status(
+                order.state,
+                has_deals,
+            ),
+            created_at: order.create_time,
+            updated_at: order.update_time,
+        }
+    }
+}
+
+impl From<&AccountAsset> for Balance {
+    fn from(asset: &AccountAsset) -> Self {
+        Self {
+            venue: Venue::Futures,
+            asset: asset
+                .currency
+                .clone(),
+            free: asset.available_balance,
+            locked: asset.frozen_balance,
+            total: asset.available_balance + asset.frozen_balance + asset.position_margin,
+        }
+    }
+}
+
+impl From<&OpenPosition> for Position {
+    fn from(position: &OpenPosition) -> Self {
+        Self {
+            instrument: instrument(&position.symbol),
+            position_id: position
+                .position_id
+                .to_string(),
+            side: match position.position_type {
+                PositionType::Long => PositionSide::Long,
+                PositionType::Short => PositionSide::Short,
+            },
+            quantity: position.holding_volume,
+            entry_price: position.open_average_price,
+            liquidation_price: match position
+                .liquidate_price
+                .is_zero()
+            {
+                true => None,
+                false => Some(position.liquidate_price),
+            },
+            margin: position.initial_margin,
+            margin_mode: position
+                .open_type
+                .into(),
+            realized_profit: position.realised,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&PersonalAssetData> for Balance {
+    fn from(asset: &PersonalAssetData) -> Self {
+        Self {
+            venue: Venue::Futures,
+            asset: asset
+                .currency
+                .clone(),
+            free: asset.available_balance,
+            locked: asset.frozen_balance,
+            total: asset.available_balance + asset.frozen_balance + asset.position_margin,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&PersonalPositionData> for Position {
+    fn from(position: &PersonalPositionData) -> Self {
+        Self::from(&OpenPosition::from(position))
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&PersonalOrderData> for Order {
+    fn from(order: &PersonalOrderData) -> Self {
+        Self::from(&OpenOrder::from(order))
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&PersonalOrderDealData> for Fill {
+    fn from(deal: &PersonalOrderDealData) -> Self {
+        let (side, position_effect) = from_order_side(deal.side);
+        Self {
+            instrument: instrument(&deal.symbol),
+            order_id: deal
+                .order_id
+                .to_string(),
+            trade_id: deal
+                .id
+                .to_string(),
+            side,
+            position_effect: Some(position_effect),
+            price: deal.price,
+            quantity: deal.vol,
+            fee: deal.fee,
+            fee_asset: deal
+                .fee_currency
+                .clone(),
+            is_maker: !deal.is_taker,
+            time: deal.timestamp,
+        }
+    }
+}
+
+/// Quantities are volumes in contracts
+impl InstrumentRules for ContractSpec {
+    fn round_price(&self, price: Decimal, side: Side) -> Decimal {
+        ContractSpec::round_price(
+            self,
+            price,
+            match side {
+                Side::Buy => RoundingStrategy::ToNegativeInfinity,
+                Side::Sell => RoundingStrategy::ToPositiveInfinity,
+            },
+        )
+    }
+
+    fn truncate_quantity(&self, quantity: Decimal) -> Decimal {
+        self.truncate_volume(quantity)
+    }
+
+    fn min_quantity(&self, _price: Option<Decimal>) -> Decimal {
+        self.min_vol
+    }
+}
+
+impl From<CandleInterval> for models::KlineInterval {
+    fn from(interval: CandleInterval) -> Self {
+        match interval {
+            CandleInterval::OneMinute => models::KlineInterval::OneMinute,
+            CandleInterval::FiveMinutes => models::KlineInterval::FiveMinutes,
+            CandleInterval::FifteenMinutes => models::KlineInterval::FifteenMinutes,
+            CandleInterval::ThirtyMinutes => models::KlineInterval::ThirtyMinutes,
+            CandleInterval::OneHour => models::KlineInterval::OneHour,
+            CandleInterval::FourHours => models::KlineInterval::FourHours,
+            CandleInterval::EightHours => models::KlineInterval::EightHours,
+            CandleInterval::OneDay => models::KlineInterval::OneDay,
+            CandleInterval::OneWeek => models::KlineInterval::OneWeek,
+            CandleInterval::OneMonth => models::KlineInterval::OneMonth,
+        }
+    }
+}
+
+impl From<&models::Kline> for Candle {
+    fn from(kline: &models::Kline) -> Self {
+        Self {
+            open_time: kline.time,
+            open: kline.open,
+            high: kline.high,
+            low: kline.low,
+            close: kline.close,
+            volume: kline.volume,
+            quote_volume: kline.amount,
+        }
+    }
+}
+
+const MAX_KLINES_PER_REQUEST: u32 = 2000;
+
+async fn fetch_candles<C: GetKline + Sync>(client: &C, symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>> {
+    let output = client
+        .get_kline(
+            GetKlineParams {
+                symbol,
+                interval: interval.into(),
+                start: Some(start),
+                end: Some(end),
+            },
+        )
+        .await?;
+    Ok(
+        output
+            .klines
+            .iter()
+            .map(Candle::from)
+            .collect(),
+    )
+}
+
+#[async_trait]
+impl CandleSource for MexcFuturesApiClient {
+    fn venue(&self) -> Venue {
+        Venue::Futures
+    }
+
+    fn max_candles_per_request(&self) -> u32 {
+        MAX_KLINES_PER_REQUEST
+    }
+
+    async fn fetch_candles(&self, symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>> {
+        fetch_candles(
+            self, symbol, interval, start, end,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl CandleSource for MexcFuturesApiClientWithAuthentication {
+    fn venue(&self) -> Venue {
+        Venue::Futures
+    }
+
+    fn max_candles_per_request(&self) -> u32 {
+        MAX_KLINES_PER_REQUEST
+    }
+
+    async fn fetch_candles(&self, symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>> {
+        fetch_candles(
+            self, symbol, interval, start, end,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Exchange for MexcFuturesApiClientWithAuthentication {
+    fn venue(&self) -> Venue {
+        Venue::Futures
+    }
+
+    async fn place_order(&self, params: PlaceOrderParams<'_>) -> ExchangeResult<PlaceOrderOutput> {
+        let params = OrderParams::try_from(params)?;
+        let symbol = params.symbol;
+        let output = order::Order::order(
+            self, params,
+        )
+        .await?;
+        Ok(
+            PlaceOrderOutput {
+                instrument: instrument(symbol),
+                order_id: output
+                    .order_id
+                    .to_string(),
+            },
+        )
+    }
+
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ExchangeResult<()> {
+        let order_id = params
+            .order_id
+            .parse::<i64>()
+            .map_err(
+                |_| ExchangeError::Unmappable {
+                    concept: "order id",
+                    value: params
+                        .order_id
+                        .to_string(),
+                },
+            )?;
+        let output = CancelOrder::cancel_order(
+            self,
+            cancel_order::CancelOrderParams {
+                order_ids: &[order_id],
+            },
+        )
+        .await?;
+        match output
+            .results
+            .into_iter()
+            .find(|result| result.error_code != 0)
+        {
+            Some(result) => Err(
+                ExchangeError::Rejected(
+                    result
+                        .error_msg
+                        .unwrap_or_else(
+                            || {
+                                format!(
+                                    "error code {}",
+                                    result.error_code
+                                )
+                            },
+                        ),
+                ),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    async fn open_orders(&self, symbol: &str) -> ExchangeResult<Vec<Order>> {
+        let mut orders = Vec::new();
+        for page_num in 1.. {
+            let page = self
+                .get_open_orders(
+                    GetOpenOrdersParams {
+                        page_num,
+                        page_size: OPEN_ORDERS_PAGE_SIZE,
+                    },
+                )
+                .await?;
+            let is_last_page = page.len() < OPEN_ORDERS_PAGE_SIZE as usize;
+            orders.extend(
+                page.iter()
+                    .filter(|order| order.symbol == symbol)
+                    .map(Order::from),
+            );
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(orders)
+    }
+
+    async fn order(&self, symbol: &str, client_order_id: &str) -> ExchangeResult<Option<Order>> {
+        let result = self
+            .get_order_by_external_id(
+                symbol,
+                client_order_id,
+            )
+            .await;
+        match result {
+            Ok(order) => Ok(Some(Order::from(&order))),
+            Err(ApiError::ErrorResponse(response))
+                if matches!(
+                    response.code,
+                    ErrorCode::UnknownOrderSent | ErrorCode::NotFound
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn balances(&self) -> ExchangeResult<Vec<Balance>> {
+        let assets = self
+            .get_account_assets()
+            .await?;
+        Ok(
+            assets
+                .iter()
+                .map(Balance::from)
+                .collect(),
+        )
+    }
+
+    async fn positions(&self, symbol: Option<&str>) -> ExchangeResult<Vec<Position>> {
+        let positions = self
+            .get_open_positions(symbol)
+            .await?;
+        Ok(
+            positions
+                .iter()
+                .map(Position::from)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn converts_order_params() {
+        let params = OrderParams::try_from(
+            PlaceOrderParams::limit(
+                "BTC_USDT",
+                Side::Sell,
+                Decimal::ONE_HUNDRED,
+                Decimal::TEN,
+            )
+            .with_position_effect(PositionEffect::Close)
+            .with_margin_mode(MarginMode::Cross)
+            .with_client_order_id("my-order"),
+        )
+        .unwrap();
+        assert_eq!(
+            params.side,
+            models::OrderSide::CloseLong
+        );
+        assert_eq!(
+            params.open_type,
+            OpenType::Cross
+        );
+        assert_eq!(
+            params.external_order_id,
+            Some("my-order")
+        );
+        assert_eq!(
+            from_order_side(params.side),
+            (
+                Side::Sell,
+                PositionEffect::Close
+            )
+        );
+
+        assert!(
+            matches!(
+                OrderParams::try_from(
+                    PlaceOrderParams::market(
+                        "BTC_USDT",
+                        Side::Buy,
+                        Decimal::ONE
+                    )
+                ),
+                Err(ExchangeError::Missing("Position effect"))
+            )
+        );
+    }
+}
@@ -0,0 +1,710 @@
+//! Downloads kline history of any length by splitting a range into windows
+//! the venue accepts in one request, optionally caching the candles on disk so
+//! later runs only fetch what is new.
+
+use crate::exchange::{ExchangeError, ExchangeResult, Venue};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Months, Utc};
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("Exchange error: {0}")]
+    Exchange(#[from] ExchangeError),
+
+    #[error("Cache error: {0}")]
+    Cache(#[from] CacheError),
+}
+
+pub type HistoryResult<T> = Result<T, HistoryError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid cache file: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Candle {
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Base asset for spot, contracts for futures
+    pub volume: Decimal,
+    pub quote_volume: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    OneMinute,
+    FiveMinutes,
+    FifteenMinutes,
+    ThirtyMinutes,
+    OneHour,
+    FourHours,
+    /// Futures only
+    EightHours,
+    OneDay,
+    OneWeek,
+    OneMonth,
+}
+
+impl CandleInterval {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::FifteenMinutes => "15m",
+            CandleInterval::ThirtyMinutes => "30m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::FourHours => "4h",
+            CandleInterval::EightHours => "8h",
+            CandleInterval::OneDay => "1d",
+            CandleInterval::OneWeek => "1w",
+            CandleInterval::OneMonth => "1M",
+        }
+    }
+
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        match self {
+            CandleInterval::OneMinute => Some(Duration::minutes(1)),
+            CandleInterval::FiveMinutes => Some(Duration::minutes(5)),
+            CandleInterval::FifteenMinutes => Some(Duration::minutes(15)),
+            CandleInterval::ThirtyMinutes => Some(Duration::minutes(30)),
+            CandleInterval::OneHour => Some(Duration::hours(1)),
+            CandleInterval::FourHours => Some(Duration::hours(4)),
+            CandleInterval::EightHours => Some(Duration::hours(8)),
+            CandleInterval::OneDay => Some(Duration::days(1)),
+            CandleInterval::OneWeek => Some(Duration::weeks(1)),
+            CandleInterval::OneMonth => None,
+        }
+    }
+
+    /// The time `candles` intervals after `time`, months are calendar months
+    pub fn advance(&self, time: DateTime<Utc>, candles: u32) -> DateTime<Utc> {
+        match self.duration() {
+            Some(duration) => time + duration * candles as i32,
+            None => time
+                .checked_add_months(Months::new(candles))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+
+    fn retreat(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        match self.duration() {
+            Some(duration) => time - duration,
+            None => time
+                .checked_sub_months(Months::new(1))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        }
+    }
+}
+
+/// Parses the names returned by [`CandleInterval::name`]
+impl FromStr for CandleInterval {
+    type Err = ExchangeError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            CandleInterval::OneMinute,
+            CandleInterval::FiveMinutes,
+            CandleInterval::FifteenMinutes,
+            CandleInterval::ThirtyMinutes,
+            CandleInterval::OneHour,
+            CandleInterval::FourHours,
+            CandleInterval::EightHours,
+            CandleInterval::OneDay,
+            CandleInterval::OneWeek,
+            CandleInterval::OneMonth,
+        ]
+        .into_iter()
+        .find(|interval| interval.name() == name)
+        .ok_or_else(
+            || ExchangeError::Unmappable {
+                concept: "interval",
+                value: name.to_string(),
+            },
+        )
+    }
+}
+
+/// A venue serving klines for a bounded range per request
+#[async_trait]
+pub trait CandleSource {
+    fn venue(&self) -> Venue;
+
+    fn max_candles_per_request(&self) -> u32;
+
+    /// Candles opening within `start..end`, at most
+    /// [`CandleSource::max_candles_per_request`] of them
+    async fn fetch_candles(&self, symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    /// Sorted by open time without duplicates. The last candle can still be
+    /// open when the range reaches into the current interval.
+    pub candles: Vec<Candle>,
+    /// Ranges of closed intervals the venue returned no candle for
+    pub gaps: Vec<Range<DateTime<Utc>>>,
+    /// Requests sent to the venue, zero when everything came from the cache
+    pub requests: usize,
+}
+
+/// What a cache file holds for one symbol and interval. Covered ranges were
+/// fetched completely, so missing candles inside them are real gaps and are
+/// not fetched again.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct CacheFile {
+    covered: Vec<Range<DateTime<Utc>>>,
+    candles: Vec<Candle>,
+}
+
+impl CacheFile {
+    fn cover(&mut self, range: Range<DateTime<Utc>>) {
+        if range.is_empty() {
+            return;
+        }
+        self.covered
+            .push(range);
+        self.covered
+            .sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<DateTime<Utc>>> = Vec::with_capacity(
+            self.covered
+                .len(),
+        );
+        for range in self
+            .covered
+            .drain(..)
+        {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => {
+                    last.end = last
+                        .end
+                        .max(range.end)
+                }
+                _ => merged.push(range),
+            }
+        }
+        self.covered = merged;
+    }
+
+    fn uncovered(&self, range: Range<DateTime<Utc>>) -> Vec<Range<DateTime<Utc>>> {
+        let mut uncovered = vec![];
+        let mut start = range.start;
+        for covered in &self.covered {
+            if covered.end <= start {
+                continue;
+            }
+            if covered.start >= range.end {
+                break;
+            }
+            if covered.start > start {
+                uncovered.push(start..covered.start);
+            }
+            start = covered.end;
+        }
+        if start < range.end {
+            uncovered.push(start..range.end);
+        }
+        uncovered
+    }
+}
+
+pub struct HistoryFetcher<S> {
+    source: Arc<S>,
+    concurrency: usize,
+    request_interval: std::time::Duration,
+    cache_dir: Option<PathBuf>,
+    pacer: tokio::sync::Mutex<Option<tokio::time::Instant>>,
+}
+
+impl<S> HistoryFetcher<S> {
+    /// Defaults to 4 requests in flight, started at most 10 per second, which
+    /// stays well below the market data limits of both venues
+    pub fn new(source: Arc<S>) -> Self {
+        Self {
+            source,
+            concurrency: 4,
+            request_interval: std::time::Duration::from_millis(100),
+            cache_dir: None,
+            pacer: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Minimum time between starting two requests
+    pub fn with_request_interval(mut self, request_interval: std::time::Duration) -> Self {
+        self.request_interval = request_interval;
+        self
+    }
+
+    /// Keeps one file per venue, symbol and interval in `cache_dir`
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    async fn pace(&self) {
+        let mut next_request_at = self
+            .pacer
+            .lock()
+            .await;
+        if let Some(next_request_at) = *next_request_at {
+            tokio::time::sleep_until(next_request_at).await;
+        }
+        *next_request_at = Some(tokio::time::Instant::now() + self.request_interval);
+    }
+}
+
+impl<S> HistoryFetcher<S>
+where
+    S: CandleSource + Send + Sync,
+{
+    /// Candles opening within `range`. With a cache, only ranges not fetched
+    /// by an earlier call are requested. Windows fetched before a request
+    /// failed are still cached, so a retry continues where it stopped.
+    pub async fn fetch(&self, symbol: &str, interval: CandleInterval, range: Range<DateTime<Utc>>) -> HistoryResult<History> {
+        self.fetch_at(
+            symbol,
+            interval,
+            range,
+            Utc::now(),
+        )
+        .await
+    }
+
+    async fn fetch_at(&self, symbol: &str, interval: CandleInterval, range: Range<DateTime<Utc>>, now: DateTime<Utc>) -> HistoryResult<History> {
+        let cache_path = self
+            .cache_dir
+            .as_ref()
+            .map(
+                |cache_dir| {
+                    let venue = match self
+                        .source
+                        .venue()
+                    {
+                        Venue::Spot => "spot",
+                        Venue::Futures => "futures",
+                    };
+                    cache_dir.join(
+                        format!(
+                            "{}-{}-{}.json",
+                            venue,
+                            symbol,
+                            interval.name()
+                        ),
+                    )
+                },
+            );
+        let mut cache = match &cache_path {
+            Some(path) => read_cache(path).await?,
+            None => CacheFile::default(),
+        };
+
+        let windows = cache
+            .uncovered(range.clone())
+            .into_iter()
+            .flat_map(
+                |uncovered| {
+                    windows(
+                        uncovered,
+                        interval,
+                        self.source
+                            .max_candles_per_request(),
+                    )
+                },
+            )
+            .collect::<Vec<_>>();
+        let requests = windows.len();
+        let results = futures::stream::iter(windows)
+            .map(
+                |window| async move {
+                    self.pace()
+                        .await;
+                    let candles = self
+                        .source
+                        .fetch_candles(
+                            symbol,
+                            interval,
+                            window.start,
+                            window.end,
+                        )
+                        .await;
+                    (
+                        window, candles,
+                    )
+                },
+            )
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        // Candles opening after this can still change
+        let closed_before = interval.retreat(now);
+        let mut candles = cache
+            .candles
+            .drain(..)
+            .map(
+                |candle| {
+                    (
+                        candle.open_time,
+                        candle,
+                    )
+                },
+            )
+            .collect::<BTreeMap<_, _>>();
+        let mut open_candles = vec![];
+        let mut error = None;
+        for (window, result) in results {
+            match result {
+                Ok(fetched) => {
+                    for candle in fetched {
+                        if !window.contains(&candle.open_time) {
+                            continue;
+                        }
+                        if candle.open_time < closed_before {
+                            candles.insert(
+                                candle.open_time,
+                                candle,
+                            );
+                        } else {
+                            open_candles.push(candle);
+                        }
+                    }
+                    cache.cover(
+                        window.start
+                            ..window
+                                .end
+                                .min(closed_before),
+                    );
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to fetch {} klines {}..{}: {}",
+                        symbol,
+                        window.start,
+                        window.end,
+                        err
+                    );
+                    error.get_or_insert(err);
+                }
+            }
+        }
+        cache.candles = candles
+            .into_values()
+            .collect();
+        if let Some(path) = &cache_path {
+            if requests > 0 {
+                write_cache(
+                    path, &cache,
+                )
+                .await?;
+            }
+        }
+        if let Some(err) = error {
+            return Err(err.into());
+        }
+
+        let mut candles = cache
+            .candles
+            .into_iter()
+            .filter(|candle| range.contains(&candle.open_time))
+            .collect::<Vec<_>>();
+        open_candles.sort_by_key(|candle| candle.open_time);
+        for candle in open_candles {
+            if candles
+                .last()
+                .is_none_or(|last| last.open_time < candle.open_time)
+            {
+                candles.push(candle);
+            }
+        }
+        let gaps = gaps(
+            &candles,
+            interval,
+            range,
+            closed_before,
+        );
+        Ok(
+            History {
+                candles,
+                gaps,
+                requests,
+            },
+        )
+    }
+}
+
+fn windows(range: Range<DateTime<Utc>>, interval: CandleInterval, max_candles: u32) -> Vec<Range<DateTime<Utc>>> {
+    let mut windows = vec![];
+    let mut start = range.start;
+    while start < range.end {
+        let end = interval
+            .advance(
+                start,
+                max_candles.max(1),
+            )
+            .min(range.end);
+        windows.push(start..end);
+        start = end;
+    }
+    windows
+}
+
+fn gaps(candles: &[Candle], interval: CandleInterval, range: Range<DateTime<Utc>>, closed_before: DateTime<Utc>) -> Vec<Range<DateTime<Utc>>> {
+    let mut gaps = vec![];
+    let mut expected = range.start;
+    for candle in candles {
+        // The range can start inside the interval of its first candle
+        if expected == range.start
+            && interval.advance(
+                expected, 1,
+            ) > candle.open_time
+        {
+            expected = interval.advance(
+                candle.open_time,
+                1,
+            );
+            continue;
+        }
+        if candle.open_time > expected {
+            gaps.push(expected..candle.open_time);
+        }
+        expected = interval.advance(
+            candle.open_time,
+            1,
+        );
+    }
+    let end = range
+        .end
+        .min(closed_before);
+    if expected < end {
+        gaps.push(expected..end);
+    }
+    gaps
+}
+
+async fn read_cache(path: &Path) -> Result<CacheFile, CacheError> {
+    match crate::fs::read_if_exists(path.to_path_buf()).await? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(CacheFile::default()),
+    }
+}
+
+async fn write_cache(path: &Path, cache: &CacheFile) -> Result<(), CacheError> {
+    crate::fs::write_atomic(
+        path.to_path_buf(),
+        serde_json::to_vec(cache)?,
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// Serves one minute candles from `start`, except for the missing ones,
+    /// and overlaps each response by one candle on both sides
+    struct TestSource {
+        start: DateTime<Utc>,
+        missing: Vec<DateTime<Utc>>,
+        requests: Mutex<Vec<Range<DateTime<Utc>>>>,
+    }
+
+    #[async_trait]
+    impl CandleSource for TestSource {
+        fn venue(&self) -> Venue {
+            Venue::Spot
+        }
+
+        fn max_candles_per_request(&self) -> u32 {
+            10
+        }
+
+        async fn fetch_candles(&self, _symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push(start..end);
+            let mut candles = vec![];
+            let mut open_time = (start - Duration::minutes(1)).max(self.start);
+            while open_time <= end {
+                if !self
+                    .missing
+                    .contains(&open_time)
+                {
+                    candles.push(candle(open_time));
+                }
+                open_time = interval.advance(
+                    open_time, 1,
+                );
+            }
+            // Newest first, as some endpoints return them
+            candles.reverse();
+            Ok(candles)
+        }
+    }
+
+    fn candle(open_time: DateTime<Utc>) -> Candle {
+        Candle {
+            open_time,
+            open: Decimal::ONE,
+            high: Decimal::ONE,
+            low: Decimal::ONE,
+            close: Decimal::ONE,
+            volume: Decimal::ONE,
+            quote_volume: Decimal::ONE,
+        }
+    }
+
+    fn minute(minute: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(
+            1_700_000_040,
+            0,
+        )
+        .unwrap()
+            + Duration::minutes(minute)
+    }
+
+    fn source() -> Arc<TestSource> {
+        Arc::new(
+            TestSource {
+                start: minute(0),
+                missing: vec![
+                    minute(3),
+                    minute(4),
+                    minute(25),
+                ],
+                requests: Mutex::new(vec![]),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn fetches_windows_and_reports_gaps() {
+        let source = source();
+        let fetcher = HistoryFetcher::new(source.clone()).with_request_interval(std::time::Duration::ZERO);
+        let history = fetcher
+            .fetch_at(
+                "BTCUSDT",
+                CandleInterval::OneMinute,
+                minute(0)..minute(35),
+                minute(32),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            history.requests,
+            4
+        );
+        let open_times = history
+            .candles
+            .iter()
+            .map(|candle| candle.open_time)
+            .collect::<Vec<_>>();
+        let expected = (0..35)
+            .map(minute)
+            .filter(
+                |open_time| {
+                    !source
+                        .missing
+                        .contains(open_time)
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            open_times,
+            expected
+        );
+        assert_eq!(
+            history.gaps,
+            vec![
+                minute(3)..minute(5),
+                minute(25)..minute(26)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_ranges_are_not_fetched_again() {
+        let cache_dir = std::env::temp_dir().join(
+            format!(
+                "mexc-rs-history-{}",
+                Uuid::new_v4()
+            ),
+        );
+        let source = source();
+        let fetcher = HistoryFetcher::new(source.clone())
+            .with_request_interval(std::time::Duration::ZERO)
+            .with_cache_dir(&cache_dir);
+        let first = fetcher
+            .fetch_at(
+                "BTCUSDT",
+                CandleInterval::OneMinute,
+                minute(0)..minute(20),
+                minute(40),
+            )
+            .await
+            .unwrap();
+        let second = fetcher
+            .fetch_at(
+                "BTCUSDT",
+                CandleInterval::OneMinute,
+                minute(5)..minute(30),
+                minute(40),
+            )
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+
+        assert_eq!(
+            first.requests,
+            2
+        );
+        assert_eq!(
+            second.requests,
+            1
+        );
+        assert_eq!(
+            source
+                .requests
+                .lock()
+                .unwrap()
+                .last(),
+            Some(&(minute(20)..minute(30)))
+        );
+        assert_eq!(
+            second
+                .candles
+                .len(),
+            24
+        );
+        assert_eq!(
+            second.gaps,
+            vec![minute(25)..minute(26)]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! A venue-agnostic view of trading, so strategy code can be written once for
+//! spot and futures.
+//!
+//! Quantities are in the unit the venue trades in: base asset for spot,
+//! contracts for futures. Concepts that only exist on one venue, like position
+//! effects and leverage, are rejected with [`ExchangeError::Unsupported`]
+//! elsewhere instead of being dropped.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+pub mod account_state;
+pub mod bars;
+pub mod execution;
+#[cfg(feature = "futures")]
+mod futures;
+pub mod history;
+pub mod order_tracker;
+#[cfg(feature = "spot")]
+mod spot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+pub enum Venue {
+    Spot,
+    Futures,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct Instrument {
+    pub venue: Venue,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// Whether a futures order opens or closes a position. Buying to close closes
+/// a short, selling to close closes a long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum PositionEffect {
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum MarginMode {
+    Isolated,
+    Cross,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum OrderType {
+    Limit,
+    Market,
+    PostOnly,
+    ImmediateOrCancel,
+    FillOrKill,
+    /// Spot only, a limit order placed once the price reaches the stop price
+    StopLimit,
+    /// Spot only, like [`OrderType::StopLimit`] but triggered from the other
+    /// side of the stop price. Pushes tell it apart by the trigger direction,
+    /// REST queries report it as [`OrderType::StopLimit`].
+    TakeProfit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum OrderStatus {
+    Open,
+    /// Stop-limit and take-profit orders only, the stop price was reached and
+    /// the limit order was placed
+    Triggered,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    PartiallyCanceled,
+    Rejected,
+}
+
+impl OrderStatus {
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Open | OrderStatus::Triggered | OrderStatus::PartiallyFilled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+#[derive(Debug)]
+pub struct PlaceOrderParams<'a> {
+    pub symbol: &'a str,
+    pub side: Side,
+    pub order_type: OrderType,
+    /// Base asset for spot, contracts for futures
+    pub quantity: Option<Decimal>,
+    /// Amount of the quote asset to spend or receive, spot market orders only
+    pub quote_quantity: Option<Decimal>,
+    /// Required except for market orders
+    pub price: Option<Decimal>,
+    pub client_order_id: Option<&'a str>,
+    /// Stop-limit and take-profit orders only, and required there
+    pub stop_price: Option<Decimal>,
+    /// Futures only, and required there
+    pub position_effect: Option<PositionEffect>,
+    /// Futures only, defaults to isolated
+    pub margin_mode: Option<MarginMode>,
+    /// Futures only
+    pub leverage: Option<u32>,
+}
+
+impl<'a> PlaceOrderParams<'a> {
+    pub fn limit(symbol: &'a str, side: Side, price: Decimal, quantity: Decimal) -> Self {
+        Self {
+            symbol,
+            side,
+            order_type: OrderType::Limit,
+            quantity: Some(quantity),
+            quote_quantity: None,
+            price: Some(price),
+            client_order_id: None,
+            stop_price: None,
+            position_effect: None,
+            margin_mode: None,
+            leverage: None,
+        }
+    }
+
+    pub fn market(symbol: &'a str, side: Side, quantity: Decimal) -> Self {
+        Self {
+            order_type: OrderType::Market,
+            price: None,
+            ..Self::limit(
+                symbol,
+                side,
+                Decimal::ZERO,
+                quantity,
+            )
+        }
+    }
+
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    pub fn with_client_order_id(mut self, client_order_id: &'a str) -> Self {
+        self.client_order_id = Some(client_order_id);
+        self
+    }
+
+    pub fn with_stop_price(mut self, stop_price: Decimal) -> Self {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    pub fn with_position_effect(mut self, position_effect: PositionEffect) -> Self {
+        self.position_effect = Some(position_effect);
+        self
+    }
+
+    pub fn with_margin_mode(mut self, margin_mode: MarginMode) -> Self {
+        self.margin_mode = Some(margin_mode);
+        self
+    }
+
+    pub fn with_leverage(mut self, leverage: u32) -> Self {
+        self.leverage = Some(leverage);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PlaceOrderOutput {
+    pub instrument: Instrument,
+    pub order_id: String,
+}
+
+#[derive(Debug)]
+pub struct CancelOrderParams<'a> {
+    pub symbol: &'a str,
+    pub order_id: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Order {
+    pub instrument: Instrument,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub side: Side,
+    /// Always `None` for spot
+    pub position_effect: Option<PositionEffect>,
+    pub order_type: OrderType,
+    /// Zero for market orders
+    pub price: Decimal,
+    /// Base asset for spot, contracts for futures
+    pub quantity: Decimal,
+    pub executed_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+    /// Stop-limit and take-profit orders only
+    pub stop_price: Option<Decimal>,
+    pub status: OrderStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    pub instrument: Instrument,
+    pub order_id: String,
+    pub trade_id: String,
+    pub side: Side,
+    pub position_effect: Option<PositionEffect>,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub fee: Decimal,
+    pub fee_asset: String,
+    pub is_maker: bool,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Balance {
+    pub venue: Venue,
+    pub asset: String,
+    pub free: Decimal,
+    pub locked: Decimal,
+    /// `free + locked` for spot, plus the position margin for futures.
+    /// Unrealized profit is left out, as pushes don't carry it.
+    pub total: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Position {
+    pub instrument: Instrument,
+    pub position_id: String,
+    pub side: PositionSide,
+    /// In contracts
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub liquidation_price: Option<Decimal>,
+    pub margin: Decimal,
+    pub margin_mode: MarginMode,
+    pub realized_profit: Decimal,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExchangeError {
+    #[cfg(feature = "spot")]
+    #[error("Spot api error: {0}")]
+    Spot(#[from] crate::spot::v3::ApiError),
+
+    #[cfg(feature = "futures")]
+    #[error("Futures api error: {0}")]
+    Futures(#[from] crate::futures::error::ApiError),
+
+    #[error("{concept} is not supported on {venue:?}")]
+    Unsupported { venue: Venue, concept: &'static str },
+
+    #[error("{0} is required")]
+    Missing(&'static str),
+
+    #[error("Unknown {concept} {value}")]
+    Unmappable { concept: &'static str, value: String },
+
+    #[error("The order was rejected: {0}")]
+    Rejected(String),
+}
+
+impl ExchangeError {
+    /// Whether the request may have taken effect despite the error. Only error
+    /// responses and errors raised before sending are definite.
+    pub fn is_ambiguous(&self) -> bool {
+        match self {
+            #[cfg(feature = "spot")]
+            Self::Spot(err) => !matches!(
+                err,
+                crate::spot::v3::ApiError::ErrorResponse(_)
+            ),
+            #[cfg(feature = "futures")]
+            Self::Futures(err) => matches!(
+                err,
+                crate::futures::error::ApiError::ReqwestError(_)
+            ),
+            _ => false,
+        }
+    }
+}
+
+pub type ExchangeResult<T> = Result<T, ExchangeError>;
+
+#[async_trait]
+pub trait Exchange {
+    fn venue(&self) -> Venue;
+
+    async fn place_order(&self, params: PlaceOrderParams<'_>) -> ExchangeResult<PlaceOrderOutput>;
+
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ExchangeResult<()>;
+
+    async fn open_orders(&self, symbol: &str) -> ExchangeResult<Vec<Order>>;
+
+    /// Looks up an open or past order by the client order ID it was placed
+    /// with, `None` when the exchange doesn't know it
+    async fn order(&self, symbol: &str, client_order_id: &str) -> ExchangeResult<Option<Order>>;
+
+    async fn balances(&self) -> ExchangeResult<Vec<Balance>>;
+
+    /// Fails with [`ExchangeError::Unsupported`] on spot
+    async fn positions(&self, symbol: Option<&str>) -> ExchangeResult<Vec<Position>>;
+}
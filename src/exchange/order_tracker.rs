@@ -0,0 +1,607 @@
+use crate::exchange::{Fill, Order, OrderStatus, PositionEffect, Side};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::watch;
+
+/// What is known about an order placed through an order manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderState {
+    pub client_order_id: String,
+    /// `None` until the venue acknowledged the order
+    pub order_id: Option<String>,
+    pub symbol: String,
+    pub side: Side,
+    pub position_effect: Option<PositionEffect>,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub status: OrderStatus,
+    pub executed_quantity: Decimal,
+    /// Sum of price times quantity over the fills
+    pub executed_quote_quantity: Decimal,
+    /// As reported by the venue, used while no fills have been seen
+    pub reported_average_price: Option<Decimal>,
+    pub fills: Vec<Fill>,
+    /// Why the order was rejected, if it was
+    pub reject_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OrderState {
+    pub fn new(client_order_id: String, symbol: String, side: Side, price: Decimal, quantity: Decimal) -> Self {
+        Self {
+            client_order_id,
+            order_id: None,
+            symbol,
+            side,
+            position_effect: None,
+            price,
+            quantity,
+            status: OrderStatus::Open,
+            executed_quantity: Decimal::ZERO,
+            executed_quote_quantity: Decimal::ZERO,
+            reported_average_price: None,
+            fills: Vec::new(),
+            reject_reason: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn with_position_effect(mut self, position_effect: PositionEffect) -> Self {
+        self.position_effect = Some(position_effect);
+        self
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        !self
+            .status
+            .is_open()
+    }
+
+    pub fn average_price(&self) -> Option<Decimal> {
+        let fills_quantity = self
+            .fills
+            .iter()
+            .map(|fill| fill.quantity)
+            .sum::<Decimal>();
+        match fills_quantity.is_zero() {
+            true => self.reported_average_price,
+            false => Some(self.executed_quote_quantity / fills_quantity),
+        }
+    }
+
+    /// Fees paid so far, by asset
+    pub fn fees(&self) -> HashMap<String, Decimal> {
+        let mut fees = HashMap::new();
+        for fill in self
+            .fills
+            .iter()
+        {
+            *fees
+                .entry(
+                    fill.fee_asset
+                        .clone(),
+                )
+                .or_default() += fill.fee;
+        }
+        fees
+    }
+}
+
+/// Orders only move forward: open, then partially filled, then a terminal
+/// status that never changes again.
+fn status_rank(status: OrderStatus) -> u8 {
+    match status {
+        OrderStatus::Open => 0,
+        OrderStatus::Triggered => 1,
+        OrderStatus::PartiallyFilled => 2,
+        OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::PartiallyCanceled | OrderStatus::Rejected => 3,
+    }
+}
+
+fn transition(state: &mut OrderState, status: OrderStatus) -> bool {
+    if state.is_terminal() || status_rank(status) < status_rank(state.status) {
+        return false;
+    }
+    state.status = status;
+    true
+}
+
+/// A handle on a tracked order
+#[derive(Debug, Clone)]
+pub struct OrderHandle {
+    rx: watch::Receiver<OrderState>,
+}
+
+impl OrderHandle {
+    pub fn client_order_id(&self) -> String {
+        self.rx
+            .borrow()
+            .client_order_id
+            .clone()
+    }
+
+    pub fn state(&self) -> OrderState {
+        self.rx
+            .borrow()
+            .clone()
+    }
+
+    /// Resolves with the final state once the order is filled, canceled or
+    /// rejected. If the manager is dropped first, resolves with the last
+    /// known state.
+    pub async fn terminal(mut self) -> OrderState {
+        let terminal = self
+            .rx
+            .wait_for(OrderState::is_terminal)
+            .await
+            .map(|state| state.clone());
+        terminal.unwrap_or_else(
+            |_| {
+                self.rx
+                    .borrow()
+                    .clone()
+            },
+        )
+    }
+
+    /// Waits for the next change of the order, returns `None` once the
+    /// manager is dropped
+    pub async fn changed(&mut self) -> Option<OrderState> {
+        self.rx
+            .changed()
+            .await
+            .ok()?;
+        Some(
+            self.rx
+                .borrow_and_update()
+                .clone(),
+        )
+    }
+}
+
+/// The venue-agnostic state machine behind the spot and futures order
+/// managers. Orders are keyed by client order ID, events referring to an
+/// order ID are matched once the order was acknowledged.
+#[derive(Debug)]
+pub struct OrderTracker {
+    orders: Mutex<TrackedOrders>,
+    client_order_id_prefix: String,
+    client_order_id_base: u64,
+    last_client_order_id: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct TrackedOrders {
+    by_client_order_id: HashMap<String, watch::Sender<OrderState>>,
+    client_order_ids: HashMap<String, String>,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self::new("mexcrs")
+    }
+}
+
+impl OrderTracker {
+    pub fn new(client_order_id_prefix: &str) -> Self {
+        Self {
+            orders: Mutex::new(TrackedOrders::default()),
+            client_order_id_prefix: client_order_id_prefix.to_string(),
+            // Keeps IDs unique across restarts
+            client_order_id_base: Utc::now().timestamp_millis() as u64,
+            last_client_order_id: AtomicU64::new(0),
+        }
+    }
+
+    fn orders(&self) -> std::sync::MutexGuard<'_, TrackedOrders> {
+        self.orders
+            .lock()
+            .expect("Order tracker lock poisoned")
+    }
+
+    pub fn next_client_order_id(&self) -> String {
+        let id = self
+            .last_client_order_id
+            .fetch_add(
+                1,
+                Ordering::Relaxed,
+            )
+            + 1;
+        format!(
+            "{}{}{}",
+            self.client_order_id_prefix, self.client_order_id_base, id
+        )
+    }
+
+    pub fn track(&self, state: OrderState) -> OrderHandle {
+        let mut orders = self.orders();
+        if let Some(order_id) = state
+            .order_id
+            .clone()
+        {
+            orders
+                .client_order_ids
+                .insert(
+                    order_id,
+                    state
+                        .client_order_id
+                        .clone(),
+                );
+        }
+        let client_order_id = state
+            .client_order_id
+            .clone();
+        let (tx, rx) = watch::channel(state);
+        orders
+            .by_client_order_id
+            .insert(
+                client_order_id,
+                tx,
+            );
+        OrderHandle {
+            rx,
+        }
+    }
+
+    pub fn handle(&self, client_order_id: &str) -> Option<OrderHandle> {
+        self.orders()
+            .by_client_order_id
+            .get(client_order_id)
+            .map(
+                |tx| OrderHandle {
+                    rx: tx.subscribe(),
+                },
+            )
+    }
+
+    pub fn get(&self, client_order_id: &str) -> Option<OrderState> {
+        self.orders()
+            .by_client_order_id
+            .get(client_order_id)
+            .map(
+                |tx| {
+                    tx.borrow()
+                        .clone()
+                },
+            )
+    }
+
+    /// Orders that are not in a terminal state yet
+    pub fn open_orders(&self) -> Vec<OrderState> {
+        self.orders()
+            .by_client_order_id
+            .values()
+            .map(
+                |tx| {
+                    tx.borrow()
+                        .clone()
+                },
+            )
+            .filter(|state| !state.is_terminal())
+            .collect()
+    }
+
+    /// Stops tracking orders in a terminal state
+    pub fn prune_terminal(&self) {
+        let mut orders = self.orders();
+        let TrackedOrders {
+            by_client_order_id,
+            client_order_ids,
+        } = &mut *orders;
+        by_client_order_id.retain(
+            |_, tx| {
+                !tx.borrow()
+                    .is_terminal()
+            },
+        );
+        client_order_ids.retain(|_, client_order_id| by_client_order_id.contains_key(client_order_id));
+    }
+
+    fn modify(&self, client_order_id: Option<&str>, order_id: Option<&str>, f: impl FnOnce(&mut OrderState) -> bool) -> bool {
+        let orders = self.orders();
+        let client_order_id = match (
+            client_order_id,
+            order_id,
+        ) {
+            (Some(client_order_id), _)
+                if orders
+                    .by_client_order_id
+                    .contains_key(client_order_id) =>
+            {
+                client_order_id.to_string()
+            }
+            (_, Some(order_id)) => match orders
+                .client_order_ids
+                .get(order_id)
+            {
+                Some(client_order_id) => client_order_id.clone(),
+                None => return false,
+            },
+            _ => return false,
+        };
+        let Some(tx) = orders
+            .by_client_order_id
+            .get(&client_order_id)
+        else {
+            return false;
+        };
+        tx.send_if_modified(
+            |state| {
+                let modified = f(state);
+                if modified {
+                    state.updated_at = Utc::now();
+                }
+                modified
+            },
+        )
+    }
+
+    /// Records the ID the venue assigned to the order
+    pub fn acknowledge(&self, client_order_id: &str, order_id: &str) {
+        self.orders()
+            .client_order_ids
+            .insert(
+                order_id.to_string(),
+                client_order_id.to_string(),
+            );
+        self.modify(
+            Some(client_order_id),
+            None,
+            |state| {
+                let modified = state
+                    .order_id
+                    .as_deref()
+                    != Some(order_id);
+                state.order_id = Some(order_id.to_string());
+                modified
+            },
+        );
+    }
+
+    pub fn reject(&self, client_order_id: &str, reason: String) {
+        self.modify(
+            Some(client_order_id),
+            None,
+            |state| {
+                let modified = transition(
+                    state,
+                    OrderStatus::Rejected,
+                );
+                if modified {
+                    state.reject_reason = Some(reason);
+                }
+                modified
+            },
+        );
+    }
+
+    /// Links an order ID to a tracked client order ID, so updates that arrive
+    /// before the placement response are not lost
+    fn link(&self, client_order_id: Option<&str>, order_id: &str) -> Option<String> {
+        let client_order_id = client_order_id.filter(|client_order_id| !client_order_id.is_empty())?;
+        let is_tracked = self
+            .orders()
+            .by_client_order_id
+            .contains_key(client_order_id);
+        if is_tracked {
+            self.acknowledge(
+                client_order_id,
+                order_id,
+            );
+        }
+        Some(client_order_id.to_string())
+    }
+
+    /// Applies an order update from a websocket push or a REST query. Returns
+    /// whether the order is tracked and changed.
+    pub fn apply_order(&self, order: &Order) -> bool {
+        let client_order_id = self.link(
+            order
+                .client_order_id
+                .as_deref(),
+            &order.order_id,
+        );
+        self.modify(
+            client_order_id.as_deref(),
+            Some(&order.order_id),
+            |state| {
+                let mut modified = transition(
+                    state,
+                    order.status,
+                );
+                if order.executed_quantity > state.executed_quantity {
+                    state.executed_quantity = order.executed_quantity;
+                    modified = true;
+                }
+                if order
+                    .average_price
+                    .is_some()
+                    && order.average_price != state.reported_average_price
+                {
+                    state.reported_average_price = order.average_price;
+                    modified = true;
+                }
+                modified
+            },
+        )
+    }
+
+    /// Applies a fill, once per trade ID. Returns whether the order is tracked
+    /// and changed.
+    pub fn apply_fill(&self, client_order_id: Option<&str>, fill: &Fill) -> bool {
+        let client_order_id = self.link(
+            client_order_id,
+            &fill.order_id,
+        );
+        self.modify(
+            client_order_id.as_deref(),
+            Some(&fill.order_id),
+            |state| {
+                if state
+                    .fills
+                    .iter()
+                    .any(|known| known.trade_id == fill.trade_id)
+                {
+                    return false;
+                }
+                state
+                    .fills
+                    .push(fill.clone());
+                state.executed_quote_quantity += fill.price * fill.quantity;
+                let fills_quantity = state
+                    .fills
+                    .iter()
+                    .map(|fill| fill.quantity)
+                    .sum::<Decimal>();
+                state.executed_quantity = state
+                    .executed_quantity
+                    .max(fills_quantity);
+                let status = match state.executed_quantity >= state.quantity
+                    && !state
+                        .quantity
+                        .is_zero()
+                {
+                    true => OrderStatus::Filled,
+                    false => OrderStatus::PartiallyFilled,
+                };
+                transition(
+                    state, status,
+                );
+                true
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{Instrument, OrderType, Venue};
+
+    fn instrument() -> Instrument {
+        Instrument {
+            venue: Venue::Spot,
+            symbol: "BTCUSDT".to_string(),
+        }
+    }
+
+    fn fill(trade_id: &str, price: i64, quantity: i64) -> Fill {
+        Fill {
+            instrument: instrument(),
+            order_id: "1".to_string(),
+            trade_id: trade_id.to_string(),
+            side: Side::Buy,
+            position_effect: None,
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            fee: Decimal::ONE,
+            fee_asset: "USDT".to_string(),
+            is_maker: false,
+            time: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregates_fills_until_terminal() {
+        let tracker = OrderTracker::default();
+        let client_order_id = tracker.next_client_order_id();
+        let handle = tracker.track(
+            OrderState::new(
+                client_order_id.clone(),
+                "BTCUSDT".to_string(),
+                Side::Buy,
+                Decimal::from(101),
+                Decimal::from(3),
+            ),
+        );
+        tracker.acknowledge(
+            &client_order_id,
+            "1",
+        );
+
+        assert!(
+            tracker.apply_fill(
+                None,
+                &fill("a", 100, 1)
+            )
+        );
+        assert!(
+            !tracker.apply_fill(
+                None,
+                &fill("a", 100, 1)
+            )
+        );
+        // A stale update doesn't move the order back
+        assert!(
+            !tracker.apply_order(
+                &Order {
+                    instrument: instrument(),
+                    order_id: "1".to_string(),
+                    client_order_id: Some(client_order_id.clone()),
+                    side: Side::Buy,
+                    position_effect: None,
+                    order_type: OrderType::Limit,
+                    price: Decimal::from(101),
+                    quantity: Decimal::from(3),
+                    executed_quantity: Decimal::ZERO,
+                    average_price: None,
+                    stop_price: None,
+                    status: OrderStatus::Open,
+                    created_at: Utc::now(),
+                    updated_at: None,
+                }
+            )
+        );
+        assert_eq!(
+            handle
+                .state()
+                .status,
+            OrderStatus::PartiallyFilled
+        );
+
+        let terminal = tokio::spawn(
+            handle
+                .clone()
+                .terminal(),
+        );
+        tracker.apply_fill(
+            None,
+            &fill(
+                "b", 102, 2,
+            ),
+        );
+        let state = terminal
+            .await
+            .unwrap();
+        assert_eq!(
+            state.status,
+            OrderStatus::Filled
+        );
+        assert_eq!(
+            state.executed_quantity,
+            Decimal::from(3)
+        );
+        assert_eq!(
+            state.average_price(),
+            Some(Decimal::from(304) / Decimal::from(3))
+        );
+        assert_eq!(
+            state.fees()["USDT"],
+            Decimal::from(2)
+        );
+
+        tracker.prune_terminal();
+        assert!(
+            tracker
+                .get(&client_order_id)
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,820 @@
+#[cfg(feature = "ws")]
+use crate::{
+    exchange::Fill,
+    spot::ws::message::{
+        account_deals::AccountDealsMessage,
+        account_orders::{LimitOrMarketAccountOrdersMessage, OrderKind, StopLimitAccountOrdersMessage, StopLimitDirection, StopLimitOrderState},
+        account_update::AccountUpdateMessage,
+    },
+};
+use crate::{
+    exchange::{
+        execution::{InstrumentRules, MarketTrade, TradeSource},
+        history::{Candle, CandleInterval, CandleSource},
+        Balance, CancelOrderParams, Exchange, ExchangeError, ExchangeResult, Instrument, Order, OrderStatus, OrderType, PlaceOrderOutput, PlaceOrderParams, Position, Side, Venue,
+    },
+    spot::v3::{
+        self,
+        account_information::{AccountBalance, AccountInformationEndpoint},
+        cancel_order::CancelOrderEndpoint,
+        get_open_orders::{GetOpenOrdersEndpoint, GetOpenOrdersParams},
+        klines::{KlinesEndpoint, KlinesParams},
+        models,
+        order::{OrderEndpoint, OrderParams},
+        query_order::{QueryOrderEndpoint, QueryOrderOutput, QueryOrderParams},
+        symbol_rules::SymbolRules,
+        trades::{TradesEndpoint, TradesParams},
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+fn instrument(symbol: &str) -> Instrument {
+    Instrument {
+        venue: Venue::Spot,
+        symbol: symbol.to_string(),
+    }
+}
+
+impl From<Side> for v3::enums::OrderSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => v3::enums::OrderSide::Buy,
+            Side::Sell => v3::enums::OrderSide::Sell,
+        }
+    }
+}
+
+impl From<v3::enums::OrderSide> for Side {
+    fn from(side: v3::enums::OrderSide) -> Self {
+        match side {
+            v3::enums::OrderSide::Buy => Side::Buy,
+            v3::enums::OrderSide::Sell => Side::Sell,
+        }
+    }
+}
+
+impl From<OrderType> for v3::enums::OrderType {
+    fn from(order_type: OrderType) -> Self {
+        match order_type {
+            OrderType::Limit => v3::enums::OrderType::Limit,
+            OrderType::Market => v3::enums::OrderType::Market,
+            OrderType::PostOnly => v3::enums::OrderType::LimitMaker,
+            OrderType::ImmediateOrCancel => v3::enums::OrderType::ImmediateOrCancel,
+            OrderType::FillOrKill => v3::enums::OrderType::FillOrKill,
+            // Told apart by the trigger direction
+            OrderType::StopLimit | OrderType::TakeProfit => v3::enums::OrderType::StopLimit,
+        }
+    }
+}
+
+impl From<v3::enums::OrderType> for OrderType {
+    fn from(order_type: v3::enums::OrderType) -> Self {
+        match order_type {
+            v3::enums::OrderType::Limit => OrderType::Limit,
+            v3::enums::OrderType::Market => OrderType::Market,
+            v3::enums::OrderType::LimitMaker => OrderType::PostOnly,
+            v3::enums::OrderType::ImmediateOrCancel => OrderType::ImmediateOrCancel,
+            v3::enums::OrderType::FillOrKill => OrderType::FillOrKill,
+            v3::enums::OrderType::StopLimit => OrderType::StopLimit,
+        }
+    }
+}
+
+impl From<v3::enums::OrderStatus> for OrderStatus {
+    fn from(status: v3::enums::OrderStatus) -> Self {
+        match status {
+            v3::enums::OrderStatus::New => OrderStatus::Open,
+            v3::enums::OrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+            v3::enums::OrderStatus::Filled => OrderStatus::Filled,
+            v3::enums::OrderStatus::Canceled => OrderStatus::Canceled,
+            v3::enums::OrderStatus::PartiallyCanceled => OrderStatus::PartiallyCanceled,
+        }
+    }
+}
+
+impl<'a> TryFrom<PlaceOrderParams<'a>> for OrderParams<'a> {
+    type Error = ExchangeError;
+
+    fn try_from(params: PlaceOrderParams<'a>) -> Result<Self, Self::Error> {
+        let unsupported = |concept| ExchangeError::Unsupported {
+            venue: Venue::Spot,
+            concept,
+        };
+        if params
+            .position_effect
+            .is_some()
+        {
+            return Err(unsupported("Position effect"));
+        }
+        if params
+            .margin_mode
+            .is_some()
+        {
+            return Err(unsupported("Margin mode"));
+        }
+        if params
+            .leverage
+            .is_some()
+        {
+            return Err(unsupported("Leverage"));
+        }
+        let price = match params.order_type {
+            OrderType::Market => None,
+            _ => Some(
+                params
+                    .price
+                    .ok_or(ExchangeError::Missing("Price"))?,
+            ),
+        };
+        let side = v3::enums::OrderSide::from(params.side);
+        let trigger_direction = match params.order_type {
+            OrderType::StopLimit | OrderType::TakeProfit
+                if params
+                    .stop_price
+                    .is_none() =>
+            {
+                return Err(ExchangeError::Missing("Stop price"));
+            }
+            OrderType::TakeProfit => v3::enums::StopLimitDirection::for_order(
+                v3::enums::OrderType::StopLimit,
+                side,
+            )
+            .map(v3::enums::StopLimitDirection::opposite),
+            _ => None,
+        };
+
+        Ok(
+            OrderParams {
+                symbol: params.symbol,
+                side,
+                order_type: params
+                    .order_type
+                    .into(),
+                quantity: params.quantity,
+                quote_order_quantity: params.quote_quantity,
+                price,
+                new_client_order_id: params.client_order_id,
+                stop_price: params.stop_price,
+                trigger_direction,
+            },
+        )
+    }
+}
+
+impl From<&models::Order> for Order {
+    fn from(order: &models::Order) -> Self {
+        Self {
+            instrument: instrument(&order.symbol),
+            order_id: order
+                .order_id
+                .clone(),
+            client_order_id: order
+                .client_order_id
+                .clone(),
+            side: order
+                .side
+                .into(),
+            position_effect: None,
+            order_type: order
+                .order_type
+                .into(),
+            price: order.price,
+            quantity: order.original_quantity,
+            executed_quantity: order.executed_quantity,
+            average_price: match order
+                .executed_quantity
+                .is_zero()
+            {
+                true => None,
+                false => Some(order.cummulative_quote_quantity / order.executed_quantity),
+            },
+            stop_price: order
+                .stop_price
+                .filter(
+                    |_| {
+                        order
+                            .order_type
+                            .is_stop()
+                    },
+                ),
+            status: order
+                .status
+                .into(),
+            created_at: order.time,
+            updated_at: order.update_time,
+        }
+    }
+}
+
+impl From<&QueryOrderOutput> for Order {
+    fn from(order: &QueryOrderOutput) -> Self {
+        Self {
+            instrument: instrument(&order.symbol),
+            order_id: order
+                .order_id
+                .clone(),
+            client_order_id: order
+                .client_order_id
+                .clone()
+                .or_else(
+                    || {
+                        order
+                            .original_client_order_id
+                            .clone()
+                    },
+                ),
+            side: order
+                .side
+                .into(),
+            position_effect: None,
+            order_type: order
+                .order_type
+                .into(),
+            price: order.price,
+            quantity: order.original_quantity,
+            executed_quantity: order.executed_quantity,
+            average_price: match order
+                .executed_quantity
+                .is_zero()
+            {
+                true => None,
+                false => Some(order.cummulative_quote_quantity / order.executed_quantity),
+            },
+            stop_price: order
+                .order_type
+                .is_stop()
+                .then_some(order.stop_price),
+            status: order
+                .status
+                .into(),
+            created_at: order.time,
+            updated_at: Some(order.update_time),
+        }
+    }
+}
+
+impl From<&AccountBalance> for Balance {
+    fn from(balance: &AccountBalance) -> Self {
+        Self {
+            venue: Venue::Spot,
+            asset: balance
+                .asset
+                .clone(),
+            free: balance.free,
+            locked: balance.locked,
+            total: balance.free + balance.locked,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&AccountUpdateMessage> for Balance {
+    fn from(update: &AccountUpdateMessage) -> Self {
+        Self {
+            venue: Venue::Spot,
+            asset: update
+                .asset
+                .clone(),
+            free: update.free_balance,
+            locked: update.frozen_amount,
+            total: update.free_balance + update.frozen_amount,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&AccountDealsMessage> for Fill {
+    fn from(deal: &AccountDealsMessage) -> Self {
+        Self {
+            instrument: instrument(&deal.asset),
+            order_id: deal
+                .order_id
+                .clone(),
+            trade_id: deal
+                .trade_id
+                .clone(),
+            side: deal
+                .trade_type
+                .into(),
+            position_effect: None,
+            price: deal.price,
+            quantity: deal.quantity,
+            fee: deal.commission_fee,
+            fee_asset: deal
+                .commission_asset
+                .clone(),
+            is_maker: deal.is_maker,
+            time: deal.trade_time,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<OrderKind> for OrderType {
+    fn from(order_kind: OrderKind) -> Self {
+        match order_kind {
+            OrderKind::LimitOrder | OrderKind::StopLimit => OrderType::Limit,
+            OrderKind::PostOnly => OrderType::PostOnly,
+            OrderKind::ImmediateOrCancel => OrderType::ImmediateOrCancel,
+            OrderKind::FillOrKill => OrderType::FillOrKill,
+            OrderKind::MarketOrder => OrderType::Market,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&LimitOrMarketAccountOrdersMessage> for Order {
+    fn from(order: &LimitOrMarketAccountOrdersMessage) -> Self {
+        Self {
+            instrument: instrument(&order.symbol),
+            order_id: order
+                .order_id
+                .clone(),
+            client_order_id: match order
+                .client_order_id
+                .is_empty()
+            {
+                true => None,
+                false => Some(
+                    order
+                        .client_order_id
+                        .clone(),
+                ),
+            },
+            side: order
+                .trade_type
+                .into(),
+            position_effect: None,
+            order_type: order
+                .order_kind
+                .into(),
+            price: order.price,
+            quantity: order.quantity,
+            executed_quantity: order.cumulative_quantity,
+            average_price: match order
+                .cumulative_quantity
+                .is_zero()
+            {
+                true => None,
+                false => Some(order.average_price),
+            },
+            stop_price: None,
+            status: order
+                .status
+                .into(),
+            created_at: order.create_time,
+            updated_at: Some(order.timestamp),
+        }
+    }
+}
+
+/// A triggered stop order stays open, the limit order it placed is pushed as
+/// a limit order
+#[cfg(feature = "ws")]
+impl From<StopLimitOrderState> for OrderStatus {
+    fn from(state: StopLimitOrderState) -> Self {
+        match state {
+            StopLimitOrderState::New => OrderStatus::Open,
+            StopLimitOrderState::Executed => OrderStatus::Triggered,
+            StopLimitOrderState::Canceled => OrderStatus::Canceled,
+            StopLimitOrderState::Failed => OrderStatus::Rejected,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&StopLimitAccountOrdersMessage> for Order {
+    fn from(order: &StopLimitAccountOrdersMessage) -> Self {
+        let side = Side::from(order.trade_type);
+        // Both are pushed as stop-limit orders, only the direction tells them
+        // apart
+        let order_type = match (
+            side,
+            order.direction,
+        ) {
+            (Side::Buy, StopLimitDirection::PriceHigherThanTriggerPrice) | (Side::Sell, StopLimitDirection::PriceLowerThanTriggerPrice) => OrderType::StopLimit,
+            _ => OrderType::TakeProfit,
+        };
+        Self {
+            instrument: instrument(&order.symbol),
+            order_id: order
+                .order_id
+                .clone(),
+            client_order_id: None,
+            side,
+            position_effect: None,
+            order_type,
+            price: order.price,
+            quantity: order.quantity,
+            executed_quantity: Decimal::ZERO,
+            average_price: None,
+            stop_price: Some(order.trigger_price),
+            status: order
+                .state
+                .into(),
+            created_at: order.create_time,
+            updated_at: Some(order.timestamp),
+        }
+    }
+}
+
+impl TryFrom<CandleInterval> for v3::enums::KlineInterval {
+    type Error = ExchangeError;
+
+    fn try_from(interval: CandleInterval) -> Result<Self, Self::Error> {
+        match interval {
+            CandleInterval::OneMinute => Ok(v3::enums::KlineInterval::OneMinute),
+            CandleInterval::FiveMinutes => Ok(v3::enums::KlineInterval::FiveMinutes),
+            CandleInterval::FifteenMinutes => Ok(v3::enums::KlineInterval::FifteenMinutes),
+            CandleInterval::ThirtyMinutes => Ok(v3::enums::KlineInterval::ThirtyMinutes),
+            CandleInterval::OneHour => Ok(v3::enums::KlineInterval::OneHour),
+            CandleInterval::FourHours => Ok(v3::enums::KlineInterval::FourHours),
+            CandleInterval::OneDay => Ok(v3::enums::KlineInterval::OneDay),
+            CandleInterval::OneWeek => Ok(v3::enums::KlineInterval::OneWeek),
+            CandleInterval::OneMonth => Ok(v3::enums::KlineInterval::OneMonth),
+            CandleInterval::EightHours => Err(
+                ExchangeError::Unsupported {
+                    venue: Venue::Spot,
+                    concept: "8 hour klines",
+                },
+            ),
+        }
+    }
+}
+
+impl From<&v3::klines::Kline> for Candle {
+    fn from(kline: &v3::klines::Kline) -> Self {
+        Self {
+            open_time: kline.open_time,
+            open: kline.open,
+            high: kline.high,
+            low: kline.low,
+            close: kline.close,
+            volume: kline.volume,
+            quote_volume: kline.quote_asset_volume,
+        }
+    }
+}
+
+#[async_trait]
+impl<T> CandleSource for T
+where
+    T: KlinesEndpoint + Sync,
+{
+    fn venue(&self) -> Venue {
+        Venue::Spot
+    }
+
+    fn max_candles_per_request(&self) -> u32 {
+        1000
+    }
+
+    async fn fetch_candles(&self, symbol: &str, interval: CandleInterval, start: DateTime<Utc>, end: DateTime<Utc>) -> ExchangeResult<Vec<Candle>> {
+        let output = self
+            .klines(
+                KlinesParams {
+                    symbol,
+                    interval: interval.try_into()?,
+                    start_time: Some(start),
+                    // The end time is inclusive
+                    end_time: Some(end - Duration::milliseconds(1)),
+                    limit: Some(self.max_candles_per_request()),
+                },
+            )
+            .await?;
+        Ok(
+            output
+                .klines
+                .iter()
+                .map(Candle::from)
+                .collect(),
+        )
+    }
+}
+
+#[async_trait]
+impl<T> TradeSource for T
+where
+    T: TradesEndpoint + Sync,
+{
+    fn venue(&self) -> Venue {
+        Venue::Spot
+    }
+
+    async fn recent_trades(&self, symbol: &str) -> ExchangeResult<Vec<MarketTrade>> {
+        let output = self
+            .trades(
+                TradesParams {
+                    symbol,
+                    limit: Some(1000),
+                },
+            )
+            .await?;
+        Ok(
+            output
+                .trades
+                .iter()
+                .map(
+                    |trade| MarketTrade {
+                        time: trade.time,
+                        price: trade.price,
+                        quantity: trade.quantity,
+                    },
+                )
+                .collect(),
+        )
+    }
+}
+
+impl InstrumentRules for SymbolRules {
+    fn round_price(&self, price: Decimal, side: Side) -> Decimal {
+        self.round_price_passive(
+            price,
+            side.into(),
+        )
+    }
+
+    fn truncate_quantity(&self, quantity: Decimal) -> Decimal {
+        SymbolRules::truncate_quantity(
+            self, quantity,
+        )
+    }
+
+    fn min_quantity(&self, price: Option<Decimal>) -> Decimal {
+        let for_notional = price
+            .filter(|price| *price > Decimal::ZERO)
+            .map(
+                |price| {
+                    self.round_quantity(
+                        self.min_notional / price,
+                        RoundingStrategy::ToPositiveInfinity,
+                    )
+                },
+            )
+            .unwrap_or_default();
+        self.min_quantity
+            .max(for_notional)
+    }
+}
+
+/// Every spot client, including the paper trading one, trades through the
+/// same endpoints
+#[async_trait]
+impl<T> Exchange for T
+where
+    T: OrderEndpoint + CancelOrderEndpoint + GetOpenOrdersEndpoint + QueryOrderEndpoint + AccountInformationEndpoint + Sync,
+{
+    fn venue(&self) -> Venue {
+        Venue::Spot
+    }
+
+    async fn place_order(&self, params: PlaceOrderParams<'_>) -> ExchangeResult<PlaceOrderOutput> {
+        let output = self
+            .order(OrderParams::try_from(params)?)
+            .await?;
+        Ok(
+            PlaceOrderOutput {
+                instrument: instrument(&output.symbol),
+                order_id: output.order_id,
+            },
+        )
+    }
+
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ExchangeResult<()> {
+        CancelOrderEndpoint::cancel_order(
+            self,
+            v3::cancel_order::CancelOrderParams {
+                symbol: params.symbol,
+                order_id: Some(params.order_id),
+                original_client_order_id: None,
+                new_client_order_id: None,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn open_orders(&self, symbol: &str) -> ExchangeResult<Vec<Order>> {
+        let output = self
+            .get_open_orders(
+                GetOpenOrdersParams {
+                    symbol,
+                },
+            )
+            .await?;
+        Ok(
+            output
+                .orders
+                .iter()
+                .map(Order::from)
+                .collect(),
+        )
+    }
+
+    async fn order(&self, symbol: &str, client_order_id: &str) -> ExchangeResult<Option<Order>> {
+        let result = self
+            .query_order(
+                QueryOrderParams {
+                    symbol,
+                    order_id: None,
+                    original_client_order_id: Some(client_order_id),
+                },
+            )
+            .await;
+        match result {
+            Ok(output) => Ok(Some(Order::from(&output))),
+            Err(v3::ApiError::ErrorResponse(response))
+                if matches!(
+                    response.code,
+                    v3::ErrorCode::OrderDoesNotExist | v3::ErrorCode::UnknownOrderSent
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn balances(&self) -> ExchangeResult<Vec<Balance>> {
+        let output = self
+            .account_information()
+            .await?;
+        Ok(
+            output
+                .balances
+                .iter()
+                .map(Balance::from)
+                .collect(),
+        )
+    }
+
+    async fn positions(&self, _symbol: Option<&str>) -> ExchangeResult<Vec<Position>> {
+        Err(
+            ExchangeError::Unsupported {
+                venue: Venue::Spot,
+                concept: "Positions",
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::PositionEffect;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn converts_order_params() {
+        let params = OrderParams::try_from(
+            PlaceOrderParams::limit(
+                "BTCUSDT",
+                Side::Buy,
+                Decimal::ONE_HUNDRED,
+                Decimal::ONE,
+            )
+            .with_order_type(OrderType::PostOnly)
+            .with_client_order_id("my-order"),
+        )
+        .unwrap();
+        assert_eq!(
+            params.order_type,
+            v3::enums::OrderType::LimitMaker
+        );
+        assert_eq!(
+            params.price,
+            Some(Decimal::ONE_HUNDRED)
+        );
+        assert_eq!(
+            params.new_client_order_id,
+            Some("my-order")
+        );
+
+        assert!(
+            matches!(
+                OrderParams::try_from(
+                    PlaceOrderParams::market(
+                        "BTCUSDT",
+                        Side::Sell,
+                        Decimal::ONE
+                    )
+                    .with_position_effect(PositionEffect::Close)
+                ),
+                Err(
+                    ExchangeError::Unsupported {
+                        venue: Venue::Spot,
+                        concept: "Position effect"
+                    }
+                )
+            )
+        );
+        assert!(
+            matches!(
+                OrderParams::try_from(
+                    PlaceOrderParams::limit(
+                        "BTCUSDT",
+                        Side::Sell,
+                        Decimal::ONE_HUNDRED,
+                        Decimal::ONE
+                    )
+                    .with_order_type(OrderType::StopLimit)
+                ),
+                Err(ExchangeError::Missing("Stop price"))
+            )
+        );
+    }
+
+    #[cfg(feature = "ws")]
+    #[test]
+    fn maps_stop_limit_pushes() {
+        let params = OrderParams::try_from(
+            PlaceOrderParams::limit(
+                "BTCUSDT",
+                Side::Sell,
+                Decimal::from(110),
+                Decimal::ONE,
+            )
+            .with_order_type(OrderType::TakeProfit)
+            .with_stop_price(Decimal::from(111)),
+        )
+        .unwrap();
+        assert_eq!(
+            params.order_type,
+            v3::enums::OrderType::StopLimit
+        );
+        assert_eq!(
+            params.trigger_direction,
+            Some(StopLimitDirection::PriceHigherThanTriggerPrice)
+        );
+        assert_eq!(
+            params.stop_price,
+            Some(Decimal::from(111))
+        );
+
+        let push = StopLimitAccountOrdersMessage {
+            symbol: "BTCUSDT".to_string(),
+            commission_asset: "USDT".to_string(),
+            create_time: Utc::now(),
+            trigger_price: Decimal::from(111),
+            trade_type: v3::enums::OrderSide::Sell,
+            direction: StopLimitDirection::PriceHigherThanTriggerPrice,
+            order_id: "1".to_string(),
+            order_kind: OrderKind::StopLimit,
+            price: Decimal::from(110),
+            state: StopLimitOrderState::Canceled,
+            quantity: Decimal::ONE,
+            timestamp: Utc::now(),
+        };
+        let order = Order::from(&push);
+        assert_eq!(
+            order.order_type,
+            OrderType::TakeProfit
+        );
+        assert_eq!(
+            order.stop_price,
+            Some(Decimal::from(111))
+        );
+        assert_eq!(
+            order.status,
+            OrderStatus::Canceled
+        );
+        assert_eq!(
+            Order::from(
+                &StopLimitAccountOrdersMessage {
+                    state: StopLimitOrderState::Executed,
+                    ..push
+                },
+            )
+            .status,
+            OrderStatus::Triggered
+        );
+
+        // REST queries carry no trigger direction
+        let queried = QueryOrderOutput {
+            symbol: "BTCUSDT".to_string(),
+            original_client_order_id: None,
+            order_id: "1".to_string(),
+            client_order_id: None,
+            price: params
+                .price
+                .unwrap(),
+            original_quantity: Decimal::ONE,
+            executed_quantity: Decimal::ZERO,
+            cummulative_quote_quantity: Decimal::ZERO,
+            status: v3::enums::OrderStatus::New,
+            time_in_force: None,
+            order_type: params.order_type,
+            side: v3::enums::OrderSide::Sell,
+            stop_price: Decimal::from(111),
+            time: Utc::now(),
+            update_time: Utc::now(),
+            is_working: true,
+        };
+        assert_eq!(
+            Order::from(&queried).order_type,
+            OrderType::StopLimit
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! File helpers shared by the caches and stores
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Reads the file at `path` on a blocking thread, `None` if there is none
+pub(crate) async fn read_if_exists(path: PathBuf) -> io::Result<Option<Vec<u8>>> {
+    tokio::task::spawn_blocking(
+        move || match std::fs::read(&path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        },
+    )
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Replaces the file at `path` with `contents` on a blocking thread. The
+/// contents are written aside and synced first, so an interrupted write or a
+/// power loss leaves either the old or the new file.
+pub(crate) async fn write_atomic(path: PathBuf, contents: Vec<u8>) -> io::Result<()> {
+    tokio::task::spawn_blocking(
+        move || {
+            let parent = path
+                .parent()
+                .filter(
+                    |parent| {
+                        !parent
+                            .as_os_str()
+                            .is_empty()
+                    },
+                )
+                .unwrap_or(".".as_ref())
+                .to_path_buf();
+            std::fs::create_dir_all(&parent)?;
+            let mut tmp_path = path
+                .clone()
+                .into_os_string();
+            tmp_path.push(".tmp");
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&contents)?;
+            file.sync_all()?;
+            std::fs::rename(
+                &tmp_path, &path,
+            )?;
+            // Makes the rename itself durable
+            #[cfg(unix)]
+            File::open(&parent)?.sync_all()?;
+            Ok(())
+        },
+    )
+    .await
+    .map_err(io::Error::other)?
+}
@@ -0,0 +1,168 @@
+//! Keeps futures balances and positions current from the personal asset and
+//! position websocket channels, seeded and periodically corrected by REST.
+
+use crate::{
+    exchange::{account_state::AccountState, Balance, Position, Venue},
+    futures::{
+        result::ApiResult,
+        v1::{
+            endpoints::{get_account_assets::GetAccountAssets, get_open_positions::GetOpenPositions},
+            models::PositionState,
+        },
+        ws::{
+            lifecycle::{FuturesLifecycleEvent, FuturesLifecycleEventKind},
+            message::FuturesMessage,
+        },
+    },
+};
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use std::{sync::Arc, time::Duration};
+
+pub struct FuturesAccountManager<C> {
+    client: Arc<C>,
+    state: Arc<AccountState>,
+}
+
+impl<C> FuturesAccountManager<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            state: Arc::new(AccountState::default()),
+        }
+    }
+
+    /// Shares the state with other managers, e.g. a spot account manager, to
+    /// watch both venues through one snapshot
+    pub fn with_state(mut self, state: Arc<AccountState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn client(&self) -> &Arc<C> {
+        &self.client
+    }
+
+    pub fn state(&self) -> &Arc<AccountState> {
+        &self.state
+    }
+
+    /// Applies a message as it is received, it is timestamped with the local
+    /// clock like reconciles
+    pub fn apply_message(&self, message: &FuturesMessage) {
+        match message {
+            FuturesMessage::PersonalAsset(message) => {
+                self.state
+                    .apply_balance(
+                        Balance::from(&message.data),
+                        Utc::now(),
+                    );
+            }
+            FuturesMessage::PersonalPosition(message) => {
+                let mut position = Position::from(&message.data);
+                if message
+                    .data
+                    .state
+                    == PositionState::Closed
+                {
+                    position.quantity = Decimal::ZERO;
+                }
+                self.state
+                    .apply_position(
+                        position,
+                        Some(
+                            message
+                                .data
+                                .version,
+                        ),
+                        Utc::now(),
+                    );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<C> FuturesAccountManager<C>
+where
+    C: GetAccountAssets + GetOpenPositions + Send + Sync,
+{
+    /// Replaces the cached balances and positions with the ones from REST,
+    /// keeping pushes that arrived while they were requested
+    pub async fn reconcile(&self) -> ApiResult<()> {
+        let requested_at = Utc::now();
+        let assets = self
+            .client
+            .get_account_assets()
+            .await?;
+        let positions = self
+            .client
+            .get_open_positions(None)
+            .await?;
+        self.state
+            .reconcile_balances(
+                Venue::Futures,
+                assets
+                    .iter()
+                    .map(Balance::from)
+                    .collect(),
+                requested_at,
+            );
+        self.state
+            .reconcile_positions(
+                positions
+                    .iter()
+                    .map(Position::from)
+                    .collect(),
+                requested_at,
+            );
+        Ok(())
+    }
+
+    /// Applies messages until both streams end, reconciling whenever the
+    /// websocket logs in again and when `reconcile_interval` passed without a
+    /// reconcile
+    pub async fn follow(&self, messages: BoxStream<'_, Arc<FuturesMessage>>, lifecycle_events: BoxStream<'_, FuturesLifecycleEvent>, reconcile_interval: Duration) {
+        let mut events = futures::stream::select(
+            messages.map(Event::Message),
+            lifecycle_events.map(Event::Lifecycle),
+        );
+        let mut reconcile_at = tokio::time::Instant::now() + reconcile_interval;
+        loop {
+            let event = tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(reconcile_at) => Event::Tick,
+            };
+            match event {
+                Event::Message(message) => self.apply_message(&message),
+                Event::Lifecycle(FuturesLifecycleEvent {
+                    kind: FuturesLifecycleEventKind::LoginOk,
+                    ..
+                })
+                | Event::Tick => {
+                    reconcile_at = tokio::time::Instant::now() + reconcile_interval;
+                    if let Err(err) = self
+                        .reconcile()
+                        .await
+                    {
+                        tracing::warn!(
+                            "Failed to reconcile futures account: {}",
+                            err
+                        );
+                    }
+                }
+                Event::Lifecycle(_) => {}
+            }
+        }
+    }
+}
+
+enum Event {
+    Message(Arc<FuturesMessage>),
+    Lifecycle(FuturesLifecycleEvent),
+    Tick,
+}
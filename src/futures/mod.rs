@@ -1,10 +1,16 @@
 use crate::futures::auth::{SignRequestParams, SignRequestParamsKind};
 use chrono::Utc;
 
+#[cfg(feature = "ws")]
+pub mod account_manager;
 pub mod auth;
 pub mod error;
+#[cfg(feature = "ws")]
+pub mod order_manager;
 pub mod response;
 pub mod result;
+#[cfg(feature = "ws")]
+pub mod risk_monitor;
 pub mod v1;
 
 #[cfg(feature = "ws")]
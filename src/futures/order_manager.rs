@@ -0,0 +1,546 @@
+//! Tracks orders placed through the futures client from placement to a
+//! terminal state, using the personal order websocket channels and REST
+//! queries to catch up after reconnects.
+
+use crate::{
+    exchange::{
+        order_tracker::{OrderHandle, OrderState, OrderTracker},
+        Fill, Order,
+    },
+    futures::{
+        error::{ApiError, ErrorCode},
+        result::ApiResult,
+        v1::endpoints::{
+            get_order::GetOrder,
+            order::{self, OrderParams},
+        },
+        ws::{
+            lifecycle::{FuturesLifecycleEvent, FuturesLifecycleEventKind},
+            message::FuturesMessage,
+        },
+    },
+};
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+
+pub struct FuturesOrderManager<C> {
+    client: Arc<C>,
+    tracker: OrderTracker,
+}
+
+impl<C> FuturesOrderManager<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            tracker: OrderTracker::default(),
+        }
+    }
+
+    /// External order IDs are the prefix followed by digits, MEXC accepts up
+    /// to 32 characters
+    pub fn with_external_order_id_prefix(mut self, prefix: &str) -> Self {
+        self.tracker = OrderTracker::new(prefix);
+        self
+    }
+
+    pub fn client(&self) -> &Arc<C> {
+        &self.client
+    }
+
+    pub fn tracker(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    pub fn order(&self, external_order_id: &str) -> Option<OrderHandle> {
+        self.tracker
+            .handle(external_order_id)
+    }
+
+    pub fn apply_message(&self, message: &FuturesMessage) {
+        match message {
+            FuturesMessage::PersonalOrder(message) => {
+                self.tracker
+                    .apply_order(&Order::from(&message.data));
+            }
+            FuturesMessage::PersonalOrderDeal(message) => {
+                self.tracker
+                    .apply_fill(
+                        Some(
+                            &message
+                                .data
+                                .external_oid,
+                        ),
+                        &Fill::from(&message.data),
+                    );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<C> FuturesOrderManager<C>
+where
+    C: order::Order + GetOrder + Send + Sync,
+{
+    /// Places an order, assigning an external order ID unless one is set. The
+    /// returned handle resolves once the order is filled, canceled or
+    /// rejected.
+    pub async fn place(&self, params: OrderParams<'_>) -> ApiResult<OrderHandle> {
+        let external_order_id = params
+            .external_order_id
+            .map(str::to_string)
+            .unwrap_or_else(
+                || {
+                    self.tracker
+                        .next_client_order_id()
+                },
+            );
+        // Tracked before sending, pushes can arrive before the response
+        let handle = self
+            .tracker
+            .track(
+                OrderState::new(
+                    external_order_id.clone(),
+                    params
+                        .symbol
+                        .to_string(),
+                    params
+                        .side
+                        .into(),
+                    params.price,
+                    params.volume,
+                )
+                .with_position_effect(
+                    params
+                        .side
+                        .into(),
+                ),
+            );
+        let params = OrderParams {
+            external_order_id: Some(&external_order_id),
+            ..params
+        };
+        match self
+            .client
+            .order(params)
+            .await
+        {
+            Ok(output) => {
+                self.tracker
+                    .acknowledge(
+                        &external_order_id,
+                        &output
+                            .order_id
+                            .to_string(),
+                    );
+                Ok(handle)
+            }
+            // Only an error response is a definite rejection, after anything
+            // else the order may be live and is left to `reconcile`
+            Err(err) => {
+                if let ApiError::ErrorResponse(_) = err {
+                    self.tracker
+                        .reject(
+                            &external_order_id,
+                            err.to_string(),
+                        );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Queries every open tracked order, for updates missed while the
+    /// websocket was down. Orders that were never acknowledged are looked up
+    /// by their external order ID, and rejected when the exchange doesn't know
+    /// them. Fails with the first error after trying all orders.
+    pub async fn reconcile(&self) -> ApiResult<()> {
+        let mut result = Ok(());
+        for state in self
+            .tracker
+            .open_orders()
+        {
+            let order_id = state
+                .order_id
+                .as_deref()
+                .and_then(
+                    |order_id| {
+                        order_id
+                            .parse()
+                            .ok()
+                    },
+                );
+            let output = match order_id {
+                Some(order_id) => {
+                    self.client
+                        .get_order(order_id)
+                        .await
+                }
+                None => {
+                    self.client
+                        .get_order_by_external_id(
+                            &state.symbol,
+                            &state.client_order_id,
+                        )
+                        .await
+                }
+            };
+            match output {
+                Ok(order) => {
+                    self.tracker
+                        .apply_order(&Order::from(&order));
+                }
+                Err(ApiError::ErrorResponse(response))
+                    if order_id.is_none()
+                        && matches!(
+                            response.code,
+                            ErrorCode::UnknownOrderSent | ErrorCode::NotFound
+                        ) =>
+                {
+                    self.tracker
+                        .reject(
+                            &state.client_order_id,
+                            response.to_string(),
+                        );
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to reconcile order {}: {}",
+                        state.client_order_id,
+                        err
+                    );
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Applies messages until both streams end, reconciling whenever the
+    /// websocket logs in again
+    pub async fn follow(&self, messages: BoxStream<'_, Arc<FuturesMessage>>, lifecycle_events: BoxStream<'_, FuturesLifecycleEvent>) {
+        let mut events = futures::stream::select(
+            messages.map(Event::Message),
+            lifecycle_events.map(Event::Lifecycle),
+        );
+        while let Some(event) = events
+            .next()
+            .await
+        {
+            match event {
+                Event::Message(message) => self.apply_message(&message),
+                Event::Lifecycle(FuturesLifecycleEvent {
+                    kind: FuturesLifecycleEventKind::LoginOk,
+                    ..
+                }) => {
+                    let _ = self
+                        .reconcile()
+                        .await;
+                }
+                Event::Lifecycle(_) => {}
+            }
+        }
+    }
+}
+
+enum Event {
+    Message(Arc<FuturesMessage>),
+    Lifecycle(FuturesLifecycleEvent),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        exchange::{OrderStatus, PositionEffect, Side},
+        futures::{
+            response::ErrorApiResponse,
+            v1::{
+                endpoints::order::OrderOutput,
+                models::{OpenOrder, OpenType, OrderSide, OrderType},
+            },
+            ws::message::RawFuturesMessage,
+        },
+    };
+    use async_trait::async_trait;
+    use rust_decimal::Decimal;
+
+    /// Fails placements with a transport error when `unreachable`, the
+    /// orders landed anyway unless they were `lost`
+    struct TestClient {
+        unreachable: bool,
+        lost: bool,
+    }
+
+    fn not_found() -> ApiError {
+        ApiError::ErrorResponse(
+            ErrorApiResponse {
+                code: ErrorCode::NotFound,
+                message: "Order not found".to_string(),
+            },
+        )
+    }
+
+    #[async_trait]
+    impl order::Order for TestClient {
+        async fn order<'a>(&self, _params: OrderParams<'a>) -> ApiResult<OrderOutput> {
+            if self.unreachable {
+                let err = reqwest::Client::new()
+                    .get("not a url")
+                    .build()
+                    .unwrap_err();
+                return Err(ApiError::ReqwestError(err));
+            }
+            Ok(
+                OrderOutput {
+                    order_id: 7,
+                },
+            )
+        }
+    }
+
+    #[async_trait]
+    impl GetOrder for TestClient {
+        async fn get_order(&self, _order_id: i64) -> ApiResult<OpenOrder> {
+            Err(not_found())
+        }
+
+        async fn get_order_by_external_id(&self, _symbol: &str, external_order_id: &str) -> ApiResult<OpenOrder> {
+            if self.lost {
+                return Err(not_found());
+            }
+            Ok(
+                serde_json::from_str(
+                    &FILLED_ORDER.replace(
+                        "test1",
+                        external_order_id,
+                    ),
+                )
+                .unwrap(),
+            )
+        }
+    }
+
+    const FILLED_ORDER: &str = r#"{
+        "orderId": 7, "symbol": "BTC_USDT", "positionId": 1, "price": 100, "vol": 3,
+        "leverage": 10, "side": 1, "category": 1, "orderType": 1, "dealAvgPrice": 100.6667,
+        "dealVol": 3, "orderMargin": 0, "usedMargin": 30, "takerFee": 0.02, "makerFee": 0,
+        "profit": 0, "feeCurrency": "USDT", "openType": 1, "state": 3, "errorCode": 0,
+        "externalOid": "test1", "createTime": 1700000000000, "updateTime": 1700000000000,
+        "remainVol": 0, "positionMode": 1, "reduceOnly": false, "bboTypeNum": 0,
+        "makerFeeRate": 0, "takerFeeRate": 0.0002
+    }"#;
+
+    fn message(json: &str) -> FuturesMessage {
+        let raw: RawFuturesMessage = serde_json::from_str(json).unwrap();
+        raw.try_into()
+            .unwrap()
+    }
+
+    fn deal(id: i64, vol: f64, price: f64) -> FuturesMessage {
+        message(
+            &format!(
+                r#"{{
+                "channel": "push.personal.order.deal",
+                "data": {{
+                    "id": {id}, "symbol": "BTC_USDT", "side": 1, "vol": {vol}, "price": {price},
+                    "feeCurrency": "USDT", "fee": 0.01, "timestamp": 1700000000000, "profit": 0,
+                    "isTaker": true, "category": 1, "orderId": 7, "isSelf": false,
+                    "externalOid": "test1", "positionMode": 1, "reduceOnly": false, "opponentUid": 1
+                }},
+                "ts": 1700000000000
+            }}"#
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn tracks_personal_order_messages() {
+        let manager = FuturesOrderManager::new(
+            Arc::new(
+                TestClient {
+                    unreachable: false,
+                    lost: false,
+                },
+            ),
+        )
+        .with_external_order_id_prefix("test");
+        let handle = manager
+            .place(
+                OrderParams {
+                    symbol: "BTC_USDT",
+                    price: Decimal::from(100),
+                    volume: Decimal::from(3),
+                    leverage: Some(10),
+                    side: OrderSide::OpenLong,
+                    order_type: OrderType::PriceLimitedOrder,
+                    open_type: OpenType::Isolated,
+                    position_id: None,
+                    external_order_id: Some("test1"),
+                    stop_loss_price: None,
+                    take_profit_price: None,
+                    position_mode: None,
+                    reduce_only: None,
+                },
+            )
+            .await
+            .unwrap();
+        let state = handle.state();
+        assert_eq!(
+            state.order_id,
+            Some("7".to_string())
+        );
+        assert_eq!(
+            (
+                state.side,
+                state.position_effect
+            ),
+            (
+                Side::Buy,
+                Some(PositionEffect::Open)
+            )
+        );
+
+        manager.apply_message(
+            &deal(
+                1, 1.0, 100.0,
+            ),
+        );
+        manager.apply_message(
+            &deal(
+                2, 2.0, 101.0,
+            ),
+        );
+        manager.apply_message(&message(&format!(r#"{{"channel": "push.personal.order", "data": {FILLED_ORDER}, "ts": 1700000000000}}"#)));
+
+        let state = handle
+            .terminal()
+            .await;
+        assert_eq!(
+            state.status,
+            OrderStatus::Filled
+        );
+        assert_eq!(
+            state.executed_quantity,
+            Decimal::from(3)
+        );
+        assert_eq!(
+            state.average_price(),
+            Some(Decimal::from(302) / Decimal::from(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn reconciles_orders_with_unknown_outcome() {
+        let manager = FuturesOrderManager::new(
+            Arc::new(
+                TestClient {
+                    unreachable: true,
+                    lost: false,
+                },
+            ),
+        )
+        .with_external_order_id_prefix("test");
+        let params = OrderParams {
+            symbol: "BTC_USDT",
+            price: Decimal::from(100),
+            volume: Decimal::from(3),
+            leverage: Some(10),
+            side: OrderSide::OpenLong,
+            order_type: OrderType::PriceLimitedOrder,
+            open_type: OpenType::Isolated,
+            position_id: None,
+            external_order_id: Some("test2"),
+            stop_loss_price: None,
+            take_profit_price: None,
+            position_mode: None,
+            reduce_only: None,
+        };
+        assert!(
+            manager
+                .place(params)
+                .await
+                .is_err()
+        );
+        let state = manager
+            .tracker()
+            .get("test2")
+            .unwrap();
+        assert_eq!(
+            (
+                state.status,
+                state.order_id
+            ),
+            (
+                OrderStatus::Open,
+                None
+            )
+        );
+
+        manager
+            .reconcile()
+            .await
+            .unwrap();
+        let state = manager
+            .tracker()
+            .get("test2")
+            .unwrap();
+        assert_eq!(
+            (
+                state.status,
+                state.order_id
+            ),
+            (
+                OrderStatus::Filled,
+                Some("7".to_string())
+            )
+        );
+    }
+    #[tokio::test]
+    async fn rejects_lost_orders_on_reconcile() {
+        let manager = FuturesOrderManager::new(
+            Arc::new(
+                TestClient {
+                    unreachable: true,
+                    lost: true,
+                },
+            ),
+        );
+        let params = OrderParams {
+            symbol: "BTC_USDT",
+            price: Decimal::from(100),
+            volume: Decimal::from(3),
+            leverage: Some(10),
+            side: OrderSide::OpenLong,
+            order_type: OrderType::PriceLimitedOrder,
+            open_type: OpenType::Isolated,
+            position_id: None,
+            external_order_id: Some("test3"),
+            stop_loss_price: None,
+            take_profit_price: None,
+            position_mode: None,
+            reduce_only: None,
+        };
+        assert!(
+            manager
+                .place(params)
+                .await
+                .is_err()
+        );
+
+        manager
+            .reconcile()
+            .await
+            .unwrap();
+        let state = manager
+            .tracker()
+            .get("test3")
+            .unwrap();
+        assert_eq!(
+            state.status,
+            OrderStatus::Rejected
+        );
+    }
+}
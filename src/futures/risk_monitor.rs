@@ -0,0 +1,911 @@
+//! Live risk of futures positions: unrealized profit against the fair price,
+//! distance to liquidation and margin usage, with alerts when configured
+//! thresholds are crossed.
+
+use crate::{
+    exchange::PositionSide,
+    futures::{
+        v1::{
+            contract_spec::ContractSpecCache,
+            models::{PositionState, PositionType},
+        },
+        ws::message::{FuturesMessage, PersonalAssetData, PersonalPositionData},
+    },
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{broadcast, watch};
+
+const ALERT_CHANNEL_CAPACITY: usize = 256;
+
+/// Thresholds alerts are raised at, unset ones are not checked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskThresholds {
+    /// Margin ratio of a position at or above which to alert, MEXC
+    /// liquidates at 1
+    pub max_margin_ratio: Option<Decimal>,
+    /// Distance between the fair and the liquidation price, as a fraction of
+    /// the fair price, at or below which to alert
+    pub min_liquidation_distance: Option<Decimal>,
+    /// Unrealized loss of a position in the settle coin at or above which to
+    /// alert
+    pub max_unrealized_loss: Option<Decimal>,
+    /// Share of an asset's balance held as position margin at or above which
+    /// to alert
+    pub max_margin_usage: Option<Decimal>,
+}
+
+impl RiskThresholds {
+    pub fn with_max_margin_ratio(mut self, max_margin_ratio: Decimal) -> Self {
+        self.max_margin_ratio = Some(max_margin_ratio);
+        self
+    }
+
+    pub fn with_min_liquidation_distance(mut self, min_liquidation_distance: Decimal) -> Self {
+        self.min_liquidation_distance = Some(min_liquidation_distance);
+        self
+    }
+
+    pub fn with_max_unrealized_loss(mut self, max_unrealized_loss: Decimal) -> Self {
+        self.max_unrealized_loss = Some(max_unrealized_loss);
+        self
+    }
+
+    pub fn with_max_margin_usage(mut self, max_margin_usage: Decimal) -> Self {
+        self.max_margin_usage = Some(max_margin_usage);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RiskMetric {
+    MarginRatio,
+    LiquidationDistance,
+    UnrealizedLoss,
+    MarginUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RiskSubject {
+    Position {
+        position_id: i64,
+        symbol: String,
+    },
+    /// A settle coin
+    Asset(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskAlert {
+    pub subject: RiskSubject,
+    pub metric: RiskMetric,
+    pub value: Decimal,
+    pub threshold: Decimal,
+    /// `false` once the metric is back within its threshold, or the position
+    /// closed, in which case value and threshold are zero
+    pub breached: bool,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionRisk {
+    pub position_id: i64,
+    pub symbol: String,
+    pub side: PositionSide,
+    /// In contracts
+    pub volume: Decimal,
+    pub entry_price: Decimal,
+    pub leverage: i32,
+    pub margin: Decimal,
+    pub margin_ratio: Decimal,
+    pub liquidation_price: Option<Decimal>,
+    /// `None` until the fair price of the symbol was pushed
+    pub fair_price: Option<Decimal>,
+    /// In the settle coin, `None` without a fair price or contract spec
+    pub unrealized_pnl: Option<Decimal>,
+    /// Fraction of the fair price the price can move before liquidation
+    pub liquidation_distance: Option<Decimal>,
+    version: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetRisk {
+    pub currency: String,
+    pub available_balance: Decimal,
+    pub frozen_balance: Decimal,
+    pub position_margin: Decimal,
+    /// Position margin as a fraction of the whole balance
+    pub margin_usage: Option<Decimal>,
+}
+
+impl From<&PersonalAssetData> for AssetRisk {
+    fn from(asset: &PersonalAssetData) -> Self {
+        let balance = asset.available_balance + asset.frozen_balance + asset.position_margin;
+        Self {
+            currency: asset
+                .currency
+                .clone(),
+            available_balance: asset.available_balance,
+            frozen_balance: asset.frozen_balance,
+            position_margin: asset.position_margin,
+            margin_usage: match balance.is_zero() {
+                true => None,
+                false => Some(asset.position_margin / balance),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskSnapshot {
+    /// Sorted by position id
+    pub positions: Vec<PositionRisk>,
+    /// Sorted by currency
+    pub assets: Vec<AssetRisk>,
+}
+
+impl RiskSnapshot {
+    /// Sum over the positions with a known unrealized profit
+    pub fn unrealized_pnl(&self) -> Decimal {
+        self.positions
+            .iter()
+            .filter_map(|position| position.unrealized_pnl)
+            .sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    positions: BTreeMap<i64, PositionRisk>,
+    /// Version each position closed at, so older updates can't reopen it
+    closed: BTreeMap<i64, i64>,
+    fair_prices: HashMap<String, Decimal>,
+    assets: BTreeMap<String, AssetRisk>,
+    breached: HashSet<(
+        RiskSubject,
+        RiskMetric,
+    )>,
+}
+
+/// Combines personal position, liquidation risk and asset pushes with fair
+/// prices. Subscribe to `push.fair.price` of every symbol held.
+pub struct RiskMonitor {
+    contracts: Arc<ContractSpecCache>,
+    thresholds: RiskThresholds,
+    inner: Mutex<Inner>,
+    sender: watch::Sender<RiskSnapshot>,
+    alerts: broadcast::Sender<RiskAlert>,
+}
+
+impl RiskMonitor {
+    /// Contract sizes for the unrealized profit come from `contracts`
+    pub fn new(contracts: Arc<ContractSpecCache>, thresholds: RiskThresholds) -> Self {
+        Self {
+            contracts,
+            thresholds,
+            inner: Mutex::new(Inner::default()),
+            sender: watch::channel(RiskSnapshot::default()).0,
+            alerts: broadcast::channel(ALERT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn thresholds(&self) -> &RiskThresholds {
+        &self.thresholds
+    }
+
+    pub fn snapshot(&self) -> RiskSnapshot {
+        self.sender
+            .borrow()
+            .clone()
+    }
+
+    /// Sees a new snapshot after every change
+    pub fn watch(&self) -> watch::Receiver<RiskSnapshot> {
+        self.sender
+            .subscribe()
+    }
+
+    /// Receives alerts raised from now on
+    pub fn alerts(&self) -> broadcast::Receiver<RiskAlert> {
+        self.alerts
+            .subscribe()
+    }
+
+    /// Applies a message, returning the alerts it raised. Irrelevant messages
+    /// are ignored.
+    pub fn apply_message(&self, message: &FuturesMessage) -> Vec<RiskAlert> {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap();
+        let (time, position_ids) = match message {
+            FuturesMessage::PersonalPosition(message) => (
+                message.ts,
+                self.apply_position(
+                    &mut inner,
+                    &message.data,
+                ),
+            ),
+            FuturesMessage::PersonalLiquidateRisk(message) => {
+                let data = &message.data;
+                let Some(position) = inner
+                    .positions
+                    .get_mut(&data.position_id)
+                else {
+                    return vec![];
+                };
+                position.liquidation_price = non_zero(data.liquidate_price);
+                position.margin_ratio = data.margin_ratio;
+                (
+                    message.ts,
+                    vec![data.position_id],
+                )
+            }
+            FuturesMessage::FairPrice(message) => {
+                inner
+                    .fair_prices
+                    .insert(
+                        message
+                            .symbol
+                            .clone(),
+                        message
+                            .data
+                            .price,
+                    );
+                let position_ids = inner
+                    .positions
+                    .values()
+                    .filter(|position| position.symbol == message.symbol)
+                    .map(|position| position.position_id)
+                    .collect();
+                (
+                    message.ts,
+                    position_ids,
+                )
+            }
+            FuturesMessage::PersonalAsset(message) => {
+                let asset = AssetRisk::from(&message.data);
+                let mut alerts = vec![];
+                self.check(
+                    &mut inner,
+                    RiskSubject::Asset(
+                        asset
+                            .currency
+                            .clone(),
+                    ),
+                    RiskMetric::MarginUsage,
+                    asset.margin_usage,
+                    message.ts,
+                    &mut alerts,
+                );
+                inner
+                    .assets
+                    .insert(
+                        asset
+                            .currency
+                            .clone(),
+                        asset,
+                    );
+                return self.publish(
+                    &inner, alerts,
+                );
+            }
+            _ => return vec![],
+        };
+
+        let mut alerts = vec![];
+        for position_id in position_ids {
+            let Some(position) = inner
+                .positions
+                .get(&position_id)
+                .cloned()
+            else {
+                self.clear_position(
+                    &mut inner,
+                    position_id,
+                    time,
+                    &mut alerts,
+                );
+                continue;
+            };
+            let fair_price = inner
+                .fair_prices
+                .get(&position.symbol)
+                .copied();
+            let position = self.evaluate(
+                position, fair_price,
+            );
+            let subject = RiskSubject::Position {
+                position_id,
+                symbol: position
+                    .symbol
+                    .clone(),
+            };
+            for (metric, value) in [
+                (
+                    RiskMetric::MarginRatio,
+                    Some(position.margin_ratio),
+                ),
+                (
+                    RiskMetric::LiquidationDistance,
+                    position.liquidation_distance,
+                ),
+                (
+                    RiskMetric::UnrealizedLoss,
+                    position
+                        .unrealized_pnl
+                        .map(|pnl| -pnl),
+                ),
+            ] {
+                self.check(
+                    &mut inner,
+                    subject.clone(),
+                    metric,
+                    value,
+                    time,
+                    &mut alerts,
+                );
+            }
+            inner
+                .positions
+                .insert(
+                    position_id,
+                    position,
+                );
+        }
+        self.publish(
+            &inner, alerts,
+        )
+    }
+
+    /// Returns the positions affected, closed ones are removed
+    fn apply_position(&self, inner: &mut Inner, data: &PersonalPositionData) -> Vec<i64> {
+        let version = inner
+            .positions
+            .get(&data.position_id)
+            .map(|position| position.version)
+            .or_else(
+                || {
+                    inner
+                        .closed
+                        .get(&data.position_id)
+                        .copied()
+                },
+            );
+        if version.is_some_and(|version| version >= data.version) {
+            return vec![];
+        }
+        if data.state == PositionState::Closed
+            || data
+                .hold_vol
+                .is_zero()
+        {
+            inner
+                .positions
+                .remove(&data.position_id);
+            inner
+                .closed
+                .insert(
+                    data.position_id,
+                    data.version,
+                );
+            return vec![data.position_id];
+        }
+        inner
+            .closed
+            .remove(&data.position_id);
+        inner
+            .positions
+            .insert(
+                data.position_id,
+                PositionRisk {
+                    position_id: data.position_id,
+                    symbol: data
+                        .symbol
+                        .clone(),
+                    side: match data.position_type {
+                        PositionType::Long => PositionSide::Long,
+                        PositionType::Short => PositionSide::Short,
+                    },
+                    volume: data.hold_vol,
+                    entry_price: data.hold_avg_price,
+                    leverage: data.leverage,
+                    margin: data.im,
+                    margin_ratio: data.margin_ratio,
+                    liquidation_price: non_zero(data.liquidate_price),
+                    fair_price: None,
+                    unrealized_pnl: None,
+                    liquidation_distance: None,
+                    version: data.version,
+                },
+            );
+        vec![data.position_id]
+    }
+
+    fn evaluate(&self, mut position: PositionRisk, fair_price: Option<Decimal>) -> PositionRisk {
+        position.fair_price = fair_price;
+        position.unrealized_pnl = fair_price.and_then(
+            |fair_price| {
+                let spec = self
+                    .contracts
+                    .get(&position.symbol)?;
+                let pnl = match spec.is_inverse() {
+                    true if fair_price.is_zero()
+                        || position
+                            .entry_price
+                            .is_zero() =>
+                    {
+                        return None
+                    }
+                    true => position.volume * spec.contract_size * (Decimal::ONE / position.entry_price - Decimal::ONE / fair_price),
+                    false => (fair_price - position.entry_price) * position.volume * spec.contract_size,
+                };
+                Some(
+                    match position.side {
+                        PositionSide::Long => pnl,
+                        PositionSide::Short => -pnl,
+                    },
+                )
+            },
+        );
+        position.liquidation_distance = match (
+            fair_price,
+            position.liquidation_price,
+        ) {
+            (Some(fair_price), Some(liquidation_price)) if !fair_price.is_zero() => Some((fair_price - liquidation_price).abs() / fair_price),
+            _ => None,
+        };
+        position
+    }
+
+    /// Raises an alert when `metric` crosses its threshold in either
+    /// direction. Unknown values keep the current state.
+    fn check(&self, inner: &mut Inner, subject: RiskSubject, metric: RiskMetric, value: Option<Decimal>, time: DateTime<Utc>, alerts: &mut Vec<RiskAlert>) {
+        let (Some(value), Some(threshold)) = (
+            value,
+            match metric {
+                RiskMetric::MarginRatio => {
+                    self.thresholds
+                        .max_margin_ratio
+                }
+                RiskMetric::LiquidationDistance => {
+                    self.thresholds
+                        .min_liquidation_distance
+                }
+                RiskMetric::UnrealizedLoss => {
+                    self.thresholds
+                        .max_unrealized_loss
+                }
+                RiskMetric::MarginUsage => {
+                    self.thresholds
+                        .max_margin_usage
+                }
+            },
+        ) else {
+            return;
+        };
+        let breached = match metric {
+            RiskMetric::LiquidationDistance => value <= threshold,
+            _ => value >= threshold,
+        };
+        let key = (
+            subject, metric,
+        );
+        let changed = match breached {
+            true => inner
+                .breached
+                .insert(key.clone()),
+            false => inner
+                .breached
+                .remove(&key),
+        };
+        if changed {
+            alerts.push(
+                RiskAlert {
+                    subject: key.0,
+                    metric,
+                    value,
+                    threshold,
+                    breached,
+                    time,
+                },
+            );
+        }
+    }
+
+    fn clear_position(&self, inner: &mut Inner, position_id: i64, time: DateTime<Utc>, alerts: &mut Vec<RiskAlert>) {
+        let cleared = inner
+            .breached
+            .iter()
+            .filter(
+                |(subject, _)| {
+                    matches!(
+                        subject,
+                        RiskSubject::Position { position_id: id, .. } if *id == position_id
+                    )
+                },
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in cleared {
+            inner
+                .breached
+                .remove(&key);
+            alerts.push(
+                RiskAlert {
+                    subject: key.0,
+                    metric: key.1,
+                    value: Decimal::ZERO,
+                    threshold: Decimal::ZERO,
+                    breached: false,
+                    time,
+                },
+            );
+        }
+    }
+
+    fn publish(&self, inner: &Inner, alerts: Vec<RiskAlert>) -> Vec<RiskAlert> {
+        self.sender
+            .send_replace(
+                RiskSnapshot {
+                    positions: inner
+                        .positions
+                        .values()
+                        .cloned()
+                        .collect(),
+                    assets: inner
+                        .assets
+                        .values()
+                        .cloned()
+                        .collect(),
+                },
+            );
+        for alert in &alerts {
+            // Nobody listening is fine, alerts are also returned
+            let _ = self
+                .alerts
+                .send(alert.clone());
+        }
+        alerts
+    }
+}
+
+fn non_zero(value: Decimal) -> Option<Decimal> {
+    match value.is_zero() {
+        true => None,
+        false => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::{
+        v1::contract_spec::ContractSpec,
+        ws::message::{FairPriceData, FairPriceMessage, PersonalPositionMessage},
+    };
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    /// A short on ETH_USDT entered at 1000 and liquidated at 1100
+    fn position(version: i64, hold_vol: &str, margin_ratio: &str) -> FuturesMessage {
+        position_on(
+            "ETH_USDT",
+            PositionType::Short,
+            version,
+            hold_vol,
+            margin_ratio,
+        )
+    }
+
+    fn position_on(symbol: &str, position_type: PositionType, version: i64, hold_vol: &str, margin_ratio: &str) -> FuturesMessage {
+        let position_type = match position_type {
+            PositionType::Long => 1,
+            PositionType::Short => 2,
+        };
+        let data = serde_json::from_value(
+            serde_json::json!({
+                "positionId": 1, "symbol": symbol, "holdVol": hold_vol, "positionType": position_type,
+                "openType": 1, "state": 1, "frozenVol": 0, "closeVol": 0, "holdAvgPrice": 1000,
+                "holdAvgPriceFullyScale": "1000", "closeAvgPrice": 0, "openAvgPrice": 1000,
+                "openAvgPriceFullyScale": "1000", "liquidatePrice": 1100, "oim": 10, "adlLevel": 1,
+                "im": 10, "holdFee": 0, "realised": 0, "leverage": 10, "autoAddIm": false, "pnl": 0,
+                "marginRatio": margin_ratio, "newOpenAvgPrice": 1000, "newCloseAvgPrice": 0,
+                "closeProfitLoss": 0, "fee": 0, "deductFeeList": [], "makerFeeRate": 0,
+                "takerFeeRate": 0.0006, "createTime": 1609991676000i64, "updateTime": 1609991676000i64,
+                "version": version
+            }),
+        )
+        .unwrap();
+        FuturesMessage::PersonalPosition(
+            PersonalPositionMessage {
+                data,
+                ts: Utc::now(),
+            },
+        )
+    }
+
+    fn fair_price(price: &str) -> FuturesMessage {
+        fair_price_on(
+            "ETH_USDT", price,
+        )
+    }
+
+    fn fair_price_on(symbol: &str, price: &str) -> FuturesMessage {
+        FuturesMessage::FairPrice(
+            FairPriceMessage {
+                data: FairPriceData {
+                    price: dec(price),
+                    symbol: symbol.to_string(),
+                },
+                symbol: symbol.to_string(),
+                ts: Utc::now(),
+            },
+        )
+    }
+
+    fn spec(symbol: &str, base_coin: &str, quote_coin: &str, settle_coin: &str, contract_size: &str) -> ContractSpec {
+        ContractSpec {
+            symbol: symbol.to_string(),
+            base_coin: base_coin.to_string(),
+            quote_coin: quote_coin.to_string(),
+            settle_coin: settle_coin.to_string(),
+            contract_size: dec(contract_size),
+            min_leverage: 1,
+            max_leverage: 200,
+            price_unit: dec("0.01"),
+            vol_unit: dec("1"),
+            min_vol: dec("1"),
+            max_vol: dec("1000000"),
+            taker_fee_rate: dec("0.0006"),
+            maker_fee_rate: dec("0"),
+            initial_margin_rate: dec("0.005"),
+            maintenance_margin_rate: dec("0.004"),
+        }
+    }
+
+    fn contracts() -> Arc<ContractSpecCache> {
+        let contracts = Arc::new(ContractSpecCache::new());
+        contracts.insert(
+            spec(
+                "ETH_USDT", "ETH", "USDT", "USDT", "0.01",
+            ),
+        );
+        contracts.insert(
+            spec(
+                "BTC_USD", "BTC", "USD", "BTC", "100",
+            ),
+        );
+        contracts
+    }
+
+    fn unrealized_pnl(monitor: &RiskMonitor) -> Option<Decimal> {
+        monitor
+            .snapshot()
+            .positions[0]
+            .unrealized_pnl
+    }
+
+    #[test]
+    fn unrealized_pnl_of_linear_and_inverse_contracts() {
+        for (symbol, position_type, price, pnl) in [
+            (
+                "ETH_USDT",
+                PositionType::Long,
+                "1020",
+                "2",
+            ),
+            (
+                "ETH_USDT",
+                PositionType::Short,
+                "1020",
+                "-2",
+            ),
+            // 10 contracts of 100 USD, from 1 BTC down to 0.8 BTC
+            (
+                "BTC_USD",
+                PositionType::Long,
+                "1250",
+                "0.2",
+            ),
+            (
+                "BTC_USD",
+                PositionType::Short,
+                "1250",
+                "-0.2",
+            ),
+        ] {
+            let monitor = RiskMonitor::new(
+                contracts(),
+                RiskThresholds::default(),
+            );
+            monitor.apply_message(
+                &position_on(
+                    symbol,
+                    position_type,
+                    1,
+                    "10",
+                    "0.1",
+                ),
+            );
+            monitor.apply_message(
+                &fair_price_on(
+                    symbol, price,
+                ),
+            );
+            assert_eq!(
+                unrealized_pnl(&monitor),
+                Some(dec(pnl)),
+                "{symbol} {position_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn alerts_and_clears_liquidation_distance() {
+        let monitor = RiskMonitor::new(
+            contracts(),
+            RiskThresholds::default().with_min_liquidation_distance(dec("0.05")),
+        );
+        monitor.apply_message(
+            &position(
+                1, "10", "0.1",
+            ),
+        );
+        let raised = monitor.apply_message(&fair_price("1060"));
+        assert_eq!(
+            raised
+                .iter()
+                .map(
+                    |alert| (
+                        alert.metric,
+                        alert.value,
+                        alert.breached
+                    )
+                )
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    RiskMetric::LiquidationDistance,
+                    dec("40") / dec("1060"),
+                    true
+                )
+            ]
+        );
+        let cleared = monitor.apply_message(&fair_price("1000"));
+        assert_eq!(
+            cleared
+                .iter()
+                .map(
+                    |alert| (
+                        alert.metric,
+                        alert.value,
+                        alert.breached
+                    )
+                )
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    RiskMetric::LiquidationDistance,
+                    dec("0.1"),
+                    false
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn alerts_when_crossing_thresholds() {
+        let monitor = RiskMonitor::new(
+            contracts(),
+            RiskThresholds::default()
+                .with_max_margin_ratio(dec("0.5"))
+                .with_min_liquidation_distance(dec("0.05")),
+        );
+        let mut alerts = monitor.alerts();
+
+        assert!(
+            monitor
+                .apply_message(&position(1, "10", "0.2"))
+                .is_empty()
+        );
+        assert!(
+            monitor
+                .apply_message(&fair_price("1020"))
+                .is_empty()
+        );
+        let snapshot = monitor.snapshot();
+        assert_eq!(
+            snapshot.positions[0].unrealized_pnl,
+            Some(dec("-2"))
+        );
+
+        // A short liquidated at 1100 is within 5% at 1060
+        let raised = monitor.apply_message(&fair_price("1060"));
+        assert_eq!(
+            raised
+                .iter()
+                .map(
+                    |alert| (
+                        alert.metric,
+                        alert.breached
+                    )
+                )
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    RiskMetric::LiquidationDistance,
+                    true
+                )
+            ]
+        );
+        assert!(
+            monitor
+                .apply_message(&fair_price("1070"))
+                .is_empty()
+        );
+        assert_eq!(
+            monitor
+                .apply_message(&position(2, "10", "0.6"))
+                .len(),
+            1
+        );
+        // Older updates are ignored
+        assert!(
+            monitor
+                .apply_message(&position(1, "10", "0.1"))
+                .is_empty()
+        );
+
+        let cleared = monitor.apply_message(
+            &position(
+                3, "0", "0",
+            ),
+        );
+        assert!(
+            cleared
+                .iter()
+                .all(|alert| !alert.breached)
+        );
+        assert_eq!(
+            cleared.len(),
+            2
+        );
+        assert!(
+            monitor
+                .snapshot()
+                .positions
+                .is_empty()
+        );
+        // A closed position stays closed when an older push arrives late
+        assert!(
+            monitor
+                .apply_message(&position(2, "10", "0.6"))
+                .is_empty()
+        );
+        assert!(
+            monitor
+                .snapshot()
+                .positions
+                .is_empty()
+        );
+        assert_eq!(
+            std::iter::from_fn(
+                || {
+                    alerts
+                        .try_recv()
+                        .ok()
+                }
+            )
+            .count(),
+            4
+        );
+    }
+}
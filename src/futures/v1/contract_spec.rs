@@ -0,0 +1,541 @@
+#[cfg(feature = "ws")]
+use crate::futures::ws::message::{ContractData, FuturesMessage};
+use crate::futures::{
+    error::ErrorCode,
+    result::ApiResult,
+    v1::{
+        endpoints::{
+            get_contract_detail::{GetContractDetail, GetContractDetailParams},
+            order::OrderParams,
+        },
+        models::{ContractDetail, OpenType, OrderSide, OrderType},
+    },
+};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum ContractRuleViolation {
+    #[error("Unknown contract {0}")]
+    UnknownContract(String),
+
+    #[error("{field} must be positive, got {value}")]
+    NotPositive { field: &'static str, value: Decimal },
+
+    #[error("Price {price} is not a multiple of the price unit {price_unit}")]
+    PriceNotOnUnit { price: Decimal, price_unit: Decimal },
+
+    #[error("Volume {volume} is not a multiple of the volume unit {vol_unit}")]
+    VolumeNotOnUnit { volume: Decimal, vol_unit: Decimal },
+
+    #[error("Volume {volume} is outside of {min}..={max}")]
+    VolumeOutOfRange { volume: Decimal, min: Decimal, max: Decimal },
+
+    #[error("Leverage is required to open an isolated position")]
+    LeverageRequired,
+
+    #[error("Leverage {leverage} is outside of {min}..={max}")]
+    LeverageOutOfRange { leverage: u32, min: u32, max: u32 },
+}
+
+impl ContractRuleViolation {
+    /// The error code the API would have rejected the order with
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            ContractRuleViolation::UnknownContract(_) => ErrorCode::ContractDoesNotExist,
+            ContractRuleViolation::NotPositive {
+                ..
+            }
+            | ContractRuleViolation::VolumeOutOfRange {
+                ..
+            } => ErrorCode::OrderQuantityError,
+            ContractRuleViolation::PriceNotOnUnit {
+                ..
+            }
+            | ContractRuleViolation::VolumeNotOnUnit {
+                ..
+            } => ErrorCode::PriceOrQuantityAccuracyError,
+            ContractRuleViolation::LeverageRequired
+            | ContractRuleViolation::LeverageOutOfRange {
+                ..
+            } => ErrorCode::LeverageRatioError,
+        }
+    }
+}
+
+/// The trading specification of a futures contract. Order volumes are in
+/// contracts of `contract_size` base coins each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractSpec {
+    pub symbol: String,
+    pub base_coin: String,
+    pub quote_coin: String,
+    pub settle_coin: String,
+    pub contract_size: Decimal,
+    pub min_leverage: u32,
+    pub max_leverage: u32,
+    pub price_unit: Decimal,
+    pub vol_unit: Decimal,
+    pub min_vol: Decimal,
+    pub max_vol: Decimal,
+    pub taker_fee_rate: Decimal,
+    pub maker_fee_rate: Decimal,
+    pub initial_margin_rate: Decimal,
+    pub maintenance_margin_rate: Decimal,
+}
+
+impl From<&ContractDetail> for ContractSpec {
+    fn from(detail: &ContractDetail) -> Self {
+        Self {
+            symbol: detail
+                .symbol
+                .clone(),
+            base_coin: detail
+                .base_coin
+                .clone(),
+            quote_coin: detail
+                .quote_coin
+                .clone(),
+            settle_coin: detail
+                .settle_coin
+                .clone(),
+            contract_size: detail.contract_size,
+            min_leverage: detail.min_leverage,
+            max_leverage: detail.max_leverage,
+            price_unit: detail.price_unit,
+            vol_unit: detail.vol_unit,
+            min_vol: detail.min_vol,
+            max_vol: detail.max_vol,
+            taker_fee_rate: detail.taker_fee_rate,
+            maker_fee_rate: detail.maker_fee_rate,
+            initial_margin_rate: detail.initial_margin_rate,
+            maintenance_margin_rate: detail.maintenance_margin_rate,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+impl From<&ContractData> for ContractSpec {
+    fn from(data: &ContractData) -> Self {
+        Self::from(&ContractDetail::from(data))
+    }
+}
+
+/// Rounds `value` to a multiple of `unit`
+fn round_to_unit(value: Decimal, unit: Decimal, strategy: RoundingStrategy) -> Decimal {
+    if unit.is_zero() {
+        return value;
+    }
+    ((value / unit).round_dp_with_strategy(
+        0, strategy,
+    ) * unit)
+        .normalize()
+}
+
+impl ContractSpec {
+    /// Inverse contracts are settled in the base coin and worth
+    /// `contract_size` quote coins each
+    pub fn is_inverse(&self) -> bool {
+        self.settle_coin == self.base_coin
+    }
+
+    pub fn round_price(&self, price: Decimal, strategy: RoundingStrategy) -> Decimal {
+        round_to_unit(
+            price,
+            self.price_unit,
+            strategy,
+        )
+    }
+
+    pub fn round_volume(&self, volume: Decimal, strategy: RoundingStrategy) -> Decimal {
+        round_to_unit(
+            volume,
+            self.vol_unit,
+            strategy,
+        )
+    }
+
+    /// Rounds `volume` down to the volume unit
+    pub fn truncate_volume(&self, volume: Decimal) -> Decimal {
+        self.round_volume(
+            volume,
+            RoundingStrategy::ToZero,
+        )
+    }
+
+    /// The volume, in contracts, for `quantity` base coins, rounded down to
+    /// the volume unit
+    pub fn volume_for_quantity(&self, quantity: Decimal) -> Decimal {
+        if self
+            .contract_size
+            .is_zero()
+        {
+            return Decimal::ZERO;
+        }
+        self.truncate_volume(quantity / self.contract_size)
+    }
+
+    /// The quantity, in base coins, of `volume` contracts
+    pub fn quantity_for_volume(&self, volume: Decimal) -> Decimal {
+        volume * self.contract_size
+    }
+
+    /// The value, in the quote coin, of `volume` contracts at `price`
+    pub fn notional(&self, volume: Decimal, price: Decimal) -> Decimal {
+        self.quantity_for_volume(volume) * price
+    }
+
+    /// The volume, in contracts, worth `notional` quote coins at `price`,
+    /// rounded down to the volume unit
+    pub fn volume_for_notional(&self, notional: Decimal, price: Decimal) -> Decimal {
+        if price.is_zero() {
+            return Decimal::ZERO;
+        }
+        self.volume_for_quantity(notional / price)
+    }
+
+    /// The initial margin of `volume` contracts at `price` with `leverage`
+    pub fn margin(&self, volume: Decimal, price: Decimal, leverage: u32) -> Decimal {
+        self.notional(
+            volume, price,
+        ) / Decimal::from(leverage.max(1))
+    }
+
+    /// Checks `params` the way the API would, before submitting them. The
+    /// price of market orders is not checked.
+    pub fn validate(&self, params: &OrderParams<'_>) -> Result<(), ContractRuleViolation> {
+        if params.symbol != self.symbol {
+            return Err(
+                ContractRuleViolation::UnknownContract(
+                    params
+                        .symbol
+                        .to_string(),
+                ),
+            );
+        }
+
+        let is_market = matches!(
+            params.order_type,
+            OrderType::MarketOrders | OrderType::ConvertMarketPriceToCurrentPrice
+        );
+        if !is_market {
+            if params.price <= Decimal::ZERO {
+                return Err(
+                    ContractRuleViolation::NotPositive {
+                        field: "price",
+                        value: params.price,
+                    },
+                );
+            }
+            if !self
+                .price_unit
+                .is_zero()
+                && !(params.price % self.price_unit).is_zero()
+            {
+                return Err(
+                    ContractRuleViolation::PriceNotOnUnit {
+                        price: params.price,
+                        price_unit: self.price_unit,
+                    },
+                );
+            }
+        }
+
+        if params.volume <= Decimal::ZERO {
+            return Err(
+                ContractRuleViolation::NotPositive {
+                    field: "volume",
+                    value: params.volume,
+                },
+            );
+        }
+        if !self
+            .vol_unit
+            .is_zero()
+            && !(params.volume % self.vol_unit).is_zero()
+        {
+            return Err(
+                ContractRuleViolation::VolumeNotOnUnit {
+                    volume: params.volume,
+                    vol_unit: self.vol_unit,
+                },
+            );
+        }
+        if params.volume < self.min_vol || params.volume > self.max_vol {
+            return Err(
+                ContractRuleViolation::VolumeOutOfRange {
+                    volume: params.volume,
+                    min: self.min_vol,
+                    max: self.max_vol,
+                },
+            );
+        }
+
+        let is_open = matches!(
+            params.side,
+            OrderSide::OpenLong | OrderSide::OpenShort
+        );
+        match params.leverage {
+            Some(leverage) if leverage < self.min_leverage || leverage > self.max_leverage => Err(
+                ContractRuleViolation::LeverageOutOfRange {
+                    leverage,
+                    min: self.min_leverage,
+                    max: self.max_leverage,
+                },
+            ),
+            None if is_open && params.open_type == OpenType::Isolated => Err(ContractRuleViolation::LeverageRequired),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Specifications of all contracts, fetched from the contract detail endpoint
+/// and kept up to date from the `push.contract` channel.
+#[derive(Debug, Default)]
+pub struct ContractSpecCache {
+    specs: RwLock<HashMap<String, Arc<ContractSpec>>>,
+}
+
+impl ContractSpecCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, spec: ContractSpec) {
+        self.specs
+            .write()
+            .expect("Contract spec lock poisoned")
+            .insert(
+                spec.symbol
+                    .clone(),
+                Arc::new(spec),
+            );
+    }
+
+    pub fn update(&self, contracts: &[ContractDetail]) {
+        for contract in contracts {
+            self.insert(ContractSpec::from(contract));
+        }
+    }
+
+    /// Fetches the details of all contracts
+    pub async fn refresh<C>(&self, client: &C) -> ApiResult<()>
+    where
+        C: GetContractDetail + Sync,
+    {
+        let output = client
+            .get_contract_detail(GetContractDetailParams::default())
+            .await?;
+        self.update(&output.contracts);
+        Ok(())
+    }
+
+    /// Applies `push.contract` messages, other messages are ignored
+    #[cfg(feature = "ws")]
+    pub fn apply_message(&self, message: &FuturesMessage) {
+        if let FuturesMessage::Contract(contract) = message {
+            self.insert(ContractSpec::from(&contract.data));
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Arc<ContractSpec>> {
+        self.specs
+            .read()
+            .expect("Contract spec lock poisoned")
+            .get(symbol)
+            .cloned()
+    }
+
+    pub fn validate(&self, params: &OrderParams<'_>) -> Result<(), ContractRuleViolation> {
+        self.get(params.symbol)
+            .ok_or_else(
+                || {
+                    ContractRuleViolation::UnknownContract(
+                        params
+                            .symbol
+                            .to_string(),
+                    )
+                },
+            )?
+            .validate(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn detail() -> ContractDetail {
+        serde_json::from_value(
+            serde_json::json!({
+                "symbol": "BTC_USDT",
+                "displayNameEn": "BTC_USDT PERPETUAL",
+                "baseCoin": "BTC",
+                "quoteCoin": "USDT",
+                "settleCoin": "USDT",
+                "contractSize": 0.0001,
+                "minLeverage": 1,
+                "maxLeverage": 125,
+                "priceScale": 1,
+                "volScale": 0,
+                "amountScale": 4,
+                "priceUnit": 0.1,
+                "volUnit": 1,
+                "minVol": 1,
+                "maxVol": 1000000,
+                "takerFeeRate": 0.0002,
+                "makerFeeRate": 0,
+                "maintenanceMarginRate": 0.004,
+                "initialMarginRate": 0.008,
+                "state": 0
+            }),
+        )
+        .unwrap()
+    }
+
+    fn order(price: &str, volume: &str, leverage: Option<u32>) -> OrderParams<'static> {
+        OrderParams {
+            symbol: "BTC_USDT",
+            price: dec(price),
+            volume: dec(volume),
+            leverage,
+            side: OrderSide::OpenLong,
+            order_type: OrderType::PriceLimitedOrder,
+            open_type: OpenType::Isolated,
+            position_id: None,
+            external_order_id: None,
+            stop_loss_price: None,
+            take_profit_price: None,
+            position_mode: None,
+            reduce_only: None,
+        }
+    }
+
+    #[test]
+    fn converts_between_quantity_notional_and_volume() {
+        let spec = ContractSpec::from(&detail());
+        assert_eq!(
+            spec.volume_for_quantity(dec("0.01234")),
+            dec("123")
+        );
+        assert_eq!(
+            spec.quantity_for_volume(dec("123")),
+            dec("0.0123")
+        );
+        assert_eq!(
+            spec.volume_for_notional(
+                dec("1000"),
+                dec("50000")
+            ),
+            dec("200")
+        );
+        assert_eq!(
+            spec.notional(
+                dec("200"),
+                dec("50000")
+            ),
+            dec("1000")
+        );
+        assert_eq!(
+            spec.margin(
+                dec("200"),
+                dec("50000"),
+                20
+            ),
+            dec("50")
+        );
+        assert_eq!(
+            spec.round_price(
+                dec("50000.16"),
+                RoundingStrategy::ToZero
+            ),
+            dec("50000.1")
+        );
+    }
+
+    #[test]
+    fn validates_order_params() {
+        let cache = ContractSpecCache::new();
+        cache.update(&[detail()]);
+
+        assert_eq!(
+            cache.validate(
+                &order(
+                    "50000.1",
+                    "10",
+                    Some(20)
+                )
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            cache.validate(
+                &order(
+                    "50000.15",
+                    "10",
+                    Some(20)
+                )
+            ),
+            Err(
+                ContractRuleViolation::PriceNotOnUnit {
+                    price: dec("50000.15"),
+                    price_unit: dec("0.1"),
+                }
+            )
+        );
+        assert_eq!(
+            cache.validate(
+                &order(
+                    "50000",
+                    "1.5",
+                    Some(20)
+                )
+            ),
+            Err(
+                ContractRuleViolation::VolumeNotOnUnit {
+                    volume: dec("1.5"),
+                    vol_unit: dec("1"),
+                }
+            )
+        );
+        assert_eq!(
+            cache.validate(
+                &order(
+                    "50000",
+                    "10",
+                    Some(200)
+                )
+            ),
+            Err(
+                ContractRuleViolation::LeverageOutOfRange {
+                    leverage: 200,
+                    min: 1,
+                    max: 125,
+                }
+            )
+        );
+        let violation = cache
+            .validate(
+                &order(
+                    "50000", "10", None,
+                ),
+            )
+            .unwrap_err();
+        assert_eq!(
+            violation,
+            ContractRuleViolation::LeverageRequired
+        );
+        assert_eq!(
+            violation.error_code(),
+            ErrorCode::LeverageRatioError
+        );
+    }
+}
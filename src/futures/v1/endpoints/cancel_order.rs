@@ -0,0 +1,59 @@
+use crate::futures::{auth::SignRequestParamsKind, response::ApiResponse, result::ApiResult, MexcFuturesApiClientWithAuthentication};
+use async_trait::async_trait;
+
+#[derive(Debug)]
+pub struct CancelOrderParams<'a> {
+    /// Up to 50 orders
+    pub order_ids: &'a [i64],
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelOrderResult {
+    pub order_id: i64,
+    /// 0 when the order was canceled
+    pub error_code: i32,
+    pub error_msg: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CancelOrderOutput {
+    pub results: Vec<CancelOrderResult>,
+}
+
+#[async_trait]
+pub trait CancelOrder {
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ApiResult<CancelOrderOutput>;
+}
+
+#[async_trait]
+impl CancelOrder for MexcFuturesApiClientWithAuthentication {
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ApiResult<CancelOrderOutput> {
+        let url = format!(
+            "{}/api/v1/private/order/cancel",
+            self.endpoint
+                .as_ref()
+        );
+        let auth_header_map = self.get_auth_header_map(
+            &params.order_ids,
+            SignRequestParamsKind::Body,
+        )?;
+        let response = self
+            .reqwest_client
+            .post(&url)
+            .headers(auth_header_map)
+            .json(&params.order_ids)
+            .send()
+            .await?;
+        let api_response = response
+            .json::<ApiResponse<Vec<CancelOrderResult>>>()
+            .await?;
+        let results = api_response.into_api_result()?;
+
+        Ok(
+            CancelOrderOutput {
+                results,
+            },
+        )
+    }
+}
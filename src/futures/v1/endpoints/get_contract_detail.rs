@@ -0,0 +1,91 @@
+use crate::futures::{response::ApiResponse, result::ApiResult, v1::models::ContractDetail, MexcFuturesApiClient, MexcFuturesApiClientWithAuthentication, MexcFuturesApiEndpoint};
+use async_trait::async_trait;
+use reqwest::Client;
+
+#[derive(Debug, Default)]
+pub struct GetContractDetailParams<'a> {
+    /// All contracts when `None`
+    pub symbol: Option<&'a str>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct GetContractDetailQuery<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<&'a str>,
+}
+
+impl<'a> From<GetContractDetailParams<'a>> for GetContractDetailQuery<'a> {
+    fn from(params: GetContractDetailParams<'a>) -> Self {
+        Self {
+            symbol: params.symbol,
+        }
+    }
+}
+
+/// The endpoint returns a single contract when asked for one symbol
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum ContractDetailData {
+    One(Box<ContractDetail>),
+    Many(Vec<ContractDetail>),
+}
+
+#[derive(Debug)]
+pub struct GetContractDetailOutput {
+    pub contracts: Vec<ContractDetail>,
+}
+
+#[async_trait]
+pub trait GetContractDetail {
+    async fn get_contract_detail(&self, params: GetContractDetailParams<'_>) -> ApiResult<GetContractDetailOutput>;
+}
+
+async fn default_impl(endpoint: &MexcFuturesApiEndpoint, reqwest: &Client, params: GetContractDetailParams<'_>) -> ApiResult<GetContractDetailOutput> {
+    let url = format!(
+        "{}/api/v1/contract/detail",
+        endpoint.as_ref()
+    );
+    let query = GetContractDetailQuery::from(params);
+    let response = reqwest
+        .get(&url)
+        .query(&query)
+        .send()
+        .await?;
+    let api_response = response
+        .json::<ApiResponse<ContractDetailData>>()
+        .await?;
+    let contracts = match api_response.into_api_result()? {
+        ContractDetailData::One(contract) => vec![*contract],
+        ContractDetailData::Many(contracts) => contracts,
+    };
+
+    Ok(
+        GetContractDetailOutput {
+            contracts,
+        },
+    )
+}
+
+#[async_trait]
+impl GetContractDetail for MexcFuturesApiClient {
+    async fn get_contract_detail(&self, params: GetContractDetailParams<'_>) -> ApiResult<GetContractDetailOutput> {
+        default_impl(
+            &self.endpoint,
+            &self.reqwest_client,
+            params,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl GetContractDetail for MexcFuturesApiClientWithAuthentication {
+    async fn get_contract_detail(&self, params: GetContractDetailParams<'_>) -> ApiResult<GetContractDetailOutput> {
+        default_impl(
+            &self.endpoint,
+            &self.reqwest_client,
+            params,
+        )
+        .await
+    }
+}
@@ -0,0 +1,61 @@
+use crate::futures::{auth::SignRequestParamsKind, response::ApiResponse, result::ApiResult, v1::models::OpenOrder, MexcFuturesApiClientWithAuthentication};
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait GetOrder {
+    /// Works for open and historical orders
+    async fn get_order(&self, order_id: i64) -> ApiResult<OpenOrder>;
+
+    /// Finds an order by the external order ID it was placed with
+    async fn get_order_by_external_id(&self, symbol: &str, external_order_id: &str) -> ApiResult<OpenOrder>;
+}
+
+#[async_trait]
+impl GetOrder for MexcFuturesApiClientWithAuthentication {
+    async fn get_order(&self, order_id: i64) -> ApiResult<OpenOrder> {
+        let url = format!(
+            "{}/api/v1/private/order/get/{}",
+            self.endpoint
+                .as_ref(),
+            order_id
+        );
+        let auth_header_map = self.get_auth_header_map(
+            &(),
+            SignRequestParamsKind::Query,
+        )?;
+        let response = self
+            .reqwest_client
+            .get(&url)
+            .headers(auth_header_map)
+            .send()
+            .await?;
+        let api_response = response
+            .json::<ApiResponse<OpenOrder>>()
+            .await?;
+        api_response.into_api_result()
+    }
+
+    async fn get_order_by_external_id(&self, symbol: &str, external_order_id: &str) -> ApiResult<OpenOrder> {
+        let url = format!(
+            "{}/api/v1/private/order/external/{}/{}",
+            self.endpoint
+                .as_ref(),
+            symbol,
+            external_order_id
+        );
+        let auth_header_map = self.get_auth_header_map(
+            &(),
+            SignRequestParamsKind::Query,
+        )?;
+        let response = self
+            .reqwest_client
+            .get(&url)
+            .headers(auth_header_map)
+            .send()
+            .await?;
+        let api_response = response
+            .json::<ApiResponse<OpenOrder>>()
+            .await?;
+        api_response.into_api_result()
+    }
+}
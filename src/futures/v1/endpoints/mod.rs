@@ -1,7 +1,10 @@
+pub mod cancel_order;
 pub mod get_account_asset;
 pub mod get_account_assets;
+pub mod get_contract_detail;
 pub mod get_kline;
 pub mod get_open_orders;
 pub mod get_open_positions;
+pub mod get_order;
 pub mod get_server_time;
 pub mod order;
@@ -1,2 +1,3 @@
+pub mod contract_spec;
 pub mod endpoints;
 pub mod models;
@@ -221,6 +221,33 @@ pub struct Kline {
     pub amount: Decimal,
 }
 
+#[derive(Debug, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractDetail {
+    pub symbol: String,
+    pub display_name_en: String,
+    pub base_coin: String,
+    pub quote_coin: String,
+    pub settle_coin: String,
+    /// Amount of the base coin per contract
+    pub contract_size: Decimal,
+    pub min_leverage: u32,
+    pub max_leverage: u32,
+    pub price_scale: u32,
+    pub vol_scale: u32,
+    pub amount_scale: u32,
+    pub price_unit: Decimal,
+    pub vol_unit: Decimal,
+    pub min_vol: Decimal,
+    pub max_vol: Decimal,
+    pub taker_fee_rate: Decimal,
+    pub maker_fee_rate: Decimal,
+    pub maintenance_margin_rate: Decimal,
+    pub initial_margin_rate: Decimal,
+    /// 0 enabled, 1 delivery, 2 delivered, 3 down, 4 paused
+    pub state: i32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,236 @@
+use crate::futures::ws::{message::FuturesMessage, topic::FuturesTopic};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// How long to wait for the server to reply to a (un)subscription.
+pub(crate) const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuturesRejectedTopic {
+    pub topic: FuturesTopic,
+    pub error: FuturesTopicRequestError,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FuturesTopicRequestError {
+    #[error("Rejected by the server: {reason}")]
+    Rejected { reason: String },
+
+    #[error("No reply from the server")]
+    NoReply,
+}
+
+#[derive(Debug)]
+struct PendingReply {
+    websocket_id: Uuid,
+    /// E.g. `rs.sub.deal`
+    channel: String,
+    tx: oneshot::Sender<Result<(), String>>,
+}
+
+/// The futures server replies with `{"channel":"rs.sub.deal","data":"success"}`
+/// without an id, so replies are matched in the order the requests were sent on
+/// the websocket.
+#[derive(Debug, Default)]
+pub(crate) struct FuturesPendingReplies {
+    pending: std::sync::Mutex<Vec<PendingReply>>,
+}
+
+impl FuturesPendingReplies {
+    /// `request` is the `sub.*` or `unsub.*` message that is sent
+    pub(crate) fn register(&self, websocket_id: Uuid, request: &serde_json::Value) -> oneshot::Receiver<Result<(), String>> {
+        let (tx, rx) = oneshot::channel();
+        let channel = format!(
+            "rs.{}",
+            request["method"]
+                .as_str()
+                .unwrap_or_default()
+        );
+        self.pending
+            .lock()
+            .expect("Pending replies lock poisoned")
+            .push(
+                PendingReply {
+                    websocket_id,
+                    channel,
+                    tx,
+                },
+            );
+        rx
+    }
+
+    /// Resolves the oldest request the message is a reply to. A `rs.error`
+    /// reply doesn't say which request failed, so it is taken to be about the
+    /// oldest request of the websocket. Returns whether a request was waiting
+    /// for the reply.
+    pub(crate) fn resolve(&self, websocket_id: Uuid, message: &FuturesMessage) -> bool {
+        let (channel, result) = match message {
+            FuturesMessage::LoginResponse(response) => (
+                response
+                    .channel
+                    .as_str(),
+                match response.data == "success" {
+                    true => Ok(()),
+                    false => Err(
+                        response
+                            .data
+                            .clone(),
+                    ),
+                },
+            ),
+            FuturesMessage::ErrorMsg(error) => (
+                "rs.error",
+                Err(
+                    error
+                        .msg
+                        .clone(),
+                ),
+            ),
+            _ => return false,
+        };
+
+        let mut pending = self
+            .pending
+            .lock()
+            .expect("Pending replies lock poisoned");
+        // Requests whose waiter gave up are of no use
+        pending.retain(
+            |pending_reply| {
+                !pending_reply
+                    .tx
+                    .is_closed()
+            },
+        );
+        let Some(index) = pending
+            .iter()
+            .position(|pending_reply| pending_reply.websocket_id == websocket_id && (pending_reply.channel == channel || channel == "rs.error"))
+        else {
+            return false;
+        };
+        pending
+            .remove(index)
+            .tx
+            .send(result)
+            .is_ok()
+    }
+
+    /// Drops the requests of a websocket that went away, so they resolve as
+    /// not replied to.
+    pub(crate) fn forget_websocket(&self, websocket_id: Uuid) {
+        self.pending
+            .lock()
+            .expect("Pending replies lock poisoned")
+            .retain(|pending_reply| pending_reply.websocket_id != websocket_id);
+    }
+}
+
+/// Waits for the reply to the request about `topic`.
+pub(crate) async fn wait_for_topic(rx: oneshot::Receiver<Result<(), String>>, topic: FuturesTopic) -> Result<FuturesTopic, FuturesRejectedTopic> {
+    match tokio::time::timeout(
+        REPLY_TIMEOUT,
+        rx,
+    )
+    .await
+    {
+        Ok(Ok(Ok(()))) => Ok(topic),
+        Ok(Ok(Err(reason))) => Err(
+            FuturesRejectedTopic {
+                topic,
+                error: FuturesTopicRequestError::Rejected {
+                    reason,
+                },
+            },
+        ),
+        _ => Err(
+            FuturesRejectedTopic {
+                topic,
+                error: FuturesTopicRequestError::NoReply,
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::ws::{message::LoginResponseMessage, topic::DealTopic};
+
+    fn response(channel: &str, data: &str) -> FuturesMessage {
+        FuturesMessage::LoginResponse(
+            LoginResponseMessage {
+                channel: channel.to_string(),
+                data: data.to_string(),
+                ts: Default::default(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn replies_are_matched_in_order() {
+        let pending_replies = FuturesPendingReplies::default();
+        let websocket_id = Uuid::new_v4();
+        let btc = FuturesTopic::Deal(DealTopic::new("BTC_USDT".to_string()));
+        let nope = FuturesTopic::Deal(DealTopic::new("NOPE_USDT".to_string()));
+        let btc_rx = pending_replies.register(
+            websocket_id,
+            &btc.to_subscription_message(),
+        );
+        let nope_rx = pending_replies.register(
+            websocket_id,
+            &nope.to_subscription_message(),
+        );
+
+        assert!(
+            !pending_replies.resolve(
+                Uuid::new_v4(),
+                &response(
+                    "rs.sub.deal",
+                    "success"
+                )
+            )
+        );
+        assert!(
+            pending_replies.resolve(
+                websocket_id,
+                &response(
+                    "rs.sub.deal",
+                    "success"
+                )
+            )
+        );
+        assert!(
+            pending_replies.resolve(
+                websocket_id,
+                &response(
+                    "rs.sub.deal",
+                    "contract not exists"
+                )
+            )
+        );
+
+        assert_eq!(
+            wait_for_topic(
+                btc_rx,
+                btc.clone()
+            )
+            .await,
+            Ok(btc)
+        );
+        assert_eq!(
+            wait_for_topic(
+                nope_rx,
+                nope.clone()
+            )
+            .await,
+            Err(
+                FuturesRejectedTopic {
+                    topic: nope,
+                    error: FuturesTopicRequestError::Rejected {
+                        reason: "contract not exists".to_string()
+                    },
+                }
+            )
+        );
+    }
+}
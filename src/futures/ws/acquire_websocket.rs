@@ -1,16 +1,35 @@
-use crate::futures::ws::{auth::FuturesWebsocketAuth, topic::FuturesTopic, FuturesWebsocketEntry, MexcFuturesWebsocketClient, SendableMessage};
-use async_channel::SendError;
+use crate::futures::ws::{
+    auth::FuturesWebsocketAuth,
+    filter::PersonalFilter,
+    lifecycle::{self, FuturesLifecycleEventKind},
+    message,
+    topic::FuturesTopic,
+    FuturesWebsocketEntry, Inner, MexcFuturesWebsocketClient, SendableMessage,
+};
+use async_channel::{SendError, Sender};
 use async_trait::async_trait;
-use futures::{SinkExt, StreamExt};
-use std::sync::Arc;
-use tokio::{sync::RwLock, time::Duration};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::{net::TcpStream, sync::RwLock, time::Duration};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{error::ProtocolError, Error, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use uuid::Uuid;
 
 #[derive(Default)]
 pub struct AcquireWebsocketsForTopicsParams {
     pub topics: Vec<FuturesTopic>,
     pub auth: Option<FuturesWebsocketAuth>,
+    pub personal_filter: Option<PersonalFilter>,
 }
 
 impl AcquireWebsocketsForTopicsParams {
@@ -18,6 +37,7 @@ impl AcquireWebsocketsForTopicsParams {
         Self {
             topics,
             auth: None,
+            personal_filter: None,
         }
     }
 
@@ -25,6 +45,11 @@ impl AcquireWebsocketsForTopicsParams {
         self.auth = Some(auth);
         self
     }
+
+    pub fn with_personal_filter(mut self, personal_filter: PersonalFilter) -> Self {
+        self.personal_filter = Some(personal_filter);
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -48,6 +73,9 @@ pub enum AcquireWebsocketForTopicsError {
 
     #[error("Serde error: {0}")]
     SerdeError(#[from] serde_json::Error),
+
+    #[error("The client has been shut down")]
+    ClientShutDown,
 }
 
 #[async_trait]
@@ -58,126 +86,66 @@ pub trait AcquireWebsocketsForTopics {
 #[async_trait]
 impl AcquireWebsocketsForTopics for MexcFuturesWebsocketClient {
     async fn acquire_websockets_for_topics(self: Arc<Self>, params: AcquireWebsocketsForTopicsParams) -> Result<AcquireWebsocketsForTopicsOutput, AcquireWebsocketForTopicsError> {
-        let (tx, _rx) = async_channel::unbounded::<SendableMessage>();
-
-        let websocket_entry = Arc::new(
-            FuturesWebsocketEntry {
-                id: Uuid::new_v4(),
-                auth: params
-                    .auth
-                    .clone(),
-                topics: Arc::new(RwLock::new(Vec::new())),
-                message_tx: Arc::new(RwLock::new(tx)),
-            },
-        );
-
-        let ws_url = self
-            .ws_endpoint
-            .as_ref();
-        let (ws_stream, _) = connect_async(ws_url.as_ref()).await?;
-        let (write, read) = ws_stream.split();
-
-        let _websocket_entry_clone = websocket_entry.clone();
-        let broadcast_tx_clone = self
-            .broadcast_tx
-            .clone();
-
-        tokio::spawn(
-            async move {
-                let mut read = read;
-                while let Some(message) = read
-                    .next()
-                    .await
-                {
-                    match message {
-                        Ok(Message::Text(text)) => {
-                            if let Ok(raw_message) = serde_json::from_str::<crate::futures::ws::message::RawFuturesMessage>(&text) {
-                                if let Ok(futures_message) = raw_message.try_into() {
-                                    let _ = broadcast_tx_clone.send(Arc::new(futures_message));
-                                }
-                            }
-                        }
-                        Ok(Message::Ping(_)) => {
-                            // Handle ping
-                        }
-                        Ok(Message::Pong(_)) => {
-                            // Handle pong
-                        }
-                        Ok(Message::Close(_)) => break,
-                        _ => {}
-                    }
-                }
-            },
-        );
-
-        let write = Arc::new(RwLock::new(write));
-
-        // Send login if auth
-        if let Some(auth) = &params.auth {
-            let req_time = chrono::Utc::now()
-                .timestamp_millis()
-                .to_string();
-            let signature = auth.generate_signature(&req_time);
-            let login_message = serde_json::json!({
-                "method": "login",
-                "param": {
-                    "apiKey": auth.api_key,
-                    "signature": signature,
-                    "reqTime": req_time
-                }
-            });
-            let sendable = SendableMessage::Login(login_message);
-            let json = serde_json::to_string(&sendable).unwrap();
-            let mut write_guard = write
-                .write()
-                .await;
-            write_guard
-                .send(Message::Text(json))
-                .await?;
-            drop(write_guard);
-        }
-
-        // Send ping periodically
-        let write_ping = write.clone();
-        tokio::spawn(
-            async move {
-                let mut interval = tokio::time::interval(Duration::from_secs(20));
-                loop {
-                    interval
-                        .tick()
-                        .await;
-                    let ping_message = SendableMessage::Ping;
-                    let json = serde_json::to_string(&ping_message).unwrap();
-                    if let Ok(mut write_guard) = write_ping.try_write() {
-                        let _ = write_guard
-                            .send(Message::Text(json))
-                            .await;
-                    }
-                }
-            },
-        );
-
-        // Send subscriptions
-        for topic in &params.topics {
-            let subscription = topic.to_subscription_message();
-            let sendable = SendableMessage::Subscription(subscription);
-            let json = serde_json::to_string(&sendable).unwrap();
-            let mut write_guard = write
-                .write()
-                .await;
-            write_guard
-                .send(Message::Text(json))
-                .await?;
-            drop(write_guard);
+        if self
+            .tasks
+            .is_closed()
+        {
+            return Err(AcquireWebsocketForTopicsError::ClientShutDown);
         }
 
         let mut inner = self
             .inner
             .write()
             .await;
-        inner
+
+        // A connection is logged in (or not) for its whole lifetime, so any
+        // open websocket with the same auth can carry the requested topics.
+        let mut existing_websocket_entry = None;
+        for websocket in inner
             .websockets
-            .push(websocket_entry.clone());
+            .iter()
+        {
+            if websocket.auth == params.auth
+                && !websocket
+                    .cancellation_token
+                    .read()
+                    .await
+                    .is_cancelled()
+            {
+                existing_websocket_entry = Some(websocket.clone());
+                break;
+            }
+        }
+
+        let websocket_entry = match existing_websocket_entry {
+            Some(websocket_entry) => {
+                if let Some(personal_filter) = params.personal_filter {
+                    let mut current_personal_filter = websocket_entry
+                        .personal_filter
+                        .write()
+                        .await;
+                    if current_personal_filter.as_ref() != Some(&personal_filter) {
+                        websocket_entry
+                            .message_tx
+                            .read()
+                            .await
+                            .send(SendableMessage::PersonalFilter(personal_filter.to_filter_message()))
+                            .await?;
+                        *current_personal_filter = Some(personal_filter);
+                    }
+                }
+                websocket_entry
+            }
+            None => {
+                create_websocket(
+                    self.clone(),
+                    &mut inner,
+                    params.auth,
+                    params.personal_filter,
+                )
+                .await?
+            }
+        };
 
         Ok(
             AcquireWebsocketsForTopicsOutput {
@@ -191,3 +159,462 @@ impl AcquireWebsocketsForTopics for MexcFuturesWebsocketClient {
         )
     }
 }
+
+async fn create_websocket(this: Arc<MexcFuturesWebsocketClient>, inner: &mut Inner, auth: Option<FuturesWebsocketAuth>, personal_filter: Option<PersonalFilter>) -> Result<Arc<FuturesWebsocketEntry>, AcquireWebsocketForTopicsError> {
+    let (ws_stream, _) = connect_async(
+        this.ws_endpoint
+            .as_ref()
+            .as_ref(),
+    )
+    .await?;
+    let (ws_tx, ws_rx) = ws_stream.split();
+    let (tx, rx) = async_channel::unbounded();
+
+    let cancellation_token = CancellationToken::new();
+
+    let websocket_id = Uuid::new_v4();
+
+    // Spawn all necessary tasks for this websocket...
+    spawn_websocket_sender_task(
+        this.clone(),
+        ws_tx,
+        rx,
+        cancellation_token.clone(),
+        websocket_id,
+    );
+    spawn_websocket_receiver_task(
+        this.clone(),
+        ws_rx,
+        cancellation_token.clone(),
+        websocket_id,
+    );
+    spawn_websocket_ping_task(
+        &this.tasks,
+        tx.clone(),
+        cancellation_token.clone(),
+    );
+
+    // The login has to be the first frame on the connection
+    if let Some(auth) = &auth {
+        tx.send(SendableMessage::Login(login_message(auth)))
+            .await?;
+        if let Some(personal_filter) = &personal_filter {
+            tx.send(SendableMessage::PersonalFilter(personal_filter.to_filter_message()))
+                .await?;
+        }
+    }
+
+    let websocket_entry = FuturesWebsocketEntry {
+        id: websocket_id,
+        auth,
+        topics: Arc::new(RwLock::new(Vec::new())),
+        personal_filter: Arc::new(RwLock::new(personal_filter)),
+        message_tx: Arc::new(RwLock::new(tx)),
+        cancellation_token: Arc::new(RwLock::new(cancellation_token)),
+        reconnecting: Arc::new(AtomicBool::new(false)),
+    };
+    let websocket_entry = Arc::new(websocket_entry);
+    inner
+        .websockets
+        .push(websocket_entry.clone());
+    this.emit_lifecycle_event(
+        websocket_id,
+        Vec::new(),
+        FuturesLifecycleEventKind::Connected,
+    );
+
+    Ok(websocket_entry)
+}
+
+pub(crate) fn login_message(auth: &FuturesWebsocketAuth) -> serde_json::Value {
+    let req_time = chrono::Utc::now()
+        .timestamp_millis()
+        .to_string();
+    let signature = auth.generate_signature(&req_time);
+    serde_json::json!({
+        "method": "login",
+        "param": {
+            "apiKey": auth.api_key,
+            "signature": signature,
+            "reqTime": req_time
+        }
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReconnectWebsocketError {
+    #[error("Unknown websocket")]
+    UnknownWebsocket,
+
+    #[error("The client has been shut down")]
+    ClientShutDown,
+
+    #[error("Tungestenite error: {0}")]
+    TungesteniteError(#[from] tokio_tungstenite::tungstenite::Error),
+
+    #[error("Resubscribe send error")]
+    ResubscribeSendError(#[from] async_channel::SendError<SendableMessage>),
+}
+
+/// Reconnects a closed websocket and resubscribes its topics. Websockets
+/// that can't be reconnected are forgotten, so they are not handed out again.
+async fn reconnect_websocket(this: Arc<MexcFuturesWebsocketClient>, websocket_id: Uuid) -> Result<(), ReconnectWebsocketError> {
+    if this
+        .tasks
+        .is_closed()
+    {
+        return Err(ReconnectWebsocketError::ClientShutDown);
+    }
+    let websocket = this
+        .inner
+        .read()
+        .await
+        .websockets
+        .iter()
+        .find(|ws| ws.id == websocket_id)
+        .cloned()
+        .ok_or(ReconnectWebsocketError::UnknownWebsocket)?;
+    // Both tasks of a connection end up here, the one that comes second finds
+    // the reconnect in flight or done
+    if websocket
+        .reconnecting
+        .swap(
+            true,
+            Ordering::AcqRel,
+        )
+    {
+        return Ok(());
+    }
+    let result = if websocket
+        .cancellation_token
+        .read()
+        .await
+        .is_cancelled()
+    {
+        reconnect_entry(
+            &this, &websocket,
+        )
+        .await
+    } else {
+        Ok(())
+    };
+    websocket
+        .reconnecting
+        .store(
+            false,
+            Ordering::Release,
+        );
+    if result.is_err() {
+        this.inner
+            .write()
+            .await
+            .websockets
+            .retain(|ws| ws.id != websocket_id);
+    }
+    result
+}
+
+async fn reconnect_entry(this: &Arc<MexcFuturesWebsocketClient>, websocket: &FuturesWebsocketEntry) -> Result<(), ReconnectWebsocketError> {
+    let websocket_id = websocket.id;
+    tracing::debug!(
+        "Reconnecting futures websocket with id...: {}",
+        websocket_id
+    );
+    this.emit_lifecycle_event(
+        websocket_id,
+        websocket
+            .topics
+            .read()
+            .await
+            .clone(),
+        FuturesLifecycleEventKind::Reconnecting,
+    );
+
+    let (ws_stream, _) = connect_async(
+        this.ws_endpoint
+            .as_ref()
+            .as_ref(),
+    )
+    .await?;
+
+    tracing::debug!(
+        "Reconnected futures websocket with id: {}",
+        websocket_id
+    );
+
+    let (ws_tx, ws_rx) = ws_stream.split();
+    let (tx, rx) = async_channel::unbounded();
+
+    let cancellation_token = CancellationToken::new();
+
+    // Spawn all necessary tasks for this websocket...
+    spawn_websocket_sender_task(
+        this.clone(),
+        ws_tx,
+        rx,
+        cancellation_token.clone(),
+        websocket_id,
+    );
+    spawn_websocket_receiver_task(
+        this.clone(),
+        ws_rx,
+        cancellation_token.clone(),
+        websocket_id,
+    );
+    spawn_websocket_ping_task(
+        &this.tasks,
+        tx.clone(),
+        cancellation_token.clone(),
+    );
+
+    if let Some(auth) = &websocket.auth {
+        tx.send(SendableMessage::Login(login_message(auth)))
+            .await?;
+        // The server forgets the filter together with the connection
+        if let Some(personal_filter) = websocket
+            .personal_filter
+            .read()
+            .await
+            .as_ref()
+        {
+            tx.send(SendableMessage::PersonalFilter(personal_filter.to_filter_message()))
+                .await?;
+        }
+    }
+
+    let mut message_tx = websocket
+        .message_tx
+        .write()
+        .await;
+    *message_tx = tx;
+    *websocket
+        .cancellation_token
+        .write()
+        .await = cancellation_token;
+
+    let topics = websocket
+        .topics
+        .read()
+        .await;
+    this.emit_lifecycle_event(
+        websocket_id,
+        topics.clone(),
+        FuturesLifecycleEventKind::Connected,
+    );
+    for topic in topics.iter() {
+        message_tx
+            .send(SendableMessage::Subscription(this.subscription_message(topic)))
+            .await?;
+    }
+    if !topics.is_empty() {
+        tracing::debug!(
+            "Resubscribed to all topics for futures websocket with id: {}",
+            websocket_id
+        );
+    }
+
+    Ok(())
+}
+
+fn spawn_websocket_sender_task(
+    this: Arc<MexcFuturesWebsocketClient>,
+    mut ws_tx: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+    rx: async_channel::Receiver<SendableMessage>,
+    cancellation_token: CancellationToken,
+    websocket_id: Uuid,
+) {
+    let this = this.task_handle();
+    let tasks = this
+        .tasks
+        .clone();
+    tasks.spawn(
+        async move {
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => {
+                        // Flush whatever was queued before the cancellation (e.g. the final
+                        // unsubscriptions) and close the connection properly.
+                        while let Ok(message) = rx.try_recv() {
+                            let json = serde_json::to_string(&message).expect("Failed to serialize message");
+                            if ws_tx.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
+                        let _ = ws_tx.send(Message::Close(None)).await;
+                        break;
+                    }
+                    message_result = rx.recv() => {
+                        let message = match message_result {
+                            Ok(x) => x,
+                            Err(err) => {
+                                disconnect(&this, &cancellation_token, websocket_id).await;
+                                tracing::error!("Error receiving message from channel: {}", err);
+                                break;
+                            }
+                        };
+                        let json = serde_json::to_string(&message).expect("Failed to serialize message");
+                        let message = Message::Text(json);
+
+                        match ws_tx.send(message).await {
+                            Ok(_) => {}
+                            Err(err) => match err {
+                                Error::ConnectionClosed | Error::AlreadyClosed | Error::Protocol(ProtocolError::ResetWithoutClosingHandshake) => {
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
+                                    tracing::error!("Failed to send message to futures websocket because the connection was closed: {}", err);
+                                    if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
+                                        tracing::error!("Failed to reconnect futures websocket: {}", err);
+                                    }
+                                    break;
+                                }
+                                _ => {
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
+                                    tracing::error!("Error sending message to futures websocket: {}", err);
+                                    break;
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        },
+    );
+}
+
+fn spawn_websocket_receiver_task(this: Arc<MexcFuturesWebsocketClient>, mut ws_rx: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>, cancellation_token: CancellationToken, websocket_id: Uuid) {
+    let this = this.task_handle();
+    let tasks = this
+        .tasks
+        .clone();
+    tasks.spawn(
+        async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = cancellation_token.cancelled() => {
+                        break;
+                    }
+                    message_result_opt = ws_rx.next() => {
+                        let message = match message_result_opt {
+                            Some(Ok(message)) => message,
+                            Some(Err(Error::ConnectionClosed | Error::AlreadyClosed | Error::Protocol(ProtocolError::ResetWithoutClosingHandshake))) | None => {
+                                disconnect(&this, &cancellation_token, websocket_id).await;
+                                tracing::error!("Failed to receive message from futures websocket because the connection was closed");
+                                if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
+                                    tracing::error!("Failed to reconnect futures websocket: {}", err);
+                                }
+                                break;
+                            }
+                            Some(Err(err)) => {
+                                disconnect(&this, &cancellation_token, websocket_id).await;
+                                tracing::error!("Error receiving message from futures websocket: {}", err);
+                                break;
+                            }
+                        };
+                        if let Some(recorder) = &this.recorder {
+                            if let Err(err) = recorder.record(websocket_id, &message) {
+                                tracing::warn!("Failed to record futures websocket frame: {}", err);
+                            }
+                        }
+
+                        let text = match message {
+                            Message::Text(text) => text,
+                            Message::Binary(data) => match message::gunzip(&data) {
+                                Ok(text) => text,
+                                Err(err) => {
+                                    tracing::trace!("Failed to inflate futures binary frame: {}", err);
+                                    continue;
+                                }
+                            },
+                            Message::Close(frame) => {
+                                disconnect(&this, &cancellation_token, websocket_id).await;
+                                tracing::debug!("Futures websocket closed by server: {:?}", frame);
+                                if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
+                                    tracing::error!("Failed to reconnect futures websocket: {}", err);
+                                }
+                                break;
+                            }
+                            _ => continue,
+                        };
+                        let raw_message = match serde_json::from_str::<message::RawFuturesMessage>(&text) {
+                            Ok(x) => x,
+                            Err(err) => {
+                                tracing::trace!("Failed to deserialize futures message: {}\njson: {}", err, &text);
+                                continue;
+                            }
+                        };
+                        let Ok(futures_message) = message::FuturesMessage::try_from(raw_message) else {
+                            tracing::trace!("Received unrecognized futures message: {}", &text);
+                            continue;
+                        };
+                        if matches!(futures_message, message::FuturesMessage::LoginResponse(_) | message::FuturesMessage::ErrorMsg(_)) {
+                            this.pending_replies.resolve(websocket_id, &futures_message);
+                            let topics = this.websocket_topics(websocket_id).await;
+                            if let Some((topics, kind)) = lifecycle::reply_event(&topics, &futures_message) {
+                                this.emit_lifecycle_event(websocket_id, topics, kind);
+                            }
+                        }
+                        dispatch_message(&this, futures_message).await;
+                    }
+                }
+            }
+        },
+    );
+}
+
+fn spawn_websocket_ping_task(tasks: &TaskTracker, sender: Sender<SendableMessage>, cancellation_token: CancellationToken) {
+    tasks.spawn(
+        async move {
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => {
+                        break;
+                    }
+                    _ = tokio::time::sleep(Duration::from_secs(20)) => {
+                        match sender.send(SendableMessage::Ping).await {
+                            Ok(_) => {}
+                            Err(err) => {
+                                cancellation_token.cancel();
+                                tracing::error!("Failed to send ping: {}", err);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        },
+    );
+}
+
+/// Hands a received message to the topic streams, bounded streams and the
+/// broadcast. Replays go through here as well.
+pub(crate) async fn dispatch_message(this: &MexcFuturesWebsocketClient, message: message::FuturesMessage) {
+    let message = Arc::new(message);
+    this.topic_routes
+        .route(&message);
+    this.bounded_subscribers
+        .dispatch(&message)
+        .await;
+    // Nobody listening is fine
+    let _ = this
+        .broadcast_tx
+        .send(message);
+}
+
+/// Stops all tasks of the connection, notifying lifecycle subscribers the first
+/// time.
+async fn disconnect(this: &MexcFuturesWebsocketClient, cancellation_token: &CancellationToken, websocket_id: Uuid) {
+    if cancellation_token.is_cancelled() {
+        return;
+    }
+    cancellation_token.cancel();
+    this.pending_replies
+        .forget_websocket(websocket_id);
+    let topics = this
+        .websocket_topics(websocket_id)
+        .await;
+    this.emit_lifecycle_event(
+        websocket_id,
+        topics,
+        FuturesLifecycleEventKind::Disconnected,
+    );
+}
@@ -0,0 +1,167 @@
+/// Restricts which personal channels are pushed on a logged in futures
+/// websocket. Without a filter MEXC pushes every personal channel after login.
+///
+/// Only [`PersonalFilterKind::Order`], [`PersonalFilterKind::OrderDeal`] and
+/// [`PersonalFilterKind::Position`] can be narrowed down to specific symbols,
+/// the other kinds are always pushed for the whole account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PersonalFilter {
+    pub entries: Vec<PersonalFilterEntry>,
+}
+
+impl PersonalFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive all events of the given kind
+    pub fn with(mut self, kind: PersonalFilterKind) -> Self {
+        self.entries
+            .push(PersonalFilterEntry::new(kind));
+        self
+    }
+
+    /// Receive only the events of the given kind that concern one of the
+    /// symbols.
+    pub fn with_symbols(mut self, kind: PersonalFilterKind, symbols: Vec<String>) -> Self {
+        self.entries
+            .push(PersonalFilterEntry::new(kind).with_symbols(symbols));
+        self
+    }
+
+    pub fn to_filter_message(&self) -> serde_json::Value {
+        let filters = self
+            .entries
+            .iter()
+            .map(
+                |entry| {
+                    if entry
+                        .kind
+                        .supports_symbols()
+                        && !entry
+                            .symbols
+                            .is_empty()
+                    {
+                        serde_json::json!({
+                            "filter": entry.kind.as_ref(),
+                            "rules": entry.symbols
+                        })
+                    } else {
+                        serde_json::json!({
+                            "filter": entry.kind.as_ref()
+                        })
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "method": "personal.filter",
+            "param": {
+                "filters": filters
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PersonalFilterEntry {
+    pub kind: PersonalFilterKind,
+    /// Empty means all symbols
+    pub symbols: Vec<String>,
+}
+
+impl PersonalFilterEntry {
+    pub fn new(kind: PersonalFilterKind) -> Self {
+        Self {
+            kind,
+            symbols: Vec::new(),
+        }
+    }
+
+    pub fn with_symbols(mut self, symbols: Vec<String>) -> Self {
+        self.symbols
+            .extend(symbols);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PersonalFilterKind {
+    /// `push.personal.asset`
+    Asset,
+    /// `push.personal.order`
+    Order,
+    /// `push.personal.order.deal`
+    OrderDeal,
+    /// `push.personal.position`
+    Position,
+    /// `push.personal.plan.order`
+    PlanOrder,
+    /// `push.personal.stop.order`
+    StopOrder,
+    /// `push.personal.stop.planorder`
+    StopPlanOrder,
+    /// `push.personal.risk.limit`
+    RiskLimit,
+    /// `push.personal.adl.level`
+    AdlLevel,
+}
+
+impl PersonalFilterKind {
+    pub fn supports_symbols(&self) -> bool {
+        matches!(
+            self,
+            PersonalFilterKind::Order | PersonalFilterKind::OrderDeal | PersonalFilterKind::Position
+        )
+    }
+}
+
+impl AsRef<str> for PersonalFilterKind {
+    fn as_ref(&self) -> &str {
+        match self {
+            PersonalFilterKind::Asset => "asset",
+            PersonalFilterKind::Order => "order",
+            PersonalFilterKind::OrderDeal => "order.deal",
+            PersonalFilterKind::Position => "position",
+            PersonalFilterKind::PlanOrder => "plan.order",
+            PersonalFilterKind::StopOrder => "stop.order",
+            PersonalFilterKind::StopPlanOrder => "stop.planorder",
+            PersonalFilterKind::RiskLimit => "risk.limit",
+            PersonalFilterKind::AdlLevel => "adl.level",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_message() {
+        let filter = PersonalFilter::new()
+            .with(PersonalFilterKind::Asset)
+            .with_symbols(
+                PersonalFilterKind::Order,
+                vec!["BTC_USDT".to_string()],
+            )
+            .with_symbols(
+                PersonalFilterKind::RiskLimit,
+                vec!["BTC_USDT".to_string()],
+            );
+        let expected = serde_json::json!({
+            "method": "personal.filter",
+            "param": {
+                "filters": [
+                    { "filter": "asset" },
+                    { "filter": "order", "rules": ["BTC_USDT"] },
+                    { "filter": "risk.limit" }
+                ]
+            }
+        });
+        assert_eq!(
+            filter.to_filter_message(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::futures::ws::{message::FuturesMessage, topic::FuturesTopic, MexcFuturesWebsocketClient};
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct FuturesLifecycleEvent {
+    pub websocket_id: Uuid,
+    /// The topics affected by the event. For connection events these are all
+    /// topics of the websocket.
+    pub topics: Vec<FuturesTopic>,
+    pub kind: FuturesLifecycleEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuturesLifecycleEventKind {
+    Connected,
+    Disconnected,
+    Reconnecting,
+    SubscriptionAcked,
+    SubscriptionFailed { reason: String },
+    LoginOk,
+    LoginFailed { reason: String },
+}
+
+pub trait FuturesLifecycle {
+    fn lifecycle_events<'a>(self: Arc<Self>) -> BoxStream<'a, FuturesLifecycleEvent>;
+}
+
+impl FuturesLifecycle for MexcFuturesWebsocketClient {
+    fn lifecycle_events<'a>(self: Arc<Self>) -> BoxStream<'a, FuturesLifecycleEvent> {
+        let mut rx = self
+            .lifecycle_tx
+            .subscribe();
+        let stream = async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => yield event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Futures lifecycle event stream lagged behind, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+        stream.boxed()
+    }
+}
+
+impl MexcFuturesWebsocketClient {
+    pub(crate) fn emit_lifecycle_event(&self, websocket_id: Uuid, topics: Vec<FuturesTopic>, kind: FuturesLifecycleEventKind) {
+        tracing::debug!(
+            "Futures websocket {} lifecycle event: {:?}",
+            websocket_id,
+            kind
+        );
+        // Nobody listening is fine
+        let _ = self
+            .lifecycle_tx
+            .send(
+                FuturesLifecycleEvent {
+                    websocket_id,
+                    topics,
+                    kind,
+                },
+            );
+    }
+
+    pub(crate) async fn websocket_topics(&self, websocket_id: Uuid) -> Vec<FuturesTopic> {
+        let websocket = self
+            .inner
+            .read()
+            .await
+            .websockets
+            .iter()
+            .find(|websocket| websocket.id == websocket_id)
+            .cloned();
+        let Some(websocket) = websocket else {
+            return Vec::new();
+        };
+        let topics = websocket
+            .topics
+            .read()
+            .await
+            .clone();
+        topics
+    }
+}
+
+/// Interprets the `rs.*` replies of the server. A `rs.sub.deal` reply doesn't
+/// say which symbol it is about, so it applies to all deal topics of the
+/// websocket.
+pub(crate) fn reply_event(
+    topics: &[FuturesTopic],
+    message: &FuturesMessage,
+) -> Option<(
+    Vec<FuturesTopic>,
+    FuturesLifecycleEventKind,
+)> {
+    match message {
+        FuturesMessage::LoginResponse(response) => {
+            let succeeded = response.data == "success";
+            if response.channel == "rs.login" {
+                let kind = match succeeded {
+                    true => FuturesLifecycleEventKind::LoginOk,
+                    false => FuturesLifecycleEventKind::LoginFailed {
+                        reason: response
+                            .data
+                            .clone(),
+                    },
+                };
+                return Some(
+                    (
+                        Vec::new(),
+                        kind,
+                    ),
+                );
+            }
+            if response.channel == "rs.error" {
+                return Some(
+                    (
+                        Vec::new(),
+                        FuturesLifecycleEventKind::SubscriptionFailed {
+                            reason: response
+                                .data
+                                .clone(),
+                        },
+                    ),
+                );
+            }
+
+            let method = response
+                .channel
+                .strip_prefix("rs.")?;
+            if !method.starts_with("sub.") {
+                return None;
+            }
+            let affected_topics = topics
+                .iter()
+                .filter(|topic| topic.to_subscription_message()["method"] == method)
+                .cloned()
+                .collect::<Vec<_>>();
+            let kind = match succeeded {
+                true => FuturesLifecycleEventKind::SubscriptionAcked,
+                false => FuturesLifecycleEventKind::SubscriptionFailed {
+                    reason: response
+                        .data
+                        .clone(),
+                },
+            };
+            Some(
+                (
+                    affected_topics,
+                    kind,
+                ),
+            )
+        }
+        FuturesMessage::ErrorMsg(error) => Some(
+            (
+                Vec::new(),
+                FuturesLifecycleEventKind::SubscriptionFailed {
+                    reason: error
+                        .msg
+                        .clone(),
+                },
+            ),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::ws::{
+        message::LoginResponseMessage,
+        topic::{DealTopic, DepthTopic},
+    };
+
+    fn response(channel: &str, data: &str) -> FuturesMessage {
+        FuturesMessage::LoginResponse(
+            LoginResponseMessage {
+                channel: channel.to_string(),
+                data: data.to_string(),
+                ts: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn replies() {
+        let topics = vec![
+            FuturesTopic::Deal(DealTopic::new("BTC_USDT".to_string())),
+            FuturesTopic::Depth(DepthTopic::new("BTC_USDT".to_string())),
+        ];
+
+        let (affected_topics, kind) = reply_event(
+            &topics,
+            &response(
+                "rs.sub.deal",
+                "success",
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            affected_topics,
+            vec![topics[0].clone()]
+        );
+        assert_eq!(
+            kind,
+            FuturesLifecycleEventKind::SubscriptionAcked
+        );
+
+        let (_, kind) = reply_event(
+            &topics,
+            &response(
+                "rs.login",
+                "invalid signature",
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            kind,
+            FuturesLifecycleEventKind::LoginFailed {
+                reason: "invalid signature".to_string()
+            }
+        );
+
+        assert!(
+            reply_event(
+                &topics,
+                &response(
+                    "rs.unsub.deal",
+                    "success"
+                )
+            )
+            .is_none()
+        );
+    }
+}
@@ -1,11 +1,17 @@
+use crate::futures::v1::models::{ContractDetail, KlineInterval, OpenOrder, OpenPosition, OpenType, OrderCategory, OrderErrorCode, OrderSide, OrderState, OrderType, PositionMode, PositionState, PositionType};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::Deserialize;
+use std::io::Read;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FuturesMessage {
     Tickers(TickersMessage),
     Ticker(TickerMessage),
     Deal(DealMessage),
     Depth(DepthMessage),
+    /// Snapshot of the top levels, pushed for [`crate::futures::ws::topic::FuturesTopic::DepthFull`]
+    DepthFull(DepthMessage),
     DepthStep(DepthStepMessage),
     Kline(KlineMessage),
     FundingRate(FundingRateMessage),
@@ -36,7 +42,7 @@ pub enum FuturesMessage {
     ErrorMsg(ErrorMessage),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub(crate) enum RawFuturesMessage {
     Pong(PongMessage),
@@ -45,33 +51,47 @@ pub(crate) enum RawFuturesMessage {
     PushMessage(RawPushMessage),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct RawPushMessage {
     pub channel: String,
     pub data: serde_json::Value,
     pub symbol: Option<String>,
-    pub ts: Option<i64>,
+    #[serde(
+        default,
+        with = "chrono::serde::ts_milliseconds_option"
+    )]
+    pub ts: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PongMessage {
     pub channel: String,
     pub data: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct LoginResponseMessage {
     pub channel: String,
     pub data: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ErrorMessage {
     pub channel: String,
     pub code: i32,
     pub msg: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
+}
+
+/// Inflates a binary frame, which is how pushes arrive for topics subscribed
+/// with gzip enabled
+pub(crate) fn gunzip(data: &[u8]) -> std::io::Result<String> {
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(data).read_to_string(&mut text)?;
+    Ok(text)
 }
 
 impl TryFrom<RawFuturesMessage> for FuturesMessage {
@@ -144,6 +164,24 @@ impl TryFrom<RawFuturesMessage> for FuturesMessage {
                             ),
                         )
                     }
+                    "push.depth.full" => {
+                        let data: DepthData = serde_json::from_value(raw.data).map_err(|_| ())?;
+                        let symbol = raw
+                            .symbol
+                            .unwrap_or_default();
+                        let ts = raw
+                            .ts
+                            .unwrap_or_default();
+                        Ok(
+                            FuturesMessage::DepthFull(
+                                DepthMessage {
+                                    data,
+                                    symbol,
+                                    ts,
+                                },
+                            ),
+                        )
+                    }
                     "push.depth.step" => {
                         let data: DepthStepData = serde_json::from_value(raw.data).map_err(|_| ())?;
                         let symbol = raw
@@ -526,187 +564,202 @@ impl TryFrom<RawFuturesMessage> for FuturesMessage {
 
 // Define the message structs here for simplicity
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TickersMessage {
     pub data: Vec<TickerData>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TickerData {
     pub symbol: String,
-    pub timestamp: Option<i64>,
-    pub last_price: Option<f64>,
-    pub bid1: Option<f64>,
-    pub ask1: Option<f64>,
-    pub hold_vol: Option<f64>,
-    pub funding_rate: Option<f64>,
-    pub rise_fall_rate: Option<f64>,
-    pub rise_fall_value: Option<f64>,
-    pub volume24: Option<f64>,
-    pub amount24: Option<f64>,
-    pub fair_price: Option<f64>,
-    pub index_price: Option<f64>,
-    pub max_bid_price: Option<f64>,
-    pub min_ask_price: Option<f64>,
-    pub lower24_price: Option<f64>,
-    pub high24_price: Option<f64>,
-}
-
-#[derive(Debug, Deserialize)]
+    #[serde(
+        default,
+        with = "chrono::serde::ts_milliseconds_option"
+    )]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub last_price: Option<Decimal>,
+    pub bid1: Option<Decimal>,
+    pub ask1: Option<Decimal>,
+    pub hold_vol: Option<Decimal>,
+    pub funding_rate: Option<Decimal>,
+    pub rise_fall_rate: Option<Decimal>,
+    pub rise_fall_value: Option<Decimal>,
+    pub volume24: Option<Decimal>,
+    pub amount24: Option<Decimal>,
+    pub fair_price: Option<Decimal>,
+    pub index_price: Option<Decimal>,
+    pub max_bid_price: Option<Decimal>,
+    pub min_ask_price: Option<Decimal>,
+    pub lower24_price: Option<Decimal>,
+    pub high24_price: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TickerMessage {
     pub data: TickerData,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DealMessage {
     pub symbol: String,
     pub data: Vec<DealData>,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DealData {
-    pub p: f64,
-    pub v: f64,
+    pub p: Decimal,
+    pub v: Decimal,
     #[serde(rename = "T")]
     pub trade_side: i32,
     #[serde(rename = "O")]
     pub open_close_flag: i32,
     #[serde(rename = "M")]
     pub self_trade: i32,
-    #[serde(rename = "t")]
-    pub trade_time: i64,
+    #[serde(
+        rename = "t",
+        with = "chrono::serde::ts_milliseconds"
+    )]
+    pub trade_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DepthMessage {
     pub data: DepthData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DepthData {
     pub asks: Vec<DepthLevel>,
     pub bids: Vec<DepthLevel>,
     pub version: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DepthLevel(
-    pub f64,
-    pub f64,
-    pub f64,
+    pub Decimal,
+    pub Decimal,
+    pub Decimal,
 );
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DepthStepMessage {
     pub data: DepthStepData,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DepthStepData {
-    pub ask_market_level_price: f64,
+    pub ask_market_level_price: Decimal,
     pub asks: Vec<DepthStepLevel>,
-    pub bid_market_level_price: f64,
+    pub bid_market_level_price: Decimal,
     pub bids: Vec<DepthStepLevel>,
-    pub ct: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ct: DateTime<Utc>,
     pub version: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DepthStepLevel(
-    pub f64,
-    pub f64,
-    pub f64,
+    pub Decimal,
+    pub Decimal,
+    pub Decimal,
 );
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct KlineMessage {
     pub data: KlineData,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct KlineData {
     pub symbol: String,
-    pub interval: String,
-    pub a: f64,
-    pub q: f64,
-    pub o: f64,
-    pub c: f64,
-    pub h: f64,
-    pub l: f64,
-    pub v: f64,
-    pub ro: f64,
-    pub rc: f64,
-    pub rh: f64,
-    pub rl: f64,
-    pub t: i64,
-}
-
-#[derive(Debug, Deserialize)]
+    pub interval: KlineInterval,
+    pub a: Decimal,
+    pub q: Decimal,
+    pub o: Decimal,
+    pub c: Decimal,
+    pub h: Decimal,
+    pub l: Decimal,
+    pub v: Decimal,
+    pub ro: Decimal,
+    pub rc: Decimal,
+    pub rh: Decimal,
+    pub rl: Decimal,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub t: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FundingRateMessage {
     pub data: FundingRateData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct FundingRateData {
-    pub rate: f64,
+    pub rate: Decimal,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexPriceMessage {
     pub data: IndexPriceData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct IndexPriceData {
-    pub price: f64,
+    pub price: Decimal,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FairPriceMessage {
     pub data: FairPriceData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct FairPriceData {
-    pub price: f64,
+    pub price: Decimal,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContractMessage {
     pub data: ContractData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContractData {
     pub symbol: String,
@@ -719,43 +772,44 @@ pub struct ContractData {
     pub quote_coin_name: String,
     pub future_type: i32,
     pub settle_coin: String,
-    pub contract_size: f64,
-    pub min_leverage: i32,
-    pub max_leverage: i32,
-    pub price_scale: i32,
-    pub vol_scale: i32,
-    pub amount_scale: i32,
-    pub price_unit: f64,
-    pub vol_unit: f64,
-    pub min_vol: f64,
-    pub max_vol: f64,
-    pub limit_max_vol: f64,
-    pub bid_limit_price_rate: f64,
-    pub ask_limit_price_rate: f64,
-    pub taker_fee_rate: f64,
-    pub maker_fee_rate: f64,
-    pub maintenance_margin_rate: f64,
-    pub initial_margin_rate: f64,
-    pub risk_base_vol: f64,
-    pub risk_incr_vol: f64,
-    pub risk_incr_mmr: f64,
-    pub risk_incr_imr: f64,
-    pub risk_level_limit: f64,
-    pub price_coefficient_variation: f64,
+    pub contract_size: Decimal,
+    pub min_leverage: u32,
+    pub max_leverage: u32,
+    pub price_scale: u32,
+    pub vol_scale: u32,
+    pub amount_scale: u32,
+    pub price_unit: Decimal,
+    pub vol_unit: Decimal,
+    pub min_vol: Decimal,
+    pub max_vol: Decimal,
+    pub limit_max_vol: Decimal,
+    pub bid_limit_price_rate: Decimal,
+    pub ask_limit_price_rate: Decimal,
+    pub taker_fee_rate: Decimal,
+    pub maker_fee_rate: Decimal,
+    pub maintenance_margin_rate: Decimal,
+    pub initial_margin_rate: Decimal,
+    pub risk_base_vol: Decimal,
+    pub risk_incr_vol: Decimal,
+    pub risk_incr_mmr: Decimal,
+    pub risk_incr_imr: Decimal,
+    pub risk_level_limit: Decimal,
+    pub price_coefficient_variation: Decimal,
     pub state: i32,
     pub is_new: bool,
     pub is_hot: bool,
     pub is_hidden: bool,
-    pub trigger_protect: f64,
+    pub trigger_protect: Decimal,
     pub risk_long_short_switch: i32,
-    pub risk_base_vol_long: Option<f64>,
-    pub risk_incr_vol_long: Option<f64>,
-    pub risk_base_vol_short: Option<f64>,
-    pub risk_incr_vol_short: Option<f64>,
+    pub risk_base_vol_long: Option<Decimal>,
+    pub risk_incr_vol_long: Option<Decimal>,
+    pub risk_base_vol_short: Option<Decimal>,
+    pub risk_incr_vol_short: Option<Decimal>,
     pub opening_countdown_option: i32,
-    pub opening_time: i64,
-    pub liquidation_fee_rate: f64,
-    pub tiered_deal_amount: Option<f64>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub opening_time: DateTime<Utc>,
+    pub liquidation_fee_rate: Decimal,
+    pub tiered_deal_amount: Option<Decimal>,
     pub tiered_effective_day: Option<i32>,
     pub tiered_exclude_zero_fee: Option<bool>,
     pub tiered_appoint_contract: Option<bool>,
@@ -768,15 +822,16 @@ pub struct ContractData {
     pub max_num_orders: Vec<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventContractMessage {
     pub data: EventContractData,
     pub symbol: String,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EventContractData {
     pub contract_id: String,
@@ -787,193 +842,205 @@ pub struct EventContractData {
     pub quote_coin_name: String,
     pub settle_coin: String,
     pub base_coin_icon_url: String,
-    pub invest_min_amount: f64,
-    pub invest_max_amount: f64,
+    pub invest_min_amount: Decimal,
+    pub invest_max_amount: Decimal,
     pub amount_scale: i32,
     pub pay_rate_scale: i32,
     pub index_price_scale: i32,
     pub available_scale: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalOrderMessage {
     pub data: PersonalOrderData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalOrderData {
     pub order_id: i64,
     pub symbol: String,
     pub position_id: i64,
-    pub price: f64,
-    pub vol: f64,
-    pub leverage: i64,
-    pub side: i32,
-    pub category: i32,
-    pub order_type: i32,
-    pub deal_avg_price: f64,
-    pub deal_vol: f64,
-    pub order_margin: f64,
-    pub used_margin: f64,
-    pub taker_fee: f64,
-    pub maker_fee: f64,
-    pub profit: f64,
+    pub price: Decimal,
+    pub vol: Decimal,
+    pub leverage: i32,
+    pub side: OrderSide,
+    pub category: OrderCategory,
+    pub order_type: OrderType,
+    pub deal_avg_price: Decimal,
+    pub deal_vol: Decimal,
+    pub order_margin: Decimal,
+    pub used_margin: Decimal,
+    pub taker_fee: Decimal,
+    pub maker_fee: Decimal,
+    pub profit: Decimal,
     pub fee_currency: String,
-    pub open_type: i32,
-    pub state: i32,
-    pub error_code: i32,
+    pub open_type: OpenType,
+    pub state: OrderState,
+    pub error_code: OrderErrorCode,
     pub external_oid: String,
-    pub create_time: i64,
-    pub update_time: i64,
-    pub remain_vol: f64,
-    pub position_mode: i32,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub update_time: DateTime<Utc>,
+    pub remain_vol: Decimal,
+    pub position_mode: PositionMode,
     pub reduce_only: bool,
     pub bbo_type_num: i32,
-    pub maker_fee_rate: f64,
-    pub taker_fee_rate: f64,
+    pub maker_fee_rate: Decimal,
+    pub taker_fee_rate: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalAssetMessage {
     pub data: PersonalAssetData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalAssetData {
     pub currency: String,
-    pub position_margin: f64,
-    pub frozen_balance: f64,
-    pub available_balance: f64,
-    pub bonus: f64,
+    pub position_margin: Decimal,
+    pub frozen_balance: Decimal,
+    pub available_balance: Decimal,
+    pub bonus: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPositionMessage {
     pub data: PersonalPositionData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPositionData {
     pub position_id: i64,
     pub symbol: String,
-    pub hold_vol: f64,
-    pub position_type: i32,
-    pub open_type: i32,
-    pub state: i32,
-    pub frozen_vol: f64,
-    pub close_vol: f64,
-    pub hold_avg_price: f64,
+    pub hold_vol: Decimal,
+    pub position_type: PositionType,
+    pub open_type: OpenType,
+    pub state: PositionState,
+    pub frozen_vol: Decimal,
+    pub close_vol: Decimal,
+    pub hold_avg_price: Decimal,
     pub hold_avg_price_fully_scale: String,
-    pub close_avg_price: f64,
-    pub open_avg_price: f64,
+    pub close_avg_price: Decimal,
+    pub open_avg_price: Decimal,
     pub open_avg_price_fully_scale: String,
-    pub liquidate_price: f64,
-    pub oim: f64,
+    pub liquidate_price: Decimal,
+    pub oim: Decimal,
     pub adl_level: i32,
-    pub im: f64,
-    pub hold_fee: f64,
-    pub realised: f64,
+    pub im: Decimal,
+    pub hold_fee: Decimal,
+    pub realised: Decimal,
     pub leverage: i32,
     pub auto_add_im: bool,
-    pub pnl: f64,
-    pub margin_ratio: f64,
-    pub new_open_avg_price: f64,
-    pub new_close_avg_price: f64,
-    pub close_profit_loss: f64,
-    pub fee: f64,
+    pub pnl: Decimal,
+    pub margin_ratio: Decimal,
+    pub new_open_avg_price: Decimal,
+    pub new_close_avg_price: Decimal,
+    pub close_profit_loss: Decimal,
+    pub fee: Decimal,
     pub deduct_fee_list: Vec<DeductFee>,
-    pub maker_fee_rate: f64,
-    pub taker_fee_rate: f64,
-    pub create_time: i64,
-    pub update_time: i64,
+    pub maker_fee_rate: Decimal,
+    pub taker_fee_rate: Decimal,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub update_time: DateTime<Utc>,
     pub version: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeductFee {
     pub currency: String,
-    pub deduct_fee: f64,
-    pub convert_settle_fee: f64,
+    pub deduct_fee: Decimal,
+    pub convert_settle_fee: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPlanOrderMessage {
     pub data: PersonalPlanOrderData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPlanOrderData {
     pub id: i64,
     pub symbol: String,
     pub leverage: i32,
-    pub side: i32,
-    pub trigger_price: f64,
-    pub price: f64,
-    pub vol: f64,
-    pub open_type: i32,
+    pub side: OrderSide,
+    pub trigger_price: Decimal,
+    pub price: Decimal,
+    pub vol: Decimal,
+    pub open_type: OpenType,
     pub trigger_type: i32,
     pub state: i32,
     pub execute_cycle: i32,
     pub trend: i32,
     pub error_code: i32,
     pub order_id: i64,
-    pub order_type: i32,
+    pub order_type: OrderType,
     pub market_order_level: i32,
-    pub position_mode: i32,
+    pub position_mode: PositionMode,
     pub loss_trend: i32,
     pub profit_trend: i32,
-    pub stop_loss_price: f64,
-    pub take_profit_price: f64,
+    pub stop_loss_price: Decimal,
+    pub take_profit_price: Decimal,
     pub reduce_only: bool,
-    pub create_time: i64,
-    pub update_time: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub update_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalRiskLimitMessage {
     pub data: PersonalRiskLimitData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalRiskLimitData {
     pub symbol: String,
-    pub position_type: i32,
+    pub position_type: PositionType,
     pub risk_source: i32,
     pub level: i32,
-    pub max_vol: f64,
+    pub max_vol: Decimal,
     pub max_leverage: i32,
-    pub mmr: f64,
-    pub imr: f64,
+    pub mmr: Decimal,
+    pub imr: Decimal,
     pub leverage: i32,
-    pub open_type: i32,
+    pub open_type: OpenType,
     pub limit_by_sys: bool,
-    pub max_vol_view: f64,
+    pub max_vol_view: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalStopPlanorderMessage {
     pub data: PersonalStopPlanorderData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalStopPlanorderData {
     pub id: i64,
@@ -982,15 +1049,15 @@ pub struct PersonalStopPlanorderData {
     pub position_id: i64,
     pub loss_trend: i32,
     pub profit_trend: i32,
-    pub stop_loss_price: f64,
-    pub take_profit_price: f64,
+    pub stop_loss_price: Decimal,
+    pub take_profit_price: Decimal,
     pub state: i32,
     pub trigger_side: i32,
-    pub position_type: i32,
-    pub vol: f64,
-    pub take_profit_vol: f64,
-    pub stop_loss_vol: f64,
-    pub reality_vol: f64,
+    pub position_type: PositionType,
+    pub vol: Decimal,
+    pub take_profit_vol: Decimal,
+    pub stop_loss_vol: Decimal,
+    pub reality_vol: Decimal,
     pub place_order_id: i64,
     pub version: i32,
     pub is_finished: i32,
@@ -1002,140 +1069,152 @@ pub struct PersonalStopPlanorderData {
     pub reverse_try_times: i32,
     pub reverse_error_code: i32,
     pub stop_loss_type: i32,
-    pub stop_loss_order_price: f64,
-    pub create_time: i64,
-    pub update_time: i64,
+    pub stop_loss_order_price: Decimal,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub update_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalTrackOrderMessage {
     pub data: PersonalTrackOrderData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalTrackOrderData {
     pub id: i64,
     pub symbol: String,
     pub leverage: i32,
-    pub side: i32,
-    pub vol: f64,
-    pub open_type: i32,
+    pub side: OrderSide,
+    pub vol: Decimal,
+    pub open_type: OpenType,
     pub trend: i32,
-    pub active_price: f64,
-    pub mark_price: f64,
+    pub active_price: Decimal,
+    pub mark_price: Decimal,
     pub back_type: i32,
-    pub back_value: f64,
-    pub trigger_price: f64,
+    pub back_value: Decimal,
+    pub trigger_price: Decimal,
     pub trigger_type: i32,
     pub order_id: i64,
     pub error_code: i32,
     pub state: i32,
-    pub position_mode: i32,
+    pub position_mode: PositionMode,
     pub reduce_only: bool,
-    pub create_time: i64,
-    pub update_time: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub update_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalStopOrderMessage {
     pub data: PersonalStopOrderData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalStopOrderData {
     pub symbol: String,
     pub order_id: i64,
     pub loss_trend: i32,
     pub profit_trend: i32,
-    pub stop_loss_price: f64,
-    pub take_profit_price: f64,
+    pub stop_loss_price: Decimal,
+    pub take_profit_price: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalOrderDealMessage {
     pub data: PersonalOrderDealData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalOrderDealData {
     pub id: i64,
     pub symbol: String,
-    pub side: i32,
-    pub vol: f64,
-    pub price: f64,
+    pub side: OrderSide,
+    pub vol: Decimal,
+    pub price: Decimal,
     pub fee_currency: String,
-    pub fee: f64,
-    pub timestamp: i64,
-    pub profit: f64,
+    pub fee: Decimal,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub profit: Decimal,
     pub is_taker: bool,
-    pub category: i32,
+    pub category: OrderCategory,
     pub order_id: i64,
     pub is_self: bool,
     pub external_oid: String,
-    pub position_mode: i32,
+    pub position_mode: PositionMode,
     pub reduce_only: bool,
     pub opponent_uid: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalLiquidateRiskMessage {
     pub data: PersonalLiquidateRiskData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalLiquidateRiskData {
     pub symbol: String,
     pub position_id: i64,
-    pub liquidate_price: f64,
-    pub margin_ratio: f64,
+    pub liquidate_price: Decimal,
+    pub margin_ratio: Decimal,
     pub adl_level: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalLeverageModeMessage {
     pub data: PersonalLeverageModeData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PersonalLeverageModeData {
     pub lm: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPositionModeMessage {
     pub data: PersonalPositionModeData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPositionModeData {
-    pub position_mode: i32,
+    pub position_mode: PositionMode,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalReversePositionMessage {
     pub data: PersonalReversePositionData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalReversePositionData {
     pub contract_id: i32,
@@ -1144,96 +1223,214 @@ pub struct PersonalReversePositionData {
     pub error_code: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalBonusMessage {
     pub data: PersonalBonusData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PersonalBonusData {
     pub c: String,
-    pub b: f64,
+    pub b: Decimal,
     pub be: i64,
     pub g: bool,
     pub ret: i64,
-    pub rea: f64,
+    pub rea: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalEventContractPositionMessage {
     pub data: PersonalEventContractPositionData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalEventContractPositionData {
     pub position_id: i64,
     pub symbol: String,
     pub side: String,
-    pub pay_rate: f64,
-    pub amount: f64,
-    pub open_price: f64,
-    pub close_price: f64,
-    pub reward_amount: f64,
-    pub reward_amount_usdt: f64,
+    pub pay_rate: Decimal,
+    pub amount: Decimal,
+    pub open_price: Decimal,
+    pub close_price: Decimal,
+    pub reward_amount: Decimal,
+    pub reward_amount_usdt: Decimal,
     pub state: String,
     pub close_result: String,
-    pub create_time: i64,
-    pub close_time: i64,
-    pub pnl_amount: f64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub close_time: DateTime<Utc>,
+    pub pnl_amount: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalGenericNotifyMessage {
     pub data: PersonalGenericNotifyData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalGenericNotifyData {
     pub r#type: i32,
     pub param: NotifyParam,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NotifyParam {
     pub notify_type: i32,
-    pub open_type: i32,
+    pub open_type: OpenType,
     pub dn: String,
     pub dne: String,
     pub multi_assets: bool,
-    pub margin_rate: f64,
+    pub margin_rate: Decimal,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalOrderChaseMessage {
     pub data: PersonalOrderChaseData,
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PersonalOrderChaseData {
     pub ec: i32,
     pub s: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PersonalPositionCloseallFailMessage {
-    pub ts: i64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub ts: DateTime<Utc>,
+}
+
+impl From<&ContractData> for ContractDetail {
+    fn from(data: &ContractData) -> Self {
+        Self {
+            symbol: data
+                .symbol
+                .clone(),
+            display_name_en: data
+                .display_name_en
+                .clone(),
+            base_coin: data
+                .base_coin
+                .clone(),
+            quote_coin: data
+                .quote_coin
+                .clone(),
+            settle_coin: data
+                .settle_coin
+                .clone(),
+            contract_size: data.contract_size,
+            min_leverage: data.min_leverage,
+            max_leverage: data.max_leverage,
+            price_scale: data.price_scale,
+            vol_scale: data.vol_scale,
+            amount_scale: data.amount_scale,
+            price_unit: data.price_unit,
+            vol_unit: data.vol_unit,
+            min_vol: data.min_vol,
+            max_vol: data.max_vol,
+            taker_fee_rate: data.taker_fee_rate,
+            maker_fee_rate: data.maker_fee_rate,
+            maintenance_margin_rate: data.maintenance_margin_rate,
+            initial_margin_rate: data.initial_margin_rate,
+            state: data.state,
+        }
+    }
+}
+
+impl From<&PersonalOrderData> for OpenOrder {
+    fn from(data: &PersonalOrderData) -> Self {
+        Self {
+            order_id: data.order_id,
+            symbol: data
+                .symbol
+                .clone(),
+            position_id: data.position_id,
+            trigger_price: data.price,
+            trigger_volume: data.vol,
+            leverage: data.leverage,
+            side: data.side,
+            category: data.category,
+            order_type: data.order_type,
+            deal_average_price: data.deal_avg_price,
+            deal_volume: data.deal_vol,
+            order_margin: data.order_margin,
+            used_margin: data.used_margin,
+            taker_fee: data.taker_fee,
+            maker_fee: data.maker_fee,
+            profit: data.profit,
+            fee_currency: data
+                .fee_currency
+                .clone(),
+            open_type: data.open_type,
+            state: data.state,
+            error_code: data.error_code,
+            external_order_id: match data
+                .external_oid
+                .is_empty()
+            {
+                true => None,
+                false => Some(
+                    data.external_oid
+                        .clone(),
+                ),
+            },
+            create_time: data.create_time,
+            update_time: Some(data.update_time),
+            stop_loss_price: None,
+            take_profit_price: None,
+        }
+    }
+}
+
+impl From<&PersonalPositionData> for OpenPosition {
+    fn from(data: &PersonalPositionData) -> Self {
+        Self {
+            position_id: data.position_id,
+            symbol: data
+                .symbol
+                .clone(),
+            holding_volume: data.hold_vol,
+            position_type: data.position_type,
+            open_type: data.open_type,
+            state: data.state,
+            frozen_volume: data.frozen_vol,
+            close_volume: data.close_vol,
+            holdings_average_price: data.hold_avg_price,
+            close_average_price: data.close_avg_price,
+            open_average_price: data.open_avg_price,
+            liquidate_price: data.liquidate_price,
+            original_initial_margin: data.oim,
+            adl_level: i8::try_from(data.adl_level).ok(),
+            initial_margin: data.im,
+            hold_fee: data.hold_fee,
+            realised: data.realised,
+            create_time: data.create_time,
+            update_time: Some(data.update_time),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn test_parse_ticker_message() {
@@ -1274,7 +1471,7 @@ mod tests {
                     ticker
                         .data
                         .last_price,
-                    Some(6865.5)
+                    Some(Decimal::from_str("6865.5").unwrap())
                 );
             }
             _ => panic!("Expected Ticker message"),
@@ -1315,7 +1512,7 @@ mod tests {
                 );
                 assert_eq!(
                     deal.data[0].p,
-                    115309.8
+                    Decimal::from_str("115309.8").unwrap()
                 );
                 assert_eq!(
                     deal.data[0].trade_side,
@@ -1331,7 +1528,7 @@ mod tests {
                 );
                 assert_eq!(
                     deal.data[0].trade_time,
-                    1755487578276
+                    DateTime::from_timestamp_millis(1755487578276).unwrap()
                 );
             }
             _ => panic!("Expected Deal message"),
@@ -1380,4 +1577,113 @@ mod tests {
             _ => panic!("Expected LoginResponse message"),
         }
     }
+
+    #[test]
+    fn test_personal_position_matches_rest_position() {
+        let data = r#"{
+            "positionId": 1394650,
+            "symbol": "ETH_USDT",
+            "holdVol": 1,
+            "positionType": 1,
+            "openType": 1,
+            "state": 1,
+            "frozenVol": 0,
+            "closeVol": 0,
+            "holdAvgPrice": 1217.3,
+            "holdAvgPriceFullyScale": "1217.3",
+            "closeAvgPrice": 0,
+            "openAvgPrice": 1217.3,
+            "openAvgPriceFullyScale": "1217.3",
+            "liquidatePrice": 1211.2,
+            "oim": 0.1290338,
+            "adlLevel": 1,
+            "im": 0.1290338,
+            "holdFee": 0,
+            "realised": -0.0073,
+            "leverage": 100,
+            "autoAddIm": false,
+            "pnl": 0,
+            "marginRatio": 0.0064,
+            "newOpenAvgPrice": 1217.3,
+            "newCloseAvgPrice": 0,
+            "closeProfitLoss": 0,
+            "fee": 0.0073,
+            "deductFeeList": [],
+            "makerFeeRate": 0,
+            "takerFeeRate": 0.0006,
+            "createTime": 1609991676000,
+            "updateTime": 1609991676000,
+            "version": 1
+        }"#;
+        let json = format!(
+            r#"{{"channel": "push.personal.position", "data": {}, "ts": 1609991676000}}"#,
+            data
+        );
+        let raw: RawFuturesMessage = serde_json::from_str(&json).unwrap();
+        let message: FuturesMessage = raw
+            .try_into()
+            .unwrap();
+        let FuturesMessage::PersonalPosition(message) = message else {
+            panic!("Expected PersonalPosition message");
+        };
+        assert_eq!(
+            message
+                .data
+                .position_type,
+            PositionType::Long
+        );
+        assert_eq!(
+            message
+                .data
+                .realised,
+            Decimal::from_str("-0.0073").unwrap()
+        );
+
+        let from_websocket = OpenPosition::from(&message.data);
+        let from_rest: OpenPosition = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            format!(
+                "{:?}",
+                from_websocket
+            ),
+            format!(
+                "{:?}",
+                from_rest
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_gzip_depth_full_message() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let json = r#"{"channel":"push.depth.full","data":{"asks":[[6859.5,3251,1]],"bids":[[6859,1520,2]],"version":96801927},"symbol":"BTC_USDT","ts":1587442022003}"#;
+        let mut encoder = GzEncoder::new(
+            Vec::new(),
+            Compression::default(),
+        );
+        encoder
+            .write_all(json.as_bytes())
+            .unwrap();
+        let data = encoder
+            .finish()
+            .unwrap();
+
+        let raw: RawFuturesMessage = serde_json::from_str(&gunzip(&data).unwrap()).unwrap();
+        let message: FuturesMessage = raw
+            .try_into()
+            .unwrap();
+        let FuturesMessage::DepthFull(depth) = message else {
+            panic!("Expected DepthFull message");
+        };
+        assert_eq!(
+            depth
+                .data
+                .asks[0]
+                .0,
+            Decimal::from_str("6859.5").unwrap()
+        );
+        assert!(gunzip(json.as_bytes()).is_err());
+    }
 }
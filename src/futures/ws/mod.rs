@@ -1,23 +1,49 @@
-use crate::futures::ws::{auth::FuturesWebsocketAuth, endpoint::MexcFuturesWebsocketEndpoint, topic::FuturesTopic};
-use std::sync::Arc;
+use crate::{
+    futures::ws::{
+        ack::FuturesPendingReplies,
+        auth::FuturesWebsocketAuth,
+        endpoint::MexcFuturesWebsocketEndpoint,
+        filter::PersonalFilter,
+        shutdown::FuturesDropGuard,
+        stream::FuturesBoundedSubscribers,
+        topic::FuturesTopic,
+        topic_stream::{FuturesTopicRoutes, DEFAULT_TOPIC_CHANNEL_CAPACITY},
+    },
+    recording::Recorder,
+};
+use std::sync::{atomic::AtomicBool, Arc};
 use tokio::sync::RwLock;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use uuid::Uuid;
 
+pub mod ack;
 pub mod acquire_websocket;
 pub mod auth;
 pub mod endpoint;
+pub mod filter;
+pub mod lifecycle;
 pub mod message;
+pub mod replay;
+pub mod shutdown;
 pub mod stream;
 pub mod subscribe;
 pub mod topic;
+pub mod topic_stream;
 pub mod unsubscribe;
 
+const DEFAULT_BROADCAST_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub struct FuturesWebsocketEntry {
     pub id: Uuid,
     pub auth: Option<FuturesWebsocketAuth>,
     pub topics: Arc<RwLock<Vec<FuturesTopic>>>,
+    pub personal_filter: Arc<RwLock<Option<PersonalFilter>>>,
     pub message_tx: Arc<RwLock<async_channel::Sender<SendableMessage>>>,
+    pub cancellation_token: Arc<RwLock<CancellationToken>>,
+    /// Set while a reconnect is in flight, so the tasks of a closed
+    /// connection reconnect it once
+    pub(crate) reconnecting: Arc<AtomicBool>,
 }
 
 #[derive(Debug)]
@@ -30,13 +56,27 @@ pub struct MexcFuturesWebsocketClient {
     inner: Arc<RwLock<Inner>>,
     ws_endpoint: Arc<MexcFuturesWebsocketEndpoint>,
     broadcast_tx: tokio::sync::broadcast::Sender<Arc<message::FuturesMessage>>,
+    topic_routes: Arc<FuturesTopicRoutes>,
+    topic_channel_capacity: usize,
+    bounded_subscribers: Arc<FuturesBoundedSubscribers>,
+    lifecycle_tx: tokio::sync::broadcast::Sender<lifecycle::FuturesLifecycleEvent>,
+    pending_replies: Arc<FuturesPendingReplies>,
+    recorder: Option<Arc<Recorder>>,
+    /// Subscribes with gzip enabled, see [`MexcFuturesWebsocketClient::with_gzip`]
+    gzip: bool,
+    /// The background tasks of all websockets
+    tasks: TaskTracker,
+    /// Shuts the client down when the last handle is dropped, `None` for the
+    /// handles of the background tasks.
+    _drop_guard: Option<Arc<FuturesDropGuard>>,
 }
 
 impl MexcFuturesWebsocketClient {
     pub fn new_with_endpoint(ws_endpoint: MexcFuturesWebsocketEndpoint) -> Self {
-        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(1024);
+        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(DEFAULT_BROADCAST_CAPACITY);
+        let (lifecycle_tx, _lifecycle_rx) = tokio::sync::broadcast::channel(256);
 
-        Self {
+        let client = Self {
             inner: Arc::new(
                 RwLock::new(
                     Inner {
@@ -46,12 +86,72 @@ impl MexcFuturesWebsocketClient {
             ),
             ws_endpoint: Arc::new(ws_endpoint),
             broadcast_tx,
+            topic_routes: Arc::new(FuturesTopicRoutes::default()),
+            topic_channel_capacity: DEFAULT_TOPIC_CHANNEL_CAPACITY,
+            bounded_subscribers: Arc::new(FuturesBoundedSubscribers::default()),
+            lifecycle_tx,
+            pending_replies: Arc::new(FuturesPendingReplies::default()),
+            recorder: None,
+            gzip: false,
+            tasks: TaskTracker::new(),
+            _drop_guard: None,
+        };
+        Self {
+            _drop_guard: Some(Arc::new(FuturesDropGuard::new(client.task_handle()))),
+            ..client
+        }
+    }
+
+    /// Amount of messages [`stream::FuturesStream::stream`] subscribers can lag
+    /// behind before they start missing messages. Defaults to 1024.
+    pub fn with_broadcast_capacity(mut self, capacity: usize) -> Self {
+        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(capacity);
+        self.broadcast_tx = broadcast_tx;
+        self
+    }
+
+    /// Amount of messages a [`topic_stream::FuturesTopicStream`] can lag behind
+    /// before it starts missing messages. Defaults to 1024.
+    pub fn with_topic_channel_capacity(mut self, capacity: usize) -> Self {
+        self.topic_channel_capacity = capacity;
+        self
+    }
+
+    /// Records every frame received by the websockets, see [`crate::recording`].
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Has MEXC send pushes as gzip compressed binary frames, which are
+    /// inflated before decoding. Saves bandwidth on busy depth and deal
+    /// topics.
+    pub fn with_gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    pub(crate) fn subscription_message(&self, topic: &topic::FuturesTopic) -> serde_json::Value {
+        match self.gzip {
+            true => topic.to_gzip_subscription_message(),
+            false => topic.to_subscription_message(),
         }
     }
 
     pub fn into_arc(self) -> Arc<Self> {
         Arc::new(self)
     }
+
+    /// A handle for background tasks, which doesn't keep the client from being
+    /// shut down on drop.
+    pub(crate) fn task_handle(&self) -> Arc<Self> {
+        Arc::new(
+            Self {
+                _drop_guard: None,
+                ..self.clone()
+            },
+        )
+    }
 }
 
 impl Default for MexcFuturesWebsocketClient {
@@ -65,6 +165,34 @@ impl Default for MexcFuturesWebsocketClient {
 pub enum SendableMessage {
     Subscription(serde_json::Value),
     Unsubscription(serde_json::Value),
+    #[serde(serialize_with = "serialize_ping")]
     Ping,
     Login(serde_json::Value),
+    PersonalFilter(serde_json::Value),
+}
+
+fn serialize_ping<S>(serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::Serialize;
+
+    serde_json::json!({
+        "method": "ping"
+    })
+    .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_ping() {
+        let json = serde_json::to_string(&SendableMessage::Ping).unwrap();
+        assert_eq!(
+            json,
+            r#"{"method":"ping"}"#
+        );
+    }
 }
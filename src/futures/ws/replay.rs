@@ -0,0 +1,121 @@
+use crate::{
+    futures::ws::{
+        acquire_websocket,
+        message::{gunzip, FuturesMessage, RawFuturesMessage},
+        MexcFuturesWebsocketClient,
+    },
+    recording::{self, RecordedPayload, ReplayError, ReplayOutput, ReplayParams},
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait FuturesReplay {
+    /// Feeds a recording through the message decoding into the streams of the
+    /// client, without connecting to MEXC. Streams have to be created before
+    /// the replay to receive all messages.
+    async fn replay(self: Arc<Self>, params: ReplayParams) -> Result<ReplayOutput, ReplayError>;
+}
+
+#[async_trait]
+impl FuturesReplay for MexcFuturesWebsocketClient {
+    async fn replay(self: Arc<Self>, params: ReplayParams) -> Result<ReplayOutput, ReplayError> {
+        recording::replay_frames(
+            params,
+            |frame| {
+                let this = self.clone();
+                async move {
+                    let Some(message) = decode_payload(&frame.payload) else {
+                        return false;
+                    };
+                    acquire_websocket::dispatch_message(
+                        &this, message,
+                    )
+                    .await;
+                    true
+                }
+            },
+        )
+        .await
+    }
+}
+
+fn decode_payload(payload: &RecordedPayload) -> Option<FuturesMessage> {
+    let text = match payload {
+        RecordedPayload::Text(text) => text.clone(),
+        RecordedPayload::Binary(data) => gunzip(data).ok()?,
+    };
+    let raw_message = serde_json::from_str::<RawFuturesMessage>(&text).ok()?;
+    FuturesMessage::try_from(raw_message).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        futures::ws::stream::FuturesStream,
+        recording::{RecordedFrame, Recorder, ReplaySpeed},
+    };
+    use chrono::Utc;
+    use futures::StreamExt;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn replays_recorded_frames_into_stream() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "mexc-rs-futures-replay-{}.rec",
+                Uuid::new_v4()
+            ),
+        );
+        let recorder = Recorder::create(&path).unwrap();
+        let websocket_id = Uuid::new_v4();
+        for payload in [
+            RecordedPayload::Text(r#"{"symbol":"BTC_USDT","data":[{"p":115309.8,"v":55,"T":2,"O":3,"M":1,"t":1755487578276}],"channel":"push.deal","ts":1755487578276}"#.to_string()),
+            RecordedPayload::Binary(
+                vec![
+                    1, 2, 3,
+                ],
+            ),
+        ] {
+            recorder
+                .record_frame(
+                    &RecordedFrame {
+                        received_at: Utc::now(),
+                        websocket_id,
+                        payload,
+                    },
+                )
+                .unwrap();
+        }
+        drop(recorder);
+
+        let client = MexcFuturesWebsocketClient::default().into_arc();
+        let mut stream = client
+            .clone()
+            .stream();
+        let output = client
+            .replay(ReplayParams::new(&path).with_speed(ReplaySpeed::AsFastAsPossible))
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            output.frames,
+            2
+        );
+        assert_eq!(
+            output.skipped_frames,
+            1
+        );
+        assert!(
+            matches!(
+                *stream
+                    .next()
+                    .await
+                    .unwrap(),
+                FuturesMessage::Deal(_)
+            )
+        );
+    }
+}
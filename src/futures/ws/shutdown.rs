@@ -0,0 +1,166 @@
+use crate::futures::ws::{lifecycle::FuturesLifecycleEventKind, MexcFuturesWebsocketClient, SendableMessage};
+use async_trait::async_trait;
+use std::{sync::Arc, time::Duration};
+
+/// How long a shutdown waits for the websockets to close.
+pub(crate) const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum FuturesShutdownError {
+    #[error("Timed out waiting for the websocket tasks to finish")]
+    TimedOut,
+}
+
+#[async_trait]
+pub trait FuturesShutdown {
+    /// Unsubscribes from all topics, closes all websockets and waits for the
+    /// background tasks to finish. The client can't be used anymore afterwards.
+    async fn shutdown(self: Arc<Self>) -> Result<(), FuturesShutdownError>;
+}
+
+#[async_trait]
+impl FuturesShutdown for MexcFuturesWebsocketClient {
+    async fn shutdown(self: Arc<Self>) -> Result<(), FuturesShutdownError> {
+        tokio::time::timeout(
+            SHUTDOWN_TIMEOUT,
+            shutdown(&self),
+        )
+        .await
+        .map_err(|_| FuturesShutdownError::TimedOut)
+    }
+}
+
+async fn shutdown(this: &MexcFuturesWebsocketClient) {
+    // Stops new websockets from being created and disconnected ones from
+    // reconnecting
+    this.tasks
+        .close();
+
+    let websocket_entries = std::mem::take(
+        &mut this
+            .inner
+            .write()
+            .await
+            .websockets,
+    );
+    for websocket_entry in websocket_entries {
+        let topics = websocket_entry
+            .topics
+            .read()
+            .await
+            .clone();
+        {
+            let message_tx = websocket_entry
+                .message_tx
+                .read()
+                .await;
+            for topic in topics.iter() {
+                // The websocket may already be gone
+                let _ = message_tx
+                    .send(SendableMessage::Unsubscription(topic.to_unsubscription_message()))
+                    .await;
+            }
+        }
+        // The sender task flushes the unsubscriptions before sending the close
+        // frame
+        websocket_entry
+            .cancellation_token
+            .read()
+            .await
+            .cancel();
+        this.pending_replies
+            .forget_websocket(websocket_entry.id);
+        this.emit_lifecycle_event(
+            websocket_entry.id,
+            topics,
+            FuturesLifecycleEventKind::Disconnected,
+        );
+    }
+
+    // Ends the topic and bounded streams
+    this.topic_routes
+        .close();
+    this.bounded_subscribers
+        .close();
+
+    this.tasks
+        .wait()
+        .await;
+    tracing::debug!("Futures websocket client shut down");
+}
+
+/// Shuts the client down in the background once the last handle of the user is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct FuturesDropGuard {
+    client: Arc<MexcFuturesWebsocketClient>,
+}
+
+impl FuturesDropGuard {
+    pub(crate) fn new(client: Arc<MexcFuturesWebsocketClient>) -> Self {
+        Self {
+            client,
+        }
+    }
+}
+
+impl Drop for FuturesDropGuard {
+    fn drop(&mut self) {
+        if self
+            .client
+            .tasks
+            .is_closed()
+        {
+            return;
+        }
+        self.client
+            .tasks
+            .close();
+        // Without a runtime the tasks are gone already
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let client = self
+            .client
+            .clone();
+        handle.spawn(
+            async move {
+                if let Err(err) = client
+                    .shutdown()
+                    .await
+                {
+                    tracing::warn!(
+                        "Failed to shut down dropped futures websocket client: {}",
+                        err
+                    );
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dropping_last_handle_shuts_down() {
+        let client = MexcFuturesWebsocketClient::default().into_arc();
+        let task_handle = client.task_handle();
+        let other_handle = client.clone();
+
+        drop(client);
+        assert!(
+            !task_handle
+                .tasks
+                .is_closed()
+        );
+
+        drop(other_handle);
+        assert!(
+            task_handle
+                .tasks
+                .is_closed()
+        );
+    }
+}
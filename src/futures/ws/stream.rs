@@ -1,21 +1,348 @@
 use crate::futures::ws::{message::FuturesMessage, MexcFuturesWebsocketClient};
+use async_channel::TrySendError;
 use futures::{stream::BoxStream, StreamExt};
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Debug, Clone)]
+pub enum FuturesStreamEvent<T> {
+    Message(T),
+    /// The subscriber could not keep up and `skipped` messages were dropped
+    /// before the next message.
+    Lagged {
+        skipped: u64,
+    },
+}
+
+/// What to do when the queue of a bounded subscriber is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuturesOverflowPolicy {
+    /// Wait until the subscriber has room. This holds up message processing
+    /// of the websocket the message came from.
+    Block,
+    /// Drop the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Drop the new message and end the stream.
+    Disconnect,
+}
 
 pub trait FuturesStream {
+    /// Stream of all messages. Messages missed because the stream lagged behind
+    /// are skipped, use [`FuturesStream::stream_with_events`] to be notified of them.
     fn stream<'a>(self: Arc<Self>) -> BoxStream<'a, Arc<FuturesMessage>>;
+
+    /// Stream of all messages, with a [`FuturesStreamEvent::Lagged`] event wherever
+    /// messages were missed.
+    fn stream_with_events<'a>(self: Arc<Self>) -> BoxStream<'a, FuturesStreamEvent<Arc<FuturesMessage>>>;
+
+    /// Stream of all messages backed by a queue of its own, so a slow
+    /// subscriber doesn't affect others.
+    fn bounded_stream<'a>(self: Arc<Self>, capacity: usize, overflow_policy: FuturesOverflowPolicy) -> BoxStream<'a, FuturesStreamEvent<Arc<FuturesMessage>>>;
 }
 
 impl FuturesStream for MexcFuturesWebsocketClient {
     fn stream<'a>(self: Arc<Self>) -> BoxStream<'a, Arc<FuturesMessage>> {
+        self.stream_with_events()
+            .filter_map(
+                |event| async move {
+                    match event {
+                        FuturesStreamEvent::Message(message) => Some(message),
+                        FuturesStreamEvent::Lagged {
+                            skipped,
+                        } => {
+                            tracing::warn!(
+                                "Futures websocket stream lagged behind, skipped {} messages",
+                                skipped
+                            );
+                            None
+                        }
+                    }
+                },
+            )
+            .boxed()
+    }
+
+    fn stream_with_events<'a>(self: Arc<Self>) -> BoxStream<'a, FuturesStreamEvent<Arc<FuturesMessage>>> {
         let mut rx = self
             .broadcast_tx
             .subscribe();
         let stream = async_stream::stream! {
-            while let Ok(message) = rx.recv().await {
-                yield message;
+            loop {
+                match rx.recv().await {
+                    Ok(message) => yield FuturesStreamEvent::Message(message),
+                    Err(RecvError::Lagged(skipped)) => yield FuturesStreamEvent::Lagged { skipped },
+                    Err(RecvError::Closed) => break,
+                }
             }
         };
         stream.boxed()
     }
+
+    fn bounded_stream<'a>(self: Arc<Self>, capacity: usize, overflow_policy: FuturesOverflowPolicy) -> BoxStream<'a, FuturesStreamEvent<Arc<FuturesMessage>>> {
+        self.bounded_subscribers
+            .subscribe(
+                capacity,
+                overflow_policy,
+            )
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct FuturesBoundedSubscribers {
+    subscribers: std::sync::Mutex<Vec<FuturesBoundedSubscriber>>,
+}
+
+#[derive(Debug, Clone)]
+struct FuturesBoundedSubscriber {
+    tx: async_channel::Sender<Arc<FuturesMessage>>,
+    /// Kept to be able to drop the oldest message
+    rx: async_channel::Receiver<Arc<FuturesMessage>>,
+    overflow_policy: FuturesOverflowPolicy,
+    skipped: Arc<AtomicU64>,
+}
+
+/// Closes the channel when the stream is dropped, so the subscriber gets
+/// cleaned up on the next dispatch.
+struct CloseOnDrop(async_channel::Receiver<Arc<FuturesMessage>>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0
+            .close();
+    }
+}
+
+impl FuturesBoundedSubscribers {
+    /// Closes all channels, which ends their streams
+    pub(crate) fn close(&self) {
+        for subscriber in self
+            .subscribers
+            .lock()
+            .expect("Bounded subscribers lock poisoned")
+            .drain(..)
+        {
+            subscriber
+                .tx
+                .close();
+        }
+    }
+
+    fn subscribe<'a>(&self, capacity: usize, overflow_policy: FuturesOverflowPolicy) -> BoxStream<'a, FuturesStreamEvent<Arc<FuturesMessage>>> {
+        let (tx, rx) = async_channel::bounded(capacity.max(1));
+        let skipped = Arc::new(AtomicU64::new(0));
+        self.subscribers
+            .lock()
+            .expect("Bounded subscribers lock poisoned")
+            .push(
+                FuturesBoundedSubscriber {
+                    tx,
+                    rx: rx.clone(),
+                    overflow_policy,
+                    skipped: skipped.clone(),
+                },
+            );
+
+        let guard = CloseOnDrop(rx);
+        let stream = async_stream::stream! {
+            let guard = guard;
+            loop {
+                let message = guard.0.recv().await;
+                let skipped = skipped.swap(0, Ordering::SeqCst);
+                if skipped > 0 {
+                    yield FuturesStreamEvent::Lagged { skipped };
+                }
+                match message {
+                    Ok(message) => yield FuturesStreamEvent::Message(message),
+                    Err(_) => break,
+                }
+            }
+        };
+        stream.boxed()
+    }
+
+    pub(crate) async fn dispatch(&self, message: &Arc<FuturesMessage>) {
+        let subscribers = self
+            .subscribers
+            .lock()
+            .expect("Bounded subscribers lock poisoned")
+            .clone();
+        if subscribers.is_empty() {
+            return;
+        }
+
+        for subscriber in subscribers.iter() {
+            match subscriber.overflow_policy {
+                FuturesOverflowPolicy::Block => {
+                    let _ = subscriber
+                        .tx
+                        .send(message.clone())
+                        .await;
+                }
+                FuturesOverflowPolicy::DropOldest => {
+                    let mut message = message.clone();
+                    loop {
+                        match subscriber
+                            .tx
+                            .try_send(message)
+                        {
+                            Ok(_) | Err(TrySendError::Closed(_)) => break,
+                            Err(TrySendError::Full(returned)) => {
+                                if subscriber
+                                    .rx
+                                    .try_recv()
+                                    .is_ok()
+                                {
+                                    subscriber
+                                        .skipped
+                                        .fetch_add(
+                                            1,
+                                            Ordering::SeqCst,
+                                        );
+                                }
+                                message = returned;
+                            }
+                        }
+                    }
+                }
+                FuturesOverflowPolicy::Disconnect => {
+                    if let Err(TrySendError::Full(_)) = subscriber
+                        .tx
+                        .try_send(message.clone())
+                    {
+                        subscriber
+                            .skipped
+                            .fetch_add(
+                                1,
+                                Ordering::SeqCst,
+                            );
+                        subscriber
+                            .tx
+                            .close();
+                    }
+                }
+            }
+        }
+
+        self.subscribers
+            .lock()
+            .expect("Bounded subscribers lock poisoned")
+            .retain(
+                |subscriber| {
+                    !subscriber
+                        .tx
+                        .is_closed()
+                },
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::ws::message::PongMessage;
+
+    fn message() -> Arc<FuturesMessage> {
+        Arc::new(
+            FuturesMessage::Pong(
+                PongMessage {
+                    channel: "pong".to_string(),
+                    data: 0,
+                },
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn bounded_drop_oldest_reports_lag() {
+        let subscribers = FuturesBoundedSubscribers::default();
+        let mut stream = subscribers.subscribe(
+            2,
+            FuturesOverflowPolicy::DropOldest,
+        );
+        for _ in 0..5 {
+            subscribers
+                .dispatch(&message())
+                .await;
+        }
+
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(
+                    FuturesStreamEvent::Lagged {
+                        skipped: 3
+                    }
+                )
+            )
+        );
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(FuturesStreamEvent::Message(_))
+            )
+        );
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(FuturesStreamEvent::Message(_))
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn bounded_disconnect_ends_stream() {
+        let subscribers = FuturesBoundedSubscribers::default();
+        let mut stream = subscribers.subscribe(
+            1,
+            FuturesOverflowPolicy::Disconnect,
+        );
+        for _ in 0..3 {
+            subscribers
+                .dispatch(&message())
+                .await;
+        }
+
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(
+                    FuturesStreamEvent::Lagged {
+                        skipped: 1
+                    }
+                )
+            )
+        );
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(FuturesStreamEvent::Message(_))
+            )
+        );
+        assert!(
+            stream
+                .next()
+                .await
+                .is_none()
+        );
+        assert!(
+            subscribers
+                .subscribers
+                .lock()
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
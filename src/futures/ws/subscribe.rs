@@ -1,6 +1,8 @@
 use crate::futures::ws::{
+    ack::{self, FuturesRejectedTopic, FuturesTopicRequestError},
     acquire_websocket::{AcquireWebsocketForTopicsError, AcquireWebsocketsForTopics, AcquireWebsocketsForTopicsParams},
     auth::FuturesWebsocketAuth,
+    filter::PersonalFilter,
     topic::FuturesTopic,
     MexcFuturesWebsocketClient, SendableMessage,
 };
@@ -12,6 +14,11 @@ use std::sync::Arc;
 pub struct FuturesSubscribeParams {
     pub auth: Option<FuturesWebsocketAuth>,
     pub topics: Vec<FuturesTopic>,
+    /// Only applies when `auth` is set, replaces the filter of the connection
+    /// that is logged in with the same auth.
+    pub personal_filter: Option<PersonalFilter>,
+    /// Wait for the server to confirm the subscription
+    pub wait_for_confirmation: bool,
 }
 
 impl Default for FuturesSubscribeParams {
@@ -19,15 +26,18 @@ impl Default for FuturesSubscribeParams {
         Self::new(
             None,
             Vec::new(),
+            true,
         )
     }
 }
 
 impl FuturesSubscribeParams {
-    pub fn new(auth: Option<FuturesWebsocketAuth>, topics: Vec<FuturesTopic>) -> Self {
+    pub fn new(auth: Option<FuturesWebsocketAuth>, topics: Vec<FuturesTopic>, wait_for_confirmation: bool) -> Self {
         Self {
             auth,
             topics,
+            personal_filter: None,
+            wait_for_confirmation,
         }
     }
 
@@ -36,6 +46,16 @@ impl FuturesSubscribeParams {
         self
     }
 
+    pub fn with_personal_filter(mut self, personal_filter: PersonalFilter) -> Self {
+        self.personal_filter = Some(personal_filter);
+        self
+    }
+
+    pub fn with_wait_for_confirmation(mut self, wait_for_confirmation: bool) -> Self {
+        self.wait_for_confirmation = wait_for_confirmation;
+        self
+    }
+
     pub fn with_topic(mut self, topic: FuturesTopic) -> Self {
         self.topics
             .push(topic);
@@ -50,7 +70,12 @@ impl FuturesSubscribeParams {
 }
 
 #[derive(Debug, Clone)]
-pub struct FuturesSubscribeOutput {}
+pub struct FuturesSubscribeOutput {
+    pub accepted_topics: Vec<FuturesTopic>,
+    /// Topics without a reply stay subscribed and are resubscribed on
+    /// reconnect, unsubscribe them to give up on them
+    pub rejected_topics: Vec<FuturesRejectedTopic>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum FuturesSubscribeError {
@@ -62,6 +87,12 @@ pub enum FuturesSubscribeError {
 
     #[error("Serde error: {0}")]
     SerdeError(#[from] serde_json::Error),
+
+    #[error("Topics were rejected: {0:?}")]
+    TopicsRejected(Vec<FuturesRejectedTopic>),
+
+    #[error("The client has been shut down")]
+    ClientShutDown,
 }
 
 #[async_trait]
@@ -72,9 +103,13 @@ pub trait FuturesSubscribe {
 #[async_trait]
 impl FuturesSubscribe for MexcFuturesWebsocketClient {
     async fn subscribe(self: Arc<Self>, params: FuturesSubscribeParams) -> Result<FuturesSubscribeOutput, FuturesSubscribeError> {
+        let wait_for_confirmation = params.wait_for_confirmation;
         let mut acquire_websocket_params = AcquireWebsocketsForTopicsParams::for_topics(params.topics);
         if let Some(auth) = params.auth {
             acquire_websocket_params = acquire_websocket_params.with_auth(auth);
+            if let Some(personal_filter) = params.personal_filter {
+                acquire_websocket_params = acquire_websocket_params.with_personal_filter(personal_filter);
+            }
         }
         let acquire_output = match self
             .clone()
@@ -92,27 +127,116 @@ impl FuturesSubscribe for MexcFuturesWebsocketClient {
                 AcquireWebsocketForTopicsError::SerdeError(err) => {
                     return Err(FuturesSubscribeError::SerdeError(err));
                 }
+                AcquireWebsocketForTopicsError::ClientShutDown => {
+                    return Err(FuturesSubscribeError::ClientShutDown);
+                }
             },
         };
 
+        let mut accepted_topics = vec![];
+        let mut waiting_for_replies = vec![];
         for acquired_ws in acquire_output
             .websockets
             .into_iter()
         {
-            // Update the topics in the websocket entry
             let mut topics = acquired_ws
                 .websocket_entry
                 .topics
                 .write()
                 .await;
-            let topics_websocket_entry_does_not_have = acquired_ws
+            let (topics_websocket_entry_has, topics_websocket_entry_does_not_have) = acquired_ws
                 .for_topics
                 .into_iter()
-                .filter(|topic| !topics.contains(topic))
-                .collect::<Vec<FuturesTopic>>();
+                .partition::<Vec<FuturesTopic>, _>(|topic| topics.contains(topic));
+            accepted_topics.extend(topics_websocket_entry_has);
+
+            let tx = acquired_ws
+                .websocket_entry
+                .message_tx
+                .read()
+                .await;
+            for topic in topics_websocket_entry_does_not_have.iter() {
+                let subscription_message = self.subscription_message(topic);
+                if wait_for_confirmation {
+                    let reply_rx = self
+                        .pending_replies
+                        .register(
+                            acquired_ws
+                                .websocket_entry
+                                .id,
+                            &subscription_message,
+                        );
+                    waiting_for_replies.push(
+                        (
+                            acquired_ws
+                                .websocket_entry
+                                .clone(),
+                            reply_rx,
+                            topic.clone(),
+                        ),
+                    );
+                }
+                tx.send(SendableMessage::Subscription(subscription_message))
+                    .await?;
+            }
+
+            if !wait_for_confirmation {
+                accepted_topics.extend(
+                    topics_websocket_entry_does_not_have
+                        .iter()
+                        .cloned(),
+                );
+            }
+            // Update the topics in the websocket entry
             topics.extend(topics_websocket_entry_does_not_have);
         }
 
-        Ok(FuturesSubscribeOutput {})
+        let replies = futures::future::join_all(
+            waiting_for_replies
+                .into_iter()
+                .map(
+                    |(websocket_entry, reply_rx, topic)| async move {
+                        let result = ack::wait_for_topic(
+                            reply_rx, topic,
+                        )
+                        .await;
+                        // Rejected topics must not be resubscribed on reconnect.
+                        // Without a reply the subscription may be live, so the
+                        // topic is kept.
+                        if let Err(
+                            rejected_topic @ FuturesRejectedTopic {
+                                error: FuturesTopicRequestError::Rejected {
+                                    ..
+                                },
+                                ..
+                            },
+                        ) = &result
+                        {
+                            websocket_entry
+                                .topics
+                                .write()
+                                .await
+                                .retain(|topic| topic != &rejected_topic.topic);
+                        }
+                        result
+                    },
+                ),
+        )
+        .await;
+
+        let mut rejected_topics = vec![];
+        for reply in replies {
+            match reply {
+                Ok(topic) => accepted_topics.push(topic),
+                Err(rejected_topic) => rejected_topics.push(rejected_topic),
+            }
+        }
+
+        Ok(
+            FuturesSubscribeOutput {
+                accepted_topics,
+                rejected_topics,
+            },
+        )
     }
 }
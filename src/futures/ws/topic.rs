@@ -1,9 +1,13 @@
+use crate::futures::v1::models::KlineInterval;
+use rust_decimal::Decimal;
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum FuturesTopic {
     Tickers,
     Ticker(TickerTopic),
     Deal(DealTopic),
     Depth(DepthTopic),
+    DepthFull(DepthFullTopic),
     DepthStep(DepthStepTopic),
     Kline(KlineTopic),
     FundingRate(FundingRateTopic),
@@ -20,6 +24,7 @@ impl FuturesTopic {
             FuturesTopic::Ticker(_) => false,
             FuturesTopic::Deal(_) => false,
             FuturesTopic::Depth(_) => false,
+            FuturesTopic::DepthFull(_) => false,
             FuturesTopic::DepthStep(_) => false,
             FuturesTopic::Kline(_) => false,
             FuturesTopic::FundingRate(_) => false,
@@ -48,17 +53,33 @@ impl FuturesTopic {
                     "symbol": deal.symbol
                 }
             }),
-            FuturesTopic::Depth(depth) => serde_json::json!({
-                "method": "sub.depth",
+            FuturesTopic::Depth(depth) => match depth.compress {
+                true => serde_json::json!({
+                    "method": "sub.depth",
+                    "param": {
+                        "symbol": depth.symbol,
+                        "compress": true
+                    }
+                }),
+                false => serde_json::json!({
+                    "method": "sub.depth",
+                    "param": {
+                        "symbol": depth.symbol
+                    }
+                }),
+            },
+            FuturesTopic::DepthFull(depth_full) => serde_json::json!({
+                "method": "sub.depth.full",
                 "param": {
-                    "symbol": depth.symbol
+                    "symbol": depth_full.symbol,
+                    "limit": depth_full.limit as u8
                 }
             }),
             FuturesTopic::DepthStep(depth_step) => serde_json::json!({
                 "method": "sub.depth.step",
                 "param": {
                     "symbol": depth_step.symbol,
-                    "step": depth_step.step
+                    "step": depth_step.step.to_string()
                 }
             }),
             FuturesTopic::Kline(kline) => serde_json::json!({
@@ -119,11 +140,18 @@ impl FuturesTopic {
                     "symbol": depth.symbol
                 }
             }),
+            FuturesTopic::DepthFull(depth_full) => serde_json::json!({
+                "method": "unsub.depth.full",
+                "param": {
+                    "symbol": depth_full.symbol,
+                    "limit": depth_full.limit as u8
+                }
+            }),
             FuturesTopic::DepthStep(depth_step) => serde_json::json!({
                 "method": "unsub.depth.step",
                 "param": {
                     "symbol": depth_step.symbol,
-                    "step": depth_step.step
+                    "step": depth_step.step.to_string()
                 }
             }),
             FuturesTopic::Kline(kline) => serde_json::json!({
@@ -158,6 +186,14 @@ impl FuturesTopic {
             }),
         }
     }
+
+    /// Like [`FuturesTopic::to_subscription_message`], but asks for the
+    /// pushes to be sent as gzip compressed binary frames
+    pub fn to_gzip_subscription_message(&self) -> serde_json::Value {
+        let mut message = self.to_subscription_message();
+        message["gzip"] = serde_json::Value::Bool(true);
+        message
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -189,12 +225,45 @@ impl DealTopic {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct DepthTopic {
     pub symbol: String,
+    /// Merges the updates of a short period into one push
+    pub compress: bool,
 }
 
 impl DepthTopic {
     pub fn new(symbol: String) -> Self {
         Self {
             symbol,
+            compress: false,
+        }
+    }
+
+    pub fn with_compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+}
+
+/// Amount of levels per side of a full depth snapshot
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DepthLimit {
+    Five = 5,
+    Ten = 10,
+    Twenty = 20,
+}
+
+/// Pushes full snapshots of the top levels instead of incremental updates
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DepthFullTopic {
+    pub symbol: String,
+    pub limit: DepthLimit,
+}
+
+impl DepthFullTopic {
+    pub fn new(symbol: String, limit: DepthLimit) -> Self {
+        Self {
+            symbol,
+            limit,
         }
     }
 }
@@ -202,11 +271,12 @@ impl DepthTopic {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct DepthStepTopic {
     pub symbol: String,
-    pub step: String,
+    /// Price step the levels are merged to
+    pub step: Decimal,
 }
 
 impl DepthStepTopic {
-    pub fn new(symbol: String, step: String) -> Self {
+    pub fn new(symbol: String, step: Decimal) -> Self {
         Self {
             symbol,
             step,
@@ -217,11 +287,11 @@ impl DepthStepTopic {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct KlineTopic {
     pub symbol: String,
-    pub interval: String,
+    pub interval: KlineInterval,
 }
 
 impl KlineTopic {
-    pub fn new(symbol: String, interval: String) -> Self {
+    pub fn new(symbol: String, interval: KlineInterval) -> Self {
         Self {
             symbol,
             interval,
@@ -267,3 +337,60 @@ impl FairPriceTopic {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn typed_subscription_parameters() {
+        assert_eq!(
+            FuturesTopic::Kline(
+                KlineTopic::new(
+                    "BTC_USDT".to_string(),
+                    KlineInterval::FourHours,
+                ),
+            )
+            .to_subscription_message(),
+            serde_json::json!({
+                "method": "sub.kline",
+                "param": {
+                    "symbol": "BTC_USDT",
+                    "interval": "Hour4"
+                }
+            })
+        );
+        assert_eq!(
+            FuturesTopic::DepthStep(
+                DepthStepTopic::new(
+                    "BTC_USDT".to_string(),
+                    Decimal::from_str("0.5").unwrap(),
+                ),
+            )
+            .to_subscription_message()["param"]["step"],
+            "0.5"
+        );
+        assert_eq!(
+            FuturesTopic::DepthFull(
+                DepthFullTopic::new(
+                    "BTC_USDT".to_string(),
+                    DepthLimit::Twenty,
+                ),
+            )
+            .to_gzip_subscription_message(),
+            serde_json::json!({
+                "method": "sub.depth.full",
+                "param": {
+                    "symbol": "BTC_USDT",
+                    "limit": 20
+                },
+                "gzip": true
+            })
+        );
+        assert_eq!(
+            FuturesTopic::Depth(DepthTopic::new("BTC_USDT".to_string()).with_compress(true)).to_subscription_message()["param"]["compress"],
+            true
+        );
+    }
+}
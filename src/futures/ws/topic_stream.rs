@@ -0,0 +1,608 @@
+use crate::futures::{
+    v1::models::KlineInterval,
+    ws::{
+        message::{DealMessage, DepthMessage, DepthStepMessage, FairPriceMessage, FundingRateMessage, FuturesMessage, IndexPriceMessage, KlineMessage, TickerMessage, TickersMessage},
+        stream::FuturesStreamEvent,
+        subscribe::{FuturesSubscribe, FuturesSubscribeError, FuturesSubscribeParams},
+        topic::{DealTopic, DepthFullTopic, DepthLimit, DepthStepTopic, DepthTopic, FairPriceTopic, FundingRateTopic, FuturesTopic, IndexPriceTopic, KlineTopic, TickerTopic},
+        unsubscribe::{FuturesUnsubscribe, FuturesUnsubscribeParams},
+        MexcFuturesWebsocketClient,
+    },
+};
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+pub(crate) const DEFAULT_TOPIC_CHANNEL_CAPACITY: usize = 1024;
+
+/// Routes incoming messages to the typed streams of the topic they belong to.
+#[derive(Debug, Default)]
+pub(crate) struct FuturesTopicRoutes {
+    routes: std::sync::RwLock<HashMap<String, FuturesTopicRoute>>,
+    /// Serializes acquiring and releasing of topic streams, so a release can
+    /// never unsubscribe a topic that was just acquired again.
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug)]
+struct FuturesTopicRoute {
+    tx: broadcast::Sender<Arc<FuturesMessage>>,
+    handle: Weak<FuturesTopicHandle>,
+}
+
+impl FuturesTopicRoutes {
+    /// Drops all routes, which ends their streams
+    pub(crate) fn close(&self) {
+        self.routes
+            .write()
+            .expect("Topic routes lock poisoned")
+            .clear();
+    }
+
+    pub(crate) fn route(&self, message: &Arc<FuturesMessage>) {
+        let Some(key) = message_route_key(message) else {
+            return;
+        };
+        let routes = self
+            .routes
+            .read()
+            .expect("Topic routes lock poisoned");
+        let Some(route) = routes.get(&key) else {
+            return;
+        };
+        // No receivers means the last stream is being dropped right now
+        let _ = route
+            .tx
+            .send(message.clone());
+    }
+}
+
+/// Push messages don't echo the subscription parameters, so topics are keyed
+/// by what can be recovered from both sides. A depth step topic is therefore
+/// keyed by its symbol only, and so is a full depth topic.
+fn topic_route_key(topic: &FuturesTopic) -> String {
+    match topic {
+        FuturesTopic::Tickers => "tickers".to_string(),
+        FuturesTopic::Ticker(ticker) => format!(
+            "ticker@{}",
+            ticker.symbol
+        ),
+        FuturesTopic::Deal(deal) => format!(
+            "deal@{}",
+            deal.symbol
+        ),
+        FuturesTopic::Depth(depth) => format!(
+            "depth@{}",
+            depth.symbol
+        ),
+        FuturesTopic::DepthFull(depth_full) => format!(
+            "depth.full@{}",
+            depth_full.symbol
+        ),
+        FuturesTopic::DepthStep(depth_step) => format!(
+            "depth.step@{}",
+            depth_step.symbol
+        ),
+        FuturesTopic::Kline(kline) => format!(
+            "kline@{}@{:?}",
+            kline.symbol, kline.interval
+        ),
+        FuturesTopic::FundingRate(fr) => format!(
+            "funding.rate@{}",
+            fr.symbol
+        ),
+        FuturesTopic::IndexPrice(ip) => format!(
+            "index.price@{}",
+            ip.symbol
+        ),
+        FuturesTopic::FairPrice(fp) => format!(
+            "fair.price@{}",
+            fp.symbol
+        ),
+        FuturesTopic::Contract => "contract".to_string(),
+        FuturesTopic::EventContract => "event.contract".to_string(),
+    }
+}
+
+fn message_route_key(message: &FuturesMessage) -> Option<String> {
+    let key = match message {
+        FuturesMessage::Tickers(_) => "tickers".to_string(),
+        FuturesMessage::Ticker(ticker) => format!(
+            "ticker@{}",
+            ticker.symbol
+        ),
+        FuturesMessage::Deal(deal) => format!(
+            "deal@{}",
+            deal.symbol
+        ),
+        FuturesMessage::Depth(depth) => format!(
+            "depth@{}",
+            depth.symbol
+        ),
+        FuturesMessage::DepthFull(depth_full) => format!(
+            "depth.full@{}",
+            depth_full.symbol
+        ),
+        FuturesMessage::DepthStep(depth_step) => format!(
+            "depth.step@{}",
+            depth_step.symbol
+        ),
+        FuturesMessage::Kline(kline) => format!(
+            "kline@{}@{:?}",
+            kline.symbol,
+            kline
+                .data
+                .interval
+        ),
+        FuturesMessage::FundingRate(fr) => format!(
+            "funding.rate@{}",
+            fr.symbol
+        ),
+        FuturesMessage::IndexPrice(ip) => format!(
+            "index.price@{}",
+            ip.symbol
+        ),
+        FuturesMessage::FairPrice(fp) => format!(
+            "fair.price@{}",
+            fp.symbol
+        ),
+        FuturesMessage::Contract(_) => "contract".to_string(),
+        FuturesMessage::EventContract(_) => "event.contract".to_string(),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Keeps the topic subscribed for as long as a [`FuturesTopicStream`] of it is
+/// alive.
+#[derive(Debug)]
+struct FuturesTopicHandle {
+    client: Arc<MexcFuturesWebsocketClient>,
+    topic: FuturesTopic,
+    key: String,
+}
+
+impl Drop for FuturesTopicHandle {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::debug!(
+                "No runtime available to unsubscribe from futures topic {:?}",
+                self.topic
+            );
+            return;
+        };
+        let client = self
+            .client
+            .clone();
+        let topic = self
+            .topic
+            .clone();
+        let key = self
+            .key
+            .clone();
+        runtime.spawn(
+            async move {
+                release_topic(
+                    client, topic, key,
+                )
+                .await;
+            },
+        );
+    }
+}
+
+async fn release_topic(client: Arc<MexcFuturesWebsocketClient>, topic: FuturesTopic, key: String) {
+    let _guard = client
+        .topic_routes
+        .lock
+        .lock()
+        .await;
+
+    {
+        let mut routes = client
+            .topic_routes
+            .routes
+            .write()
+            .expect("Topic routes lock poisoned");
+        let still_in_use = routes
+            .get(&key)
+            .is_some_and(
+                |route| {
+                    route
+                        .handle
+                        .strong_count()
+                        > 0
+                },
+            );
+        if still_in_use {
+            return;
+        }
+        routes.remove(&key);
+    }
+
+    let params = FuturesUnsubscribeParams::new(vec![topic.clone()]);
+    if let Err(err) = client
+        .clone()
+        .unsubscribe(params)
+        .await
+    {
+        tracing::error!(
+            "Failed to unsubscribe from futures topic {:?}: {}",
+            topic,
+            err
+        );
+    }
+}
+
+/// A stream of the messages of a single futures topic, with a
+/// [`FuturesStreamEvent::Lagged`] event wherever messages were missed.
+///
+/// The topic is unsubscribed once the last stream for it is dropped.
+pub struct FuturesTopicStream<T> {
+    stream: BoxStream<'static, FuturesStreamEvent<T>>,
+    _handle: Arc<FuturesTopicHandle>,
+}
+
+impl<T> futures::Stream for FuturesTopicStream<T> {
+    type Item = FuturesStreamEvent<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .stream
+            .poll_next_unpin(cx)
+    }
+}
+
+impl<T> std::fmt::Debug for FuturesTopicStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FuturesTopicStream")
+            .field(
+                "topic",
+                &self
+                    ._handle
+                    .topic,
+            )
+            .finish()
+    }
+}
+
+#[async_trait]
+pub trait FuturesTopicStreams {
+    async fn subscribe_tickers(self: Arc<Self>) -> Result<FuturesTopicStream<TickersMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_ticker(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<TickerMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_deal(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<DealMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_depth(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<DepthMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_depth_full(self: Arc<Self>, symbol: String, limit: DepthLimit) -> Result<FuturesTopicStream<DepthMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_depth_step(self: Arc<Self>, symbol: String, step: Decimal) -> Result<FuturesTopicStream<DepthStepMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_kline(self: Arc<Self>, symbol: String, interval: KlineInterval) -> Result<FuturesTopicStream<KlineMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_funding_rate(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<FundingRateMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_index_price(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<IndexPriceMessage>, FuturesSubscribeError>;
+
+    async fn subscribe_fair_price(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<FairPriceMessage>, FuturesSubscribeError>;
+}
+
+#[async_trait]
+impl FuturesTopicStreams for MexcFuturesWebsocketClient {
+    async fn subscribe_tickers(self: Arc<Self>) -> Result<FuturesTopicStream<TickersMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::Tickers,
+            |message| match message {
+                FuturesMessage::Tickers(tickers) => Some(tickers.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_ticker(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<TickerMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::Ticker(TickerTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::Ticker(ticker) => Some(ticker.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_deal(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<DealMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::Deal(DealTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::Deal(deal) => Some(deal.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_depth(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<DepthMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::Depth(DepthTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::Depth(depth) => Some(depth.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_depth_full(self: Arc<Self>, symbol: String, limit: DepthLimit) -> Result<FuturesTopicStream<DepthMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::DepthFull(
+                DepthFullTopic::new(
+                    symbol, limit,
+                ),
+            ),
+            |message| match message {
+                FuturesMessage::DepthFull(depth_full) => Some(depth_full.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_depth_step(self: Arc<Self>, symbol: String, step: Decimal) -> Result<FuturesTopicStream<DepthStepMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::DepthStep(
+                DepthStepTopic::new(
+                    symbol, step,
+                ),
+            ),
+            |message| match message {
+                FuturesMessage::DepthStep(depth_step) => Some(depth_step.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_kline(self: Arc<Self>, symbol: String, interval: KlineInterval) -> Result<FuturesTopicStream<KlineMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::Kline(
+                KlineTopic::new(
+                    symbol, interval,
+                ),
+            ),
+            |message| match message {
+                FuturesMessage::Kline(kline) => Some(kline.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_funding_rate(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<FundingRateMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::FundingRate(FundingRateTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::FundingRate(funding_rate) => Some(funding_rate.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_index_price(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<IndexPriceMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::IndexPrice(IndexPriceTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::IndexPrice(index_price) => Some(index_price.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_fair_price(self: Arc<Self>, symbol: String) -> Result<FuturesTopicStream<FairPriceMessage>, FuturesSubscribeError> {
+        subscribe_topic(
+            self,
+            FuturesTopic::FairPrice(FairPriceTopic::new(symbol)),
+            |message| match message {
+                FuturesMessage::FairPrice(fair_price) => Some(fair_price.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+}
+
+async fn subscribe_topic<T, F>(client: Arc<MexcFuturesWebsocketClient>, topic: FuturesTopic, extract: F) -> Result<FuturesTopicStream<T>, FuturesSubscribeError>
+where
+    T: Send + 'static,
+    F: Fn(&FuturesMessage) -> Option<T> + Send + 'static,
+{
+    let _guard = client
+        .topic_routes
+        .lock
+        .lock()
+        .await;
+
+    let key = topic_route_key(&topic);
+
+    let existing = {
+        let routes = client
+            .topic_routes
+            .routes
+            .read()
+            .expect("Topic routes lock poisoned");
+        routes
+            .get(&key)
+            .and_then(
+                |route| {
+                    route
+                        .handle
+                        .upgrade()
+                        .map(
+                            |handle| {
+                                (
+                                    route
+                                        .tx
+                                        .subscribe(),
+                                    handle,
+                                )
+                            },
+                        )
+                },
+            )
+    };
+
+    let (rx, handle) = match existing {
+        Some(x) => x,
+        None => {
+            // Register the route before subscribing so no messages are missed
+            let (tx, rx) = broadcast::channel(client.topic_channel_capacity);
+            client
+                .topic_routes
+                .routes
+                .write()
+                .expect("Topic routes lock poisoned")
+                .insert(
+                    key.clone(),
+                    FuturesTopicRoute {
+                        tx,
+                        handle: Weak::new(),
+                    },
+                );
+
+            let params = FuturesSubscribeParams::new(
+                None,
+                vec![topic.clone()],
+                true,
+            );
+            let result = match client
+                .clone()
+                .subscribe(params)
+                .await
+            {
+                Ok(output)
+                    if !output
+                        .rejected_topics
+                        .is_empty() =>
+                {
+                    Err(FuturesSubscribeError::TopicsRejected(output.rejected_topics))
+                }
+                Ok(_) => Ok(()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                client
+                    .topic_routes
+                    .routes
+                    .write()
+                    .expect("Topic routes lock poisoned")
+                    .remove(&key);
+                return Err(err);
+            }
+
+            let handle = Arc::new(
+                FuturesTopicHandle {
+                    client: client.clone(),
+                    topic,
+                    key: key.clone(),
+                },
+            );
+            if let Some(route) = client
+                .topic_routes
+                .routes
+                .write()
+                .expect("Topic routes lock poisoned")
+                .get_mut(&key)
+            {
+                route.handle = Arc::downgrade(&handle);
+            }
+            (
+                rx, handle,
+            )
+        }
+    };
+
+    Ok(
+        FuturesTopicStream {
+            stream: typed_stream(
+                rx, extract,
+            ),
+            _handle: handle,
+        },
+    )
+}
+
+fn typed_stream<T, F>(mut rx: broadcast::Receiver<Arc<FuturesMessage>>, extract: F) -> BoxStream<'static, FuturesStreamEvent<T>>
+where
+    T: Send + 'static,
+    F: Fn(&FuturesMessage) -> Option<T> + Send + 'static,
+{
+    let stream = async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(message) => {
+                    if let Some(item) = extract(&message) {
+                        yield FuturesStreamEvent::Message(item);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => yield FuturesStreamEvent::Lagged { skipped },
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+    stream.boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::ws::message::RawFuturesMessage;
+
+    #[test]
+    fn message_and_topic_route_keys_match() {
+        let json = r#"{"channel":"push.kline","data":{"a":233.740269343644737245,"c":6885,"h":6910.5,"interval":"Min60","l":6885,"o":6894.5,"q":1611754,"symbol":"BTC_USDT","t":1587448800,"ro":0,"rc":0,"rh":0,"rl":0,"v":0},"symbol":"BTC_USDT"}"#;
+        let raw_message: RawFuturesMessage = serde_json::from_str(json).unwrap();
+        let message = FuturesMessage::try_from(raw_message).unwrap();
+
+        let topic = FuturesTopic::Kline(
+            KlineTopic::new(
+                "BTC_USDT".to_string(),
+                KlineInterval::OneHour,
+            ),
+        );
+        assert_eq!(
+            message_route_key(&message),
+            Some(topic_route_key(&topic))
+        );
+
+        let other_interval = FuturesTopic::Kline(
+            KlineTopic::new(
+                "BTC_USDT".to_string(),
+                KlineInterval::OneMinute,
+            ),
+        );
+        assert_ne!(
+            message_route_key(&message),
+            Some(topic_route_key(&other_interval))
+        );
+    }
+}
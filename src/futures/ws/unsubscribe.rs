@@ -1,4 +1,9 @@
-use crate::futures::ws::{topic::FuturesTopic, MexcFuturesWebsocketClient, SendableMessage};
+use crate::futures::ws::{
+    ack::{self, FuturesRejectedTopic},
+    lifecycle::FuturesLifecycleEventKind,
+    topic::FuturesTopic,
+    MexcFuturesWebsocketClient, SendableMessage,
+};
 use async_channel::SendError;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -6,6 +11,8 @@ use std::sync::Arc;
 #[derive(Debug)]
 pub struct FuturesUnsubscribeParams {
     pub topics: Vec<FuturesTopic>,
+    /// Wait for the server to confirm the unsubscription
+    pub wait_for_confirmation: bool,
 }
 
 impl Default for FuturesUnsubscribeParams {
@@ -18,9 +25,15 @@ impl FuturesUnsubscribeParams {
     pub fn new(topics: Vec<FuturesTopic>) -> Self {
         Self {
             topics,
+            wait_for_confirmation: true,
         }
     }
 
+    pub fn with_wait_for_confirmation(mut self, wait_for_confirmation: bool) -> Self {
+        self.wait_for_confirmation = wait_for_confirmation;
+        self
+    }
+
     pub fn with_topic(mut self, topic: FuturesTopic) -> Self {
         self.topics
             .push(topic);
@@ -35,7 +48,10 @@ impl FuturesUnsubscribeParams {
 }
 
 #[derive(Debug, Clone)]
-pub struct FuturesUnsubscribeOutput {}
+pub struct FuturesUnsubscribeOutput {
+    pub accepted_topics: Vec<FuturesTopic>,
+    pub rejected_topics: Vec<FuturesRejectedTopic>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum FuturesUnsubscribeError {
@@ -51,12 +67,131 @@ pub trait FuturesUnsubscribe {
 #[async_trait]
 impl FuturesUnsubscribe for MexcFuturesWebsocketClient {
     async fn unsubscribe(self: Arc<Self>, params: FuturesUnsubscribeParams) -> Result<FuturesUnsubscribeOutput, FuturesUnsubscribeError> {
-        // Placeholder: send unsubscription for each topic
-        for topic in params.topics {
-            let _message = SendableMessage::Unsubscription(topic.to_unsubscription_message());
-            // Need to get the tx from websocket entry
+        let mut inner = self
+            .inner
+            .write()
+            .await;
+
+        let mut accepted_topics = vec![];
+        let mut waiting_for_replies = vec![];
+        let mut websocket_ids_to_close = vec![];
+        for websocket_entry in inner
+            .websockets
+            .iter()
+        {
+            let mut topics = websocket_entry
+                .topics
+                .write()
+                .await;
+            let topics_to_unsubscribe = params
+                .topics
+                .iter()
+                .filter(|topic| topics.contains(topic))
+                .cloned()
+                .collect::<Vec<FuturesTopic>>();
+            if topics_to_unsubscribe.is_empty() {
+                continue;
+            }
+
+            let tx = websocket_entry
+                .message_tx
+                .read()
+                .await;
+            for topic in topics_to_unsubscribe.iter() {
+                let unsubscription_message = topic.to_unsubscription_message();
+                match params.wait_for_confirmation {
+                    true => waiting_for_replies.push(
+                        (
+                            self.pending_replies
+                                .register(
+                                    websocket_entry.id,
+                                    &unsubscription_message,
+                                ),
+                            topic.clone(),
+                        ),
+                    ),
+                    false => accepted_topics.push(topic.clone()),
+                }
+                tx.send(SendableMessage::Unsubscription(unsubscription_message))
+                    .await?;
+            }
+            topics.retain(|topic| !topics_to_unsubscribe.contains(topic));
+
+            if topics.is_empty() {
+                websocket_ids_to_close.push(
+                    (
+                        websocket_entry.id,
+                        topics_to_unsubscribe,
+                    ),
+                );
+            }
+        }
+
+        let mut websocket_entries_to_close = vec![];
+        for (websocket_id, unsubscribed_topics) in websocket_ids_to_close {
+            let Some(index) = inner
+                .websockets
+                .iter()
+                .position(|websocket_entry| websocket_entry.id == websocket_id)
+            else {
+                continue;
+            };
+            websocket_entries_to_close.push(
+                (
+                    inner
+                        .websockets
+                        .remove(index),
+                    unsubscribed_topics,
+                ),
+            );
+        }
+        // The receiver tasks need the lock to handle the replies
+        drop(inner);
+
+        let mut rejected_topics = vec![];
+        let replies = futures::future::join_all(
+            waiting_for_replies
+                .into_iter()
+                .map(
+                    |(reply_rx, topic)| {
+                        ack::wait_for_topic(
+                            reply_rx, topic,
+                        )
+                    },
+                ),
+        )
+        .await;
+        for reply in replies {
+            match reply {
+                Ok(topic) => accepted_topics.push(topic),
+                Err(rejected_topic) => rejected_topics.push(rejected_topic),
+            }
+        }
+
+        // Websockets without any topics left are of no use, so close them. The
+        // sender task flushes the unsubscriptions before sending the close frame.
+        for (websocket_entry, unsubscribed_topics) in websocket_entries_to_close {
+            websocket_entry
+                .cancellation_token
+                .read()
+                .await
+                .cancel();
+            self.emit_lifecycle_event(
+                websocket_entry.id,
+                unsubscribed_topics,
+                FuturesLifecycleEventKind::Disconnected,
+            );
+            tracing::debug!(
+                "Closed futures websocket with id {} as it has no topics left",
+                websocket_entry.id
+            );
         }
 
-        Ok(FuturesUnsubscribeOutput {})
+        Ok(
+            FuturesUnsubscribeOutput {
+                accepted_topics,
+                rejected_topics,
+            },
+        )
     }
 }
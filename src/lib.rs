@@ -4,6 +4,28 @@ pub mod spot;
 #[cfg(feature = "futures")]
 pub mod futures;
 
+#[cfg(
+    any(
+        feature = "spot",
+        feature = "futures"
+    )
+)]
+pub mod exchange;
+
+#[cfg(
+    any(
+        feature = "spot",
+        feature = "futures"
+    )
+)]
+mod fs;
+
+#[cfg(feature = "ws")]
+pub mod recording;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
 pub mod proto {
     tonic::include_proto!("_");
 }
@@ -0,0 +1,422 @@
+use crate::mock::{signature, MockMethod, MockRequest, MockResponse, MockState};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+
+/// Spot routes that require a signed query
+const SPOT_SIGNED_PATHS: &[&str] = &[
+    "/api/v3/account",
+    "/api/v3/order",
+    "/api/v3/openOrders",
+    "/api/v3/allOrders",
+    "/api/v3/myTrades",
+    "/api/v3/userDataStream",
+];
+
+const FUTURES_PREFIX: &str = "/api/v1/";
+const FUTURES_PRIVATE_PREFIX: &str = "/api/v1/private/";
+
+pub(crate) async fn serve(listener: TcpListener, state: Arc<MockState>, cancellation_token: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, state.clone(), cancellation_token.clone()));
+                }
+                Err(err) => {
+                    tracing::warn!("Mock server failed to accept connection: {}", err);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<MockState>, cancellation_token: CancellationToken) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let request = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            request = read_request(&mut reader) => request,
+        };
+        let response = match request {
+            Ok(Some(Some(request))) => respond(
+                &state, request,
+            ),
+            Ok(Some(None)) => MockResponse::json(serde_json::json!({ "msg": "Method not allowed" })).with_status(405),
+            Ok(None) => break,
+            Err(err) => {
+                tracing::debug!(
+                    "Mock server failed to read request: {}",
+                    err
+                );
+                break;
+            }
+        };
+        if write_response(
+            &mut writer,
+            &response,
+        )
+        .await
+        .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Returns `None` once the connection is closed, and `Some(None)` for a
+/// request with an unsupported method.
+async fn read_request<R>(reader: &mut R) -> std::io::Result<Option<Option<MockRequest>>>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    let mut request_line = String::new();
+    if reader
+        .read_line(&mut request_line)
+        .await?
+        == 0
+    {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts
+        .next()
+        .unwrap_or_default()
+        .parse::<MockMethod>();
+    let target = parts
+        .next()
+        .unwrap_or_default();
+    let (path, query) = target
+        .split_once('?')
+        .unwrap_or(
+            (
+                target, "",
+            ),
+        );
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader
+            .read_line(&mut line)
+            .await?
+            == 0
+        {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(
+                name.trim()
+                    .to_ascii_lowercase(),
+                value
+                    .trim()
+                    .to_string(),
+            );
+        }
+    }
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(
+            |content_length| {
+                content_length
+                    .parse::<usize>()
+                    .ok()
+            },
+        )
+        .unwrap_or_default();
+    let mut body = vec![0u8; content_length];
+    reader
+        .read_exact(&mut body)
+        .await?;
+
+    let Ok(method) = method else {
+        return Ok(Some(None));
+    };
+    Ok(
+        Some(
+            Some(
+                MockRequest {
+                    method,
+                    path: path.to_string(),
+                    query: query.to_string(),
+                    headers,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                },
+            ),
+        ),
+    )
+}
+
+async fn write_response<W>(writer: &mut W, response: &MockResponse) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response
+            .body
+            .len()
+    );
+    writer
+        .write_all(head.as_bytes())
+        .await?;
+    writer
+        .write_all(
+            response
+                .body
+                .as_bytes(),
+        )
+        .await?;
+    writer
+        .flush()
+        .await
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+fn is_futures(request: &MockRequest) -> bool {
+    request
+        .path
+        .starts_with(FUTURES_PREFIX)
+}
+
+fn respond(state: &MockState, request: MockRequest) -> MockResponse {
+    state.record_request(request.clone());
+
+    let authenticated = match is_futures(&request) {
+        true => authenticate_futures(
+            state, &request,
+        ),
+        false => authenticate_spot(
+            state, &request,
+        ),
+    };
+    if let Err(response) = authenticated {
+        return response;
+    }
+
+    if let Some(response) = state.scripted_response(
+        request.method,
+        &request.path,
+    ) {
+        return response;
+    }
+    built_in_response(
+        state, &request,
+    )
+    .unwrap_or_else(
+        || {
+            let message = format!(
+                "No mock response for {:?} {}",
+                request.method, request.path
+            );
+            match is_futures(&request) {
+                true => MockResponse::futures_error(
+                    404, &message,
+                ),
+                false => MockResponse::spot_error(
+                    730003, &message,
+                )
+                .with_status(404),
+            }
+        },
+    )
+}
+
+fn authenticate_spot(state: &MockState, request: &MockRequest) -> Result<(), MockResponse> {
+    if !SPOT_SIGNED_PATHS.contains(
+        &request
+            .path
+            .as_str(),
+    ) {
+        return Ok(());
+    }
+    let Some(api_key) = request.header("X-MEXC-APIKEY") else {
+        return Err(
+            MockResponse::spot_error(
+                400,
+                "Api key info invalid",
+            ),
+        );
+    };
+    let Some(secret_key) = state.secret_key(api_key) else {
+        return Err(
+            MockResponse::spot_error(
+                10072,
+                "Api key info invalid",
+            ),
+        );
+    };
+    if !signature::verify_spot_query(
+        &secret_key,
+        &request.query,
+    ) {
+        return Err(
+            MockResponse::spot_error(
+                602,
+                "Signature verification failed",
+            ),
+        );
+    }
+    Ok(())
+}
+
+fn authenticate_futures(state: &MockState, request: &MockRequest) -> Result<(), MockResponse> {
+    if !request
+        .path
+        .starts_with(FUTURES_PRIVATE_PREFIX)
+    {
+        return Ok(());
+    }
+    let (Some(api_key), Some(request_time), Some(signature)) = (
+        request.header("ApiKey"),
+        request.header("Request-Time"),
+        request.header("Signature"),
+    ) else {
+        return Err(
+            MockResponse::futures_error(
+                401,
+                "Unauthorized",
+            ),
+        );
+    };
+    let Some(secret_key) = state.secret_key(api_key) else {
+        return Err(
+            MockResponse::futures_error(
+                401,
+                "Unauthorized",
+            ),
+        );
+    };
+    let data = match request.method {
+        MockMethod::Post | MockMethod::Put => &request.body,
+        MockMethod::Get | MockMethod::Delete => &request.query,
+    };
+    if !signature::verify_futures_request(
+        api_key,
+        &secret_key,
+        request_time,
+        data,
+        signature,
+    ) {
+        return Err(
+            MockResponse::futures_error(
+                602,
+                "Verify failed",
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// The routes that work without scripting
+fn built_in_response(state: &MockState, request: &MockRequest) -> Option<MockResponse> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let response = match (
+        request.method,
+        request
+            .path
+            .as_str(),
+    ) {
+        (MockMethod::Get, "/api/v3/ping") => MockResponse::json(serde_json::json!({})),
+        (MockMethod::Get, "/api/v3/time") => MockResponse::json(serde_json::json!({ "serverTime": now })),
+        (MockMethod::Post, "/api/v3/userDataStream") => MockResponse::json(serde_json::json!({ "listenKey": state.create_listen_key() })),
+        (MockMethod::Put | MockMethod::Delete, "/api/v3/userDataStream") => {
+            let listen_key = request
+                .query_params()
+                .remove("listenKey")
+                .unwrap_or_default();
+            let known = match request.method {
+                MockMethod::Delete => state.remove_listen_key(&listen_key),
+                _ => state.has_listen_key(&listen_key),
+            };
+            match known {
+                true => MockResponse::json(serde_json::json!({ "listenKey": listen_key })),
+                false => MockResponse::spot_error(
+                    730002,
+                    "Listen key does not exist",
+                ),
+            }
+        }
+        (MockMethod::Get, "/api/v1/contract/ping") => MockResponse::futures_data(serde_json::json!(now)),
+        _ => return None,
+    };
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_request() {
+        let raw = "POST /api/v1/private/order/submit?a=1 HTTP/1.1\r\nHost: localhost\r\nApiKey: key\r\nContent-Length: 2\r\n\r\n{}GET /api/v3/ping HTTP/1.1\r\n\r\n";
+        let mut reader = BufReader::new(raw.as_bytes());
+
+        let request = read_request(&mut reader)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.method,
+            MockMethod::Post
+        );
+        assert_eq!(
+            request.path,
+            "/api/v1/private/order/submit"
+        );
+        assert_eq!(
+            request.query,
+            "a=1"
+        );
+        assert_eq!(
+            request.header("apikey"),
+            Some("key")
+        );
+        assert_eq!(
+            request.body,
+            "{}"
+        );
+
+        let request = read_request(&mut reader)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.path,
+            "/api/v3/ping"
+        );
+        assert!(
+            read_request(&mut reader)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,664 @@
+//! An in-process mock of the MEXC spot v3 and futures v1 REST APIs and of the
+//! spot and futures websockets, so the clients can be tested without network
+//! access or real api keys.
+//!
+//! Requests are signature checked like MEXC does, against the credentials
+//! known to the server ([`MOCK_API_KEY`] and [`MOCK_SECRET_KEY`] by default).
+//! Apart from ping, time and the listen keys, responses have to be scripted
+//! with [`MockServer::set_response`]. Websocket messages are pushed to the
+//! connections subscribed to them with [`MockServer::push_spot`] and
+//! [`MockServer::push_futures`].
+
+use crate::{
+    futures::{ws::endpoint::MexcFuturesWebsocketEndpoint, MexcFuturesApiEndpoint},
+    spot::{ws::endpoint::MexcWebsocketEndpoint, MexcSpotApiEndpoint},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+mod http;
+mod signature;
+mod ws;
+
+pub const MOCK_API_KEY: &str = "mock-api-key";
+pub const MOCK_SECRET_KEY: &str = "mock-secret-key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockMethod {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl std::str::FromStr for MockMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Self::Get),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "DELETE" => Ok(Self::Delete),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(body: serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    /// Wraps `data` in the envelope of the futures API
+    pub fn futures_data(data: serde_json::Value) -> Self {
+        Self::json(
+            serde_json::json!({
+                "success": true,
+                "code": 0,
+                "data": data
+            }),
+        )
+    }
+
+    pub fn spot_error(code: i32, msg: &str) -> Self {
+        Self::json(
+            serde_json::json!({
+                "code": code,
+                "msg": msg
+            }),
+        )
+        .with_status(400)
+    }
+
+    pub fn futures_error(code: i32, message: &str) -> Self {
+        Self::json(
+            serde_json::json!({
+                "success": false,
+                "code": code,
+                "message": message
+            }),
+        )
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// A request as received by the server, before any checks.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: MockMethod,
+    pub path: String,
+    /// Still url encoded
+    pub query: String,
+    /// Names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn query_params(&self) -> HashMap<String, String> {
+        serde_urlencoded::from_str(&self.query).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FuturesRejection {
+    method: String,
+    symbol: Option<String>,
+    reason: String,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MockState {
+    /// Api key to secret key
+    credentials: Mutex<HashMap<String, String>>,
+    responses: Mutex<
+        HashMap<
+            (
+                MockMethod,
+                String,
+            ),
+            MockResponse,
+        >,
+    >,
+    requests: Mutex<Vec<MockRequest>>,
+    listen_keys: Mutex<HashSet<String>>,
+    /// Spot topic to the reason it is rejected
+    spot_rejections: Mutex<HashMap<String, String>>,
+    futures_rejections: Mutex<Vec<FuturesRejection>>,
+    connections: Mutex<Vec<ws::Connection>>,
+}
+
+impl MockState {
+    pub(crate) fn secret_key(&self, api_key: &str) -> Option<String> {
+        self.credentials
+            .lock()
+            .expect("Mock state lock poisoned")
+            .get(api_key)
+            .cloned()
+    }
+
+    pub(crate) fn record_request(&self, request: MockRequest) {
+        self.requests
+            .lock()
+            .expect("Mock state lock poisoned")
+            .push(request);
+    }
+
+    pub(crate) fn scripted_response(&self, method: MockMethod, path: &str) -> Option<MockResponse> {
+        self.responses
+            .lock()
+            .expect("Mock state lock poisoned")
+            .get(
+                &(
+                    method,
+                    path.to_string(),
+                ),
+            )
+            .cloned()
+    }
+
+    pub(crate) fn create_listen_key(&self) -> String {
+        let listen_key = uuid::Uuid::new_v4()
+            .simple()
+            .to_string();
+        self.listen_keys
+            .lock()
+            .expect("Mock state lock poisoned")
+            .insert(listen_key.clone());
+        listen_key
+    }
+
+    pub(crate) fn has_listen_key(&self, listen_key: &str) -> bool {
+        self.listen_keys
+            .lock()
+            .expect("Mock state lock poisoned")
+            .contains(listen_key)
+    }
+
+    /// Returns whether the listen key existed
+    pub(crate) fn remove_listen_key(&self, listen_key: &str) -> bool {
+        self.listen_keys
+            .lock()
+            .expect("Mock state lock poisoned")
+            .remove(listen_key)
+    }
+
+    pub(crate) fn spot_rejection(&self, topic: &str) -> Option<String> {
+        self.spot_rejections
+            .lock()
+            .expect("Mock state lock poisoned")
+            .get(topic)
+            .cloned()
+    }
+
+    pub(crate) fn futures_rejection(&self, method: &str, symbol: Option<&str>) -> Option<String> {
+        self.futures_rejections
+            .lock()
+            .expect("Mock state lock poisoned")
+            .iter()
+            .find(
+                |rejection| {
+                    rejection.method == method
+                        && (rejection
+                            .symbol
+                            .is_none()
+                            || rejection
+                                .symbol
+                                .as_deref()
+                                == symbol)
+                },
+            )
+            .map(
+                |rejection| {
+                    rejection
+                        .reason
+                        .clone()
+                },
+            )
+    }
+}
+
+/// Serves until it is dropped. Every test should start its own server, they
+/// don't share any state.
+#[derive(Debug)]
+pub struct MockServer {
+    state: Arc<MockState>,
+    http_addr: SocketAddr,
+    ws_addr: SocketAddr,
+    cancellation_token: CancellationToken,
+}
+
+impl MockServer {
+    /// Listens on random local ports, one for the REST APIs and one for the
+    /// websockets.
+    pub async fn start() -> std::io::Result<Self> {
+        let http_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let server = Self {
+            state: Arc::new(MockState::default()),
+            http_addr: http_listener.local_addr()?,
+            ws_addr: ws_listener.local_addr()?,
+            cancellation_token: CancellationToken::new(),
+        };
+        server.add_credentials(
+            MOCK_API_KEY,
+            MOCK_SECRET_KEY,
+        );
+
+        tokio::spawn(
+            http::serve(
+                http_listener,
+                server
+                    .state
+                    .clone(),
+                server
+                    .cancellation_token
+                    .clone(),
+            ),
+        );
+        tokio::spawn(
+            ws::serve(
+                ws_listener,
+                server
+                    .state
+                    .clone(),
+                server
+                    .cancellation_token
+                    .clone(),
+            ),
+        );
+
+        Ok(server)
+    }
+
+    pub fn spot_api_endpoint(&self) -> MexcSpotApiEndpoint {
+        MexcSpotApiEndpoint::Custom(
+            format!(
+                "http://{}",
+                self.http_addr
+            ),
+        )
+    }
+
+    pub fn futures_api_endpoint(&self) -> MexcFuturesApiEndpoint {
+        MexcFuturesApiEndpoint::Custom(
+            format!(
+                "http://{}",
+                self.http_addr
+            ),
+        )
+    }
+
+    pub fn spot_ws_endpoint(&self) -> MexcWebsocketEndpoint {
+        MexcWebsocketEndpoint::Custom(
+            format!(
+                "ws://{}{}",
+                self.ws_addr,
+                ws::SPOT_PATH
+            ),
+        )
+    }
+
+    pub fn futures_ws_endpoint(&self) -> MexcFuturesWebsocketEndpoint {
+        MexcFuturesWebsocketEndpoint::Custom(
+            format!(
+                "ws://{}{}",
+                self.ws_addr,
+                ws::FUTURES_PATH
+            ),
+        )
+    }
+
+    /// Makes the server accept requests signed with these credentials
+    pub fn add_credentials(&self, api_key: &str, secret_key: &str) {
+        self.state
+            .credentials
+            .lock()
+            .expect("Mock state lock poisoned")
+            .insert(
+                api_key.to_string(),
+                secret_key.to_string(),
+            );
+    }
+
+    /// Replaces the response for a route, including the built in ones. Signed
+    /// routes only respond with it to correctly signed requests.
+    pub fn set_response(&self, method: MockMethod, path: &str, response: MockResponse) {
+        self.state
+            .responses
+            .lock()
+            .expect("Mock state lock poisoned")
+            .insert(
+                (
+                    method,
+                    path.to_string(),
+                ),
+                response,
+            );
+    }
+
+    /// All REST requests received so far, in order
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state
+            .requests
+            .lock()
+            .expect("Mock state lock poisoned")
+            .clone()
+    }
+
+    /// The listen keys that have been created and not deleted
+    pub fn listen_keys(&self) -> Vec<String> {
+        self.state
+            .listen_keys
+            .lock()
+            .expect("Mock state lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Subscriptions to the topic, e.g. `spot@public.deals.v3.api@BTCUSDT`,
+    /// are answered with a rejection for `reason`.
+    pub fn reject_spot_topic(&self, topic: &str, reason: &str) {
+        self.state
+            .spot_rejections
+            .lock()
+            .expect("Mock state lock poisoned")
+            .insert(
+                topic.to_string(),
+                reason.to_string(),
+            );
+    }
+
+    /// Requests with the method, e.g. `sub.deal`, are answered with `reason`
+    /// instead of `success`. Without a symbol all symbols are rejected.
+    pub fn reject_futures_subscription(&self, method: &str, symbol: Option<&str>, reason: &str) {
+        self.state
+            .futures_rejections
+            .lock()
+            .expect("Mock state lock poisoned")
+            .push(
+                FuturesRejection {
+                    method: method.to_string(),
+                    symbol: symbol.map(str::to_string),
+                    reason: reason.to_string(),
+                },
+            );
+    }
+
+    /// Sends the message to the spot websockets subscribed to the channel.
+    /// Returns the number of websockets it was sent to.
+    pub fn push_spot(&self, channel: &str, message: Message) -> usize {
+        ws::push_spot(
+            &self.state,
+            channel,
+            message,
+        )
+    }
+
+    /// Sends the message, e.g. `{"channel":"push.deal","symbol":"BTC_USDT",..}`,
+    /// to the futures websockets subscribed to it. Personal messages go to all
+    /// logged in websockets. Returns the number of websockets it was sent to.
+    pub fn push_futures(&self, message: serde_json::Value) -> usize {
+        ws::push_futures(
+            &self.state,
+            message,
+        )
+    }
+
+    /// Closes all websockets, e.g. to test reconnecting
+    pub fn disconnect_websockets(&self) {
+        ws::disconnect_all(&self.state);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.cancellation_token
+            .cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        futures::{
+            v1::endpoints::{get_account_assets::GetAccountAssets, get_server_time::GetServerTime},
+            MexcFuturesApiClient, MexcFuturesApiClientWithAuthentication,
+        },
+        spot::{
+            v3::{create_user_data_stream::CreateUserDataStreamEndpoint, time::TimeEndpoint, ApiError},
+            ws::{
+                auth::WebsocketAuth,
+                message::Message as SpotMessage,
+                stream::Stream,
+                subscribe::{Subscribe, SubscribeParams},
+                topic::{DealsTopic, Topic},
+                MexcSpotWebsocketClient,
+            },
+            MexcSpotApiClient, MexcSpotApiClientWithAuthentication,
+        },
+    };
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn verifies_spot_signatures() {
+        let server = MockServer::start()
+            .await
+            .unwrap();
+        assert!(
+            MexcSpotApiClient::new(server.spot_api_endpoint())
+                .time()
+                .await
+                .is_ok()
+        );
+
+        let client = MexcSpotApiClientWithAuthentication::new(
+            server.spot_api_endpoint(),
+            MOCK_API_KEY.to_string(),
+            MOCK_SECRET_KEY.to_string(),
+        );
+        let output = client
+            .create_user_data_stream()
+            .await
+            .unwrap();
+        assert_eq!(
+            server.listen_keys(),
+            vec![output.listen_key]
+        );
+
+        let client = MexcSpotApiClientWithAuthentication::new(
+            server.spot_api_endpoint(),
+            MOCK_API_KEY.to_string(),
+            "wrong-secret-key".to_string(),
+        );
+        assert!(
+            matches!(
+                client
+                    .create_user_data_stream()
+                    .await,
+                Err(ApiError::ErrorResponse(_))
+            )
+        );
+        assert_eq!(
+            server
+                .requests()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_scripted_futures_responses() {
+        let server = MockServer::start()
+            .await
+            .unwrap();
+        assert!(
+            MexcFuturesApiClient::new(server.futures_api_endpoint())
+                .get_server_time()
+                .await
+                .is_ok()
+        );
+
+        server.set_response(
+            MockMethod::Get,
+            "/api/v1/private/account/assets",
+            MockResponse::futures_data(
+                serde_json::json!([{
+                    "currency": "USDT",
+                    "positionMargin": 0,
+                    "frozenBalance": 0,
+                    "availableBalance": 100,
+                    "cashBalance": 100,
+                    "equity": 100,
+                    "unrealized": 0
+                }]),
+            ),
+        );
+        let client = MexcFuturesApiClientWithAuthentication::new(
+            server.futures_api_endpoint(),
+            MOCK_API_KEY.to_string(),
+            MOCK_SECRET_KEY.to_string(),
+        );
+        let assets = client
+            .get_account_assets()
+            .await
+            .unwrap();
+        assert_eq!(
+            assets.len(),
+            1
+        );
+
+        let client = MexcFuturesApiClientWithAuthentication::new(
+            server.futures_api_endpoint(),
+            "unknown-api-key".to_string(),
+            MOCK_SECRET_KEY.to_string(),
+        );
+        assert!(
+            client
+                .get_account_assets()
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn pushes_to_spot_subscribers() {
+        let server = MockServer::start()
+            .await
+            .unwrap();
+        let rejected_topic = Topic::Deals(DealsTopic::new("NOPEUSDT".to_string()));
+        server.reject_spot_topic(
+            &rejected_topic.to_topic_subscription_string(),
+            "Blocked!",
+        );
+
+        let client = MexcSpotWebsocketClient::new_with_endpoints(
+            server.spot_ws_endpoint(),
+            server.spot_api_endpoint(),
+        )
+        .into_arc();
+        let mut stream = client
+            .clone()
+            .stream();
+        let topic = Topic::Deals(DealsTopic::new("BTCUSDT".to_string()));
+        let output = client
+            .clone()
+            .subscribe(
+                SubscribeParams::new(
+                    Some(
+                        WebsocketAuth::new(
+                            MOCK_API_KEY.to_string(),
+                            MOCK_SECRET_KEY.to_string(),
+                        ),
+                    ),
+                    vec![
+                        topic.clone(),
+                        Topic::AccountUpdate,
+                        rejected_topic.clone(),
+                    ],
+                    true,
+                ),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output
+                .accepted_topics
+                .len(),
+            2
+        );
+        assert!(
+            output
+                .accepted_topics
+                .contains(&topic)
+        );
+        assert_eq!(
+            output.rejected_topics[0].topic,
+            rejected_topic
+        );
+        assert_eq!(
+            server
+                .listen_keys()
+                .len(),
+            1
+        );
+
+        let channel = topic.to_topic_subscription_string();
+        let pushed = server.push_spot(
+            &channel,
+            Message::Text(
+                serde_json::json!({
+                    "c": channel,
+                    "d": {
+                        "deals": [{ "S": 1, "p": "26288.47", "t": 1695680458622_i64, "v": "0.001" }],
+                        "e": "spot@public.deals.v3.api"
+                    },
+                    "s": "BTCUSDT",
+                    "t": 1695680458622_i64
+                })
+                .to_string(),
+            ),
+        );
+        assert_eq!(
+            pushed,
+            1
+        );
+        assert!(
+            matches!(
+                *stream
+                    .next()
+                    .await
+                    .unwrap(),
+                SpotMessage::Deals(_)
+            )
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+fn verify(secret_key: &str, data: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(data.as_bytes());
+    mac.verify_slice(&signature)
+        .is_ok()
+}
+
+/// `query` is the raw query string of a spot request, which ends with the
+/// `signature` parameter appended by `sign_query`.
+pub(crate) fn verify_spot_query(secret_key: &str, query: &str) -> bool {
+    let (signed, signature) = match query.rsplit_once("&signature=") {
+        Some(split) => split,
+        None => match query.strip_prefix("signature=") {
+            Some(signature) => (
+                "", signature,
+            ),
+            None => return false,
+        },
+    };
+    verify(
+        secret_key, signed, signature,
+    )
+}
+
+/// `data` is the raw query string for GET and DELETE requests and the raw body
+/// for POST requests, as signed by `futures::auth::sign_request`.
+pub(crate) fn verify_futures_request(api_key: &str, secret_key: &str, request_time: &str, data: &str, signature: &str) -> bool {
+    verify(
+        secret_key,
+        &format!(
+            "{}{}{}",
+            api_key, request_time, data
+        ),
+        signature,
+    )
+}
+
+/// The signature of a futures websocket login, see
+/// `FuturesWebsocketAuth::generate_signature`.
+pub(crate) fn verify_futures_login(api_key: &str, secret_key: &str, request_time: &str, signature: &str) -> bool {
+    verify_futures_request(
+        api_key,
+        secret_key,
+        request_time,
+        "",
+        signature,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futures::{
+        auth::{sign_request, SignRequestParams, SignRequestParamsKind},
+        ws::auth::FuturesWebsocketAuth,
+    };
+    use chrono::Utc;
+
+    #[test]
+    fn verifies_futures_signatures() {
+        let now = Utc::now();
+        let body = serde_json::json!({ "symbol": "BTC_USDT", "vol": 1 });
+        let output = sign_request(
+            SignRequestParams {
+                time: now,
+                api_key: "api_key",
+                secret_key: "secret_key",
+                params: &body,
+                params_kind: SignRequestParamsKind::Body,
+            },
+        )
+        .unwrap();
+        let request_time = now
+            .timestamp_millis()
+            .to_string();
+        assert!(
+            verify_futures_request(
+                "api_key",
+                "secret_key",
+                &request_time,
+                &body.to_string(),
+                &output.signature
+            )
+        );
+        assert!(
+            !verify_futures_request(
+                "api_key",
+                "other_secret_key",
+                &request_time,
+                &body.to_string(),
+                &output.signature
+            )
+        );
+
+        let auth = FuturesWebsocketAuth::new(
+            "api_key".to_string(),
+            "secret_key".to_string(),
+        );
+        assert!(
+            verify_futures_login(
+                "api_key",
+                "secret_key",
+                &request_time,
+                &auth.generate_signature(&request_time)
+            )
+        );
+    }
+}
@@ -0,0 +1,595 @@
+use crate::mock::{signature, MockState};
+use futures::{SinkExt, StreamExt};
+use std::{collections::HashSet, sync::Arc};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+pub(crate) const SPOT_PATH: &str = "/ws";
+pub(crate) const FUTURES_PATH: &str = "/edge";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Spot,
+    Futures,
+}
+
+#[derive(Debug)]
+pub(crate) struct Connection {
+    id: Uuid,
+    protocol: Protocol,
+    /// Connected with a listen key for spot, logged in for futures
+    authenticated: bool,
+    /// Spot topics, or futures channels like `deal@BTC_USDT`
+    subscriptions: HashSet<String>,
+    tx: mpsc::UnboundedSender<Message>,
+    cancellation_token: CancellationToken,
+}
+
+pub(crate) async fn serve(listener: TcpListener, state: Arc<MockState>, cancellation_token: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, state.clone(), cancellation_token.child_token()));
+                }
+                Err(err) => {
+                    tracing::warn!("Mock server failed to accept websocket connection: {}", err);
+                }
+            }
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(message.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+// The handshake callback has to return the error response of tungstenite
+#[allow(clippy::result_large_err)]
+async fn handle_connection(stream: TcpStream, state: Arc<MockState>, cancellation_token: CancellationToken) {
+    let mut connection_kind = None;
+    let handshake = tokio_tungstenite::accept_hdr_async(
+        stream,
+        |request: &Request, response: Response| {
+            let listen_key = request
+                .uri()
+                .query()
+                .and_then(
+                    |query| {
+                        serde_urlencoded::from_str::<
+                            Vec<(
+                                String,
+                                String,
+                            )>,
+                        >(query)
+                        .ok()?
+                        .into_iter()
+                        .find(|(name, _)| name == "listenKey")
+                        .map(|(_, listen_key)| listen_key)
+                    },
+                )
+                .filter(|listen_key| !listen_key.is_empty());
+            match request
+                .uri()
+                .path()
+            {
+                SPOT_PATH => {
+                    if let Some(listen_key) = &listen_key {
+                        if !state.has_listen_key(listen_key) {
+                            return Err(
+                                error_response(
+                                    StatusCode::UNAUTHORIZED,
+                                    "Invalid listen key",
+                                ),
+                            );
+                        }
+                    }
+                    connection_kind = Some(
+                        (
+                            Protocol::Spot,
+                            listen_key.is_some(),
+                        ),
+                    );
+                }
+                FUTURES_PATH => {
+                    connection_kind = Some(
+                        (
+                            Protocol::Futures,
+                            false,
+                        ),
+                    )
+                }
+                _ => {
+                    return Err(
+                        error_response(
+                            StatusCode::NOT_FOUND,
+                            "Unknown websocket path",
+                        ),
+                    )
+                }
+            }
+            Ok(response)
+        },
+    )
+    .await;
+    let (ws_stream, Some((protocol, authenticated))) = (
+        match handshake {
+            Ok(ws_stream) => ws_stream,
+            Err(err) => {
+                tracing::debug!(
+                    "Mock server rejected websocket: {}",
+                    err
+                );
+                return;
+            }
+        },
+        connection_kind,
+    ) else {
+        return;
+    };
+
+    let id = Uuid::new_v4();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .push(
+            Connection {
+                id,
+                protocol,
+                authenticated,
+                subscriptions: HashSet::new(),
+                tx,
+                cancellation_token: cancellation_token.clone(),
+            },
+        );
+
+    let (mut ws_tx, mut ws_rx) = ws_stream.split();
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                let _ = ws_tx.send(Message::Close(None)).await;
+                break;
+            }
+            Some(message) = rx.recv() => {
+                if ws_tx.send(message).await.is_err() {
+                    break;
+                }
+            }
+            message = ws_rx.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = match protocol {
+                    Protocol::Spot => handle_spot_request(&state, id, &text),
+                    Protocol::Futures => handle_futures_request(&state, id, &text),
+                };
+                let Some(reply) = reply else {
+                    continue;
+                };
+                if ws_tx.send(Message::Text(reply.to_string())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .retain(|connection| connection.id != id);
+}
+
+/// Runs `f` on the connection, returning `None` if it is gone
+fn with_connection<T>(state: &MockState, id: Uuid, f: impl FnOnce(&mut Connection) -> T) -> Option<T> {
+    state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .iter_mut()
+        .find(|connection| connection.id == id)
+        .map(f)
+}
+
+fn handle_spot_request(state: &MockState, id: Uuid, text: &str) -> Option<serde_json::Value> {
+    let request = serde_json::from_str::<serde_json::Value>(text).ok()?;
+    let request_id = request["id"]
+        .as_i64()
+        .unwrap_or_default();
+    let params = request["params"]
+        .as_array()
+        .map(
+            |params| {
+                params
+                    .iter()
+                    .filter_map(|param| param.as_str())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            },
+        )
+        .unwrap_or_default();
+
+    let msg = match request["method"].as_str()? {
+        "PING" => "PONG".to_string(),
+        "SUBSCRIPTION" => {
+            let authenticated = with_connection(
+                state,
+                id,
+                |connection| connection.authenticated,
+            )?;
+            let mut rejection_reason = None;
+            let (accepted, rejected) = params
+                .iter()
+                .cloned()
+                .partition::<Vec<_>, _>(
+                    |topic| {
+                        let reason = match !authenticated && topic.starts_with("spot@private.") {
+                            true => Some("No authority!".to_string()),
+                            false => state.spot_rejection(topic),
+                        };
+                        let accepted = reason.is_none();
+                        rejection_reason = rejection_reason
+                            .take()
+                            .or(reason);
+                        accepted
+                    },
+                );
+            with_connection(
+                state,
+                id,
+                |connection| {
+                    connection
+                        .subscriptions
+                        .extend(accepted)
+                },
+            )?;
+            match rejection_reason {
+                None => params.join(","),
+                Some(reason) => format!(
+                    "Not Subscribed successfully! [{}].  Reason： {}",
+                    rejected.join(","),
+                    reason
+                ),
+            }
+        }
+        "UNSUBSCRIPTION" => {
+            with_connection(
+                state,
+                id,
+                |connection| {
+                    for topic in params.iter() {
+                        connection
+                            .subscriptions
+                            .remove(topic);
+                    }
+                },
+            )?;
+            params.join(",")
+        }
+        _ => return None,
+    };
+
+    Some(
+        serde_json::json!({
+            "id": request_id,
+            "code": 0,
+            "msg": msg
+        }),
+    )
+}
+
+/// E.g. `deal@BTC_USDT` for `sub.deal` with a symbol, `tickers` for
+/// `sub.tickers`
+fn futures_channel_key(channel: &str, symbol: Option<&str>) -> String {
+    match symbol {
+        Some(symbol) => format!(
+            "{}@{}",
+            channel, symbol
+        ),
+        None => channel.to_string(),
+    }
+}
+
+fn handle_futures_request(state: &MockState, id: Uuid, text: &str) -> Option<serde_json::Value> {
+    let request = serde_json::from_str::<serde_json::Value>(text).ok()?;
+    let method = request["method"].as_str()?;
+    let ts = chrono::Utc::now().timestamp_millis();
+
+    if method == "ping" {
+        return Some(
+            serde_json::json!({
+                "channel": "pong",
+                "data": ts
+            }),
+        );
+    }
+
+    let data = match method {
+        "login" => {
+            let param = &request["param"];
+            let api_key = param["apiKey"]
+                .as_str()
+                .unwrap_or_default();
+            let valid = state
+                .secret_key(api_key)
+                .is_some_and(
+                    |secret_key| {
+                        signature::verify_futures_login(
+                            api_key,
+                            &secret_key,
+                            param["reqTime"]
+                                .as_str()
+                                .unwrap_or_default(),
+                            param["signature"]
+                                .as_str()
+                                .unwrap_or_default(),
+                        )
+                    },
+                );
+            with_connection(
+                state,
+                id,
+                |connection| connection.authenticated = valid,
+            )?;
+            match valid {
+                true => "success".to_string(),
+                false => "Verify failed".to_string(),
+            }
+        }
+        "personal.filter" => "success".to_string(),
+        _ => {
+            if let Some(channel) = method.strip_prefix("sub.") {
+                let symbol = request["param"]["symbol"].as_str();
+                match state.futures_rejection(
+                    method, symbol,
+                ) {
+                    Some(reason) => reason,
+                    None => {
+                        with_connection(
+                            state,
+                            id,
+                            |connection| {
+                                connection
+                                    .subscriptions
+                                    .insert(
+                                        futures_channel_key(
+                                            channel, symbol,
+                                        ),
+                                    )
+                            },
+                        )?;
+                        "success".to_string()
+                    }
+                }
+            } else if let Some(channel) = method.strip_prefix("unsub.") {
+                let symbol = request["param"]["symbol"].as_str();
+                with_connection(
+                    state,
+                    id,
+                    |connection| {
+                        connection
+                            .subscriptions
+                            .remove(
+                                &futures_channel_key(
+                                    channel, symbol,
+                                ),
+                            )
+                    },
+                )?;
+                "success".to_string()
+            } else {
+                return None;
+            }
+        }
+    };
+
+    Some(
+        serde_json::json!({
+            "channel": format!("rs.{}", method),
+            "data": data,
+            "ts": ts
+        }),
+    )
+}
+
+pub(crate) fn push_spot(state: &MockState, channel: &str, message: Message) -> usize {
+    state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .iter()
+        .filter(
+            |connection| {
+                connection.protocol == Protocol::Spot
+                    && connection
+                        .subscriptions
+                        .contains(channel)
+            },
+        )
+        .filter(
+            |connection| {
+                connection
+                    .tx
+                    .send(message.clone())
+                    .is_ok()
+            },
+        )
+        .count()
+}
+
+pub(crate) fn push_futures(state: &MockState, message: serde_json::Value) -> usize {
+    let channel = message["channel"]
+        .as_str()
+        .unwrap_or_default();
+    let channel = channel
+        .strip_prefix("push.")
+        .unwrap_or(channel);
+    let personal = channel.starts_with("personal.");
+    let keys = [
+        futures_channel_key(
+            channel, None,
+        ),
+        futures_channel_key(
+            channel,
+            message["symbol"].as_str(),
+        ),
+    ];
+    let text = message.to_string();
+
+    state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .iter()
+        .filter(
+            |connection| {
+                connection.protocol == Protocol::Futures
+                    && match personal {
+                        true => connection.authenticated,
+                        false => keys
+                            .iter()
+                            .any(
+                                |key| {
+                                    connection
+                                        .subscriptions
+                                        .contains(key)
+                                },
+                            ),
+                    }
+            },
+        )
+        .filter(
+            |connection| {
+                connection
+                    .tx
+                    .send(Message::Text(text.clone()))
+                    .is_ok()
+            },
+        )
+        .count()
+}
+
+pub(crate) fn disconnect_all(state: &MockState) {
+    for connection in state
+        .connections
+        .lock()
+        .expect("Mock state lock poisoned")
+        .iter()
+    {
+        connection
+            .cancellation_token
+            .cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        futures::ws::{
+            auth::FuturesWebsocketAuth,
+            message::FuturesMessage,
+            stream::FuturesStream,
+            subscribe::{FuturesSubscribe, FuturesSubscribeParams},
+            topic::{DealTopic, FuturesTopic},
+            MexcFuturesWebsocketClient,
+        },
+        mock::{MockServer, MOCK_API_KEY, MOCK_SECRET_KEY},
+    };
+
+    #[tokio::test]
+    async fn pushes_to_futures_subscribers() {
+        let server = MockServer::start()
+            .await
+            .unwrap();
+        server.reject_futures_subscription(
+            "sub.deal",
+            Some("NOPE_USDT"),
+            "contract not exists",
+        );
+
+        let client = MexcFuturesWebsocketClient::new_with_endpoint(server.futures_ws_endpoint()).into_arc();
+        let mut stream = client
+            .clone()
+            .stream();
+        let topic = FuturesTopic::Deal(DealTopic::new("BTC_USDT".to_string()));
+        let rejected_topic = FuturesTopic::Deal(DealTopic::new("NOPE_USDT".to_string()));
+        let output = client
+            .clone()
+            .subscribe(
+                FuturesSubscribeParams::new(
+                    Some(
+                        FuturesWebsocketAuth::new(
+                            MOCK_API_KEY.to_string(),
+                            MOCK_SECRET_KEY.to_string(),
+                        ),
+                    ),
+                    vec![
+                        topic.clone(),
+                        rejected_topic.clone(),
+                    ],
+                    true,
+                ),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output.accepted_topics,
+            vec![topic]
+        );
+        assert_eq!(
+            output.rejected_topics[0].topic,
+            rejected_topic
+        );
+
+        assert_eq!(
+            server.push_futures(
+                serde_json::json!({
+                    "channel": "push.deal",
+                    "data": [{ "M": 1, "O": 1, "T": 1, "p": 27000.5, "t": 1695680458622_i64, "v": 2 }],
+                    "symbol": "BTC_USDT",
+                    "ts": 1695680458622_i64
+                })
+            ),
+            1
+        );
+        // Replies to the login and the subscriptions come first
+        loop {
+            if let FuturesMessage::Deal(_) = *stream
+                .next()
+                .await
+                .unwrap()
+            {
+                break;
+            }
+        }
+
+        // The login was accepted, so personal messages are delivered too
+        assert_eq!(
+            server.push_futures(
+                serde_json::json!({
+                    "channel": "push.personal.position.closeall.fail",
+                    "data": {},
+                    "ts": 1695680458622_i64
+                })
+            ),
+            1
+        );
+    }
+}
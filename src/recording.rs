@@ -0,0 +1,484 @@
+//! Recording of raw websocket frames, to replay them later through the
+//! websocket clients without a network connection.
+//!
+//! A recording starts with [`MAGIC`], followed by a record per frame:
+//!
+//! | bytes | content                                              |
+//! |-------|------------------------------------------------------|
+//! | 4     | length of the rest of the record, u32 little endian |
+//! | 8     | receive time in microseconds, i64 little endian      |
+//! | 16    | websocket id                                         |
+//! | 1     | 0 for a text frame, 1 for a binary frame             |
+//! | rest  | payload                                              |
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::mpsc,
+    thread::JoinHandle,
+    time::Duration,
+};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+pub const MAGIC: &[u8; 8] = b"MEXCREC\x01";
+
+const TEXT_FRAME: u8 = 0;
+const BINARY_FRAME: u8 = 1;
+const HEADER_LEN: usize = 8 + 16 + 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    pub received_at: DateTime<Utc>,
+    pub websocket_id: Uuid,
+    pub payload: RecordedPayload,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedPayload {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Writes every frame received by the websockets of a client it is attached
+/// to. Frames are written on a thread of their own, so recording never blocks
+/// the websocket tasks; dropping the recorder waits for the pending ones.
+pub struct Recorder {
+    commands: Option<mpsc::Sender<Command>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+enum Command {
+    Frame(RecordedFrame),
+    Flush(mpsc::SyncSender<std::io::Result<()>>),
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::from_writer(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn from_writer(mut writer: impl Write + Send + 'static) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        let (commands, received) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("mexc-recorder".to_string())
+            .spawn(
+                move || {
+                    if let Err(err) = write_commands(
+                        writer, received,
+                    ) {
+                        tracing::warn!(
+                            "Failed to write recording: {}",
+                            err
+                        );
+                    }
+                },
+            )?;
+        Ok(
+            Self {
+                commands: Some(commands),
+                writer: Some(writer),
+            },
+        )
+    }
+
+    /// Ignores control frames
+    pub fn record(&self, websocket_id: Uuid, message: &Message) -> std::io::Result<()> {
+        let payload = match message {
+            Message::Text(text) => RecordedPayload::Text(text.clone()),
+            Message::Binary(binary) => RecordedPayload::Binary(binary.clone()),
+            _ => return Ok(()),
+        };
+        self.record_frame(
+            &RecordedFrame {
+                received_at: Utc::now(),
+                websocket_id,
+                payload,
+            },
+        )
+    }
+
+    /// Queues the frame. Fails once an earlier write failed, the error itself
+    /// is logged by the writer thread.
+    pub fn record_frame(&self, frame: &RecordedFrame) -> std::io::Result<()> {
+        self.send(Command::Frame(frame.clone()))
+    }
+
+    /// Waits until the frames recorded so far are written
+    pub fn flush(&self) -> std::io::Result<()> {
+        let (flushed, result) = mpsc::sync_channel(1);
+        self.send(Command::Flush(flushed))?;
+        result
+            .recv()
+            .map_err(|_| writer_stopped())?
+    }
+
+    fn send(&self, command: Command) -> std::io::Result<()> {
+        self.commands
+            .as_ref()
+            .expect("Sender taken on drop")
+            .send(command)
+            .map_err(|_| writer_stopped())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // Ends the writer thread once it wrote what is queued
+        self.commands
+            .take();
+        if let Some(writer) = self
+            .writer
+            .take()
+        {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_commands(mut writer: impl Write, commands: mpsc::Receiver<Command>) -> std::io::Result<()> {
+    for command in commands {
+        match command {
+            Command::Frame(frame) => write_frame(
+                &mut writer,
+                &frame,
+            )?,
+            Command::Flush(flushed) => {
+                let _ = flushed.send(writer.flush());
+            }
+        }
+    }
+    writer.flush()
+}
+
+fn writer_stopped() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "Recorder stopped after a write error",
+    )
+}
+
+fn write_frame(writer: &mut dyn Write, frame: &RecordedFrame) -> std::io::Result<()> {
+    let (kind, payload) = match &frame.payload {
+        RecordedPayload::Text(text) => (
+            TEXT_FRAME,
+            text.as_bytes(),
+        ),
+        RecordedPayload::Binary(binary) => (
+            BINARY_FRAME,
+            binary.as_slice(),
+        ),
+    };
+    let len = u32::try_from(HEADER_LEN + payload.len()).map_err(
+        |_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Frame too large to record",
+            )
+        },
+    )?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(
+        &frame
+            .received_at
+            .timestamp_micros()
+            .to_le_bytes(),
+    )?;
+    writer.write_all(
+        frame
+            .websocket_id
+            .as_bytes(),
+    )?;
+    writer.write_all(&[kind])?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads the frames of a recording in order.
+pub struct RecordingReader<R> {
+    reader: R,
+}
+
+impl RecordingReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a websocket recording"));
+        }
+        Ok(
+            Self {
+                reader,
+            },
+        )
+    }
+
+    /// Returns `None` at the end of the recording
+    pub fn read_frame(&mut self) -> std::io::Result<Option<RecordedFrame>> {
+        let mut len = [0u8; 4];
+        match self
+            .reader
+            .read_exact(&mut len)
+        {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len < HEADER_LEN {
+            return Err(invalid_data("Record too short"));
+        }
+        let mut record = vec![0u8; len];
+        self.reader
+            .read_exact(&mut record)?;
+
+        let micros = i64::from_le_bytes(
+            record[0..8]
+                .try_into()
+                .expect("Slice of 8 bytes"),
+        );
+        let received_at = Utc
+            .timestamp_micros(micros)
+            .single()
+            .ok_or_else(|| invalid_data("Invalid receive time"))?;
+        let websocket_id = Uuid::from_slice(&record[8..24]).map_err(|_| invalid_data("Invalid websocket id"))?;
+        let payload = record.split_off(HEADER_LEN);
+        let payload = match record[24] {
+            TEXT_FRAME => RecordedPayload::Text(String::from_utf8(payload).map_err(|_| invalid_data("Text frame is not utf-8"))?),
+            BINARY_FRAME => RecordedPayload::Binary(payload),
+            _ => return Err(invalid_data("Unknown frame kind")),
+        };
+
+        Ok(
+            Some(
+                RecordedFrame {
+                    received_at,
+                    websocket_id,
+                    payload,
+                },
+            ),
+        )
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = std::io::Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame()
+            .transpose()
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message.to_string(),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keeps the time between frames as recorded
+    Original,
+    /// Divides the time between frames by the factor
+    Accelerated(f64),
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    /// How long after the start of the replay a frame received `elapsed` after
+    /// the first frame is due.
+    pub(crate) fn due_after(&self, elapsed: chrono::Duration) -> Option<Duration> {
+        let elapsed = elapsed
+            .to_std()
+            .unwrap_or_default();
+        match self {
+            ReplaySpeed::Original => Some(elapsed),
+            ReplaySpeed::Accelerated(factor) if *factor > 0.0 => Some(elapsed.div_f64(*factor)),
+            ReplaySpeed::Accelerated(_) | ReplaySpeed::AsFastAsPossible => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayParams {
+    pub path: std::path::PathBuf,
+    pub speed: ReplaySpeed,
+}
+
+impl ReplayParams {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            speed: ReplaySpeed::Original,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayOutput {
+    pub frames: usize,
+    /// Frames that could not be decoded into a message
+    pub skipped_frames: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Feeds the frames of a recording to `dispatch` at the requested speed.
+/// `dispatch` returns whether the frame decoded into a message.
+pub(crate) async fn replay_frames<F, Fut>(params: ReplayParams, mut dispatch: F) -> Result<ReplayOutput, ReplayError>
+where
+    F: FnMut(RecordedFrame) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let reader = RecordingReader::open(&params.path)?;
+    let started_at = tokio::time::Instant::now();
+    let mut first_received_at = None;
+    let mut output = ReplayOutput {
+        frames: 0,
+        skipped_frames: 0,
+    };
+
+    for frame in reader {
+        let frame = frame?;
+        let first_received_at = *first_received_at.get_or_insert(frame.received_at);
+        if let Some(due_after) = params
+            .speed
+            .due_after(frame.received_at - first_received_at)
+        {
+            tokio::time::sleep_until(started_at + due_after).await;
+        }
+
+        output.frames += 1;
+        if !dispatch(frame).await {
+            output.skipped_frames += 1;
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_roundtrip() {
+        let frames = vec![
+            RecordedFrame {
+                received_at: Utc
+                    .timestamp_micros(1_695_680_458_622_123)
+                    .unwrap(),
+                websocket_id: Uuid::new_v4(),
+                payload: RecordedPayload::Text(r#"{"channel":"pong","data":1}"#.to_string()),
+            },
+            RecordedFrame {
+                received_at: Utc
+                    .timestamp_micros(1_695_680_458_700_000)
+                    .unwrap(),
+                websocket_id: Uuid::new_v4(),
+                payload: RecordedPayload::Binary(
+                    vec![
+                        0x0a, 0x01, 0x41,
+                    ],
+                ),
+            },
+        ];
+
+        let mut bytes = MAGIC.to_vec();
+        for frame in frames.iter() {
+            write_frame(
+                &mut bytes, frame,
+            )
+            .unwrap();
+        }
+
+        let read_frames = RecordingReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            read_frames,
+            frames
+        );
+        assert!(RecordingReader::new(&b"not a recording"[..]).is_err());
+    }
+
+    #[test]
+    fn flush_waits_for_recorded_frames() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "mexc-rs-recorder-{}.rec",
+                Uuid::new_v4()
+            ),
+        );
+        let recorder = Recorder::create(&path).unwrap();
+        let frame = RecordedFrame {
+            received_at: Utc
+                .timestamp_micros(1_695_680_458_622_123)
+                .unwrap(),
+            websocket_id: Uuid::new_v4(),
+            payload: RecordedPayload::Text("{}".to_string()),
+        };
+        recorder
+            .record_frame(&frame)
+            .unwrap();
+        recorder
+            .flush()
+            .unwrap();
+
+        let read_frames = RecordingReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            read_frames,
+            [frame]
+        );
+        drop(recorder);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replay_speed() {
+        let elapsed = chrono::Duration::seconds(10);
+        assert_eq!(
+            ReplaySpeed::Original.due_after(elapsed),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            ReplaySpeed::Accelerated(5.0).due_after(elapsed),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            ReplaySpeed::AsFastAsPossible.due_after(elapsed),
+            None
+        );
+    }
+}
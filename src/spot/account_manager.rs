@@ -0,0 +1,274 @@
+//! Keeps spot balances current from the private account websocket channel,
+//! seeded and periodically corrected by REST account information.
+
+use crate::{
+    exchange::{account_state::AccountState, Balance, Venue},
+    spot::{
+        v3::{account_information::AccountInformationEndpoint, ApiResult},
+        ws::{
+            lifecycle::{LifecycleEvent, LifecycleEventKind},
+            message::Message,
+        },
+    },
+};
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt};
+use std::{sync::Arc, time::Duration};
+
+pub struct AccountManager<C> {
+    client: Arc<C>,
+    state: Arc<AccountState>,
+}
+
+impl<C> AccountManager<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            state: Arc::new(AccountState::default()),
+        }
+    }
+
+    /// Shares the state with other managers, e.g. a futures account manager,
+    /// to watch both venues through one snapshot
+    pub fn with_state(mut self, state: Arc<AccountState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn client(&self) -> &Arc<C> {
+        &self.client
+    }
+
+    pub fn state(&self) -> &Arc<AccountState> {
+        &self.state
+    }
+
+    /// Applies a message as it is received, it is timestamped with the local
+    /// clock like reconciles
+    pub fn apply_message(&self, message: &Message) {
+        if let Message::AccountUpdate(update) = message {
+            self.state
+                .apply_balance(
+                    Balance::from(update),
+                    Utc::now(),
+                );
+        }
+    }
+}
+
+impl<C> AccountManager<C>
+where
+    C: AccountInformationEndpoint + Send + Sync,
+{
+    /// Replaces the cached balances with the ones from the account
+    /// information, keeping pushes that arrived while it was requested
+    pub async fn reconcile(&self) -> ApiResult<()> {
+        let requested_at = Utc::now();
+        let output = self
+            .client
+            .account_information()
+            .await?;
+        self.state
+            .reconcile_balances(
+                Venue::Spot,
+                output
+                    .balances
+                    .iter()
+                    .map(Balance::from)
+                    .collect(),
+                requested_at,
+            );
+        Ok(())
+    }
+
+    /// Applies messages until both streams end, reconciling whenever the
+    /// websocket (re)connects and when `reconcile_interval` passed without a
+    /// reconcile
+    pub async fn follow(&self, messages: BoxStream<'_, Arc<Message>>, lifecycle_events: BoxStream<'_, LifecycleEvent>, reconcile_interval: Duration) {
+        let mut events = futures::stream::select(
+            messages.map(Event::Message),
+            lifecycle_events.map(Event::Lifecycle),
+        );
+        let mut reconcile_at = tokio::time::Instant::now() + reconcile_interval;
+        loop {
+            let event = tokio::select! {
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(reconcile_at) => Event::Tick,
+            };
+            match event {
+                Event::Message(message) => self.apply_message(&message),
+                Event::Lifecycle(LifecycleEvent {
+                    kind: LifecycleEventKind::Connected
+                    | LifecycleEventKind::ListenKeyRotated {
+                        ..
+                    },
+                    ..
+                })
+                | Event::Tick => {
+                    reconcile_at = tokio::time::Instant::now() + reconcile_interval;
+                    if let Err(err) = self
+                        .reconcile()
+                        .await
+                    {
+                        tracing::warn!(
+                            "Failed to reconcile spot balances: {}",
+                            err
+                        );
+                    }
+                }
+                Event::Lifecycle(_) => {}
+            }
+        }
+    }
+}
+
+enum Event {
+    Message(Arc<Message>),
+    Lifecycle(LifecycleEvent),
+    Tick,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spot::{
+        paper::{tests::symbol_info, PaperTradingClient},
+        v3::{
+            depth::{DepthOutput, PriceAndQuantity},
+            enums::{OrderSide, OrderType},
+            order::{OrderEndpoint, OrderParams},
+        },
+        ws::stream::Stream,
+    };
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn follows_paper_balance_updates() {
+        let client = PaperTradingClient::new(vec![symbol_info()])
+            .with_balance(
+                "USDT",
+                dec("1000"),
+            )
+            .into_arc();
+        client
+            .set_order_book(
+                "BTCUSDT",
+                &DepthOutput {
+                    last_update_id: 1,
+                    bids: vec![],
+                    asks: vec![
+                        PriceAndQuantity {
+                            price: dec("100"),
+                            quantity: dec("1"),
+                        },
+                    ],
+                },
+            )
+            .await;
+        let manager = AccountManager::new(client.clone());
+        manager
+            .reconcile()
+            .await
+            .unwrap();
+        let mut watch = manager
+            .state()
+            .watch();
+        assert_eq!(
+            watch
+                .borrow_and_update()
+                .balance(
+                    Venue::Spot,
+                    "USDT"
+                )
+                .map(|balance| balance.free),
+            Some(dec("1000"))
+        );
+
+        let messages = client
+            .clone()
+            .stream();
+        let follow = async {
+            manager
+                .follow(
+                    messages,
+                    futures::stream::empty().boxed(),
+                    Duration::from_secs(3600),
+                )
+                .await
+        };
+        let trade = async {
+            client
+                .order(
+                    OrderParams {
+                        symbol: "BTCUSDT",
+                        side: OrderSide::Buy,
+                        order_type: OrderType::Limit,
+                        quantity: Some(dec("1")),
+                        quote_order_quantity: None,
+                        price: Some(dec("100")),
+                        new_client_order_id: None,
+                        stop_price: None,
+                        trigger_direction: None,
+                    },
+                )
+                .await
+                .unwrap();
+            watch
+                .wait_for(
+                    |snapshot| {
+                        snapshot
+                            .balance(
+                                Venue::Spot,
+                                "BTC",
+                            )
+                            .is_some()
+                            && snapshot
+                                .balance(
+                                    Venue::Spot,
+                                    "USDT",
+                                )
+                                .is_some_and(
+                                    |balance| {
+                                        balance
+                                            .locked
+                                            .is_zero()
+                                    },
+                                )
+                    },
+                )
+                .await
+                .unwrap()
+                .clone()
+        };
+        let snapshot = tokio::select! {
+            _ = follow => unreachable!(),
+            snapshot = trade => snapshot,
+        };
+        assert_eq!(
+            snapshot
+                .balance(
+                    Venue::Spot,
+                    "BTC"
+                )
+                .map(|balance| balance.free),
+            Some(dec("0.999"))
+        );
+        assert_eq!(
+            snapshot
+                .balance(
+                    Venue::Spot,
+                    "USDT"
+                )
+                .map(|balance| balance.free),
+            Some(dec("900"))
+        );
+    }
+}
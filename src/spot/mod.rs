@@ -2,6 +2,14 @@ use async_trait::async_trait;
 use hmac::{digest::InvalidLength, Hmac, Mac};
 use sha2::Sha256;
 
+#[cfg(feature = "ws")]
+pub mod account_manager;
+#[cfg(feature = "ws")]
+pub mod oco;
+#[cfg(feature = "ws")]
+pub mod order_manager;
+#[cfg(feature = "ws")]
+pub mod paper;
 pub mod v3;
 #[cfg(feature = "ws")]
 pub mod ws;
@@ -0,0 +1,1916 @@
+//! Client side OCO and bracket orders for spot, which MEXC does not offer
+//! natively. Both exit legs are working orders on the exchange; when one of
+//! them fills, the other is canceled, or resized by canceling and placing it
+//! again for what is left.
+//!
+//! Replacements are only placed once the cancel is confirmed, with the
+//! executed quantity the exchange reported for the canceled order, so fills
+//! that race with the cancel are never exited twice. Fills reported after the
+//! cancel shrink the replacement again. The state can be written to a file
+//! after every change and restored after a crash, see
+//! [`OcoEngine::with_store`] and [`OcoEngine::recover`].
+
+use crate::spot::{
+    v3::{
+        cancel_order::{CancelOrderEndpoint, CancelOrderOutput, CancelOrderParams},
+        enums::{OrderSide, OrderStatus, OrderType},
+        order::{OrderEndpoint, OrderParams},
+        query_order::{QueryOrderEndpoint, QueryOrderOutput, QueryOrderParams},
+        ApiError, ApiResult, ErrorCode,
+    },
+    ws::{
+        lifecycle::{LifecycleEvent, LifecycleEventKind},
+        message::{
+            account_orders::{AccountOrdersMessage, StopLimitOrderState},
+            Message,
+        },
+    },
+};
+use futures::{stream::BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::broadcast;
+
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OcoError {
+    #[error("Api error: {0}")]
+    Api(#[from] ApiError),
+
+    #[error("Failed to persist state: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid stored state: {0}")]
+    State(#[from] serde_json::Error),
+
+    #[error("Unknown group {0}")]
+    UnknownGroup(String),
+
+    #[error("Invalid params: {0}")]
+    InvalidParams(&'static str),
+}
+
+pub type OcoResult<T> = Result<T, OcoError>;
+
+/// Exits a position of `quantity` with a take-profit limit order and a
+/// stop-limit order, whichever is reached first
+#[derive(Debug, Clone)]
+pub struct OcoParams {
+    pub symbol: String,
+    /// Side of both exit orders
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub take_profit_price: Decimal,
+    pub stop_price: Decimal,
+    /// Limit price of the stop order once triggered
+    pub stop_limit_price: Decimal,
+}
+
+/// A limit entry order, with OCO exits for whatever it has filled
+#[derive(Debug, Clone)]
+pub struct BracketParams {
+    pub symbol: String,
+    /// Side of the entry, the exits are on the other side
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub take_profit_price: Decimal,
+    pub stop_price: Decimal,
+    /// Limit price of the stop order once triggered
+    pub stop_limit_price: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Leg {
+    Entry,
+    TakeProfit,
+    Stop,
+}
+
+impl Leg {
+    fn tag(self) -> char {
+        match self {
+            Leg::Entry => 'E',
+            Leg::TakeProfit => 'T',
+            Leg::Stop => 'S',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LegOrderStatus {
+    /// Sent, no response yet
+    Pending,
+    Open,
+    /// Cancel sent, no response yet
+    Canceling,
+    /// Filled or canceled
+    Closed,
+    Rejected,
+    /// Unknown to the exchange, the leg can be placed again
+    Missing,
+}
+
+/// One order placed for a leg
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LegOrder {
+    pub client_order_id: String,
+    pub order_id: Option<String>,
+    pub quantity: Decimal,
+    /// As reported by order pushes and REST responses
+    pub reported_quantity: Decimal,
+    /// Sum of the deals pushed for this order
+    pub dealt_quantity: Decimal,
+    pub trade_ids: BTreeSet<String>,
+    pub status: LegOrderStatus,
+}
+
+impl LegOrder {
+    fn new(client_order_id: String, quantity: Decimal) -> Self {
+        Self {
+            client_order_id,
+            order_id: None,
+            quantity,
+            reported_quantity: Decimal::ZERO,
+            dealt_quantity: Decimal::ZERO,
+            trade_ids: BTreeSet::new(),
+            status: LegOrderStatus::Pending,
+        }
+    }
+
+    /// Deals and order pushes arrive in any order, the larger one is newer
+    pub fn executed_quantity(&self) -> Decimal {
+        self.reported_quantity
+            .max(self.dealt_quantity)
+    }
+
+    fn is_working(&self) -> bool {
+        matches!(
+            self.status,
+            LegOrderStatus::Pending | LegOrderStatus::Open | LegOrderStatus::Canceling
+        )
+    }
+
+    fn matches(&self, client_order_id: Option<&str>, order_id: &str) -> bool {
+        client_order_id
+            == Some(
+                self.client_order_id
+                    .as_str(),
+            )
+            || self
+                .order_id
+                .as_deref()
+                == Some(order_id)
+    }
+
+    fn apply_status(&mut self, status: OrderStatus) {
+        match status {
+            OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::PartiallyCanceled => {
+                self.status = LegOrderStatus::Closed;
+            }
+            // A rejected placement can still have reached the exchange
+            OrderStatus::New | OrderStatus::PartiallyFilled => {
+                if matches!(
+                    self.status,
+                    LegOrderStatus::Pending | LegOrderStatus::Rejected | LegOrderStatus::Missing
+                ) {
+                    self.status = LegOrderStatus::Open;
+                }
+            }
+        }
+    }
+
+    fn apply_query(&mut self, output: &QueryOrderOutput) {
+        self.order_id = Some(
+            output
+                .order_id
+                .clone(),
+        );
+        self.reported_quantity = self
+            .reported_quantity
+            .max(output.executed_quantity);
+        self.apply_status(output.status);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LegState {
+    pub leg: Leg,
+    pub price: Decimal,
+    pub stop_price: Option<Decimal>,
+    /// Every order placed for the leg, a resize cancels the last one and
+    /// places another
+    pub orders: Vec<LegOrder>,
+}
+
+impl LegState {
+    fn new(leg: Leg, price: Decimal, stop_price: Option<Decimal>) -> Self {
+        Self {
+            leg,
+            price,
+            stop_price,
+            orders: vec![],
+        }
+    }
+
+    pub fn executed_quantity(&self) -> Decimal {
+        self.orders
+            .iter()
+            .map(LegOrder::executed_quantity)
+            .sum()
+    }
+
+    pub fn working_order(&self) -> Option<&LegOrder> {
+        self.orders
+            .last()
+            .filter(|order| order.is_working())
+    }
+
+    fn order_type(&self) -> OrderType {
+        match self.leg {
+            Leg::Entry | Leg::TakeProfit => OrderType::Limit,
+            Leg::Stop => OrderType::StopLimit,
+        }
+    }
+
+    fn is_rejected(&self) -> bool {
+        self.orders
+            .last()
+            .is_some_and(|order| order.status == LegOrderStatus::Rejected)
+    }
+
+    /// Whether any order of this leg may have reached the exchange
+    fn was_placed(&self) -> bool {
+        self.orders
+            .iter()
+            .any(|order| order.status != LegOrderStatus::Missing)
+    }
+
+    /// The last order if it is working or its rejection may be retried
+    fn unresolved_order(&self) -> Option<&LegOrder> {
+        self.orders
+            .last()
+            .filter(|order| order.is_working() || order.status == LegOrderStatus::Rejected)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GroupStatus {
+    Active,
+    Canceling,
+    Completed,
+    Canceled,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OcoGroup {
+    pub id: String,
+    pub symbol: String,
+    /// Side of the exit legs
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    /// Brackets only, the exits cover what it executed
+    pub entry: Option<LegState>,
+    pub take_profit: LegState,
+    pub stop: LegState,
+    pub status: GroupStatus,
+    overfill_reported: bool,
+}
+
+impl OcoGroup {
+    /// The quantity the exits have to cover
+    pub fn exit_quantity(&self) -> Decimal {
+        match &self.entry {
+            Some(entry) => entry.executed_quantity(),
+            None => self.quantity,
+        }
+    }
+
+    pub fn exited_quantity(&self) -> Decimal {
+        self.take_profit
+            .executed_quantity()
+            + self
+                .stop
+                .executed_quantity()
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.status,
+            GroupStatus::Completed | GroupStatus::Canceled
+        )
+    }
+
+    fn side_of(&self, leg: Leg) -> OrderSide {
+        match (
+            leg, self.side,
+        ) {
+            (Leg::Entry, OrderSide::Buy) => OrderSide::Sell,
+            (Leg::Entry, OrderSide::Sell) => OrderSide::Buy,
+            _ => self.side,
+        }
+    }
+
+    fn leg(&self, leg: Leg) -> Option<&LegState> {
+        match leg {
+            Leg::Entry => self
+                .entry
+                .as_ref(),
+            Leg::TakeProfit => Some(&self.take_profit),
+            Leg::Stop => Some(&self.stop),
+        }
+    }
+
+    fn leg_mut(&mut self, leg: Leg) -> Option<&mut LegState> {
+        match leg {
+            Leg::Entry => self
+                .entry
+                .as_mut(),
+            Leg::TakeProfit => Some(&mut self.take_profit),
+            Leg::Stop => Some(&mut self.stop),
+        }
+    }
+
+    fn legs_mut(&mut self) -> impl Iterator<Item = &mut LegState> {
+        self.entry
+            .iter_mut()
+            .chain(
+                [
+                    &mut self.take_profit,
+                    &mut self.stop,
+                ],
+            )
+    }
+
+    fn order_mut(&mut self, client_order_id: Option<&str>, order_id: &str) -> Option<&mut LegOrder> {
+        self.legs_mut()
+            .flat_map(
+                |leg| {
+                    leg.orders
+                        .iter_mut()
+                },
+            )
+            .find(
+                |order| {
+                    order.matches(
+                        client_order_id,
+                        order_id,
+                    )
+                },
+            )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoEvent {
+    pub group_id: String,
+    pub kind: OcoEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OcoEventKind {
+    OrderPlaced {
+        leg: Leg,
+        client_order_id: String,
+        quantity: Decimal,
+    },
+    OrderCanceled {
+        leg: Leg,
+        client_order_id: String,
+        executed_quantity: Decimal,
+    },
+    /// The leg is not placed again, cancel the group or place the exit
+    /// manually
+    OrderRejected {
+        leg: Leg,
+        client_order_id: String,
+        error: String,
+    },
+    /// Both exits filled before the sibling could be canceled
+    Overfilled {
+        excess: Decimal,
+    },
+    Completed,
+    Canceled,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct StoredState {
+    next_group: u64,
+    groups: BTreeMap<String, OcoGroup>,
+}
+
+enum Action {
+    Place {
+        group_id: String,
+        symbol: String,
+        side: OrderSide,
+        leg: Leg,
+        order_type: OrderType,
+        price: Decimal,
+        stop_price: Option<Decimal>,
+        client_order_id: String,
+        quantity: Decimal,
+    },
+    Cancel {
+        group_id: String,
+        symbol: String,
+        leg: Leg,
+        client_order_id: String,
+        order_id: Option<String>,
+    },
+}
+
+pub struct OcoEngine<C> {
+    client: Arc<C>,
+    prefix: String,
+    state: Mutex<StoredState>,
+    store: Option<PathBuf>,
+    /// Set when the state changed since it was last saved
+    dirty: AtomicBool,
+    /// Keeps saves in order, so an older state never replaces a newer one
+    writing: tokio::sync::Mutex<()>,
+    events: broadcast::Sender<OcoEvent>,
+}
+
+impl<C> OcoEngine<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            prefix: "oco".to_string(),
+            state: Mutex::new(StoredState::default()),
+            store: None,
+            dirty: AtomicBool::new(false),
+            writing: tokio::sync::Mutex::new(()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Group IDs are the prefix followed by digits, client order IDs append
+    /// the leg and attempt. MEXC accepts up to 32 characters.
+    pub fn with_client_order_id_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Restores the groups saved at `path`, if any, and saves every change
+    /// there. Call [`OcoEngine::recover`] afterwards to catch up with what
+    /// happened while the engine was not running.
+    pub fn with_store(mut self, path: impl AsRef<Path>) -> OcoResult<Self> {
+        let path = path
+            .as_ref()
+            .to_path_buf();
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                *self
+                    .state
+                    .get_mut()
+                    .unwrap() = serde_json::from_slice(&bytes)?;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.store = Some(path);
+        Ok(self)
+    }
+
+    pub fn client(&self) -> &Arc<C> {
+        &self.client
+    }
+
+    /// Receives events raised from now on
+    pub fn events(&self) -> broadcast::Receiver<OcoEvent> {
+        self.events
+            .subscribe()
+    }
+
+    pub fn group(&self, group_id: &str) -> Option<OcoGroup> {
+        self.state
+            .lock()
+            .unwrap()
+            .groups
+            .get(group_id)
+            .cloned()
+    }
+
+    pub fn groups(&self) -> Vec<OcoGroup> {
+        self.state
+            .lock()
+            .unwrap()
+            .groups
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Drops completed and canceled groups
+    pub async fn forget_finished(&self) -> OcoResult<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .groups
+            .retain(|_, group| !group.is_terminal());
+        self.mark_dirty();
+        self.save()
+            .await
+    }
+
+    /// Applies order and deal pushes, returning whether they belonged to a
+    /// group. Nothing is placed, canceled or saved until [`OcoEngine::drive`].
+    pub fn apply_message(&self, message: &Message) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap();
+        let applied = state
+            .groups
+            .values_mut()
+            .filter(|group| !group.is_terminal())
+            .any(
+                |group| match message {
+                    Message::AccountOrders(AccountOrdersMessage::LimitOrMarket(message)) => {
+                        let Some(order) = group.order_mut(
+                            Some(&message.client_order_id),
+                            &message.order_id,
+                        ) else {
+                            return false;
+                        };
+                        order.order_id = Some(
+                            message
+                                .order_id
+                                .clone(),
+                        );
+                        order.reported_quantity = order
+                            .reported_quantity
+                            .max(message.cumulative_quantity);
+                        order.apply_status(message.status);
+                        true
+                    }
+                    Message::AccountOrders(AccountOrdersMessage::StopLimit(message)) => {
+                        let Some(order) = group.order_mut(
+                            None,
+                            &message.order_id,
+                        ) else {
+                            return false;
+                        };
+                        // Executed means triggered, the limit order it placed
+                        // is pushed like any other
+                        order.apply_status(
+                            match message.state {
+                                StopLimitOrderState::New | StopLimitOrderState::Executed => OrderStatus::New,
+                                StopLimitOrderState::Canceled | StopLimitOrderState::Failed => OrderStatus::Canceled,
+                            },
+                        );
+                        true
+                    }
+                    Message::AccountDeals(deal) => {
+                        let Some(order) = group.order_mut(
+                            Some(&deal.client_order_id),
+                            &deal.order_id,
+                        ) else {
+                            return false;
+                        };
+                        if order
+                            .trade_ids
+                            .insert(
+                                deal.trade_id
+                                    .clone(),
+                            )
+                        {
+                            order.dealt_quantity += deal.quantity;
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+            );
+        if applied {
+            self.mark_dirty();
+        }
+        applied
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty
+            .store(
+                true,
+                Ordering::Release,
+            );
+    }
+
+    /// Writes the state if it changed since the last save. The state is only
+    /// locked while it is serialized.
+    async fn save(&self) -> OcoResult<()> {
+        let Some(path) = &self.store else {
+            return Ok(());
+        };
+        let _writing = self
+            .writing
+            .lock()
+            .await;
+        if !self
+            .dirty
+            .swap(
+                false,
+                Ordering::AcqRel,
+            )
+        {
+            return Ok(());
+        }
+        let written = async {
+            let contents = serde_json::to_vec(
+                &*self
+                    .state
+                    .lock()
+                    .unwrap(),
+            )?;
+            crate::fs::write_atomic(
+                path.clone(),
+                contents,
+            )
+            .await?;
+            OcoResult::Ok(())
+        }
+        .await;
+        if written.is_err() {
+            self.mark_dirty();
+        }
+        written
+    }
+
+    fn emit(&self, group_id: &str, kind: OcoEventKind) {
+        let _ = self
+            .events
+            .send(
+                OcoEvent {
+                    group_id: group_id.to_string(),
+                    kind,
+                },
+            );
+    }
+
+    /// `exits` are sized by the entry when it is given
+    fn insert_group(&self, exits: OcoParams, entry_price: Option<Decimal>) -> OcoResult<String> {
+        let OcoParams {
+            symbol,
+            side,
+            quantity,
+            take_profit_price,
+            stop_price,
+            stop_limit_price,
+        } = exits;
+        if quantity <= Decimal::ZERO {
+            return Err(OcoError::InvalidParams("Quantity must be positive"));
+        }
+        let take_profit_above = match side {
+            OrderSide::Sell => take_profit_price > stop_price,
+            OrderSide::Buy => take_profit_price < stop_price,
+        };
+        if !take_profit_above {
+            return Err(OcoError::InvalidParams("The take-profit price must be on the profitable side of the stop price"));
+        }
+        let mut state = self
+            .state
+            .lock()
+            .unwrap();
+        let id = format!(
+            "{}{}",
+            self.prefix, state.next_group
+        );
+        state.next_group += 1;
+        state
+            .groups
+            .insert(
+                id.clone(),
+                OcoGroup {
+                    id: id.clone(),
+                    symbol,
+                    side,
+                    quantity,
+                    entry: entry_price.map(
+                        |price| {
+                            LegState::new(
+                                Leg::Entry,
+                                price,
+                                None,
+                            )
+                        },
+                    ),
+                    take_profit: LegState::new(
+                        Leg::TakeProfit,
+                        take_profit_price,
+                        None,
+                    ),
+                    stop: LegState::new(
+                        Leg::Stop,
+                        stop_limit_price,
+                        Some(stop_price),
+                    ),
+                    status: GroupStatus::Active,
+                    overfill_reported: false,
+                },
+            );
+        self.mark_dirty();
+        Ok(id)
+    }
+
+    /// Decides what to place and cancel, marking the orders so concurrent
+    /// callers do not act on them twice
+    fn plan(&self) -> Vec<Action> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap();
+        let mut actions = vec![];
+        let mut events = vec![];
+        for group in state
+            .groups
+            .values_mut()
+            .filter(|group| !group.is_terminal())
+        {
+            let canceling = group.status == GroupStatus::Canceling;
+            let target = group.exit_quantity();
+            let exited = group.exited_quantity();
+            if exited > target && !group.overfill_reported {
+                group.overfill_reported = true;
+                events.push(
+                    (
+                        group
+                            .id
+                            .clone(),
+                        OcoEventKind::Overfilled {
+                            excess: exited - target,
+                        },
+                    ),
+                );
+            }
+            let remaining = (target - exited).max(Decimal::ZERO);
+
+            for leg in [
+                Leg::Entry,
+                Leg::TakeProfit,
+                Leg::Stop,
+            ] {
+                let wanted = match leg {
+                    Leg::Entry => group.quantity,
+                    _ => remaining,
+                };
+                let (symbol, side, group_id) = (
+                    group
+                        .symbol
+                        .clone(),
+                    group.side_of(leg),
+                    group
+                        .id
+                        .clone(),
+                );
+                let Some(leg_state) = group.leg_mut(leg) else {
+                    continue;
+                };
+                let order_type = leg_state.order_type();
+                let rejected = leg_state.is_rejected();
+                let placed = leg_state.was_placed();
+                let attempt = leg_state
+                    .orders
+                    .len();
+                match leg_state
+                    .orders
+                    .last_mut()
+                    .filter(|order| order.is_working())
+                {
+                    Some(order) if order.status == LegOrderStatus::Open => {
+                        let resize = leg != Leg::Entry && order.quantity - order.executed_quantity() != wanted;
+                        if canceling || resize {
+                            order.status = LegOrderStatus::Canceling;
+                            actions.push(
+                                Action::Cancel {
+                                    group_id,
+                                    symbol,
+                                    leg,
+                                    client_order_id: order
+                                        .client_order_id
+                                        .clone(),
+                                    order_id: order
+                                        .order_id
+                                        .clone(),
+                                },
+                            );
+                        }
+                    }
+                    // Waiting for the outcome
+                    Some(_) => {}
+                    None => {
+                        // The entry is placed once, exits until they are rejected.
+                        // Orders the exchange never saw are placed again
+                        let place = match leg {
+                            Leg::Entry => !placed,
+                            _ => !rejected,
+                        };
+                        if canceling || !place || wanted <= Decimal::ZERO {
+                            continue;
+                        }
+                        let client_order_id = format!(
+                            "{}{}{}",
+                            group_id,
+                            leg.tag(),
+                            attempt
+                        );
+                        leg_state
+                            .orders
+                            .push(
+                                LegOrder::new(
+                                    client_order_id.clone(),
+                                    wanted,
+                                ),
+                            );
+                        actions.push(
+                            Action::Place {
+                                group_id,
+                                symbol,
+                                side,
+                                leg,
+                                order_type,
+                                price: leg_state.price,
+                                stop_price: leg_state.stop_price,
+                                client_order_id,
+                                quantity: wanted,
+                            },
+                        );
+                    }
+                }
+            }
+
+            let working = group
+                .legs_mut()
+                .any(
+                    |leg| {
+                        leg.working_order()
+                            .is_some()
+                    },
+                );
+            if !working {
+                let entry_done = group
+                    .entry
+                    .as_ref()
+                    .is_none_or(
+                        |entry| {
+                            !entry
+                                .orders
+                                .is_empty()
+                        },
+                    );
+                if canceling {
+                    group.status = GroupStatus::Canceled;
+                    events.push(
+                        (
+                            group
+                                .id
+                                .clone(),
+                            OcoEventKind::Canceled,
+                        ),
+                    );
+                } else if entry_done && remaining <= Decimal::ZERO {
+                    group.status = GroupStatus::Completed;
+                    events.push(
+                        (
+                            group
+                                .id
+                                .clone(),
+                            OcoEventKind::Completed,
+                        ),
+                    );
+                }
+            }
+        }
+        if !actions.is_empty() || !events.is_empty() {
+            self.mark_dirty();
+        }
+        drop(state);
+        for (group_id, kind) in events {
+            self.emit(
+                &group_id, kind,
+            );
+        }
+        actions
+    }
+
+    fn update_order(&self, group_id: &str, leg: Leg, client_order_id: &str, update: impl FnOnce(&mut LegOrder)) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap();
+        let order = state
+            .groups
+            .get_mut(group_id)
+            .and_then(|group| group.leg_mut(leg))
+            .and_then(
+                |leg| {
+                    leg.orders
+                        .iter_mut()
+                        .find(|order| order.client_order_id == client_order_id)
+                },
+            );
+        if let Some(order) = order {
+            update(order);
+            self.mark_dirty();
+        }
+    }
+}
+
+impl<C> OcoEngine<C>
+where
+    C: OrderEndpoint + CancelOrderEndpoint + QueryOrderEndpoint + Send + Sync,
+{
+    /// Places both exits for `params.quantity`
+    pub async fn place_oco(&self, params: OcoParams) -> OcoResult<OcoGroup> {
+        let id = self.insert_group(
+            params, None,
+        )?;
+        self.save()
+            .await?;
+        self.drive()
+            .await;
+        self.group(&id)
+            .ok_or(OcoError::UnknownGroup(id))
+    }
+
+    /// Places the entry, the exits follow as it fills
+    pub async fn place_bracket(&self, params: BracketParams) -> OcoResult<OcoGroup> {
+        let exit_side = match params.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        let id = self.insert_group(
+            OcoParams {
+                symbol: params.symbol,
+                side: exit_side,
+                quantity: params.quantity,
+                take_profit_price: params.take_profit_price,
+                stop_price: params.stop_price,
+                stop_limit_price: params.stop_limit_price,
+            },
+            Some(params.entry_price),
+        )?;
+        self.save()
+            .await?;
+        self.drive()
+            .await;
+        self.group(&id)
+            .ok_or(OcoError::UnknownGroup(id))
+    }
+
+    /// Cancels every working order of the group. Exits already filled stay
+    /// filled.
+    pub async fn cancel(&self, group_id: &str) -> OcoResult<()> {
+        {
+            let mut state = self
+                .state
+                .lock()
+                .unwrap();
+            let group = state
+                .groups
+                .get_mut(group_id)
+                .ok_or_else(|| OcoError::UnknownGroup(group_id.to_string()))?;
+            if group.status == GroupStatus::Active {
+                group.status = GroupStatus::Canceling;
+            }
+        }
+        self.mark_dirty();
+        self.save()
+            .await?;
+        self.drive()
+            .await;
+        Ok(())
+    }
+
+    /// Applies a message and acts on it
+    pub async fn handle_message(&self, message: &Message) {
+        if self.apply_message(message) {
+            self.drive()
+                .await;
+        }
+    }
+
+    /// Places and cancels orders until the groups match their fills. Rejected
+    /// placements, failed cancels and requests with an unknown outcome are
+    /// not retried until the next [`OcoEngine::recover`].
+    pub async fn drive(&self) {
+        loop {
+            let mut actions = self.plan();
+            // Before placing, so the client order IDs survive a crash. The
+            // skipped placements stay pending for `recover`
+            if let Err(err) = self
+                .save()
+                .await
+            {
+                tracing::warn!(
+                    "Failed to save OCO state, not placing orders: {}",
+                    err
+                );
+                actions.retain(
+                    |action| {
+                        matches!(
+                            action,
+                            Action::Cancel { .. }
+                        )
+                    },
+                );
+            }
+            if actions.is_empty() {
+                return;
+            }
+            for action in actions {
+                self.execute(action)
+                    .await;
+            }
+        }
+    }
+
+    async fn execute(&self, action: Action) {
+        match action {
+            Action::Place {
+                group_id,
+                symbol,
+                side,
+                leg,
+                order_type,
+                price,
+                stop_price,
+                client_order_id,
+                quantity,
+            } => {
+                let result = self
+                    .client
+                    .order(
+                        OrderParams {
+                            symbol: &symbol,
+                            side,
+                            order_type,
+                            quantity: Some(quantity),
+                            quote_order_quantity: None,
+                            price: Some(price),
+                            new_client_order_id: Some(&client_order_id),
+                            stop_price,
+                            trigger_direction: None,
+                        },
+                    )
+                    .await;
+                match result {
+                    Ok(output) => {
+                        // Pushes can arrive before the response
+                        self.update_order(
+                            &group_id,
+                            leg,
+                            &client_order_id,
+                            |order| {
+                                order.order_id = Some(output.order_id);
+                                if matches!(
+                                    order.status,
+                                    LegOrderStatus::Pending | LegOrderStatus::Rejected
+                                ) {
+                                    order.status = LegOrderStatus::Open;
+                                }
+                            },
+                        );
+                        self.emit(
+                            &group_id,
+                            OcoEventKind::OrderPlaced {
+                                leg,
+                                client_order_id,
+                                quantity,
+                            },
+                        );
+                    }
+                    // Only an error response is a definite rejection, after
+                    // anything else the order is left pending for `recover`
+                    Err(err)
+                        if !matches!(
+                            err,
+                            ApiError::ErrorResponse(_)
+                        ) =>
+                    {
+                        tracing::warn!(
+                            "Placing {:?} leg of OCO group {} had an unknown outcome: {}",
+                            leg,
+                            group_id,
+                            err
+                        );
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to place {:?} leg of OCO group {}: {}",
+                            leg,
+                            group_id,
+                            err
+                        );
+                        self.update_order(
+                            &group_id,
+                            leg,
+                            &client_order_id,
+                            |order| {
+                                if order.status == LegOrderStatus::Pending {
+                                    order.status = LegOrderStatus::Rejected;
+                                }
+                            },
+                        );
+                        self.emit(
+                            &group_id,
+                            OcoEventKind::OrderRejected {
+                                leg,
+                                client_order_id,
+                                error: err.to_string(),
+                            },
+                        );
+                    }
+                }
+            }
+            Action::Cancel {
+                group_id,
+                symbol,
+                leg,
+                client_order_id,
+                order_id,
+            } => {
+                let result = self
+                    .client
+                    .cancel_order(
+                        CancelOrderParams {
+                            symbol: &symbol,
+                            order_id: order_id.as_deref(),
+                            original_client_order_id: Some(&client_order_id),
+                            new_client_order_id: None,
+                        },
+                    )
+                    .await;
+                match result {
+                    Ok(output) => self.canceled(
+                        &group_id,
+                        leg,
+                        &client_order_id,
+                        &output,
+                    ),
+                    // Most likely filled in the meantime, the query tells.
+                    // An order still open stays canceling until `recover`
+                    Err(err) => {
+                        tracing::debug!(
+                            "Failed to cancel {}, querying it: {}",
+                            client_order_id,
+                            err
+                        );
+                        if let Err(err) = self
+                            .query(
+                                &group_id,
+                                &symbol,
+                                leg,
+                                &client_order_id,
+                                order_id.as_deref(),
+                            )
+                            .await
+                        {
+                            tracing::warn!(
+                                "Failed to query {}: {}",
+                                client_order_id,
+                                err
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn canceled(&self, group_id: &str, leg: Leg, client_order_id: &str, output: &CancelOrderOutput) {
+        let mut executed_quantity = Decimal::ZERO;
+        self.update_order(
+            group_id,
+            leg,
+            client_order_id,
+            |order| {
+                order.reported_quantity = order
+                    .reported_quantity
+                    .max(output.executed_quantity);
+                order.status = LegOrderStatus::Closed;
+                executed_quantity = order.executed_quantity();
+            },
+        );
+        self.emit(
+            group_id,
+            OcoEventKind::OrderCanceled {
+                leg,
+                client_order_id: client_order_id.to_string(),
+                executed_quantity,
+            },
+        );
+    }
+
+    /// Settles an order whose outcome is unknown. Orders the exchange does
+    /// not know were never placed; other failures leave the order as is.
+    async fn query(&self, group_id: &str, symbol: &str, leg: Leg, client_order_id: &str, order_id: Option<&str>) -> ApiResult<()> {
+        let result = self
+            .client
+            .query_order(
+                QueryOrderParams {
+                    symbol,
+                    order_id,
+                    original_client_order_id: Some(client_order_id),
+                },
+            )
+            .await;
+        match result {
+            Ok(output) => {
+                self.update_order(
+                    group_id,
+                    leg,
+                    client_order_id,
+                    |order| order.apply_query(&output),
+                );
+                Ok(())
+            }
+            Err(ApiError::ErrorResponse(response))
+                if matches!(
+                    response.code,
+                    ErrorCode::OrderDoesNotExist | ErrorCode::UnknownOrderSent
+                ) =>
+            {
+                self.update_order(
+                    group_id,
+                    leg,
+                    client_order_id,
+                    |order| {
+                        order.status = match order.status {
+                            LegOrderStatus::Pending | LegOrderStatus::Rejected | LegOrderStatus::Missing => LegOrderStatus::Missing,
+                            _ => LegOrderStatus::Closed,
+                        };
+                    },
+                );
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Queries every order whose state may be stale, e.g. after a restart or
+    /// a reconnect, then acts on the result. Rejected and unanswered
+    /// placements the exchange doesn't know are placed again. Fails with the
+    /// first error after trying all orders.
+    pub async fn recover(&self) -> OcoResult<()> {
+        let orders = {
+            let state = self
+                .state
+                .lock()
+                .unwrap();
+            state
+                .groups
+                .values()
+                .filter(|group| !group.is_terminal())
+                .flat_map(
+                    |group| {
+                        [
+                            Leg::Entry,
+                            Leg::TakeProfit,
+                            Leg::Stop,
+                        ]
+                        .into_iter()
+                        .filter_map(|leg| group.leg(leg))
+                        .filter_map(
+                            |leg| {
+                                Some(
+                                    (
+                                        group
+                                            .id
+                                            .clone(),
+                                        group
+                                            .symbol
+                                            .clone(),
+                                        leg.leg,
+                                        leg.unresolved_order()?
+                                            .clone(),
+                                    ),
+                                )
+                            },
+                        )
+                    },
+                )
+                .collect::<Vec<_>>()
+        };
+        let mut result = Ok(());
+        for (group_id, symbol, leg, order) in orders {
+            let queried = self
+                .query(
+                    &group_id,
+                    &symbol,
+                    leg,
+                    &order.client_order_id,
+                    order
+                        .order_id
+                        .as_deref(),
+                )
+                .await;
+            match queried {
+                // Still open, a failed cancel is decided again
+                Ok(()) => self.update_order(
+                    &group_id,
+                    leg,
+                    &order.client_order_id,
+                    |order| {
+                        if order.status == LegOrderStatus::Canceling {
+                            order.status = LegOrderStatus::Open;
+                        }
+                    },
+                ),
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to recover order {}: {}",
+                        order.client_order_id,
+                        err
+                    );
+                    if result.is_ok() {
+                        result = Err(err.into());
+                    }
+                }
+            }
+        }
+        self.drive()
+            .await;
+        result
+    }
+
+    /// Handles messages until both streams end, recovering whenever the
+    /// websocket (re)connects
+    pub async fn follow(&self, messages: BoxStream<'_, Arc<Message>>, lifecycle_events: BoxStream<'_, LifecycleEvent>) {
+        let mut events = futures::stream::select(
+            messages.map(Event::Message),
+            lifecycle_events.map(Event::Lifecycle),
+        );
+        while let Some(event) = events
+            .next()
+            .await
+        {
+            match event {
+                Event::Message(message) => {
+                    self.handle_message(&message)
+                        .await
+                }
+                Event::Lifecycle(LifecycleEvent {
+                    kind: LifecycleEventKind::Connected
+                    | LifecycleEventKind::ListenKeyRotated {
+                        ..
+                    },
+                    ..
+                }) => {
+                    let _ = self
+                        .recover()
+                        .await;
+                }
+                Event::Lifecycle(_) => {}
+            }
+        }
+    }
+}
+
+enum Event {
+    Message(Arc<Message>),
+    Lifecycle(LifecycleEvent),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spot::{
+        v3::{order::OrderOutput, ApiResult, ErrorResponse},
+        ws::message::account_deals::AccountDealsMessage,
+    };
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[derive(Debug, Clone)]
+    struct FakeOrder {
+        order_type: OrderType,
+        quantity: Decimal,
+        executed: Decimal,
+        open: bool,
+    }
+
+    /// Order IDs are the client order IDs, fills are set by the tests
+    #[derive(Default)]
+    struct FakeClient {
+        orders: Mutex<BTreeMap<String, FakeOrder>>,
+        placed: Mutex<Vec<String>>,
+        canceled: Mutex<Vec<String>>,
+        /// The next stop order is lost on the way
+        lose_stop: Mutex<bool>,
+        /// Cancels fail while set, attempts are still recorded
+        fail_cancels: Mutex<bool>,
+    }
+
+    impl FakeClient {
+        fn fill(&self, client_order_id: &str, executed: &str) {
+            let mut orders = self
+                .orders
+                .lock()
+                .unwrap();
+            let order = orders
+                .get_mut(client_order_id)
+                .unwrap();
+            order.executed = dec(executed);
+            order.open = order.executed < order.quantity;
+        }
+
+        fn order(&self, client_order_id: &str) -> FakeOrder {
+            self.orders
+                .lock()
+                .unwrap()[client_order_id]
+                .clone()
+        }
+    }
+
+    #[async_trait]
+    impl OrderEndpoint for FakeClient {
+        async fn order(&self, params: OrderParams<'_>) -> ApiResult<OrderOutput> {
+            if params.order_type == OrderType::StopLimit
+                && std::mem::take(
+                    &mut *self
+                        .lose_stop
+                        .lock()
+                        .unwrap(),
+                )
+            {
+                let err = reqwest::Client::new()
+                    .get("not a url")
+                    .build()
+                    .unwrap_err();
+                return Err(ApiError::ReqwestError(err));
+            }
+            let client_order_id = params
+                .new_client_order_id
+                .unwrap()
+                .to_string();
+            self.orders
+                .lock()
+                .unwrap()
+                .insert(
+                    client_order_id.clone(),
+                    FakeOrder {
+                        order_type: params.order_type,
+                        quantity: params
+                            .quantity
+                            .unwrap(),
+                        executed: Decimal::ZERO,
+                        open: true,
+                    },
+                );
+            self.placed
+                .lock()
+                .unwrap()
+                .push(client_order_id.clone());
+            Ok(
+                OrderOutput {
+                    symbol: params
+                        .symbol
+                        .to_string(),
+                    order_id: client_order_id,
+                    order_list_id: None,
+                    price: params
+                        .price
+                        .unwrap(),
+                    orig_qty: params
+                        .quantity
+                        .unwrap(),
+                    order_type: params.order_type,
+                    side: params.side,
+                    transact_time: Utc::now(),
+                },
+            )
+        }
+    }
+
+    #[async_trait]
+    impl CancelOrderEndpoint for FakeClient {
+        async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ApiResult<CancelOrderOutput> {
+            let client_order_id = params
+                .original_client_order_id
+                .unwrap();
+            if *self
+                .fail_cancels
+                .lock()
+                .unwrap()
+            {
+                self.canceled
+                    .lock()
+                    .unwrap()
+                    .push(client_order_id.to_string());
+                let err = reqwest::Client::new()
+                    .get("not a url")
+                    .build()
+                    .unwrap_err();
+                return Err(ApiError::ReqwestError(err));
+            }
+            let mut orders = self
+                .orders
+                .lock()
+                .unwrap();
+            let order = orders
+                .get_mut(client_order_id)
+                .unwrap();
+            order.open = false;
+            self.canceled
+                .lock()
+                .unwrap()
+                .push(client_order_id.to_string());
+            Ok(
+                CancelOrderOutput {
+                    symbol: params
+                        .symbol
+                        .to_string(),
+                    original_client_order_id: Some(client_order_id.to_string()),
+                    order_id: client_order_id.to_string(),
+                    client_order_id: None,
+                    price: Decimal::ZERO,
+                    original_quantity: order.quantity,
+                    executed_quantity: order.executed,
+                    cummulative_quote_quantity: Decimal::ZERO,
+                    status: OrderStatus::Canceled,
+                    time_in_force: None,
+                    order_type: order.order_type,
+                    side: OrderSide::Sell,
+                    stop_price: None,
+                },
+            )
+        }
+    }
+
+    #[async_trait]
+    impl QueryOrderEndpoint for FakeClient {
+        async fn query_order(&self, params: QueryOrderParams<'_>) -> ApiResult<QueryOrderOutput> {
+            let client_order_id = params
+                .original_client_order_id
+                .unwrap();
+            let Some(order) = self
+                .orders
+                .lock()
+                .unwrap()
+                .get(client_order_id)
+                .cloned()
+            else {
+                return Err(
+                    ApiError::ErrorResponse(
+                        ErrorResponse {
+                            code: ErrorCode::OrderDoesNotExist,
+                            msg: "Order does not exist.".to_string(),
+                            _extend: None,
+                        },
+                    ),
+                );
+            };
+            Ok(
+                QueryOrderOutput {
+                    symbol: params
+                        .symbol
+                        .to_string(),
+                    original_client_order_id: Some(client_order_id.to_string()),
+                    order_id: client_order_id.to_string(),
+                    client_order_id: None,
+                    price: Decimal::ZERO,
+                    original_quantity: order.quantity,
+                    executed_quantity: order.executed,
+                    cummulative_quote_quantity: Decimal::ZERO,
+                    status: match (
+                        order.open,
+                        order.executed == order.quantity,
+                    ) {
+                        (true, _) => OrderStatus::New,
+                        (false, true) => OrderStatus::Filled,
+                        (false, false) => OrderStatus::Canceled,
+                    },
+                    time_in_force: None,
+                    order_type: order.order_type,
+                    side: OrderSide::Sell,
+                    stop_price: Decimal::ZERO,
+                    time: Utc::now(),
+                    update_time: Utc::now(),
+                    is_working: order.open,
+                },
+            )
+        }
+    }
+
+    fn deal(client_order_id: &str, trade_id: &str, quantity: &str) -> Message {
+        Message::AccountDeals(
+            AccountDealsMessage {
+                asset: "BTCUSDT".to_string(),
+                trade_type: OrderSide::Sell,
+                trade_time: Utc::now(),
+                client_order_id: client_order_id.to_string(),
+                order_id: client_order_id.to_string(),
+                is_maker: true,
+                price: dec("110"),
+                is_self_trade: false,
+                trade_id: trade_id.to_string(),
+                quantity: dec(quantity),
+                deals_amount: Decimal::ZERO,
+                commission_fee: Decimal::ZERO,
+                commission_asset: "USDT".to_string(),
+                event_time: Utc::now(),
+            },
+        )
+    }
+
+    fn oco_params() -> OcoParams {
+        OcoParams {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Sell,
+            quantity: dec("1"),
+            take_profit_price: dec("110"),
+            stop_price: dec("90"),
+            stop_limit_price: dec("89"),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_cancels_wait_for_recover() {
+        let client = Arc::new(FakeClient::default());
+        let engine = OcoEngine::new(client.clone());
+        let group = engine
+            .place_oco(oco_params())
+            .await
+            .unwrap();
+        *client
+            .fail_cancels
+            .lock()
+            .unwrap() = true;
+        engine
+            .cancel(&group.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            *client
+                .canceled
+                .lock()
+                .unwrap(),
+            ["oco0T0", "oco0S0"]
+        );
+        assert_eq!(
+            engine
+                .group(&group.id)
+                .unwrap()
+                .status,
+            GroupStatus::Canceling
+        );
+
+        // Each recover tries once more
+        engine
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .canceled
+                .lock()
+                .unwrap()
+                .len(),
+            4
+        );
+        *client
+            .fail_cancels
+            .lock()
+            .unwrap() = false;
+        engine
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(
+            engine
+                .group(&group.id)
+                .unwrap()
+                .status,
+            GroupStatus::Canceled
+        );
+    }
+
+    #[tokio::test]
+    async fn resizes_and_cancels_the_sibling() {
+        let client = Arc::new(FakeClient::default());
+        let engine = OcoEngine::new(client.clone());
+        let group = engine
+            .place_oco(oco_params())
+            .await
+            .unwrap();
+        assert_eq!(
+            *client
+                .placed
+                .lock()
+                .unwrap(),
+            ["oco0T0", "oco0S0"]
+        );
+        assert_eq!(
+            client
+                .order("oco0S0")
+                .order_type,
+            OrderType::StopLimit
+        );
+
+        // Partial fill of the take profit, the stop shrinks to what is left
+        client.fill(
+            "oco0T0", "0.4",
+        );
+        engine
+            .handle_message(
+                &deal(
+                    "oco0T0", "1", "0.4",
+                ),
+            )
+            .await;
+        engine
+            .handle_message(
+                &deal(
+                    "oco0T0", "1", "0.4",
+                ),
+            )
+            .await;
+        assert_eq!(
+            *client
+                .canceled
+                .lock()
+                .unwrap(),
+            ["oco0S0"]
+        );
+        assert_eq!(
+            client
+                .order("oco0S1")
+                .quantity,
+            dec("0.6")
+        );
+
+        // The stop fills 0.1 while being canceled for the next take profit
+        // fill, which leaves nothing to exit
+        client.fill(
+            "oco0S1", "0.1",
+        );
+        client.fill(
+            "oco0T0", "0.9",
+        );
+        engine
+            .handle_message(
+                &deal(
+                    "oco0T0", "2", "0.5",
+                ),
+            )
+            .await;
+        assert_eq!(
+            *client
+                .canceled
+                .lock()
+                .unwrap(),
+            ["oco0S0", "oco0S1", "oco0T0"]
+        );
+        assert_eq!(
+            client
+                .placed
+                .lock()
+                .unwrap()
+                .len(),
+            3
+        );
+        let group = engine
+            .group(&group.id)
+            .unwrap();
+        assert_eq!(
+            group.status,
+            GroupStatus::Completed
+        );
+        assert_eq!(
+            group.exited_quantity(),
+            dec("1")
+        );
+    }
+
+    #[tokio::test]
+    async fn places_lost_legs_again_on_recover() {
+        let client = Arc::new(FakeClient::default());
+        *client
+            .lose_stop
+            .lock()
+            .unwrap() = true;
+        let engine = OcoEngine::new(client.clone());
+        let group = engine
+            .place_oco(oco_params())
+            .await
+            .unwrap();
+        assert_eq!(
+            *client
+                .placed
+                .lock()
+                .unwrap(),
+            ["oco0T0"]
+        );
+        assert_eq!(
+            group
+                .leg(Leg::Stop)
+                .and_then(LegState::working_order)
+                .map(|order| order.status),
+            Some(LegOrderStatus::Pending)
+        );
+
+        engine
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(
+            *client
+                .placed
+                .lock()
+                .unwrap(),
+            ["oco0T0", "oco0S1"]
+        );
+        let group = engine
+            .group(&group.id)
+            .unwrap();
+        assert_eq!(
+            group
+                .leg(Leg::Stop)
+                .and_then(LegState::working_order)
+                .map(|order| order.status),
+            Some(LegOrderStatus::Open)
+        );
+    }
+
+    #[tokio::test]
+    async fn recovers_brackets_from_the_store() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "mexc-rs-oco-{}.json",
+                Uuid::new_v4()
+            ),
+        );
+        let client = Arc::new(FakeClient::default());
+        let engine = OcoEngine::new(client.clone())
+            .with_store(&path)
+            .unwrap();
+        let group = engine
+            .place_bracket(
+                BracketParams {
+                    symbol: "BTCUSDT".to_string(),
+                    side: OrderSide::Buy,
+                    quantity: dec("2"),
+                    entry_price: dec("100"),
+                    take_profit_price: dec("110"),
+                    stop_price: dec("90"),
+                    stop_limit_price: dec("89"),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            group.side,
+            OrderSide::Sell
+        );
+        assert_eq!(
+            *client
+                .placed
+                .lock()
+                .unwrap(),
+            ["oco0E0"]
+        );
+        drop(engine);
+
+        // The entry fills while nothing runs
+        client.fill(
+            "oco0E0", "1.5",
+        );
+        let engine = OcoEngine::new(client.clone())
+            .with_store(&path)
+            .unwrap();
+        engine
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(
+            *client
+                .placed
+                .lock()
+                .unwrap(),
+            ["oco0E0", "oco0T0", "oco0S0"]
+        );
+        assert_eq!(
+            client
+                .order("oco0T0")
+                .quantity,
+            dec("1.5")
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,419 @@
+//! Tracks orders placed through any spot client from placement to a terminal
+//! state, using the private account websocket channels and REST queries to
+//! catch up after reconnects.
+
+use crate::{
+    exchange::{
+        order_tracker::{OrderHandle, OrderState, OrderTracker},
+        Fill, Order,
+    },
+    spot::{
+        v3::{
+            order::{OrderEndpoint, OrderParams},
+            query_order::{QueryOrderEndpoint, QueryOrderParams},
+            ApiError, ApiResult, ErrorCode,
+        },
+        ws::{
+            lifecycle::{LifecycleEvent, LifecycleEventKind},
+            message::{account_orders::AccountOrdersMessage, Message},
+        },
+    },
+};
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+
+pub struct OrderManager<C> {
+    client: Arc<C>,
+    tracker: OrderTracker,
+}
+
+impl<C> OrderManager<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            tracker: OrderTracker::default(),
+        }
+    }
+
+    /// Client order IDs are the prefix followed by digits, MEXC accepts up to
+    /// 32 characters
+    pub fn with_client_order_id_prefix(mut self, prefix: &str) -> Self {
+        self.tracker = OrderTracker::new(prefix);
+        self
+    }
+
+    pub fn client(&self) -> &Arc<C> {
+        &self.client
+    }
+
+    pub fn tracker(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    pub fn order(&self, client_order_id: &str) -> Option<OrderHandle> {
+        self.tracker
+            .handle(client_order_id)
+    }
+
+    pub fn apply_message(&self, message: &Message) {
+        match message {
+            Message::AccountOrders(AccountOrdersMessage::LimitOrMarket(order)) => {
+                self.tracker
+                    .apply_order(&Order::from(order));
+            }
+            Message::AccountOrders(AccountOrdersMessage::StopLimit(order)) => {
+                self.tracker
+                    .apply_order(&Order::from(order));
+            }
+            Message::AccountDeals(deal) => {
+                self.tracker
+                    .apply_fill(
+                        Some(&deal.client_order_id),
+                        &Fill::from(deal),
+                    );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<C> OrderManager<C>
+where
+    C: OrderEndpoint + QueryOrderEndpoint + Send + Sync,
+{
+    /// Places an order, assigning a client order ID unless one is set. The
+    /// returned handle resolves once the order is filled, canceled or
+    /// rejected.
+    pub async fn place(&self, params: OrderParams<'_>) -> ApiResult<OrderHandle> {
+        let client_order_id = params
+            .new_client_order_id
+            .map(str::to_string)
+            .unwrap_or_else(
+                || {
+                    self.tracker
+                        .next_client_order_id()
+                },
+            );
+        // Tracked before sending, pushes can arrive before the response
+        let handle = self
+            .tracker
+            .track(
+                OrderState::new(
+                    client_order_id.clone(),
+                    params
+                        .symbol
+                        .to_string(),
+                    params
+                        .side
+                        .into(),
+                    params
+                        .price
+                        .unwrap_or_default(),
+                    params
+                        .quantity
+                        .unwrap_or_default(),
+                ),
+            );
+        let params = OrderParams {
+            new_client_order_id: Some(&client_order_id),
+            ..params
+        };
+        match self
+            .client
+            .order(params)
+            .await
+        {
+            Ok(output) => {
+                self.tracker
+                    .acknowledge(
+                        &client_order_id,
+                        &output.order_id,
+                    );
+                Ok(handle)
+            }
+            // Only an error response is a definite rejection, after anything
+            // else the order may be live and is left to `reconcile`
+            Err(err) => {
+                if let ApiError::ErrorResponse(_) = err {
+                    self.tracker
+                        .reject(
+                            &client_order_id,
+                            err.to_string(),
+                        );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Queries every open tracked order, for updates missed while the
+    /// websocket was down. Unacknowledged orders the exchange doesn't know
+    /// never reached it and are rejected. Fails with the first error after
+    /// trying all orders.
+    pub async fn reconcile(&self) -> ApiResult<()> {
+        let mut result = Ok(());
+        for state in self
+            .tracker
+            .open_orders()
+        {
+            let output = self
+                .client
+                .query_order(
+                    QueryOrderParams {
+                        symbol: &state.symbol,
+                        order_id: state
+                            .order_id
+                            .as_deref(),
+                        original_client_order_id: Some(&state.client_order_id),
+                    },
+                )
+                .await;
+            match output {
+                Ok(output) => {
+                    self.tracker
+                        .apply_order(&Order::from(&output));
+                }
+                Err(ApiError::ErrorResponse(response))
+                    if state
+                        .order_id
+                        .is_none()
+                        && matches!(
+                            response.code,
+                            ErrorCode::OrderDoesNotExist | ErrorCode::UnknownOrderSent
+                        ) =>
+                {
+                    self.tracker
+                        .reject(
+                            &state.client_order_id,
+                            response.to_string(),
+                        );
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to reconcile order {}: {}",
+                        state.client_order_id,
+                        err
+                    );
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Applies messages until both streams end, reconciling whenever the
+    /// websocket (re)connects
+    pub async fn follow(&self, messages: BoxStream<'_, Arc<Message>>, lifecycle_events: BoxStream<'_, LifecycleEvent>) {
+        let mut events = futures::stream::select(
+            messages.map(Event::Message),
+            lifecycle_events.map(Event::Lifecycle),
+        );
+        while let Some(event) = events
+            .next()
+            .await
+        {
+            match event {
+                Event::Message(message) => self.apply_message(&message),
+                Event::Lifecycle(LifecycleEvent {
+                    kind: LifecycleEventKind::Connected
+                    | LifecycleEventKind::ListenKeyRotated {
+                        ..
+                    },
+                    ..
+                }) => {
+                    let _ = self
+                        .reconcile()
+                        .await;
+                }
+                Event::Lifecycle(_) => {}
+            }
+        }
+    }
+}
+
+enum Event {
+    Message(Arc<Message>),
+    Lifecycle(LifecycleEvent),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        exchange::OrderStatus,
+        spot::{
+            paper::{tests::symbol_info, PaperTradingClient},
+            v3::{
+                depth::{DepthOutput, PriceAndQuantity},
+                enums::{OrderSide, OrderType},
+                order::OrderOutput,
+                query_order::QueryOrderOutput,
+                ErrorResponse,
+            },
+            ws::stream::Stream,
+        },
+    };
+    use async_trait::async_trait;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    /// Loses every placement on the way to the exchange
+    struct LossyClient;
+
+    #[async_trait]
+    impl OrderEndpoint for LossyClient {
+        async fn order(&self, _params: OrderParams<'_>) -> ApiResult<OrderOutput> {
+            let err = reqwest::Client::new()
+                .get("not a url")
+                .build()
+                .unwrap_err();
+            Err(ApiError::ReqwestError(err))
+        }
+    }
+
+    #[async_trait]
+    impl QueryOrderEndpoint for LossyClient {
+        async fn query_order(&self, _params: QueryOrderParams<'_>) -> ApiResult<QueryOrderOutput> {
+            Err(
+                ApiError::ErrorResponse(
+                    ErrorResponse {
+                        code: ErrorCode::OrderDoesNotExist,
+                        msg: "Order does not exist.".to_string(),
+                        _extend: None,
+                    },
+                ),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn tracks_paper_orders_to_terminal() {
+        let client = PaperTradingClient::new(vec![symbol_info()])
+            .with_balance(
+                "USDT",
+                dec("1000"),
+            )
+            .into_arc();
+        client
+            .set_order_book(
+                "BTCUSDT",
+                &DepthOutput {
+                    last_update_id: 1,
+                    bids: vec![],
+                    asks: vec![
+                        PriceAndQuantity {
+                            price: dec("100"),
+                            quantity: dec("1"),
+                        },
+                        PriceAndQuantity {
+                            price: dec("101"),
+                            quantity: dec("1"),
+                        },
+                    ],
+                },
+            )
+            .await;
+        let manager = OrderManager::new(client.clone()).with_client_order_id_prefix("test");
+        let messages = client
+            .clone()
+            .stream();
+        let follow = async {
+            manager
+                .follow(
+                    messages,
+                    futures::stream::empty().boxed(),
+                )
+                .await
+        };
+
+        let place = async {
+            let handle = manager
+                .place(
+                    OrderParams {
+                        symbol: "BTCUSDT",
+                        side: OrderSide::Buy,
+                        order_type: OrderType::Limit,
+                        quantity: Some(dec("1.5")),
+                        quote_order_quantity: None,
+                        price: Some(dec("101")),
+                        new_client_order_id: None,
+                        stop_price: None,
+                        trigger_direction: None,
+                    },
+                )
+                .await
+                .unwrap();
+            assert!(
+                handle
+                    .client_order_id()
+                    .starts_with("test")
+            );
+            handle
+                .terminal()
+                .await
+        };
+
+        let state = tokio::select! {
+            _ = follow => unreachable!(),
+            state = place => state,
+        };
+        assert_eq!(
+            state.status,
+            OrderStatus::Filled
+        );
+        assert_eq!(
+            state.executed_quantity,
+            dec("1.5")
+        );
+        assert_eq!(
+            state.average_price(),
+            Some(dec("150.5") / dec("1.5"))
+        );
+
+        manager
+            .reconcile()
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn rejects_lost_orders_on_reconcile() {
+        let manager = OrderManager::new(Arc::new(LossyClient)).with_client_order_id_prefix("test");
+        assert!(
+            manager
+                .place(
+                    OrderParams {
+                        symbol: "BTCUSDT",
+                        side: OrderSide::Buy,
+                        order_type: OrderType::Limit,
+                        quantity: Some(dec("1")),
+                        quote_order_quantity: None,
+                        price: Some(dec("100")),
+                        new_client_order_id: Some("test1"),
+                        stop_price: None,
+                        trigger_direction: None,
+                    },
+                )
+                .await
+                .is_err()
+        );
+
+        manager
+            .reconcile()
+            .await
+            .unwrap();
+        let state = manager
+            .tracker()
+            .get("test1")
+            .unwrap();
+        assert_eq!(
+            state.status,
+            OrderStatus::Rejected
+        );
+    }
+}
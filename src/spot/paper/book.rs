@@ -0,0 +1,298 @@
+use crate::spot::v3::{depth::PriceAndQuantity, enums::OrderSide};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// The price levels of a symbol, as last seen on the market data feed.
+#[derive(Debug, Default, Clone)]
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fill {
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+impl OrderBook {
+    pub fn replace(&mut self, bids: &[PriceAndQuantity], asks: &[PriceAndQuantity]) {
+        self.bids
+            .clear();
+        self.asks
+            .clear();
+        self.update(
+            bids, asks,
+        );
+    }
+
+    /// Levels with a quantity of zero are removed
+    pub fn update(&mut self, bids: &[PriceAndQuantity], asks: &[PriceAndQuantity]) {
+        for (levels, updates) in [
+            (
+                &mut self.bids,
+                bids,
+            ),
+            (
+                &mut self.asks,
+                asks,
+            ),
+        ] {
+            for level in updates {
+                match level
+                    .quantity
+                    .is_zero()
+                {
+                    true => levels.remove(&level.price),
+                    false => levels.insert(
+                        level.price,
+                        level.quantity,
+                    ),
+                };
+            }
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<PriceAndQuantity> {
+        self.bids
+            .iter()
+            .next_back()
+            .map(
+                |(price, quantity)| {
+                    to_price_and_quantity(
+                        *price, *quantity,
+                    )
+                },
+            )
+    }
+
+    pub fn best_ask(&self) -> Option<PriceAndQuantity> {
+        self.asks
+            .iter()
+            .next()
+            .map(
+                |(price, quantity)| {
+                    to_price_and_quantity(
+                        *price, *quantity,
+                    )
+                },
+            )
+    }
+
+    /// The levels a taker on `side` trades against, best first, up to
+    /// `limit_price`.
+    fn opposite_levels(
+        &self,
+        side: OrderSide,
+        limit_price: Option<Decimal>,
+    ) -> Vec<(
+        Decimal,
+        Decimal,
+    )> {
+        match side {
+            OrderSide::Buy => self
+                .asks
+                .iter()
+                .take_while(|(price, _)| limit_price.is_none_or(|limit_price| **price <= limit_price))
+                .map(
+                    |(price, quantity)| {
+                        (
+                            *price, *quantity,
+                        )
+                    },
+                )
+                .collect(),
+            OrderSide::Sell => self
+                .bids
+                .iter()
+                .rev()
+                .take_while(|(price, _)| limit_price.is_none_or(|limit_price| **price >= limit_price))
+                .map(
+                    |(price, quantity)| {
+                        (
+                            *price, *quantity,
+                        )
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// The fills a taker on `side` would get for `quantity`, or for
+    /// `quote_quantity` when spending an amount of the quote asset. Quantities
+    /// are rounded down to `base_scale` decimals. Doesn't change the book.
+    pub(crate) fn simulate_taker(&self, side: OrderSide, limit_price: Option<Decimal>, quantity: Option<Decimal>, quote_quantity: Option<Decimal>, base_scale: u32) -> Vec<Fill> {
+        let mut remaining_quantity = quantity;
+        let mut remaining_quote_quantity = quote_quantity;
+        let mut fills = Vec::new();
+        for (price, level_quantity) in self.opposite_levels(
+            side,
+            limit_price,
+        ) {
+            let mut fill_quantity = level_quantity;
+            if let Some(remaining_quantity) = remaining_quantity {
+                fill_quantity = fill_quantity.min(remaining_quantity);
+            }
+            if let Some(remaining_quote_quantity) = remaining_quote_quantity {
+                fill_quantity = fill_quantity.min(
+                    (remaining_quote_quantity / price).round_dp_with_strategy(
+                        base_scale,
+                        rust_decimal::RoundingStrategy::ToZero,
+                    ),
+                );
+            }
+            if fill_quantity <= Decimal::ZERO {
+                break;
+            }
+
+            fills.push(
+                Fill {
+                    price,
+                    quantity: fill_quantity,
+                },
+            );
+            if let Some(remaining_quantity) = remaining_quantity.as_mut() {
+                *remaining_quantity -= fill_quantity;
+            }
+            if let Some(remaining_quote_quantity) = remaining_quote_quantity.as_mut() {
+                *remaining_quote_quantity -= fill_quantity * price;
+            }
+        }
+        fills
+    }
+
+    /// Removes filled liquidity, until the feed updates the level again
+    pub(crate) fn consume(&mut self, side: OrderSide, fills: &[Fill]) {
+        let levels = match side {
+            OrderSide::Buy => &mut self.asks,
+            OrderSide::Sell => &mut self.bids,
+        };
+        for fill in fills {
+            if let Some(level_quantity) = levels.get_mut(&fill.price) {
+                *level_quantity -= fill
+                    .quantity
+                    .min(*level_quantity);
+                if level_quantity.is_zero() {
+                    levels.remove(&fill.price);
+                }
+            }
+        }
+    }
+}
+
+fn to_price_and_quantity(price: Decimal, quantity: Decimal) -> PriceAndQuantity {
+    PriceAndQuantity {
+        price,
+        quantity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn level(price: Decimal, quantity: Decimal) -> PriceAndQuantity {
+        to_price_and_quantity(
+            price, quantity,
+        )
+    }
+
+    #[test]
+    fn takes_levels_up_to_limit_price() {
+        let mut book = OrderBook::default();
+        book.replace(
+            &[
+                level(
+                    dec("99"),
+                    dec("1"),
+                ),
+            ],
+            &[
+                level(
+                    dec("100"),
+                    dec("1"),
+                ),
+                level(
+                    dec("101"),
+                    dec("2"),
+                ),
+                level(
+                    dec("102"),
+                    dec("5"),
+                ),
+            ],
+        );
+
+        let fills = book.simulate_taker(
+            OrderSide::Buy,
+            Some(dec("101")),
+            Some(dec("5")),
+            None,
+            8,
+        );
+        assert_eq!(
+            fills,
+            vec![
+                Fill {
+                    price: dec("100"),
+                    quantity: dec("1")
+                },
+                Fill {
+                    price: dec("101"),
+                    quantity: dec("2")
+                },
+            ]
+        );
+
+        let fills = book.simulate_taker(
+            OrderSide::Buy,
+            None,
+            None,
+            Some(dec("200")),
+            2,
+        );
+        assert_eq!(
+            fills,
+            vec![
+                Fill {
+                    price: dec("100"),
+                    quantity: dec("1")
+                },
+                Fill {
+                    price: dec("101"),
+                    quantity: dec("0.99")
+                },
+            ]
+        );
+
+        book.consume(
+            OrderSide::Buy,
+            &fills,
+        );
+        assert_eq!(
+            book.best_ask()
+                .unwrap()
+                .price,
+            dec("101")
+        );
+
+        book.update(
+            &[
+                level(
+                    dec("99"),
+                    Decimal::ZERO,
+                ),
+            ],
+            &[],
+        );
+        assert!(
+            book.best_bid()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,1149 @@
+use crate::spot::{
+    paper::book::{Fill, OrderBook},
+    v3::{
+        depth::PriceAndQuantity,
+        enums::{ChangedType, OrderSide, OrderStatus, OrderType},
+        exchange_information::ExchangeInformationSymbol,
+        models::Order,
+        order::OrderParams,
+        ApiError, ApiResult, ErrorCode, ErrorResponse,
+    },
+    ws::message::{
+        account_deals::AccountDealsMessage,
+        account_orders::{AccountOrdersMessage, LimitOrMarketAccountOrdersMessage, OrderKind},
+        account_update::AccountUpdateMessage,
+        Message,
+    },
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaperBalance {
+    pub free: Decimal,
+    pub locked: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PaperOrder {
+    pub symbol: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    /// Zero for market orders
+    pub price: Decimal,
+    /// Zero for market orders placed with a quote quantity
+    pub original_quantity: Decimal,
+    pub original_quote_order_quantity: Decimal,
+    pub executed_quantity: Decimal,
+    pub cummulative_quote_quantity: Decimal,
+    pub status: OrderStatus,
+    pub time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+    /// Still locked for the order, in the quote asset for buys and in the base
+    /// asset for sells
+    locked: Decimal,
+    last_fill_is_maker: bool,
+}
+
+impl PaperOrder {
+    pub(crate) fn is_open(&self) -> bool {
+        matches!(
+            self.status,
+            OrderStatus::New | OrderStatus::PartiallyFilled
+        )
+    }
+
+    fn remaining_quantity(&self) -> Decimal {
+        self.original_quantity - self.executed_quantity
+    }
+
+    fn rests(&self) -> bool {
+        matches!(
+            self.order_type,
+            OrderType::Limit | OrderType::LimitMaker
+        )
+    }
+
+    pub(crate) fn to_order(&self) -> Order {
+        Order {
+            symbol: self
+                .symbol
+                .clone(),
+            order_id: self
+                .order_id
+                .clone(),
+            client_order_id: self
+                .client_order_id
+                .clone(),
+            price: self.price,
+            original_quantity: self.original_quantity,
+            executed_quantity: self.executed_quantity,
+            cummulative_quote_quantity: self.cummulative_quote_quantity,
+            status: self.status,
+            time_in_force: None,
+            order_type: self.order_type,
+            side: self.side,
+            stop_price: None,
+            time: self.time,
+            update_time: Some(self.update_time),
+            is_working: self.is_open(),
+            original_quote_order_qty: self.original_quote_order_quantity,
+        }
+    }
+
+    fn to_message(&self, now: DateTime<Utc>) -> Message {
+        let amount = match self
+            .original_quote_order_quantity
+            .is_zero()
+        {
+            true => self.price * self.original_quantity,
+            false => self.original_quote_order_quantity,
+        };
+        let average_price = match self
+            .executed_quantity
+            .is_zero()
+        {
+            true => Decimal::ZERO,
+            false => self.cummulative_quote_quantity / self.executed_quantity,
+        };
+        Message::AccountOrders(
+            AccountOrdersMessage::LimitOrMarket(
+                LimitOrMarketAccountOrdersMessage {
+                    symbol: self
+                        .symbol
+                        .clone(),
+                    remain_amount: amount - self.cummulative_quote_quantity,
+                    create_time: self.time,
+                    trade_type: self.side,
+                    remain_quantity: self.remaining_quantity(),
+                    amount,
+                    client_order_id: self
+                        .client_order_id
+                        .clone()
+                        .unwrap_or_default(),
+                    order_id: self
+                        .order_id
+                        .clone(),
+                    is_maker: self.last_fill_is_maker,
+                    order_kind: match self.order_type {
+                        OrderType::Limit => OrderKind::LimitOrder,
+                        OrderType::LimitMaker => OrderKind::PostOnly,
+                        OrderType::ImmediateOrCancel => OrderKind::ImmediateOrCancel,
+                        OrderType::FillOrKill => OrderKind::FillOrKill,
+                        OrderType::Market => OrderKind::MarketOrder,
+                        OrderType::StopLimit => OrderKind::StopLimit,
+                    },
+                    price: self.price,
+                    status: self.status,
+                    quantity: self.original_quantity,
+                    average_price,
+                    cumulative_quantity: self.executed_quantity,
+                    cumulative_amount: self.cummulative_quote_quantity,
+                    timestamp: now,
+                },
+            ),
+        )
+    }
+}
+
+fn rejection(code: ErrorCode, msg: &str) -> ApiError {
+    ApiError::ErrorResponse(
+        ErrorResponse {
+            code,
+            msg: msg.to_string(),
+            _extend: None,
+        },
+    )
+}
+
+fn scale(precision: i32) -> u32 {
+    precision.max(0) as u32
+}
+
+/// The state of a simulated account, together with the order books its orders
+/// are matched against. Every change is recorded as the websocket message MEXC
+/// would have pushed for it, see [`PaperExchange::take_events`].
+#[derive(Debug, Default)]
+pub(crate) struct PaperExchange {
+    symbols: HashMap<String, ExchangeInformationSymbol>,
+    books: HashMap<String, OrderBook>,
+    balances: HashMap<String, PaperBalance>,
+    /// In the order they were placed, which is also their time priority
+    orders: Vec<PaperOrder>,
+    last_order_id: u64,
+    last_trade_id: u64,
+    events: Vec<Message>,
+}
+
+impl PaperExchange {
+    pub(crate) fn new(symbols: Vec<ExchangeInformationSymbol>) -> Self {
+        Self {
+            symbols: symbols
+                .into_iter()
+                .map(
+                    |symbol| {
+                        (
+                            symbol
+                                .symbol
+                                .clone(),
+                            symbol,
+                        )
+                    },
+                )
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn take_events(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub(crate) fn deposit(&mut self, asset: &str, amount: Decimal) {
+        self.balances
+            .entry(asset.to_string())
+            .or_default()
+            .free += amount;
+    }
+
+    pub(crate) fn balance(&self, asset: &str) -> PaperBalance {
+        self.balances
+            .get(asset)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn balances(&self) -> &HashMap<String, PaperBalance> {
+        &self.balances
+    }
+
+    pub(crate) fn order_book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.books
+            .get(symbol)
+    }
+
+    fn change_balance(&mut self, asset: &str, free_delta: Decimal, locked_delta: Decimal, changed_type: ChangedType, now: DateTime<Utc>) {
+        if free_delta.is_zero() && locked_delta.is_zero() {
+            return;
+        }
+        let balance = self
+            .balances
+            .entry(asset.to_string())
+            .or_default();
+        balance.free += free_delta;
+        balance.locked += locked_delta;
+        let message = Message::AccountUpdate(
+            AccountUpdateMessage {
+                asset: asset.to_string(),
+                change_time: now,
+                free_balance: balance.free,
+                free_changed_amount: free_delta,
+                frozen_amount: balance.locked,
+                frozen_changed_amount: locked_delta,
+                changed_type,
+                event_time: now,
+            },
+        );
+        self.events
+            .push(message);
+    }
+
+    fn symbol_info(&self, symbol: &str) -> ApiResult<ExchangeInformationSymbol> {
+        self.symbols
+            .get(symbol)
+            .cloned()
+            .ok_or_else(
+                || {
+                    rejection(
+                        ErrorCode::InvalidSymbol,
+                        "Invalid symbol",
+                    )
+                },
+            )
+    }
+
+    /// The asset locked by an order on `side`
+    fn locked_asset(info: &ExchangeInformationSymbol, side: OrderSide) -> &str {
+        match side {
+            OrderSide::Buy => &info.quote_asset,
+            OrderSide::Sell => &info.base_asset,
+        }
+    }
+
+    pub(crate) fn place_order(&mut self, params: &OrderParams<'_>, now: DateTime<Utc>) -> ApiResult<PaperOrder> {
+        let info = self.symbol_info(params.symbol)?;
+        if !info.is_spot_trading_allowed {
+            return Err(
+                rejection(
+                    ErrorCode::TradingDisabled,
+                    "Trading disabled",
+                ),
+            );
+        }
+        if params
+            .order_type
+            .is_stop()
+        {
+            return Err(
+                rejection(
+                    ErrorCode::CurrentOrderTypeCanNotPlaceOrder,
+                    "Stop orders are not simulated",
+                ),
+            );
+        }
+        let base_scale = scale(info.base_asset_precision);
+
+        let (price, quantity, quote_quantity) = match params.order_type {
+            OrderType::Market => match (
+                params.quantity,
+                params.quote_order_quantity,
+            ) {
+                (Some(quantity), None) => (
+                    None,
+                    Some(quantity),
+                    None,
+                ),
+                (None, Some(quote_quantity)) => (
+                    None,
+                    None,
+                    Some(quote_quantity),
+                ),
+                _ => {
+                    return Err(
+                        rejection(
+                            ErrorCode::ParamIsError,
+                            "Market orders need either a quantity or a quote order quantity",
+                        ),
+                    )
+                }
+            },
+            _ => match (
+                params.price,
+                params.quantity,
+            ) {
+                (Some(price), Some(quantity)) => (
+                    Some(price),
+                    Some(quantity),
+                    None,
+                ),
+                _ => {
+                    return Err(
+                        rejection(
+                            ErrorCode::ParamCannotBeNull,
+                            "Price and quantity are required",
+                        ),
+                    )
+                }
+            },
+        };
+        if [
+            price,
+            quantity,
+            quote_quantity,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|value| value <= Decimal::ZERO)
+        {
+            return Err(
+                rejection(
+                    ErrorCode::AmountCannotBeZeroOrNegative,
+                    "Amount cannot be zero or negative",
+                ),
+            );
+        }
+
+        let book = self
+            .books
+            .entry(
+                params
+                    .symbol
+                    .to_string(),
+            )
+            .or_default();
+        let mut taker_fills = book.simulate_taker(
+            params.side,
+            price,
+            quantity,
+            quote_quantity,
+            base_scale,
+        );
+        let fillable_quantity = taker_fills
+            .iter()
+            .map(|fill| fill.quantity)
+            .sum::<Decimal>();
+        match params.order_type {
+            OrderType::LimitMaker if !taker_fills.is_empty() => {
+                return Err(
+                    rejection(
+                        ErrorCode::CurrentOrderTypeCanNotPlaceOrder,
+                        "Limit maker order would match immediately",
+                    ),
+                )
+            }
+            OrderType::FillOrKill if Some(fillable_quantity) != quantity => taker_fills.clear(),
+            OrderType::Market if taker_fills.is_empty() => {
+                return Err(
+                    rejection(
+                        ErrorCode::NoExistOpponentOrder,
+                        "No opposite orders",
+                    ),
+                )
+            }
+            _ => {}
+        }
+
+        let to_lock = match (
+            params.side,
+            price,
+            quote_quantity,
+        ) {
+            (OrderSide::Buy, Some(price), _) => price * quantity.unwrap_or_default(),
+            (OrderSide::Buy, None, Some(quote_quantity)) => quote_quantity,
+            (OrderSide::Buy, None, None) => taker_fills
+                .iter()
+                .map(|fill| fill.price * fill.quantity)
+                .sum(),
+            (OrderSide::Sell, _, Some(_)) => fillable_quantity,
+            (OrderSide::Sell, _, None) => quantity.unwrap_or_default(),
+        };
+        let locked_asset = Self::locked_asset(
+            &info,
+            params.side,
+        )
+        .to_string();
+        if self
+            .balance(&locked_asset)
+            .free
+            < to_lock
+        {
+            return Err(
+                rejection(
+                    ErrorCode::InsufficientBalance,
+                    "Insufficient balance",
+                ),
+            );
+        }
+
+        self.last_order_id += 1;
+        let order = PaperOrder {
+            symbol: params
+                .symbol
+                .to_string(),
+            order_id: format!(
+                "paper-{}",
+                self.last_order_id
+            ),
+            client_order_id: params
+                .new_client_order_id
+                .map(str::to_string),
+            side: params.side,
+            order_type: params.order_type,
+            price: price.unwrap_or_default(),
+            original_quantity: quantity.unwrap_or_default(),
+            original_quote_order_quantity: quote_quantity.unwrap_or_default(),
+            executed_quantity: Decimal::ZERO,
+            cummulative_quote_quantity: Decimal::ZERO,
+            status: OrderStatus::New,
+            time: now,
+            update_time: now,
+            locked: to_lock,
+            last_fill_is_maker: false,
+        };
+        self.change_balance(
+            &locked_asset,
+            -to_lock,
+            to_lock,
+            ChangedType::EntrustPlace,
+            now,
+        );
+        self.events
+            .push(order.to_message(now));
+        self.orders
+            .push(order);
+        let index = self
+            .orders
+            .len()
+            - 1;
+
+        for fill in taker_fills.iter() {
+            self.fill(
+                index,
+                fill.price,
+                fill.quantity,
+                false,
+                now,
+            );
+        }
+        if let Some(book) = self
+            .books
+            .get_mut(params.symbol)
+        {
+            book.consume(
+                params.side,
+                &taker_fills,
+            );
+        }
+
+        let order = &self.orders[index];
+        if order.is_open() && !order.rests() {
+            // A market order spending a quote quantity is done once the rest
+            // doesn't buy a single unit anymore
+            let spent = !order
+                .original_quote_order_quantity
+                .is_zero()
+                && taker_fills
+                    .last()
+                    .is_some_and(
+                        |fill| {
+                            ((order.original_quote_order_quantity - order.cummulative_quote_quantity) / fill.price)
+                                .round_dp_with_strategy(
+                                    base_scale,
+                                    RoundingStrategy::ToZero,
+                                )
+                                .is_zero()
+                        },
+                    );
+            let status = match spent {
+                true => OrderStatus::Filled,
+                false => canceled_status(order),
+            };
+            self.close(
+                index, status, now,
+            );
+        }
+
+        Ok(self.orders[index].clone())
+    }
+
+    /// Fills `quantity` of the order at `price`, charging the maker or taker
+    /// commission of the symbol on what is received.
+    fn fill(&mut self, index: usize, price: Decimal, quantity: Decimal, is_maker: bool, now: DateTime<Utc>) {
+        let info = self.symbols[&self.orders[index].symbol].clone();
+        let commission = match is_maker {
+            true => info.maker_commission,
+            false => info.taker_commission,
+        };
+        let quote_amount = price * quantity;
+        let side = self.orders[index].side;
+        let (fee, commission_asset) = match side {
+            OrderSide::Buy => {
+                let fee = (quantity * commission).round_dp(scale(info.base_commission_precision));
+                self.change_balance(
+                    &info.quote_asset,
+                    Decimal::ZERO,
+                    -quote_amount,
+                    ChangedType::Entrust,
+                    now,
+                );
+                self.change_balance(
+                    &info.base_asset,
+                    quantity - fee,
+                    Decimal::ZERO,
+                    ChangedType::Entrust,
+                    now,
+                );
+                self.orders[index].locked -= quote_amount;
+                (
+                    fee,
+                    &info.base_asset,
+                )
+            }
+            OrderSide::Sell => {
+                let fee = (quote_amount * commission).round_dp(scale(info.quote_commission_precision));
+                self.change_balance(
+                    &info.base_asset,
+                    Decimal::ZERO,
+                    -quantity,
+                    ChangedType::Entrust,
+                    now,
+                );
+                self.change_balance(
+                    &info.quote_asset,
+                    quote_amount - fee,
+                    Decimal::ZERO,
+                    ChangedType::Entrust,
+                    now,
+                );
+                self.orders[index].locked -= quantity;
+                (
+                    fee,
+                    &info.quote_asset,
+                )
+            }
+        };
+
+        self.last_trade_id += 1;
+        let order = &mut self.orders[index];
+        order.executed_quantity += quantity;
+        order.cummulative_quote_quantity += quote_amount;
+        order.update_time = now;
+        order.last_fill_is_maker = is_maker;
+        order.status = match !order
+            .original_quantity
+            .is_zero()
+            && order.executed_quantity >= order.original_quantity
+        {
+            true => OrderStatus::Filled,
+            false => OrderStatus::PartiallyFilled,
+        };
+        let deal = Message::AccountDeals(
+            AccountDealsMessage {
+                asset: order
+                    .symbol
+                    .clone(),
+                trade_type: side,
+                trade_time: now,
+                client_order_id: order
+                    .client_order_id
+                    .clone()
+                    .unwrap_or_default(),
+                order_id: order
+                    .order_id
+                    .clone(),
+                is_maker,
+                price,
+                is_self_trade: false,
+                trade_id: format!(
+                    "paper-trade-{}",
+                    self.last_trade_id
+                ),
+                quantity,
+                deals_amount: quote_amount,
+                commission_fee: fee,
+                commission_asset: commission_asset.clone(),
+                event_time: now,
+            },
+        );
+        self.events
+            .push(deal);
+
+        match self.orders[index].status {
+            OrderStatus::Filled => self.close(
+                index,
+                OrderStatus::Filled,
+                now,
+            ),
+            _ => {
+                let message = self.orders[index].to_message(now);
+                self.events
+                    .push(message);
+            }
+        }
+    }
+
+    /// Ends the order with `status`, unlocking what it didn't use
+    fn close(&mut self, index: usize, status: OrderStatus, now: DateTime<Utc>) {
+        let info = self.symbols[&self.orders[index].symbol].clone();
+        let order = &mut self.orders[index];
+        let unlocked = std::mem::take(&mut order.locked);
+        order.status = status;
+        order.update_time = now;
+        let locked_asset = Self::locked_asset(
+            &info, order.side,
+        );
+        let changed_type = match status {
+            OrderStatus::Filled => ChangedType::EntrustUnfrozen,
+            _ => ChangedType::EntrustCancel,
+        };
+        let message = order.to_message(now);
+        self.change_balance(
+            locked_asset,
+            unlocked,
+            -unlocked,
+            changed_type,
+            now,
+        );
+        self.events
+            .push(message);
+    }
+
+    fn find_order_index(&self, symbol: &str, order_id: Option<&str>, client_order_id: Option<&str>) -> ApiResult<usize> {
+        self.orders
+            .iter()
+            .position(
+                |order| {
+                    order.symbol == symbol
+                        && match (
+                            order_id,
+                            client_order_id,
+                        ) {
+                            (Some(order_id), _) => order.order_id == order_id,
+                            (None, Some(client_order_id)) => {
+                                order
+                                    .client_order_id
+                                    .as_deref()
+                                    == Some(client_order_id)
+                            }
+                            (None, None) => false,
+                        }
+                },
+            )
+            .ok_or_else(
+                || {
+                    rejection(
+                        ErrorCode::OrderDoesNotExist,
+                        "Order does not exist",
+                    )
+                },
+            )
+    }
+
+    pub(crate) fn find_order(&self, symbol: &str, order_id: Option<&str>, client_order_id: Option<&str>) -> ApiResult<PaperOrder> {
+        let index = self.find_order_index(
+            symbol,
+            order_id,
+            client_order_id,
+        )?;
+        Ok(self.orders[index].clone())
+    }
+
+    pub(crate) fn cancel_order(&mut self, symbol: &str, order_id: Option<&str>, client_order_id: Option<&str>, now: DateTime<Utc>) -> ApiResult<PaperOrder> {
+        let index = self.find_order_index(
+            symbol,
+            order_id,
+            client_order_id,
+        )?;
+        if !self.orders[index].is_open() {
+            return Err(
+                rejection(
+                    ErrorCode::UnknownOrderSent,
+                    "Order is not open",
+                ),
+            );
+        }
+        let status = canceled_status(&self.orders[index]);
+        self.close(
+            index, status, now,
+        );
+        Ok(self.orders[index].clone())
+    }
+
+    pub(crate) fn open_orders(&self, symbol: &str) -> Vec<PaperOrder> {
+        self.orders
+            .iter()
+            .filter(|order| order.symbol == symbol && order.is_open())
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn replace_order_book(&mut self, symbol: &str, bids: &[PriceAndQuantity], asks: &[PriceAndQuantity], now: DateTime<Utc>) {
+        self.books
+            .entry(symbol.to_string())
+            .or_default()
+            .replace(
+                bids, asks,
+            );
+        self.match_resting_orders(
+            symbol, now,
+        );
+    }
+
+    pub(crate) fn update_order_book(&mut self, symbol: &str, bids: &[PriceAndQuantity], asks: &[PriceAndQuantity], now: DateTime<Utc>) {
+        self.books
+            .entry(symbol.to_string())
+            .or_default()
+            .update(
+                bids, asks,
+            );
+        self.match_resting_orders(
+            symbol, now,
+        );
+    }
+
+    /// Resting orders priced through a public trade are filled, up to its
+    /// quantity.
+    pub(crate) fn apply_public_trade(&mut self, symbol: &str, price: Decimal, quantity: Decimal, now: DateTime<Utc>) {
+        let mut remaining = quantity;
+        for index in self.resting_order_indices(symbol) {
+            let order = &self.orders[index];
+            let crosses = match order.side {
+                OrderSide::Buy => order.price >= price,
+                OrderSide::Sell => order.price <= price,
+            };
+            if !crosses || remaining <= Decimal::ZERO {
+                continue;
+            }
+            let fill_quantity = order
+                .remaining_quantity()
+                .min(remaining);
+            remaining -= fill_quantity;
+            let order_price = order.price;
+            self.fill(
+                index,
+                order_price,
+                fill_quantity,
+                true,
+                now,
+            );
+        }
+    }
+
+    /// Open limit orders of the symbol, best price first, then oldest first
+    fn resting_order_indices(&self, symbol: &str) -> Vec<usize> {
+        let mut indices = self
+            .orders
+            .iter()
+            .enumerate()
+            .filter(|(_, order)| order.symbol == symbol && order.is_open() && order.rests())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        indices.sort_by_key(
+            |index| {
+                let order = &self.orders[*index];
+                let price = match order.side {
+                    OrderSide::Buy => -order.price,
+                    OrderSide::Sell => order.price,
+                };
+                (
+                    order.side == OrderSide::Sell,
+                    price,
+                    *index,
+                )
+            },
+        );
+        indices
+    }
+
+    /// Resting orders the book moved through are filled at their own price,
+    /// as makers.
+    fn match_resting_orders(&mut self, symbol: &str, now: DateTime<Utc>) {
+        let Some(info) = self
+            .symbols
+            .get(symbol)
+        else {
+            return;
+        };
+        let base_scale = scale(info.base_asset_precision);
+        for index in self.resting_order_indices(symbol) {
+            let order = &self.orders[index];
+            let (side, price) = (
+                order.side,
+                order.price,
+            );
+            let Some(book) = self
+                .books
+                .get_mut(symbol)
+            else {
+                return;
+            };
+            let fills = book.simulate_taker(
+                side,
+                Some(price),
+                Some(order.remaining_quantity()),
+                None,
+                base_scale,
+            );
+            if fills.is_empty() {
+                continue;
+            }
+            book.consume(
+                side, &fills,
+            );
+            let quantity = fills
+                .iter()
+                .map(|fill: &Fill| fill.quantity)
+                .sum();
+            self.fill(
+                index, price, quantity, true, now,
+            );
+        }
+    }
+}
+
+fn canceled_status(order: &PaperOrder) -> OrderStatus {
+    match order
+        .executed_quantity
+        .is_zero()
+    {
+        true => OrderStatus::Canceled,
+        false => OrderStatus::PartiallyCanceled,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    pub(crate) fn symbol_info() -> ExchangeInformationSymbol {
+        serde_json::from_value(
+            serde_json::json!({
+                "symbol": "BTCUSDT",
+                "status": "1",
+                "baseAsset": "BTC",
+                "baseAssetPrecision": 6,
+                "quoteAsset": "USDT",
+                "quotePrecision": 2,
+                "quoteAssetPrecision": 2,
+                "baseCommissionPrecision": 6,
+                "quoteCommissionPrecision": 2,
+                "orderTypes": ["LIMIT", "MARKET", "LIMIT_MAKER"],
+                "isSpotTradingAllowed": true,
+                "isMarginTradingAllowed": false,
+                "quoteAmountPrecision": "5",
+                "baseSizePrecision": "0",
+                "permissions": ["SPOT"],
+                "filters": [],
+                "maxQuoteAmount": "2000000",
+                "makerCommission": "0",
+                "takerCommission": "0.001"
+            }),
+        )
+        .unwrap()
+    }
+
+    fn level(price: &str, quantity: &str) -> PriceAndQuantity {
+        PriceAndQuantity {
+            price: dec(price),
+            quantity: dec(quantity),
+        }
+    }
+
+    fn order_params(side: OrderSide, order_type: OrderType, price: Option<&str>, quantity: &str) -> OrderParams<'static> {
+        OrderParams {
+            symbol: "BTCUSDT",
+            side,
+            order_type,
+            quantity: Some(dec(quantity)),
+            quote_order_quantity: None,
+            price: price.map(dec),
+            new_client_order_id: None,
+            stop_price: None,
+            trigger_direction: None,
+        }
+    }
+
+    fn exchange() -> PaperExchange {
+        let mut exchange = PaperExchange::new(vec![symbol_info()]);
+        exchange.deposit(
+            "USDT",
+            dec("10000"),
+        );
+        exchange.replace_order_book(
+            "BTCUSDT",
+            &[
+                level(
+                    "99", "1",
+                ),
+            ],
+            &[
+                level(
+                    "100", "1",
+                ),
+                level(
+                    "101", "1",
+                ),
+            ],
+            Utc::now(),
+        );
+        exchange
+    }
+
+    #[test]
+    fn market_buy_pays_taker_commission() {
+        let mut exchange = exchange();
+        let order = exchange
+            .place_order(
+                &order_params(
+                    OrderSide::Buy,
+                    OrderType::Market,
+                    None,
+                    "1.5",
+                ),
+                Utc::now(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            order.status,
+            OrderStatus::Filled
+        );
+        assert_eq!(
+            order.cummulative_quote_quantity,
+            dec("150.5")
+        );
+        assert_eq!(
+            exchange.balance("BTC"),
+            PaperBalance {
+                free: dec("1.4985"),
+                locked: Decimal::ZERO,
+            }
+        );
+        assert_eq!(
+            exchange.balance("USDT"),
+            PaperBalance {
+                free: dec("9849.5"),
+                locked: Decimal::ZERO,
+            }
+        );
+        assert_eq!(
+            exchange
+                .order_book("BTCUSDT")
+                .unwrap()
+                .best_ask()
+                .unwrap()
+                .quantity,
+            dec("0.5")
+        );
+    }
+
+    #[test]
+    fn resting_limit_order_fills_when_book_moves_through_it() {
+        let mut exchange = exchange();
+        let order = exchange
+            .place_order(
+                &order_params(
+                    OrderSide::Buy,
+                    OrderType::Limit,
+                    Some("98"),
+                    "2",
+                ),
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(
+            order.status,
+            OrderStatus::New
+        );
+        assert_eq!(
+            exchange
+                .balance("USDT")
+                .locked,
+            dec("196")
+        );
+        exchange.take_events();
+
+        exchange.update_order_book(
+            "BTCUSDT",
+            &[],
+            &[
+                level(
+                    "97.5", "1",
+                ),
+            ],
+            Utc::now(),
+        );
+        let order = exchange
+            .find_order(
+                "BTCUSDT",
+                Some(&order.order_id),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            order.status,
+            OrderStatus::PartiallyFilled
+        );
+        assert_eq!(
+            exchange.balance("BTC"),
+            PaperBalance {
+                free: dec("1"),
+                locked: Decimal::ZERO,
+            }
+        );
+        assert!(
+            exchange
+                .take_events()
+                .iter()
+                .any(|event| matches!(event, Message::AccountDeals(deal) if deal.is_maker && deal.price == dec("98")))
+        );
+
+        let order = exchange
+            .cancel_order(
+                "BTCUSDT",
+                Some(&order.order_id),
+                None,
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(
+            order.status,
+            OrderStatus::PartiallyCanceled
+        );
+        assert_eq!(
+            exchange.balance("USDT"),
+            PaperBalance {
+                free: dec("9902"),
+                locked: Decimal::ZERO,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_orders_it_cannot_take() {
+        let mut exchange = exchange();
+        assert!(
+            matches!(
+                exchange.place_order(
+                    &order_params(
+                        OrderSide::Sell,
+                        OrderType::Limit,
+                        Some("100"),
+                        "1"
+                    ),
+                    Utc::now()
+                ),
+                Err(
+                    ApiError::ErrorResponse(
+                        ErrorResponse {
+                            code: ErrorCode::InsufficientBalance,
+                            ..
+                        }
+                    )
+                )
+            )
+        );
+        assert!(
+            matches!(
+                exchange.place_order(
+                    &order_params(
+                        OrderSide::Buy,
+                        OrderType::LimitMaker,
+                        Some("100"),
+                        "1"
+                    ),
+                    Utc::now()
+                ),
+                Err(
+                    ApiError::ErrorResponse(
+                        ErrorResponse {
+                            code: ErrorCode::CurrentOrderTypeCanNotPlaceOrder,
+                            ..
+                        }
+                    )
+                )
+            )
+        );
+
+        let order = exchange
+            .place_order(
+                &order_params(
+                    OrderSide::Buy,
+                    OrderType::FillOrKill,
+                    Some("101"),
+                    "3",
+                ),
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(
+            order.status,
+            OrderStatus::Canceled
+        );
+        assert_eq!(
+            exchange.balance("USDT"),
+            PaperBalance {
+                free: dec("10000"),
+                locked: Decimal::ZERO,
+            }
+        );
+    }
+}
@@ -0,0 +1,538 @@
+//! Paper trading: a simulated spot account that implements the order and
+//! account endpoints, filling orders against a live or replayed order book
+//! instead of sending them to MEXC.
+
+use crate::spot::{
+    paper::engine::PaperExchange,
+    v3::{
+        account_information::{AccountBalance, AccountInformationEndpoint, AccountInformationOutput},
+        cancel_order::{CancelOrderEndpoint, CancelOrderOutput, CancelOrderParams},
+        depth::DepthOutput,
+        exchange_information::ExchangeInformationSymbol,
+        get_open_orders::{GetOpenOrdersEndpoint, GetOpenOrdersParams, GetOrderOutput},
+        order::{OrderEndpoint, OrderOutput, OrderParams},
+        query_order::{QueryOrderEndpoint, QueryOrderOutput, QueryOrderParams},
+        ApiResult,
+    },
+    ws::{
+        message::Message,
+        stream::{BoundedSubscribers, OverflowPolicy, Stream, StreamEvent},
+    },
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt};
+use rust_decimal::Decimal;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+pub mod book;
+mod engine;
+
+pub use book::OrderBook;
+pub use engine::PaperBalance;
+
+const BROADCAST_CAPACITY: usize = 1024;
+
+/// A simulated spot account.
+///
+/// Market data is fed with [`PaperTradingClient::set_order_book`],
+/// [`PaperTradingClient::apply_message`] or [`PaperTradingClient::follow`].
+/// Orders taking liquidity fill against the book at the taker commission of the
+/// symbol, resting orders fill at their own price at the maker commission once
+/// the book or a public trade moves through them. Fills consume the liquidity
+/// they took until the feed updates the level again.
+///
+/// Every change is streamed as the `AccountOrders`, `AccountDeals` and
+/// `AccountUpdate` messages the private websocket channels would push.
+#[derive(Debug)]
+pub struct PaperTradingClient {
+    exchange: Mutex<PaperExchange>,
+    broadcast_tx: broadcast::Sender<Arc<Message>>,
+    bounded_subscribers: BoundedSubscribers,
+}
+
+impl PaperTradingClient {
+    pub fn new(symbols: Vec<ExchangeInformationSymbol>) -> Self {
+        let (broadcast_tx, _broadcast_rx) = broadcast::channel(BROADCAST_CAPACITY);
+        Self {
+            exchange: Mutex::new(PaperExchange::new(symbols)),
+            broadcast_tx,
+            bounded_subscribers: BoundedSubscribers::default(),
+        }
+    }
+
+    /// Adds `amount` to the free balance of `asset`
+    pub fn with_balance(self, asset: &str, amount: Decimal) -> Self {
+        self.exchange()
+            .deposit(
+                asset, amount,
+            );
+        self
+    }
+
+    pub fn into_arc(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    fn exchange(&self) -> MutexGuard<'_, PaperExchange> {
+        self.exchange
+            .lock()
+            .expect("Paper exchange lock poisoned")
+    }
+
+    pub fn balance(&self, asset: &str) -> PaperBalance {
+        self.exchange()
+            .balance(asset)
+    }
+
+    pub fn order_book(&self, symbol: &str) -> Option<OrderBook> {
+        self.exchange()
+            .order_book(symbol)
+            .cloned()
+    }
+
+    /// Runs `f` on the exchange and publishes the messages it produced, after
+    /// the lock is released.
+    async fn with_exchange<T>(&self, f: impl FnOnce(&mut PaperExchange) -> T) -> T {
+        let (output, events) = {
+            let mut exchange = self.exchange();
+            let output = f(&mut exchange);
+            (
+                output,
+                exchange.take_events(),
+            )
+        };
+        for event in events {
+            let event = Arc::new(event);
+            self.bounded_subscribers
+                .dispatch(&event)
+                .await;
+            // No receivers is fine
+            let _ = self
+                .broadcast_tx
+                .send(event);
+        }
+        output
+    }
+
+    /// Replaces the book of `symbol` with a depth snapshot
+    pub async fn set_order_book(&self, symbol: &str, depth: &DepthOutput) {
+        let now = Utc::now();
+        self.with_exchange(
+            |exchange| {
+                exchange.replace_order_book(
+                    symbol,
+                    &depth.bids,
+                    &depth.asks,
+                    now,
+                )
+            },
+        )
+        .await
+    }
+
+    /// Applies `OrderbookUpdate` and `Deals` messages, other messages are
+    /// ignored.
+    pub async fn apply_message(&self, message: &Message) {
+        let now = Utc::now();
+        self.with_exchange(
+            |exchange| match message {
+                Message::OrderbookUpdate(update) => exchange.update_order_book(
+                    &update.symbol,
+                    &update.bids,
+                    &update.asks,
+                    now,
+                ),
+                Message::Deals(deals) => {
+                    for deal in deals
+                        .deals
+                        .iter()
+                    {
+                        exchange.apply_public_trade(
+                            &deal.symbol,
+                            deal.price,
+                            deal.quantity,
+                            now,
+                        );
+                    }
+                }
+                _ => {}
+            },
+        )
+        .await
+    }
+
+    /// Applies every message of `stream`, e.g. a websocket or replay stream,
+    /// until it ends.
+    pub async fn follow(&self, mut stream: BoxStream<'_, Arc<Message>>) {
+        while let Some(message) = stream
+            .next()
+            .await
+        {
+            self.apply_message(&message)
+                .await;
+        }
+    }
+}
+
+impl Stream for PaperTradingClient {
+    fn stream<'a>(self: Arc<Self>) -> BoxStream<'a, Arc<Message>> {
+        self.stream_with_events()
+            .filter_map(
+                |event| async move {
+                    match event {
+                        StreamEvent::Message(message) => Some(message),
+                        StreamEvent::Lagged {
+                            skipped,
+                        } => {
+                            tracing::warn!(
+                                "Paper trading stream lagged behind, skipped {} messages",
+                                skipped
+                            );
+                            None
+                        }
+                    }
+                },
+            )
+            .boxed()
+    }
+
+    fn stream_with_events<'a>(self: Arc<Self>) -> BoxStream<'a, StreamEvent<Arc<Message>>> {
+        let mut rx = self
+            .broadcast_tx
+            .subscribe();
+        let stream = async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(message) => yield StreamEvent::Message(message),
+                    Err(RecvError::Lagged(skipped)) => yield StreamEvent::Lagged { skipped },
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+        stream.boxed()
+    }
+
+    fn bounded_stream<'a>(self: Arc<Self>, capacity: usize, overflow_policy: OverflowPolicy) -> BoxStream<'a, StreamEvent<Arc<Message>>> {
+        self.bounded_subscribers
+            .subscribe(
+                capacity,
+                overflow_policy,
+            )
+    }
+}
+
+impl Drop for PaperTradingClient {
+    fn drop(&mut self) {
+        self.bounded_subscribers
+            .close();
+    }
+}
+
+#[async_trait]
+impl OrderEndpoint for PaperTradingClient {
+    async fn order(&self, params: OrderParams<'_>) -> ApiResult<OrderOutput> {
+        let now = Utc::now();
+        let order = self
+            .with_exchange(
+                |exchange| {
+                    exchange.place_order(
+                        &params, now,
+                    )
+                },
+            )
+            .await?;
+        Ok(
+            OrderOutput {
+                symbol: order.symbol,
+                order_id: order.order_id,
+                order_list_id: None,
+                price: order.price,
+                orig_qty: order.original_quantity,
+                order_type: order.order_type,
+                side: order.side,
+                transact_time: order.time,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl CancelOrderEndpoint for PaperTradingClient {
+    async fn cancel_order(&self, params: CancelOrderParams<'_>) -> ApiResult<CancelOrderOutput> {
+        let now = Utc::now();
+        let order = self
+            .with_exchange(
+                |exchange| {
+                    exchange.cancel_order(
+                        params.symbol,
+                        params.order_id,
+                        params.original_client_order_id,
+                        now,
+                    )
+                },
+            )
+            .await?;
+        Ok(
+            CancelOrderOutput {
+                symbol: order.symbol,
+                original_client_order_id: order
+                    .client_order_id
+                    .clone(),
+                order_id: order.order_id,
+                client_order_id: params
+                    .new_client_order_id
+                    .map(str::to_string)
+                    .or(order.client_order_id),
+                price: order.price,
+                original_quantity: order.original_quantity,
+                executed_quantity: order.executed_quantity,
+                cummulative_quote_quantity: order.cummulative_quote_quantity,
+                status: order.status,
+                time_in_force: None,
+                order_type: order.order_type,
+                side: order.side,
+                stop_price: None,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl QueryOrderEndpoint for PaperTradingClient {
+    async fn query_order(&self, params: QueryOrderParams<'_>) -> ApiResult<QueryOrderOutput> {
+        let order = self
+            .exchange()
+            .find_order(
+                params.symbol,
+                params.order_id,
+                params.original_client_order_id,
+            )?;
+        Ok(
+            QueryOrderOutput {
+                symbol: order
+                    .symbol
+                    .clone(),
+                original_client_order_id: order
+                    .client_order_id
+                    .clone(),
+                order_id: order
+                    .order_id
+                    .clone(),
+                client_order_id: order
+                    .client_order_id
+                    .clone(),
+                price: order.price,
+                original_quantity: order.original_quantity,
+                executed_quantity: order.executed_quantity,
+                cummulative_quote_quantity: order.cummulative_quote_quantity,
+                status: order.status,
+                time_in_force: None,
+                order_type: order.order_type,
+                side: order.side,
+                stop_price: Decimal::ZERO,
+                time: order.time,
+                update_time: order.update_time,
+                is_working: order.is_open(),
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl GetOpenOrdersEndpoint for PaperTradingClient {
+    async fn get_open_orders(&self, params: GetOpenOrdersParams<'_>) -> ApiResult<GetOrderOutput> {
+        let orders = self
+            .exchange()
+            .open_orders(params.symbol)
+            .iter()
+            .map(|order| order.to_order())
+            .collect();
+        Ok(
+            GetOrderOutput {
+                orders,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl AccountInformationEndpoint for PaperTradingClient {
+    async fn account_information(&self) -> ApiResult<AccountInformationOutput> {
+        let mut balances = self
+            .exchange()
+            .balances()
+            .iter()
+            .filter(
+                |(_, balance)| {
+                    !balance
+                        .free
+                        .is_zero()
+                        || !balance
+                            .locked
+                            .is_zero()
+                },
+            )
+            .map(
+                |(asset, balance)| AccountBalance {
+                    asset: asset.clone(),
+                    free: balance.free,
+                    locked: balance.locked,
+                },
+            )
+            .collect::<Vec<_>>();
+        balances.sort_by(
+            |a, b| {
+                a.asset
+                    .cmp(&b.asset)
+            },
+        );
+        Ok(
+            AccountInformationOutput {
+                maker_commission: None,
+                taker_commission: None,
+                buyer_commission: None,
+                seller_commission: None,
+                can_trade: true,
+                can_withdraw: false,
+                can_deposit: false,
+                update_time: Some(Utc::now()),
+                account_type: "SPOT".to_string(),
+                balances,
+                permissions: vec!["SPOT".to_string()],
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    pub(crate) use super::engine::tests::symbol_info;
+    use super::*;
+    use crate::spot::{
+        v3::{
+            depth::PriceAndQuantity,
+            enums::{OrderSide, OrderStatus, OrderType},
+        },
+        ws::message::{account_orders::AccountOrdersMessage, orderbook_update::OrderbookUpdateMessage},
+    };
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn streams_account_messages_of_filled_orders() {
+        let client = PaperTradingClient::new(vec![symbol_info()])
+            .with_balance(
+                "USDT",
+                dec("1000"),
+            )
+            .into_arc();
+        let mut stream = client
+            .clone()
+            .stream();
+
+        let order = client
+            .order(
+                OrderParams {
+                    symbol: "BTCUSDT",
+                    side: OrderSide::Buy,
+                    order_type: OrderType::Limit,
+                    quantity: Some(dec("1")),
+                    quote_order_quantity: None,
+                    price: Some(dec("100")),
+                    new_client_order_id: Some("my-order"),
+                    stop_price: None,
+                    trigger_direction: None,
+                },
+            )
+            .await
+            .unwrap();
+        client
+            .apply_message(
+                &Message::OrderbookUpdate(
+                    OrderbookUpdateMessage {
+                        symbol: "BTCUSDT".to_string(),
+                        version: 1,
+                        asks: vec![
+                            PriceAndQuantity {
+                                price: dec("99"),
+                                quantity: dec("5"),
+                            },
+                        ],
+                        bids: vec![],
+                    },
+                ),
+            )
+            .await;
+
+        let mut statuses = Vec::new();
+        while statuses.last() != Some(&OrderStatus::Filled) {
+            if let Message::AccountOrders(AccountOrdersMessage::LimitOrMarket(message)) = stream
+                .next()
+                .await
+                .unwrap()
+                .as_ref()
+            {
+                assert_eq!(
+                    message.client_order_id,
+                    "my-order"
+                );
+                statuses.push(message.status);
+            }
+        }
+        assert_eq!(
+            statuses,
+            vec![
+                OrderStatus::New,
+                OrderStatus::Filled
+            ]
+        );
+
+        let order = client
+            .query_order(
+                QueryOrderParams {
+                    symbol: "BTCUSDT",
+                    order_id: Some(&order.order_id),
+                    original_client_order_id: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            order.cummulative_quote_quantity,
+            dec("100")
+        );
+        let account = client
+            .account_information()
+            .await
+            .unwrap();
+        assert_eq!(
+            account
+                .balances
+                .iter()
+                .map(
+                    |balance| (
+                        balance
+                            .asset
+                            .as_str(),
+                        balance.free
+                    )
+                )
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "BTC",
+                    dec("1")
+                ),
+                (
+                    "USDT",
+                    dec("900")
+                )
+            ]
+        );
+    }
+}
@@ -65,6 +65,8 @@ pub struct CancelOrderOutput {
     #[serde(rename = "type")]
     pub order_type: OrderType,
     pub side: OrderSide,
+    /// Stop-limit and take-profit orders only
+    pub stop_price: Option<Decimal>,
 }
 
 #[async_trait]
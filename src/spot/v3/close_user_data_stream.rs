@@ -0,0 +1,58 @@
+use crate::spot::{
+    v3::{ApiResponse, ApiResult},
+    MexcSpotApiClientWithAuthentication,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug)]
+pub struct CloseUserDataStreamParams<'a> {
+    pub listen_key: &'a str,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseUserDataStreamQuery<'a> {
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    timestamp: DateTime<Utc>,
+    listen_key: &'a str,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseUserDataStreamOutput {
+    pub listen_key: String,
+}
+
+#[async_trait]
+pub trait CloseUserDataStreamEndpoint {
+    async fn close_user_data_stream(&self, params: CloseUserDataStreamParams<'_>) -> ApiResult<CloseUserDataStreamOutput>;
+}
+
+#[async_trait]
+impl CloseUserDataStreamEndpoint for MexcSpotApiClientWithAuthentication {
+    async fn close_user_data_stream(&self, params: CloseUserDataStreamParams<'_>) -> ApiResult<CloseUserDataStreamOutput> {
+        let url = format!(
+            "{}/api/v3/userDataStream",
+            self.endpoint
+                .as_ref()
+        );
+        let query = CloseUserDataStreamQuery {
+            timestamp: Utc::now(),
+            listen_key: params.listen_key,
+        };
+        let query = self.sign_query(&query)?;
+        let response = self
+            .reqwest_client
+            .delete(&url)
+            .query(&query)
+            .send()
+            .await?;
+        let api_response = response
+            .json::<ApiResponse<CloseUserDataStreamOutput>>()
+            .await?;
+        let output = api_response.into_api_result()?;
+
+        Ok(output)
+    }
+}
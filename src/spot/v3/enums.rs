@@ -23,6 +23,51 @@ pub enum OrderType {
 
     /// Fill or kill order
     FillOrKill,
+
+    /// Limit order placed once the price reaches the stop price. Take-profit
+    /// orders are stop-limit orders with the opposite trigger direction, see
+    /// [`StopLimitDirection`].
+    StopLimit,
+}
+
+impl OrderType {
+    /// Stop-limit orders wait for their stop price
+    pub fn is_stop(&self) -> bool {
+        matches!(
+            self,
+            OrderType::StopLimit
+        )
+    }
+}
+
+/// Which side of the stop price triggers a stop order
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde_repr::Deserialize_repr, serde_repr::Serialize_repr, num_derive::FromPrimitive)]
+#[repr(u8)]
+pub enum StopLimitDirection {
+    PriceHigherThanTriggerPrice = 0,
+    PriceLowerThanTriggerPrice = 1,
+}
+
+impl StopLimitDirection {
+    /// A stop-limit buy triggers when the price rises to the stop price and a
+    /// stop-limit sell when it falls to it
+    pub fn for_order(order_type: OrderType, side: OrderSide) -> Option<Self> {
+        match (
+            order_type, side,
+        ) {
+            (OrderType::StopLimit, OrderSide::Buy) => Some(StopLimitDirection::PriceHigherThanTriggerPrice),
+            (OrderType::StopLimit, OrderSide::Sell) => Some(StopLimitDirection::PriceLowerThanTriggerPrice),
+            _ => None,
+        }
+    }
+
+    /// Take-profit orders trigger the other way around than stop-losses
+    pub fn opposite(self) -> Self {
+        match self {
+            StopLimitDirection::PriceHigherThanTriggerPrice => StopLimitDirection::PriceLowerThanTriggerPrice,
+            StopLimitDirection::PriceLowerThanTriggerPrice => StopLimitDirection::PriceHigherThanTriggerPrice,
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
@@ -7,6 +7,7 @@ pub mod account_information;
 pub mod avg_price;
 pub mod cancel_all_open_orders_on_a_symbol;
 pub mod cancel_order;
+pub mod close_user_data_stream;
 pub mod create_user_data_stream;
 pub mod default_symbols;
 pub mod depth;
@@ -20,6 +21,7 @@ pub mod models;
 pub mod order;
 pub mod ping;
 pub mod query_order;
+pub mod symbol_rules;
 pub mod time;
 pub mod trades;
 
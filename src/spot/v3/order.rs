@@ -1,6 +1,6 @@
 use crate::spot::{
     v3::{
-        enums::{OrderSide, OrderType},
+        enums::{OrderSide, OrderType, StopLimitDirection},
         ApiResponse, ApiResult,
     },
     MexcSpotApiClientWithAuthentication,
@@ -18,6 +18,11 @@ pub struct OrderParams<'a> {
     pub quote_order_quantity: Option<Decimal>,
     pub price: Option<Decimal>,
     pub new_client_order_id: Option<&'a str>,
+    /// Required for stop-limit orders
+    pub stop_price: Option<Decimal>,
+    /// Derived from the order type and side when not set, see
+    /// [`StopLimitDirection::for_order`]
+    pub trigger_direction: Option<StopLimitDirection>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -38,6 +43,10 @@ pub struct OrderQuery<'a> {
     pub price: Option<Decimal>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub new_client_order_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_direction: Option<StopLimitDirection>,
     /// Max 60000
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recv_window: Option<u64>,
@@ -55,6 +64,17 @@ impl<'a> From<OrderParams<'a>> for OrderQuery<'a> {
             quote_order_quantity: params.quote_order_quantity,
             price: params.price,
             new_client_order_id: params.new_client_order_id,
+            stop_price: params.stop_price,
+            trigger_direction: params
+                .trigger_direction
+                .or_else(
+                    || {
+                        StopLimitDirection::for_order(
+                            params.order_type,
+                            params.side,
+                        )
+                    },
+                ),
             recv_window: None,
             timestamp: Utc::now(),
         }
@@ -127,10 +147,31 @@ mod tests {
             quote_order_quantity: None,
             price: Some(Decimal::from_str("0.001").unwrap()),
             new_client_order_id: None,
+            stop_price: None,
+            trigger_direction: None,
         };
         let result = client
             .order(params)
             .await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn stop_orders_derive_their_trigger_direction() {
+        let query = OrderQuery::from(
+            OrderParams {
+                symbol: "BTCUSDT",
+                side: OrderSide::Sell,
+                order_type: OrderType::StopLimit,
+                quantity: Some(Decimal::ONE),
+                quote_order_quantity: None,
+                price: Some(Decimal::from(95)),
+                new_client_order_id: None,
+                stop_price: Some(Decimal::from(96)),
+                trigger_direction: None,
+            },
+        );
+        let query = serde_urlencoded::to_string(&query).unwrap();
+        assert!(query.starts_with("symbol=BTCUSDT&side=SELL&type=STOP_LIMIT&quantity=1&price=95&stopPrice=96&triggerDirection=1&timestamp="));
+    }
 }
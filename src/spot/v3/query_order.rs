@@ -92,7 +92,7 @@ impl QueryOrderEndpoint for MexcSpotApiClientWithAuthentication {
 
         let response = self
             .reqwest_client
-            .delete(&endpoint)
+            .get(&endpoint)
             .query(&query_with_signature)
             .send()
             .await?;
@@ -0,0 +1,753 @@
+use crate::spot::v3::{
+    enums::{OrderSide, OrderType},
+    exchange_information::{ExchangeInformationEndpoint, ExchangeInformationOutput, ExchangeInformationParams, ExchangeInformationSymbol},
+    order::OrderParams,
+    ApiResult, ErrorCode,
+};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// An entry of `ExchangeInformationSymbol::filters`
+#[derive(Debug, serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(
+    tag = "filterType",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum SymbolFilter {
+    #[serde(rename_all = "camelCase")]
+    PriceFilter { min_price: Option<Decimal>, max_price: Option<Decimal>, tick_size: Option<Decimal> },
+    #[serde(rename_all = "camelCase")]
+    LotSize { min_qty: Option<Decimal>, max_qty: Option<Decimal>, step_size: Option<Decimal> },
+    #[serde(rename_all = "camelCase")]
+    MinNotional { min_notional: Decimal },
+    #[serde(rename_all = "camelCase")]
+    Notional { min_notional: Option<Decimal>, max_notional: Option<Decimal> },
+    /// Limits how far the price may be from the average price, as multipliers
+    /// of it
+    #[serde(rename_all = "camelCase")]
+    PercentPriceBySide {
+        bid_multiplier_up: Decimal,
+        bid_multiplier_down: Decimal,
+        ask_multiplier_up: Decimal,
+        ask_multiplier_down: Decimal,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum SymbolRuleViolation {
+    #[error("Unknown symbol {0}")]
+    UnknownSymbol(String),
+
+    #[error("Spot trading is not allowed for {0}")]
+    TradingNotAllowed(String),
+
+    #[error("Order type {0:?} is not supported by the symbol")]
+    UnsupportedOrderType(OrderType),
+
+    #[error("Market orders by quote order quantity are not allowed for the symbol")]
+    QuoteOrderQuantityNotAllowed,
+
+    #[error("{0} is required")]
+    Missing(&'static str),
+
+    #[error("{field} must be positive, got {value}")]
+    NotPositive { field: &'static str, value: Decimal },
+
+    #[error("Price {price} is not a multiple of the tick size {tick_size}")]
+    PriceNotOnTick { price: Decimal, tick_size: Decimal },
+
+    #[error("Price {price} is outside of {min:?}..={max:?}")]
+    PriceOutOfRange { price: Decimal, min: Option<Decimal>, max: Option<Decimal> },
+
+    #[error("Quantity {quantity} is not a multiple of the step size {step_size}")]
+    QuantityNotOnStep { quantity: Decimal, step_size: Decimal },
+
+    #[error("Quote order quantity {quote_quantity} has more than {decimal_places} decimal places")]
+    QuoteQuantityTooPrecise { quote_quantity: Decimal, decimal_places: u32 },
+
+    #[error("Quantity {quantity} is outside of {min}..={max:?}")]
+    QuantityOutOfRange { quantity: Decimal, min: Decimal, max: Option<Decimal> },
+
+    #[error("Notional {notional} is below the minimum of {min}")]
+    NotionalBelowMinimum { notional: Decimal, min: Decimal },
+
+    #[error("Notional {notional} is above the maximum of {max}")]
+    NotionalAboveMaximum { notional: Decimal, max: Decimal },
+}
+
+impl SymbolRuleViolation {
+    /// The error code the API would have rejected the order with
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            SymbolRuleViolation::UnknownSymbol(_) => ErrorCode::InvalidSymbol,
+            SymbolRuleViolation::TradingNotAllowed(_) => ErrorCode::TradingDisabled,
+            SymbolRuleViolation::UnsupportedOrderType(_) | SymbolRuleViolation::QuoteOrderQuantityNotAllowed => ErrorCode::CurrentOrderTypeCanNotPlaceOrder,
+            SymbolRuleViolation::Missing(_) => ErrorCode::ParamCannotBeNull,
+            SymbolRuleViolation::NotPositive {
+                ..
+            } => ErrorCode::AmountCannotBeZeroOrNegative,
+            SymbolRuleViolation::PriceNotOnTick {
+                ..
+            }
+            | SymbolRuleViolation::QuantityNotOnStep {
+                ..
+            }
+            | SymbolRuleViolation::QuoteQuantityTooPrecise {
+                ..
+            } => ErrorCode::AmountDecimalPlacesIsTooLong,
+            SymbolRuleViolation::PriceOutOfRange {
+                ..
+            } => ErrorCode::NoValidTradePrice,
+            SymbolRuleViolation::QuantityOutOfRange {
+                ..
+            }
+            | SymbolRuleViolation::NotionalBelowMinimum {
+                ..
+            } => ErrorCode::TheMinimumTransactionVolumeCannotBeLessThan,
+            SymbolRuleViolation::NotionalAboveMaximum {
+                ..
+            } => ErrorCode::TheMaximumTransactionVolumeCannotBeGreaterThan,
+        }
+    }
+}
+
+/// The trading rules of a symbol, from its exchange information.
+///
+/// MEXC mostly expresses them through the symbol itself: prices have
+/// `quotePrecision` decimals, quantities `baseAssetPrecision` decimals,
+/// `baseSizePrecision` is the minimum quantity, `quoteAmountPrecision` the
+/// minimum notional and `maxQuoteAmount` the maximum one. Filters, where
+/// present, take precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolRules {
+    pub symbol: String,
+    pub trading_allowed: bool,
+    pub order_types: Vec<OrderType>,
+    pub quote_order_quantity_market_allowed: bool,
+    pub tick_size: Decimal,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub step_size: Decimal,
+    pub min_quantity: Decimal,
+    pub max_quantity: Option<Decimal>,
+    pub min_notional: Decimal,
+    pub max_notional: Option<Decimal>,
+    /// Decimal places of quote order quantities
+    pub quote_amount_decimal_places: u32,
+    pub filters: Vec<SymbolFilter>,
+}
+
+fn step_of(decimal_places: i32) -> Decimal {
+    Decimal::new(
+        1,
+        decimal_places.clamp(
+            0,
+            Decimal::MAX_SCALE as i32,
+        ) as u32,
+    )
+}
+
+fn positive(value: Decimal) -> Option<Decimal> {
+    match value > Decimal::ZERO {
+        true => Some(value),
+        false => None,
+    }
+}
+
+/// Rounds `value` to a multiple of `step`
+fn round_to_step(value: Decimal, step: Decimal, strategy: RoundingStrategy) -> Decimal {
+    if step.is_zero() {
+        return value;
+    }
+    ((value / step).round_dp_with_strategy(
+        0, strategy,
+    ) * step)
+        .normalize()
+}
+
+impl From<&ExchangeInformationSymbol> for SymbolRules {
+    fn from(symbol: &ExchangeInformationSymbol) -> Self {
+        let filters = symbol
+            .filters
+            .iter()
+            .map(
+                |filter| {
+                    serde_json::from_value::<SymbolFilter>(filter.clone()).unwrap_or_else(
+                        |err| {
+                            tracing::debug!(
+                                "Could not parse filter {} of {}: {}",
+                                filter,
+                                symbol.symbol,
+                                err
+                            );
+                            SymbolFilter::Unknown
+                        },
+                    )
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let mut rules = Self {
+            symbol: symbol
+                .symbol
+                .clone(),
+            trading_allowed: symbol.is_spot_trading_allowed,
+            order_types: symbol
+                .order_types
+                .clone(),
+            quote_order_quantity_market_allowed: symbol
+                .quote_order_qty_market_allowed
+                .unwrap_or(true),
+            tick_size: step_of(symbol.quote_precision),
+            min_price: None,
+            max_price: None,
+            step_size: step_of(symbol.base_asset_precision),
+            min_quantity: symbol.base_size_precision,
+            max_quantity: None,
+            min_notional: symbol.quote_amount_precision,
+            max_notional: positive(symbol.max_quote_amount),
+            quote_amount_decimal_places: symbol
+                .quote_asset_precision
+                .max(0) as u32,
+            filters: Vec::new(),
+        };
+        for filter in filters.iter() {
+            match filter {
+                SymbolFilter::PriceFilter {
+                    min_price,
+                    max_price,
+                    tick_size,
+                } => {
+                    rules.min_price = min_price.and_then(positive);
+                    rules.max_price = max_price.and_then(positive);
+                    if let Some(tick_size) = tick_size.and_then(positive) {
+                        rules.tick_size = tick_size;
+                    }
+                }
+                SymbolFilter::LotSize {
+                    min_qty,
+                    max_qty,
+                    step_size,
+                } => {
+                    if let Some(min_qty) = min_qty {
+                        rules.min_quantity = *min_qty;
+                    }
+                    rules.max_quantity = max_qty.and_then(positive);
+                    if let Some(step_size) = step_size.and_then(positive) {
+                        rules.step_size = step_size;
+                    }
+                }
+                SymbolFilter::MinNotional {
+                    min_notional,
+                } => rules.min_notional = *min_notional,
+                SymbolFilter::Notional {
+                    min_notional,
+                    max_notional,
+                } => {
+                    if let Some(min_notional) = min_notional {
+                        rules.min_notional = *min_notional;
+                    }
+                    if let Some(max_notional) = max_notional.and_then(positive) {
+                        rules.max_notional = Some(max_notional);
+                    }
+                }
+                SymbolFilter::PercentPriceBySide {
+                    ..
+                }
+                | SymbolFilter::Unknown => {}
+            }
+        }
+        rules.filters = filters;
+        rules
+    }
+}
+
+impl SymbolRules {
+    /// Rounds `price` to the tick size, e.g. with [`RoundingStrategy::ToZero`]
+    /// to not cross a level
+    pub fn round_price(&self, price: Decimal, strategy: RoundingStrategy) -> Decimal {
+        round_to_step(
+            price,
+            self.tick_size,
+            strategy,
+        )
+    }
+
+    /// Rounds `price` to the tick size on the passive side: down for buys, up
+    /// for sells
+    pub fn round_price_passive(&self, price: Decimal, side: OrderSide) -> Decimal {
+        self.round_price(
+            price,
+            match side {
+                OrderSide::Buy => RoundingStrategy::ToNegativeInfinity,
+                OrderSide::Sell => RoundingStrategy::ToPositiveInfinity,
+            },
+        )
+    }
+
+    pub fn round_quantity(&self, quantity: Decimal, strategy: RoundingStrategy) -> Decimal {
+        round_to_step(
+            quantity,
+            self.step_size,
+            strategy,
+        )
+    }
+
+    /// Rounds `quantity` down to the step size, so it never exceeds what is
+    /// available
+    pub fn truncate_quantity(&self, quantity: Decimal) -> Decimal {
+        self.round_quantity(
+            quantity,
+            RoundingStrategy::ToZero,
+        )
+    }
+
+    pub fn truncate_quote_quantity(&self, quote_quantity: Decimal) -> Decimal {
+        quote_quantity
+            .round_dp_with_strategy(
+                self.quote_amount_decimal_places,
+                RoundingStrategy::ToZero,
+            )
+            .normalize()
+    }
+
+    pub fn check_price(&self, price: Decimal) -> Result<(), SymbolRuleViolation> {
+        if price <= Decimal::ZERO {
+            return Err(
+                SymbolRuleViolation::NotPositive {
+                    field: "price",
+                    value: price,
+                },
+            );
+        }
+        if !(price % self.tick_size).is_zero() {
+            return Err(
+                SymbolRuleViolation::PriceNotOnTick {
+                    price,
+                    tick_size: self.tick_size,
+                },
+            );
+        }
+        if self
+            .min_price
+            .is_some_and(|min| price < min)
+            || self
+                .max_price
+                .is_some_and(|max| price > max)
+        {
+            return Err(
+                SymbolRuleViolation::PriceOutOfRange {
+                    price,
+                    min: self.min_price,
+                    max: self.max_price,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn check_quantity(&self, quantity: Decimal) -> Result<(), SymbolRuleViolation> {
+        if quantity <= Decimal::ZERO {
+            return Err(
+                SymbolRuleViolation::NotPositive {
+                    field: "quantity",
+                    value: quantity,
+                },
+            );
+        }
+        if !(quantity % self.step_size).is_zero() {
+            return Err(
+                SymbolRuleViolation::QuantityNotOnStep {
+                    quantity,
+                    step_size: self.step_size,
+                },
+            );
+        }
+        if quantity < self.min_quantity
+            || self
+                .max_quantity
+                .is_some_and(|max| quantity > max)
+        {
+            return Err(
+                SymbolRuleViolation::QuantityOutOfRange {
+                    quantity,
+                    min: self.min_quantity,
+                    max: self.max_quantity,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn check_notional(&self, notional: Decimal) -> Result<(), SymbolRuleViolation> {
+        if notional < self.min_notional {
+            return Err(
+                SymbolRuleViolation::NotionalBelowMinimum {
+                    notional,
+                    min: self.min_notional,
+                },
+            );
+        }
+        if let Some(max) = self
+            .max_notional
+            .filter(|max| notional > *max)
+        {
+            return Err(
+                SymbolRuleViolation::NotionalAboveMaximum {
+                    notional,
+                    max,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks `params` the way the API would, before sending them. The notional
+    /// of a market order by quantity is not known in advance and isn't checked.
+    pub fn validate(&self, params: &OrderParams<'_>) -> Result<(), SymbolRuleViolation> {
+        if params.symbol != self.symbol {
+            return Err(
+                SymbolRuleViolation::UnknownSymbol(
+                    params
+                        .symbol
+                        .to_string(),
+                ),
+            );
+        }
+        if !self.trading_allowed {
+            return Err(
+                SymbolRuleViolation::TradingNotAllowed(
+                    self.symbol
+                        .clone(),
+                ),
+            );
+        }
+        let order_type_supported = self
+            .order_types
+            .contains(&params.order_type)
+            // IOC and FOK are limit orders with a different time in force
+            || (matches!(params.order_type, OrderType::ImmediateOrCancel | OrderType::FillOrKill)
+                && self
+                    .order_types
+                    .contains(&OrderType::Limit));
+        if !order_type_supported {
+            return Err(SymbolRuleViolation::UnsupportedOrderType(params.order_type));
+        }
+
+        if params.order_type == OrderType::Market {
+            return match (
+                params.quantity,
+                params.quote_order_quantity,
+            ) {
+                (Some(quantity), _) => self.check_quantity(quantity),
+                (None, Some(quote_quantity)) => {
+                    if !self.quote_order_quantity_market_allowed {
+                        return Err(SymbolRuleViolation::QuoteOrderQuantityNotAllowed);
+                    }
+                    if quote_quantity <= Decimal::ZERO {
+                        return Err(
+                            SymbolRuleViolation::NotPositive {
+                                field: "quote order quantity",
+                                value: quote_quantity,
+                            },
+                        );
+                    }
+                    if self.truncate_quote_quantity(quote_quantity) != quote_quantity {
+                        return Err(
+                            SymbolRuleViolation::QuoteQuantityTooPrecise {
+                                quote_quantity,
+                                decimal_places: self.quote_amount_decimal_places,
+                            },
+                        );
+                    }
+                    self.check_notional(quote_quantity)
+                }
+                (None, None) => Err(SymbolRuleViolation::Missing("quantity or quote order quantity")),
+            };
+        }
+
+        if params
+            .order_type
+            .is_stop()
+        {
+            let stop_price = params
+                .stop_price
+                .ok_or(SymbolRuleViolation::Missing("stop price"))?;
+            self.check_price(stop_price)?;
+        }
+        let price = params
+            .price
+            .ok_or(SymbolRuleViolation::Missing("price"))?;
+        let quantity = params
+            .quantity
+            .ok_or(SymbolRuleViolation::Missing("quantity"))?;
+        self.check_price(price)?;
+        self.check_quantity(quantity)?;
+        self.check_notional(price * quantity)
+    }
+}
+
+/// Trading rules of all symbols, fetched once from the exchange information
+/// and looked up locally afterwards.
+#[derive(Debug, Default)]
+pub struct SymbolRulesCache {
+    rules: RwLock<HashMap<String, Arc<SymbolRules>>>,
+}
+
+impl SymbolRulesCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_exchange_information(output: &ExchangeInformationOutput) -> Self {
+        let cache = Self::new();
+        cache.update(&output.symbols);
+        cache
+    }
+
+    /// Replaces the rules of the given symbols, others are kept
+    pub fn update(&self, symbols: &[ExchangeInformationSymbol]) {
+        let mut rules = self
+            .rules
+            .write()
+            .expect("Symbol rules lock poisoned");
+        for symbol in symbols {
+            rules.insert(
+                symbol
+                    .symbol
+                    .clone(),
+                Arc::new(SymbolRules::from(symbol)),
+            );
+        }
+    }
+
+    /// Fetches the exchange information of all symbols
+    pub async fn refresh<C>(&self, client: &C) -> ApiResult<()>
+    where
+        C: ExchangeInformationEndpoint + Sync,
+    {
+        let output = client
+            .exchange_information(ExchangeInformationParams::None)
+            .await?;
+        self.update(&output.symbols);
+        Ok(())
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Arc<SymbolRules>> {
+        self.rules
+            .read()
+            .expect("Symbol rules lock poisoned")
+            .get(symbol)
+            .cloned()
+    }
+
+    pub fn validate(&self, params: &OrderParams<'_>) -> Result<(), SymbolRuleViolation> {
+        self.get(params.symbol)
+            .ok_or_else(
+                || {
+                    SymbolRuleViolation::UnknownSymbol(
+                        params
+                            .symbol
+                            .to_string(),
+                    )
+                },
+            )?
+            .validate(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn symbol() -> ExchangeInformationSymbol {
+        serde_json::from_value(
+            serde_json::json!({
+                "symbol": "BTCUSDT",
+                "status": "1",
+                "baseAsset": "BTC",
+                "baseAssetPrecision": 6,
+                "quoteAsset": "USDT",
+                "quotePrecision": 2,
+                "quoteAssetPrecision": 2,
+                "baseCommissionPrecision": 6,
+                "quoteCommissionPrecision": 2,
+                "orderTypes": ["LIMIT", "MARKET", "LIMIT_MAKER", "STOP_LIMIT"],
+                "isSpotTradingAllowed": true,
+                "isMarginTradingAllowed": false,
+                "quoteAmountPrecision": "5",
+                "baseSizePrecision": "0.0001",
+                "permissions": ["SPOT"],
+                "filters": [
+                    {
+                        "filterType": "PERCENT_PRICE_BY_SIDE",
+                        "bidMultiplierUp": "5",
+                        "bidMultiplierDown": "0.2",
+                        "askMultiplierUp": "5",
+                        "askMultiplierDown": "0.2"
+                    },
+                    { "filterType": "SOMETHING_NEW" }
+                ],
+                "maxQuoteAmount": "2000000",
+                "makerCommission": "0",
+                "takerCommission": "0.0005"
+            }),
+        )
+        .unwrap()
+    }
+
+    fn limit(price: &str, quantity: &str) -> OrderParams<'static> {
+        OrderParams {
+            symbol: "BTCUSDT",
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            quantity: Some(dec(quantity)),
+            quote_order_quantity: None,
+            price: Some(dec(price)),
+            new_client_order_id: None,
+            stop_price: None,
+            trigger_direction: None,
+        }
+    }
+
+    #[test]
+    fn reads_rules_from_symbol() {
+        let rules = SymbolRules::from(&symbol());
+        assert_eq!(
+            rules.tick_size,
+            dec("0.01")
+        );
+        assert_eq!(
+            rules.step_size,
+            dec("0.000001")
+        );
+        assert_eq!(
+            rules.min_notional,
+            dec("5")
+        );
+        assert_eq!(
+            rules.filters,
+            vec![
+                SymbolFilter::PercentPriceBySide {
+                    bid_multiplier_up: dec("5"),
+                    bid_multiplier_down: dec("0.2"),
+                    ask_multiplier_up: dec("5"),
+                    ask_multiplier_down: dec("0.2"),
+                },
+                SymbolFilter::Unknown,
+            ]
+        );
+
+        assert_eq!(
+            rules.round_price_passive(
+                dec("100.129"),
+                OrderSide::Buy
+            ),
+            dec("100.12")
+        );
+        assert_eq!(
+            rules.round_price_passive(
+                dec("100.121"),
+                OrderSide::Sell
+            ),
+            dec("100.13")
+        );
+        assert_eq!(
+            rules.truncate_quantity(dec("0.1234567")),
+            dec("0.123456")
+        );
+    }
+
+    #[test]
+    fn validates_order_params() {
+        let cache = SymbolRulesCache::new();
+        cache.update(&[symbol()]);
+
+        assert_eq!(
+            cache.validate(&limit("100.01", "0.1")),
+            Ok(())
+        );
+        assert_eq!(
+            cache.validate(&limit("100.011", "0.1")),
+            Err(
+                SymbolRuleViolation::PriceNotOnTick {
+                    price: dec("100.011"),
+                    tick_size: dec("0.01"),
+                }
+            )
+        );
+        let mut stop_limit = limit(
+            "100", "0.1",
+        );
+        stop_limit.order_type = OrderType::StopLimit;
+        assert_eq!(
+            cache.validate(&stop_limit),
+            Err(SymbolRuleViolation::Missing("stop price"))
+        );
+        stop_limit.stop_price = Some(dec("100.5"));
+        assert_eq!(
+            cache.validate(&stop_limit),
+            Ok(())
+        );
+        assert_eq!(
+            cache.validate(&limit("100", "0.00001")),
+            Err(
+                SymbolRuleViolation::QuantityOutOfRange {
+                    quantity: dec("0.00001"),
+                    min: dec("0.0001"),
+                    max: None,
+                }
+            )
+        );
+        let violation = cache
+            .validate(
+                &limit(
+                    "10", "0.1",
+                ),
+            )
+            .unwrap_err();
+        assert_eq!(
+            violation,
+            SymbolRuleViolation::NotionalBelowMinimum {
+                notional: dec("1.0"),
+                min: dec("5"),
+            }
+        );
+        assert_eq!(
+            violation.error_code(),
+            ErrorCode::TheMinimumTransactionVolumeCannotBeLessThan
+        );
+
+        let mut market = limit(
+            "100", "1",
+        );
+        market.order_type = OrderType::Market;
+        market.quantity = None;
+        market.quote_order_quantity = Some(dec("10.001"));
+        assert!(
+            matches!(
+                cache.validate(&market),
+                Err(SymbolRuleViolation::QuoteQuantityTooPrecise { .. })
+            )
+        );
+
+        let mut unknown = limit(
+            "100", "1",
+        );
+        unknown.symbol = "ETHUSDT";
+        assert_eq!(
+            cache.validate(&unknown),
+            Err(SymbolRuleViolation::UnknownSymbol("ETHUSDT".to_string()))
+        );
+    }
+}
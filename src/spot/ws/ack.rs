@@ -0,0 +1,231 @@
+use crate::spot::ws::{
+    lifecycle::{self, LifecycleEventKind},
+    topic::Topic,
+};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicI32, Ordering},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// How long to wait for the server to reply to a (un)subscription.
+pub(crate) const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedTopic {
+    pub topic: Topic,
+    pub error: TopicRequestError,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TopicRequestError {
+    /// E.g. an invalid symbol or a blocked channel
+    #[error("Rejected by the server: {reason}")]
+    Rejected { reason: String },
+
+    #[error("No reply from the server")]
+    NoReply,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Reply {
+    pub code: i32,
+    pub message: String,
+}
+
+/// Correlates the `{"id":..,"code":..,"msg":..}` replies of the server with the
+/// requests that are waiting for them.
+#[derive(Debug, Default)]
+pub(crate) struct PendingReplies {
+    last_id: AtomicI32,
+    pending: std::sync::Mutex<HashMap<i32, oneshot::Sender<Reply>>>,
+}
+
+impl PendingReplies {
+    pub(crate) fn next_id(&self) -> i32 {
+        self.last_id
+            .fetch_add(
+                1,
+                Ordering::SeqCst,
+            )
+            + 1
+    }
+
+    pub(crate) fn register(&self, id: i32) -> oneshot::Receiver<Reply> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("Pending replies lock poisoned")
+            .insert(
+                id, tx,
+            );
+        rx
+    }
+
+    /// Returns whether a request was waiting for the reply
+    pub(crate) fn resolve(&self, id: i32, reply: Reply) -> bool {
+        let tx = self
+            .pending
+            .lock()
+            .expect("Pending replies lock poisoned")
+            .remove(&id);
+        match tx {
+            Some(tx) => tx
+                .send(reply)
+                .is_ok(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn forget(&self, id: i32) {
+        self.pending
+            .lock()
+            .expect("Pending replies lock poisoned")
+            .remove(&id);
+    }
+
+    /// Waits for the reply to the request with `id` about `topics`, and splits
+    /// the topics in accepted and rejected ones.
+    pub(crate) async fn wait_for_topics(
+        &self,
+        id: i32,
+        rx: oneshot::Receiver<Reply>,
+        topics: Vec<Topic>,
+    ) -> (
+        Vec<Topic>,
+        Vec<RejectedTopic>,
+    ) {
+        match tokio::time::timeout(
+            REPLY_TIMEOUT,
+            rx,
+        )
+        .await
+        {
+            Ok(Ok(reply)) => split_topics_by_reply(
+                topics, &reply,
+            ),
+            _ => {
+                self.forget(id);
+                let rejected = topics
+                    .into_iter()
+                    .map(
+                        |topic| RejectedTopic {
+                            topic,
+                            error: TopicRequestError::NoReply,
+                        },
+                    )
+                    .collect();
+                (
+                    Vec::new(),
+                    rejected,
+                )
+            }
+        }
+    }
+}
+
+/// A rejection lists the rejected topics, if it doesn't list any of the
+/// requested topics all of them are considered rejected.
+fn split_topics_by_reply(
+    topics: Vec<Topic>,
+    reply: &Reply,
+) -> (
+    Vec<Topic>,
+    Vec<RejectedTopic>,
+) {
+    let LifecycleEventKind::SubscriptionFailed {
+        reason,
+    } = lifecycle::subscription_reply_event_kind(
+        reply.code,
+        &reply.message,
+    )
+    else {
+        return (
+            topics,
+            Vec::new(),
+        );
+    };
+
+    let mentioned_topics = lifecycle::topics_in_reply(
+        &topics,
+        &reply.message,
+    );
+    let (rejected, accepted) = topics
+        .into_iter()
+        .partition::<Vec<_>, _>(|topic| mentioned_topics.is_empty() || mentioned_topics.contains(topic));
+    let rejected = rejected
+        .into_iter()
+        .map(
+            |topic| RejectedTopic {
+                topic,
+                error: TopicRequestError::Rejected {
+                    reason: reason.clone(),
+                },
+            },
+        )
+        .collect();
+    (
+        accepted, rejected,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spot::ws::topic::DealsTopic;
+
+    #[tokio::test]
+    async fn rejected_topics_are_split_off() {
+        let pending_replies = PendingReplies::default();
+        let id = pending_replies.next_id();
+        let rx = pending_replies.register(id);
+        let topics = vec![
+            Topic::Deals(DealsTopic::new("BTCUSDT".to_string())),
+            Topic::Deals(DealsTopic::new("NOPEUSDT".to_string())),
+        ];
+
+        assert!(
+            pending_replies.resolve(
+                id,
+                Reply {
+                    code: 0,
+                    message: "Not Subscribed successfully! [spot@public.deals.v3.api@NOPEUSDT].  Reason： Blocked! ".to_string(),
+                },
+            )
+        );
+        let (accepted, rejected) = pending_replies
+            .wait_for_topics(
+                id,
+                rx,
+                topics.clone(),
+            )
+            .await;
+        assert_eq!(
+            accepted,
+            vec![topics[0].clone()]
+        );
+        assert_eq!(
+            rejected.len(),
+            1
+        );
+        assert_eq!(
+            rejected[0].topic,
+            topics[1]
+        );
+    }
+
+    #[test]
+    fn unknown_reply_is_ignored() {
+        let pending_replies = PendingReplies::default();
+        assert!(
+            !pending_replies.resolve(
+                42,
+                Reply {
+                    code: 0,
+                    message: "PONG".to_string(),
+                },
+            )
+        );
+    }
+}
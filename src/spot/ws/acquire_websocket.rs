@@ -1,14 +1,21 @@
 use crate::spot::{
     v3::{
-        create_user_data_stream::CreateUserDataStreamEndpoint,
         keep_alive_user_data_stream::{KeepAliveUserDataStreamEndpoint, KeepAliveUserDataStreamParams},
         ApiError,
     },
-    ws::{auth::WebsocketAuth, message, topic::Topic, Inner, MexcSpotWebsocketClient, SendableMessage, WebsocketEntry},
+    ws::{
+        ack,
+        auth::WebsocketAuth,
+        lifecycle::{self, LifecycleEventKind},
+        listen_key, message,
+        topic::Topic,
+        Inner, MexcSpotWebsocketClient, SendableMessage, WebsocketEntry,
+    },
     MexcSpotApiClientWithAuthentication,
 };
 use async_channel::Sender;
 use async_trait::async_trait;
+use chrono::Utc;
 use futures::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
@@ -19,7 +26,7 @@ use tokio_tungstenite::{
     tungstenite::{error::ProtocolError, Error, Message},
     MaybeTlsStream, WebSocketStream,
 };
-use tokio_util::sync::CancellationToken;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -93,6 +100,9 @@ pub(crate) enum AcquireWebsocketForTopicsError {
 
     #[error("Could not create datastream (listen key)")]
     CouldNotCreateDataStream(#[from] ApiError),
+
+    #[error("The client has been shut down")]
+    ClientShutDown,
 }
 
 #[async_trait]
@@ -116,6 +126,13 @@ impl AcquireWebsocketsForTopics for MexcSpotWebsocketClient {
             return Err(AcquireWebsocketForTopicsError::RequestedTopicsRequireAuthentication);
         }
 
+        if self
+            .tasks
+            .is_closed()
+        {
+            return Err(AcquireWebsocketForTopicsError::ClientShutDown);
+        }
+
         let mut inner = self
             .inner
             .write()
@@ -587,40 +604,39 @@ async fn create_private_websocket(this: Arc<MexcSpotWebsocketClient>, inner: &mu
         return Err(CreatePrivateWebsocketError::MaximumAmountOfTopicsForUserWillBeExceeded);
     }
 
+    let websocket_id = Uuid::new_v4();
     tracing::debug!("Creating listen key for private websocket...");
-    let spot_client_with_auth = MexcSpotApiClientWithAuthentication::new(
-        this.spot_api_endpoint
-            .as_ref()
-            .clone(),
-        auth.api_key
-            .clone(),
-        auth.secret_key
-            .clone(),
-    );
-    let user_data_stream_output = spot_client_with_auth
-        .create_user_data_stream()
+    let listen_key = this
+        .create_listen_key(
+            websocket_id,
+            &auth,
+        )
         .await?;
-    tracing::debug!(
-        "Listen key created: {}",
-        &user_data_stream_output.listen_key
-    );
 
     let endpoint_str = this
         .ws_endpoint
         .to_string();
     let ws_url = format!(
         "{}?listenKey={}",
-        endpoint_str, &user_data_stream_output.listen_key
+        endpoint_str, listen_key
     );
 
-    let (ws_stream, _) = tokio_tungstenite::connect_async(&ws_url).await?;
+    let (ws_stream, _) = match tokio_tungstenite::connect_async(&ws_url).await {
+        Ok(x) => x,
+        Err(err) => {
+            this.delete_listen_key(
+                websocket_id,
+                &auth,
+            )
+            .await;
+            return Err(err.into());
+        }
+    };
     let (ws_tx, ws_rx) = ws_stream.split();
     let (tx, rx) = async_channel::unbounded();
 
     let cancellation_token = CancellationToken::new();
 
-    let websocket_id = Uuid::new_v4();
-
     // Spawn all necessary tasks for this websocket...
     spawn_websocket_sender_task(
         this.clone(),
@@ -634,39 +650,39 @@ async fn create_private_websocket(this: Arc<MexcSpotWebsocketClient>, inner: &mu
         ws_rx,
         cancellation_token.clone(),
         websocket_id,
+        Some(
+            auth.api_key
+                .clone(),
+        ),
     );
     spawn_websocket_ping_task(
+        &this.tasks,
         tx.clone(),
         cancellation_token.clone(),
     );
     spawn_websocket_keepalive_task(
-        spot_client_with_auth,
-        user_data_stream_output
-            .listen_key
-            .clone(),
-        cancellation_token,
+        this.clone(),
+        this.spot_client_with_auth(&auth),
+        cancellation_token.clone(),
+        websocket_id,
     );
 
-    inner
-        .auth_to_listen_key_map
-        .insert(
-            auth.clone(),
-            user_data_stream_output
-                .listen_key
-                .clone(),
-        );
-
     let websocket_entry = WebsocketEntry {
         id: websocket_id,
         auth: Some(auth),
-        listen_key: Some(user_data_stream_output.listen_key),
         topics: Arc::new(RwLock::new(vec![])),
         message_tx: Arc::new(RwLock::new(tx)),
+        cancellation_token: Arc::new(RwLock::new(cancellation_token)),
     };
     let websocket_entry = Arc::new(websocket_entry);
     inner
         .websockets
         .push(websocket_entry.clone());
+    this.emit_lifecycle_event(
+        websocket_id,
+        Vec::new(),
+        LifecycleEventKind::Connected,
+    );
 
     Ok(websocket_entry)
 }
@@ -703,23 +719,30 @@ async fn create_public_websocket(this: Arc<MexcSpotWebsocketClient>, inner: &mut
         ws_rx,
         cancellation_token.clone(),
         websocket_id,
+        None,
     );
     spawn_websocket_ping_task(
+        &this.tasks,
         tx.clone(),
-        cancellation_token,
+        cancellation_token.clone(),
     );
 
     let websocket_entry = WebsocketEntry {
         id: websocket_id,
         auth: None,
-        listen_key: None,
         topics: Arc::new(RwLock::new(vec![])),
         message_tx: Arc::new(RwLock::new(tx)),
+        cancellation_token: Arc::new(RwLock::new(cancellation_token)),
     };
     let websocket_entry = Arc::new(websocket_entry);
     inner
         .websockets
         .push(websocket_entry.clone());
+    this.emit_lifecycle_event(
+        websocket_id,
+        Vec::new(),
+        LifecycleEventKind::Connected,
+    );
 
     Ok(websocket_entry)
 }
@@ -729,14 +752,26 @@ pub enum ReconnectWebsocketError {
     #[error("Unknown websocket")]
     UnknownWebsocket,
 
+    #[error("The client has been shut down")]
+    ClientShutDown,
+
     #[error("Tungestenite error: {0}")]
     TungesteniteError(#[from] tokio_tungstenite::tungstenite::Error),
 
     #[error("Resubscribe send error")]
     ResubscribeSendError(#[from] async_channel::SendError<SendableMessage>),
+
+    #[error("Could not create datastream (listen key)")]
+    CouldNotCreateDataStream(#[from] ApiError),
 }
 
 async fn reconnect_websocket(this: Arc<MexcSpotWebsocketClient>, websocket_id: Uuid) -> Result<(), ReconnectWebsocketError> {
+    if this
+        .tasks
+        .is_closed()
+    {
+        return Err(ReconnectWebsocketError::ClientShutDown);
+    }
     tracing::debug!(
         "Reconnecting websocket with id...: {}",
         websocket_id
@@ -750,12 +785,46 @@ async fn reconnect_websocket(this: Arc<MexcSpotWebsocketClient>, websocket_id: U
         .iter()
         .find(|ws| ws.id == websocket_id)
         .ok_or(ReconnectWebsocketError::UnknownWebsocket)?;
+    let topics = websocket
+        .topics
+        .read()
+        .await
+        .clone();
+    this.emit_lifecycle_event(
+        websocket_id,
+        topics.clone(),
+        LifecycleEventKind::Reconnecting,
+    );
 
     let endpoint_str = this
         .ws_endpoint
         .to_string();
-    let ws_url = match &websocket.listen_key {
-        Some(listen_key) => {
+    let ws_url = match &websocket.auth {
+        Some(auth) => {
+            // Reconnect onto a new listen key when the old one expired or was
+            // invalidated
+            let listen_key = match this
+                .listen_keys
+                .get(websocket_id)
+            {
+                Some(info) if !info.needs_rotation(Utc::now()) => info.listen_key,
+                _ => {
+                    let listen_key = this
+                        .create_listen_key(
+                            websocket_id,
+                            auth,
+                        )
+                        .await?;
+                    this.emit_lifecycle_event(
+                        websocket_id,
+                        topics.clone(),
+                        LifecycleEventKind::ListenKeyRotated {
+                            listen_key: listen_key.clone(),
+                        },
+                    );
+                    listen_key
+                }
+            };
             format!(
                 "{}?listenKey={}",
                 endpoint_str, listen_key
@@ -789,33 +858,27 @@ async fn reconnect_websocket(this: Arc<MexcSpotWebsocketClient>, websocket_id: U
         ws_rx,
         cancellation_token.clone(),
         websocket_id,
+        websocket
+            .auth
+            .as_ref()
+            .map(
+                |auth| {
+                    auth.api_key
+                        .clone()
+                },
+            ),
     );
     spawn_websocket_ping_task(
+        &this.tasks,
         tx.clone(),
         cancellation_token.clone(),
     );
-    if let Some(listen_key) = &websocket.listen_key {
-        let spot_client_with_auth = MexcSpotApiClientWithAuthentication::new(
-            this.spot_api_endpoint
-                .as_ref()
-                .clone(),
-            websocket
-                .auth
-                .as_ref()
-                .expect("Listen key set but not auth?")
-                .api_key
-                .clone(),
-            websocket
-                .auth
-                .as_ref()
-                .expect("Listen key set but not auth?")
-                .secret_key
-                .clone(),
-        );
+    if let Some(auth) = &websocket.auth {
         spawn_websocket_keepalive_task(
-            spot_client_with_auth,
-            listen_key.clone(),
+            this.clone(),
+            this.spot_client_with_auth(auth),
             cancellation_token.clone(),
+            websocket_id,
         );
     }
 
@@ -824,17 +887,29 @@ async fn reconnect_websocket(this: Arc<MexcSpotWebsocketClient>, websocket_id: U
         .write()
         .await;
     *message_tx = tx;
+    *websocket
+        .cancellation_token
+        .write()
+        .await = cancellation_token;
+
+    this.emit_lifecycle_event(
+        websocket_id,
+        topics.clone(),
+        LifecycleEventKind::Connected,
+    );
 
-    let topics = websocket
-        .topics
-        .read()
-        .await;
     if !topics.is_empty() {
         let topic_strs = topics
             .iter()
             .map(|topic| topic.to_topic_subscription_string())
             .collect();
-        let sendable_message = SendableMessage::Subscription(topic_strs);
+        // Nobody waits for the reply, it only shows up as a lifecycle event
+        let sendable_message = SendableMessage::Subscription {
+            params: topic_strs,
+            id: this
+                .pending_replies
+                .next_id(),
+        };
         message_tx
             .send(sendable_message)
             .await?;
@@ -849,18 +924,31 @@ async fn reconnect_websocket(this: Arc<MexcSpotWebsocketClient>, websocket_id: U
 }
 
 fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>, rx: async_channel::Receiver<SendableMessage>, cancellation_token: CancellationToken, websocket_id: Uuid) {
-    tokio::spawn(
+    let this = this.task_handle();
+    let tasks = this
+        .tasks
+        .clone();
+    tasks.spawn(
         async move {
             loop {
                 tokio::select! {
+                    biased;
                     _ = cancellation_token.cancelled() => {
+                        // Flush whatever is still queued (e.g. unsubscriptions) before closing
+                        while let Ok(message) = rx.try_recv() {
+                            let json = serde_json::to_string(&message).expect("Failed to serialize message");
+                            if ws_tx.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
+                        let _ = ws_tx.send(Message::Close(None)).await;
                         break;
                     }
                     message_result = rx.recv() => {
                         let message = match message_result {
                             Ok(x) => x,
                             Err(err) => {
-                                cancellation_token.cancel();
+                                disconnect(&this, &cancellation_token, websocket_id).await;
                                 tracing::error!("Error receiving message from channel: {}", err);
                                 break;
                             }
@@ -872,7 +960,7 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
                             Ok(_) => {}
                             Err(err) => match err {
                                 Error::ConnectionClosed => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Failed to send message to websocket because the connection was closed");
                                     if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                         tracing::error!("Failed to reconnect websocket: {}", err);
@@ -880,7 +968,7 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
                                     break;
                                 }
                                 Error::AlreadyClosed => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Failed to send message to websocket because the connection was already closed");
                                     if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                         tracing::error!("Failed to reconnect websocket: {}", err);
@@ -889,7 +977,7 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
                                 }
                                 Error::Protocol(protocol_err) => match protocol_err {
                                     ProtocolError::ResetWithoutClosingHandshake => {
-                                        cancellation_token.cancel();
+                                        disconnect(&this, &cancellation_token, websocket_id).await;
                                         tracing::error!("Failed to send message to websocket because the connection was reset without closing handshake");
                                         if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                             tracing::error!("Failed to reconnect websocket: {}", err);
@@ -897,7 +985,7 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
                                         break;
                                     }
                                     _ => {
-                                        cancellation_token.cancel();
+                                        disconnect(&this, &cancellation_token, websocket_id).await;
                                         tracing::error!(
                                             "Protocol error sending message to websocket: {}",
                                             protocol_err
@@ -906,7 +994,7 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
                                     }
                                 },
                                 _ => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Error sending message to websocket: {}", err);
                                     break;
                                 }
@@ -919,11 +1007,12 @@ fn spawn_websocket_sender_task(this: Arc<MexcSpotWebsocketClient>, mut ws_tx: Sp
     );
 }
 
-fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>, cancellation_token: CancellationToken, websocket_id: Uuid) {
-    let broadcast_tx = this
-        .broadcast_tx
+fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>, cancellation_token: CancellationToken, websocket_id: Uuid, api_key: Option<String>) {
+    let this = this.task_handle();
+    let tasks = this
+        .tasks
         .clone();
-    tokio::spawn(
+    tasks.spawn(
         async move {
             loop {
                 tokio::select! {
@@ -934,7 +1023,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                         let message_result = match message_result_opt {
                             Some(x) => x,
                             None => {
-                                cancellation_token.cancel();
+                                disconnect(&this, &cancellation_token, websocket_id).await;
                                 break;
                             }
                         };
@@ -942,7 +1031,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                             Ok(message) => message,
                             Err(err) => match err {
                                 Error::ConnectionClosed => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Failed to receive message from websocket because the connection was closed");
                                     if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                         tracing::error!("Failed to reconnect websocket: {}", err);
@@ -950,7 +1039,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                                     break;
                                 }
                                 Error::AlreadyClosed => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Failed to receive message from websocket because the connection was already closed");
                                     if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                         tracing::error!("Failed to reconnect websocket: {}", err);
@@ -959,7 +1048,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                                 }
                                 Error::Protocol(protocol_err) => match protocol_err {
                                     ProtocolError::ResetWithoutClosingHandshake => {
-                                        cancellation_token.cancel();
+                                        disconnect(&this, &cancellation_token, websocket_id).await;
                                         tracing::error!("Failed to receive message from websocket because the connection was reset without closing handshake");
                                         if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
                                             tracing::error!("Failed to reconnect websocket: {}", err);
@@ -967,7 +1056,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                                         break;
                                     }
                                     _ => {
-                                        cancellation_token.cancel();
+                                        disconnect(&this, &cancellation_token, websocket_id).await;
                                         tracing::error!(
                                             "Protocol error receiving message from websocket: {}",
                                             protocol_err
@@ -976,36 +1065,66 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                                     }
                                 },
                                 _ => {
-                                    cancellation_token.cancel();
+                                    disconnect(&this, &cancellation_token, websocket_id).await;
                                     tracing::error!("Error receiving message from websocket: {}", err);
                                     break;
                                 }
                             },
                         };
+                        if let Some(recorder) = &this.recorder {
+                            if let Err(err) = recorder.record(websocket_id, &message) {
+                                tracing::warn!("Failed to record websocket frame: {}", err);
+                            }
+                        }
 
-                        let mex_msg = match message {
+                        let (channel, mex_msg) = match message {
                             Message::Text(text) => {
                                 let raw_message = match serde_json::from_str::<message::RawMessage>(&text) {
                                     Ok(x) => x,
                                     Err(err) => {
-                                        cancellation_token.cancel();
+                                        disconnect(&this, &cancellation_token, websocket_id).await;
                                         tracing::error!("Failed to deserialize message: {}\njson: {}", err, &text);
                                         break;
                                     }
                                 };
 
+                                if let message::RawMessage::IdCodeMessage(id_code_message) = &raw_message {
+                                    this.pending_replies.resolve(
+                                        id_code_message.id,
+                                        ack::Reply {
+                                            code: id_code_message.code,
+                                            message: id_code_message.message.clone(),
+                                        },
+                                    );
+                                    let topics = lifecycle::topics_in_reply(
+                                        &this.websocket_topics(websocket_id).await,
+                                        &id_code_message.message,
+                                    );
+                                    if !topics.is_empty() {
+                                        this.emit_lifecycle_event(
+                                            websocket_id,
+                                            topics,
+                                            lifecycle::subscription_reply_event_kind(id_code_message.code, &id_code_message.message),
+                                        );
+                                    }
+                                    continue;
+                                }
+
                                 let Ok(mexc_message) = message::Message::try_from(&raw_message) else {
                                     tracing::trace!("Received unrecognized message: {raw_message:?}");
                                     continue;
                                 };
-                                mexc_message
+                                let message::RawMessage::ChannelMessage(raw_channel_message) = raw_message else {
+                                    continue;
+                                };
+                                (raw_channel_message.channel, mexc_message)
                             },
                             Message::Binary(proto) => {
-                                let Ok(mexc_message) = message::Message::from_proto(&proto)  else {
+                                let Ok(channel_and_message) = message::Message::from_proto_with_channel(&proto)  else {
                                     tracing::trace!("Fail to parse binary message: {}", hex::encode(&proto));
                                     continue;
                                 };
-                                mexc_message
+                                channel_and_message
                             }
                             _ => {
                                 tracing::debug!("Received non-text message: {:?}", message);
@@ -1013,16 +1132,7 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
                             }
                         };
 
-                         match broadcast_tx.send(Arc::new(mex_msg)) {
-                            Ok(_) => {}
-                            Err(err) => {
-                                // cancellation_token.cancel();
-                                tracing::error!("Failed to broadcast message: {}", err);
-                                break;
-                            }
-                        }
-
-
+                        dispatch_message(&this, &channel, api_key.as_deref(), mex_msg).await;
                     }
                 }
             }
@@ -1030,8 +1140,8 @@ fn spawn_websocket_receiver_task(this: Arc<MexcSpotWebsocketClient>, mut ws_rx:
     );
 }
 
-fn spawn_websocket_ping_task(sender: Sender<SendableMessage>, cancellation_token: CancellationToken) {
-    tokio::spawn(
+fn spawn_websocket_ping_task(tasks: &TaskTracker, sender: Sender<SendableMessage>, cancellation_token: CancellationToken) {
+    tasks.spawn(
         async move {
             loop {
                 tokio::select! {
@@ -1054,27 +1164,62 @@ fn spawn_websocket_ping_task(sender: Sender<SendableMessage>, cancellation_token
     );
 }
 
-fn spawn_websocket_keepalive_task(spot_client_with_auth: MexcSpotApiClientWithAuthentication, listen_key: String, cancellation_token: CancellationToken) {
-    tokio::spawn(
+fn spawn_websocket_keepalive_task(this: Arc<MexcSpotWebsocketClient>, spot_client_with_auth: MexcSpotApiClientWithAuthentication, cancellation_token: CancellationToken, websocket_id: Uuid) {
+    let this = this.task_handle();
+    let tasks = this
+        .tasks
+        .clone();
+    tasks.spawn(
         async move {
+            let mut interval = listen_key::KEEPALIVE_INTERVAL;
             loop {
                 tokio::select! {
                     _ = cancellation_token.cancelled() => {
                         break;
                     }
-                    _ = tokio::time::sleep(std::time::Duration::from_secs(60 * 30)) => {
+                    _ = tokio::time::sleep(interval) => {
+                        let Some(info) = this.listen_keys.get(websocket_id) else {
+                            break;
+                        };
+                        // Reconnecting creates a new listen key
+                        if info.needs_rotation(Utc::now()) {
+                            tracing::debug!("Rotating listen key {} of websocket {}", info.listen_key, websocket_id);
+                            disconnect(&this, &cancellation_token, websocket_id).await;
+                            if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
+                                tracing::error!("Failed to reconnect websocket with a new listen key: {}", err);
+                            }
+                            break;
+                        }
+
                         match spot_client_with_auth
                             .keep_alive_user_data_stream(KeepAliveUserDataStreamParams {
-                                listen_key: &listen_key,
+                                listen_key: &info.listen_key,
                             })
                             .await
                         {
-                            Ok(_) => {}
-                            Err(err) => {
-                                cancellation_token.cancel();
-                                tracing::error!("Failed to keep alive user data stream: {}", err);
+                            Ok(_) => {
+                                interval = listen_key::KEEPALIVE_INTERVAL;
+                                this.listen_keys.renewed(websocket_id, Utc::now());
+                                let topics = this.websocket_topics(websocket_id).await;
+                                this.emit_lifecycle_event(
+                                    websocket_id,
+                                    topics,
+                                    LifecycleEventKind::ListenKeyRenewed { listen_key: info.listen_key.clone() },
+                                );
+                            }
+                            Err(err) if listen_key::is_invalidated(&err) => {
+                                tracing::warn!("Listen key {} was invalidated: {}", info.listen_key, err);
+                                this.listen_keys.remove(websocket_id);
+                                disconnect(&this, &cancellation_token, websocket_id).await;
+                                if let Err(err) = reconnect_websocket(this.clone(), websocket_id).await {
+                                    tracing::error!("Failed to reconnect websocket with a new listen key: {}", err);
+                                }
                                 break;
                             }
+                            Err(err) => {
+                                tracing::warn!("Failed to keep alive user data stream, retrying: {}", err);
+                                interval = listen_key::KEEPALIVE_RETRY_INTERVAL;
+                            }
                         }
                     }
                 }
@@ -1082,3 +1227,46 @@ fn spawn_websocket_keepalive_task(spot_client_with_auth: MexcSpotApiClientWithAu
         },
     );
 }
+
+/// Hands a received message to the topic streams, bounded streams and the
+/// broadcast. Replays go through here as well.
+pub(crate) async fn dispatch_message(this: &MexcSpotWebsocketClient, channel: &str, api_key: Option<&str>, message: message::Message) {
+    let message = Arc::new(message);
+    this.topic_routes
+        .route(
+            channel, api_key, &message,
+        );
+    this.bounded_subscribers
+        .dispatch(&message)
+        .await;
+
+    // Nobody listening on the broadcast is fine, typed topic streams may be
+    // the only consumers.
+    if this
+        .broadcast_tx
+        .send(message)
+        .is_err()
+    {
+        tracing::trace!(
+            "No broadcast receivers for message on channel {}",
+            channel
+        );
+    }
+}
+
+/// Stops all tasks of the connection, notifying lifecycle subscribers the first
+/// time.
+async fn disconnect(this: &MexcSpotWebsocketClient, cancellation_token: &CancellationToken, websocket_id: Uuid) {
+    if cancellation_token.is_cancelled() {
+        return;
+    }
+    cancellation_token.cancel();
+    let topics = this
+        .websocket_topics(websocket_id)
+        .await;
+    this.emit_lifecycle_event(
+        websocket_id,
+        topics,
+        LifecycleEventKind::Disconnected,
+    );
+}
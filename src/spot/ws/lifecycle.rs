@@ -0,0 +1,162 @@
+use crate::spot::ws::{topic::Topic, MexcSpotWebsocketClient};
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct LifecycleEvent {
+    pub websocket_id: Uuid,
+    /// The topics affected by the event. For connection events these are all
+    /// topics of the websocket.
+    pub topics: Vec<Topic>,
+    pub kind: LifecycleEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleEventKind {
+    Connected,
+    Disconnected,
+    Reconnecting,
+    SubscriptionAcked,
+    SubscriptionFailed {
+        reason: String,
+    },
+    ListenKeyRenewed {
+        listen_key: String,
+    },
+    /// The websocket reconnected onto a new listen key
+    ListenKeyRotated {
+        listen_key: String,
+    },
+}
+
+pub trait Lifecycle {
+    fn lifecycle_events<'a>(self: Arc<Self>) -> BoxStream<'a, LifecycleEvent>;
+}
+
+impl Lifecycle for MexcSpotWebsocketClient {
+    fn lifecycle_events<'a>(self: Arc<Self>) -> BoxStream<'a, LifecycleEvent> {
+        let mut rx = self
+            .lifecycle_tx
+            .subscribe();
+        let stream = async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => yield event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Lifecycle event stream lagged behind, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        };
+        stream.boxed()
+    }
+}
+
+impl MexcSpotWebsocketClient {
+    pub(crate) fn emit_lifecycle_event(&self, websocket_id: Uuid, topics: Vec<Topic>, kind: LifecycleEventKind) {
+        tracing::debug!(
+            "Websocket {} lifecycle event: {:?}",
+            websocket_id,
+            kind
+        );
+        // Nobody listening is fine
+        let _ = self
+            .lifecycle_tx
+            .send(
+                LifecycleEvent {
+                    websocket_id,
+                    topics,
+                    kind,
+                },
+            );
+    }
+
+    pub(crate) async fn websocket_topics(&self, websocket_id: Uuid) -> Vec<Topic> {
+        let websocket = self
+            .inner
+            .read()
+            .await
+            .websockets
+            .iter()
+            .find(|websocket| websocket.id == websocket_id)
+            .cloned();
+        let Some(websocket) = websocket else {
+            return Vec::new();
+        };
+        let topics = websocket
+            .topics
+            .read()
+            .await
+            .clone();
+        topics
+    }
+}
+
+/// Interprets the `{"id":0,"code":0,"msg":"..."}` reply to a (un)subscription.
+/// The message lists the topics, and is prefixed with `Not Subscribed
+/// successfully!` when (some of) the topics were rejected.
+pub(crate) fn subscription_reply_event_kind(code: i32, message: &str) -> LifecycleEventKind {
+    if code != 0 || message.starts_with("Not Subscribed successfully") {
+        LifecycleEventKind::SubscriptionFailed {
+            reason: message.to_string(),
+        }
+    } else {
+        LifecycleEventKind::SubscriptionAcked
+    }
+}
+
+/// The topics from `topics` that are mentioned in the reply message.
+pub(crate) fn topics_in_reply(topics: &[Topic], message: &str) -> Vec<Topic> {
+    topics
+        .iter()
+        .filter(
+            |topic| {
+                message
+                    .split(|c: char| c == ',' || c == '[' || c == ']' || c.is_whitespace())
+                    .any(|part| part == topic.to_topic_subscription_string())
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spot::ws::topic::DealsTopic;
+
+    #[test]
+    fn subscription_reply() {
+        let topics = vec![
+            Topic::Deals(DealsTopic::new("BTCUSDT".to_string())),
+            Topic::Deals(DealsTopic::new("ETHUSDT".to_string())),
+        ];
+
+        let message = "spot@public.deals.v3.api@BTCUSDT";
+        assert_eq!(
+            subscription_reply_event_kind(0, message),
+            LifecycleEventKind::SubscriptionAcked
+        );
+        assert_eq!(
+            topics_in_reply(&topics, message),
+            vec![topics[0].clone()]
+        );
+
+        let message = "Not Subscribed successfully! [spot@public.deals.v3.api@ETHUSDT].  Reason： Blocked! ";
+        assert!(
+            matches!(
+                subscription_reply_event_kind(0, message),
+                LifecycleEventKind::SubscriptionFailed { .. }
+            )
+        );
+        assert_eq!(
+            topics_in_reply(&topics, message),
+            vec![topics[1].clone()]
+        );
+
+        assert!(topics_in_reply(&topics, "PONG").is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::spot::{
+    v3::{
+        close_user_data_stream::{CloseUserDataStreamEndpoint, CloseUserDataStreamParams},
+        create_user_data_stream::CreateUserDataStreamEndpoint,
+        ApiError, ApiResult, ErrorCode,
+    },
+    ws::{auth::WebsocketAuth, lifecycle::LifecycleEventKind, MexcSpotWebsocketClient},
+    MexcSpotApiClientWithAuthentication,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// A listen key expires 60 minutes after the last keepalive.
+pub(crate) const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60 * 30);
+
+/// Retry interval for keepalives that failed for reasons other than the listen
+/// key being invalid, e.g. a network error.
+pub(crate) const KEEPALIVE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A listen key is valid for at most 24 hours, rotate it well before that.
+const ROTATE_AFTER: chrono::Duration = chrono::Duration::hours(23);
+
+#[derive(Debug, Clone)]
+pub struct ListenKeyInfo {
+    pub websocket_id: Uuid,
+    pub api_key: String,
+    pub listen_key: String,
+    pub created_at: DateTime<Utc>,
+    pub renewed_at: DateTime<Utc>,
+}
+
+impl ListenKeyInfo {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        std::cmp::min(
+            self.renewed_at + chrono::Duration::minutes(60),
+            self.created_at + chrono::Duration::hours(24),
+        )
+    }
+
+    pub(crate) fn needs_rotation(&self, now: DateTime<Utc>) -> bool {
+        now >= self.created_at + ROTATE_AFTER || now >= self.expires_at()
+    }
+}
+
+/// The listen keys of the private websockets, one per websocket.
+#[derive(Debug, Default)]
+pub(crate) struct ListenKeyManager {
+    listen_keys: std::sync::Mutex<HashMap<Uuid, ListenKeyInfo>>,
+}
+
+impl ListenKeyManager {
+    pub(crate) fn get(&self, websocket_id: Uuid) -> Option<ListenKeyInfo> {
+        self.listen_keys
+            .lock()
+            .expect("Listen keys lock poisoned")
+            .get(&websocket_id)
+            .cloned()
+    }
+
+    pub(crate) fn list(&self) -> Vec<ListenKeyInfo> {
+        self.listen_keys
+            .lock()
+            .expect("Listen keys lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the listen key that was replaced
+    pub(crate) fn insert(&self, info: ListenKeyInfo) -> Option<ListenKeyInfo> {
+        self.listen_keys
+            .lock()
+            .expect("Listen keys lock poisoned")
+            .insert(
+                info.websocket_id,
+                info,
+            )
+    }
+
+    pub(crate) fn renewed(&self, websocket_id: Uuid, now: DateTime<Utc>) {
+        if let Some(info) = self
+            .listen_keys
+            .lock()
+            .expect("Listen keys lock poisoned")
+            .get_mut(&websocket_id)
+        {
+            info.renewed_at = now;
+        }
+    }
+
+    pub(crate) fn remove(&self, websocket_id: Uuid) -> Option<ListenKeyInfo> {
+        self.listen_keys
+            .lock()
+            .expect("Listen keys lock poisoned")
+            .remove(&websocket_id)
+    }
+}
+
+/// Whether a failed keepalive means the listen key is gone, rather than the
+/// request failing. Unknown and expired listen keys are rejected as an invalid
+/// parameter, the listen key being the only one.
+pub(crate) fn is_invalidated(err: &ApiError) -> bool {
+    matches!(
+        err,
+        ApiError::ErrorResponse(response) if response.code == ErrorCode::YourInputParamIsInvalidOrParameterError
+    )
+}
+
+#[async_trait]
+pub trait ListenKeys {
+    async fn listen_keys(self: Arc<Self>) -> Vec<ListenKeyInfo>;
+
+    /// Closes all private websockets and deletes their listen keys.
+    async fn close_listen_keys(self: Arc<Self>);
+}
+
+#[async_trait]
+impl ListenKeys for MexcSpotWebsocketClient {
+    async fn listen_keys(self: Arc<Self>) -> Vec<ListenKeyInfo> {
+        self.listen_keys
+            .list()
+    }
+
+    async fn close_listen_keys(self: Arc<Self>) {
+        let websocket_entries = {
+            let mut inner = self
+                .inner
+                .write()
+                .await;
+            let (private, public) = inner
+                .websockets
+                .drain(..)
+                .partition::<Vec<_>, _>(
+                    |websocket_entry| {
+                        websocket_entry
+                            .auth
+                            .is_some()
+                    },
+                );
+            inner.websockets = public;
+            private
+        };
+
+        for websocket_entry in websocket_entries {
+            let topics = websocket_entry
+                .topics
+                .read()
+                .await
+                .clone();
+            websocket_entry
+                .cancellation_token
+                .read()
+                .await
+                .cancel();
+            self.emit_lifecycle_event(
+                websocket_entry.id,
+                topics,
+                LifecycleEventKind::Disconnected,
+            );
+            if let Some(auth) = &websocket_entry.auth {
+                self.delete_listen_key(
+                    websocket_entry.id,
+                    auth,
+                )
+                .await;
+            }
+        }
+    }
+}
+
+impl MexcSpotWebsocketClient {
+    pub(crate) fn spot_client_with_auth(&self, auth: &WebsocketAuth) -> MexcSpotApiClientWithAuthentication {
+        MexcSpotApiClientWithAuthentication::new(
+            self.spot_api_endpoint
+                .as_ref()
+                .clone(),
+            auth.api_key
+                .clone(),
+            auth.secret_key
+                .clone(),
+        )
+    }
+
+    /// Creates a new listen key for the websocket, deleting the one it replaces.
+    pub(crate) async fn create_listen_key(&self, websocket_id: Uuid, auth: &WebsocketAuth) -> ApiResult<String> {
+        let spot_client_with_auth = self.spot_client_with_auth(auth);
+        let output = spot_client_with_auth
+            .create_user_data_stream()
+            .await?;
+        tracing::debug!(
+            "Listen key created for websocket {}: {}",
+            websocket_id,
+            &output.listen_key
+        );
+
+        let now = Utc::now();
+        let replaced = self
+            .listen_keys
+            .insert(
+                ListenKeyInfo {
+                    websocket_id,
+                    api_key: auth
+                        .api_key
+                        .clone(),
+                    listen_key: output
+                        .listen_key
+                        .clone(),
+                    created_at: now,
+                    renewed_at: now,
+                },
+            );
+        if let Some(replaced) = replaced {
+            // The old key may already be gone, which is fine
+            if let Err(err) = spot_client_with_auth
+                .close_user_data_stream(
+                    CloseUserDataStreamParams {
+                        listen_key: &replaced.listen_key,
+                    },
+                )
+                .await
+            {
+                tracing::debug!(
+                    "Failed to delete replaced listen key {}: {}",
+                    replaced.listen_key,
+                    err
+                );
+            }
+        }
+
+        Ok(output.listen_key)
+    }
+
+    pub(crate) async fn delete_listen_key(&self, websocket_id: Uuid, auth: &WebsocketAuth) {
+        let Some(info) = self
+            .listen_keys
+            .remove(websocket_id)
+        else {
+            return;
+        };
+        if let Err(err) = self
+            .spot_client_with_auth(auth)
+            .close_user_data_stream(
+                CloseUserDataStreamParams {
+                    listen_key: &info.listen_key,
+                },
+            )
+            .await
+        {
+            tracing::warn!(
+                "Failed to delete listen key {}: {}",
+                info.listen_key,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        let created_at = Utc::now();
+        let mut info = ListenKeyInfo {
+            websocket_id: Uuid::new_v4(),
+            api_key: "api_key".to_string(),
+            listen_key: "listen_key".to_string(),
+            created_at,
+            renewed_at: created_at,
+        };
+        assert!(!info.needs_rotation(created_at + chrono::Duration::minutes(30)));
+        // Not renewed in time
+        assert!(info.needs_rotation(created_at + chrono::Duration::minutes(61)));
+
+        info.renewed_at = created_at + chrono::Duration::hours(22) + chrono::Duration::minutes(30);
+        assert!(!info.needs_rotation(created_at + chrono::Duration::hours(22) + chrono::Duration::minutes(45)));
+        assert!(info.needs_rotation(created_at + chrono::Duration::hours(23)));
+    }
+
+    #[test]
+    fn replaced_listen_key_is_returned() {
+        let listen_keys = ListenKeyManager::default();
+        let websocket_id = Uuid::new_v4();
+        let now = Utc::now();
+        let info = |listen_key: &str| ListenKeyInfo {
+            websocket_id,
+            api_key: "api_key".to_string(),
+            listen_key: listen_key.to_string(),
+            created_at: now,
+            renewed_at: now,
+        };
+
+        assert!(
+            listen_keys
+                .insert(info("first"))
+                .is_none()
+        );
+        assert_eq!(
+            listen_keys
+                .insert(info("second"))
+                .unwrap()
+                .listen_key,
+            "first"
+        );
+        assert_eq!(
+            listen_keys
+                .list()
+                .len(),
+            1
+        );
+        assert!(
+            listen_keys
+                .remove(websocket_id)
+                .is_some()
+        );
+        assert!(
+            listen_keys
+                .get(websocket_id)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn only_unknown_listen_keys_are_invalidated() {
+        let response = |code| {
+            ApiError::ErrorResponse(
+                crate::spot::v3::ErrorResponse {
+                    code,
+                    msg: String::new(),
+                    _extend: None,
+                },
+            )
+        };
+        assert!(is_invalidated(&response(ErrorCode::YourInputParamIsInvalidOrParameterError)));
+        assert!(!is_invalidated(&response(ErrorCode::TooManyRequests)));
+        assert!(!is_invalidated(&ApiError::MalformedRequest));
+        assert!(!is_invalidated(&ApiError::InternalServerError));
+    }
+}
@@ -19,7 +19,7 @@ pub mod deals;
 pub mod kline;
 pub mod orderbook_update;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Message {
     AccountDeals(AccountDealsMessage),
     AccountUpdate(AccountUpdateMessage),
@@ -32,12 +32,31 @@ pub enum Message {
 
 impl Message {
     pub fn from_proto(data: &[u8]) -> Result<Message, ()> {
+        Self::from_proto_with_channel(data).map(|(_, message)| message)
+    }
+
+    /// Same as [`Message::from_proto`], but also returns the channel the
+    /// message was pushed on.
+    pub(crate) fn from_proto_with_channel(
+        data: &[u8],
+    ) -> Result<
+        (
+            String,
+            Message,
+        ),
+        (),
+    > {
         let wrapper = PushDataV3ApiWrapper::decode(data).or_else(|_| Err(()))?;
         let Some(body) = wrapper.body else {
             return Err(());
         };
         match body {
-            PublicAggreDepths(depth) => Ok(Message::PublicAggreDepthsV3Api(depth)),
+            PublicAggreDepths(depth) => Ok(
+                (
+                    wrapper.channel,
+                    Message::PublicAggreDepthsV3Api(depth),
+                ),
+            ),
             _ => Err(()),
         }
     }
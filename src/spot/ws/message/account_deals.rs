@@ -23,7 +23,7 @@ pub(crate) struct RawAccountDealsData {
     pub N: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AccountDealsMessage {
     pub asset: String,
     pub trade_type: OrderSide,
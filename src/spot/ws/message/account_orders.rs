@@ -1,3 +1,4 @@
+pub use crate::spot::v3::enums::StopLimitDirection;
 use crate::spot::{
     v3::enums::{OrderSide, OrderStatus},
     ws::message::{RawChannelMessage, RawChannelMessageData},
@@ -141,13 +142,13 @@ pub(crate) fn channel_message_to_account_orders_message(message: &RawChannelMess
     Ok(message)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AccountOrdersMessage {
     LimitOrMarket(LimitOrMarketAccountOrdersMessage),
     StopLimit(StopLimitAccountOrdersMessage),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LimitOrMarketAccountOrdersMessage {
     pub symbol: String,
     pub remain_amount: Decimal,
@@ -179,7 +180,7 @@ pub enum OrderKind {
     StopLimit = 100,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StopLimitAccountOrdersMessage {
     pub symbol: String,
     pub commission_asset: String,
@@ -195,13 +196,6 @@ pub struct StopLimitAccountOrdersMessage {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde_repr::Deserialize_repr, serde_repr::Serialize_repr, num_derive::FromPrimitive)]
-#[repr(u8)]
-pub enum StopLimitDirection {
-    PriceHigherThanTriggerPrice = 0,
-    PriceLowerThanTriggerPrice = 1,
-}
-
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde_repr::Deserialize_repr, serde_repr::Serialize_repr, num_derive::FromPrimitive)]
 #[repr(u8)]
 pub enum StopLimitOrderState {
@@ -45,7 +45,7 @@ pub(crate) struct RawAccountUpdateData {
     pub o: ChangedType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AccountUpdateMessage {
     pub asset: String,
     pub change_time: DateTime<Utc>,
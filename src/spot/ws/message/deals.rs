@@ -18,12 +18,12 @@ pub(crate) struct RawSpotDealData {
     pub trade_type: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpotDealsMessage {
     pub deals: Vec<SpotDeal>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpotDeal {
     pub symbol: String,
     pub price: Decimal,
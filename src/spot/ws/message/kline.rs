@@ -117,7 +117,7 @@ impl From<KlineInterval> for KlineIntervalTopic {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SpotKlineMessage {
     pub symbol: String,
     pub volume: Decimal,
@@ -14,7 +14,7 @@ pub(crate) struct RawOrderData {
     pub quantity: Decimal,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OrderbookUpdateMessage {
     pub symbol: String,
     pub version: u64,
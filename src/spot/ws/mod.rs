@@ -1,32 +1,52 @@
-use crate::spot::{
-    ws::{auth::WebsocketAuth, endpoint::MexcWebsocketEndpoint, topic::Topic},
-    MexcSpotApiEndpoint,
+use crate::{
+    recording::Recorder,
+    spot::{
+        ws::{
+            ack::PendingReplies,
+            auth::WebsocketAuth,
+            endpoint::MexcWebsocketEndpoint,
+            listen_key::ListenKeyManager,
+            shutdown::DropGuard,
+            stream::BoundedSubscribers,
+            topic::Topic,
+            topic_stream::{TopicRoutes, DEFAULT_TOPIC_CHANNEL_CAPACITY},
+        },
+        MexcSpotApiEndpoint,
+    },
 };
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use uuid::Uuid;
 
+pub mod ack;
 pub mod acquire_websocket;
 pub mod auth;
 pub mod endpoint;
+pub mod lifecycle;
+pub mod listen_key;
 pub mod message;
+pub mod replay;
+pub mod shutdown;
 pub mod stream;
 pub mod subscribe;
 pub mod topic;
+pub mod topic_stream;
 pub mod unsubscribe;
 
+const DEFAULT_BROADCAST_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub struct WebsocketEntry {
     pub id: Uuid,
     pub auth: Option<WebsocketAuth>,
-    pub listen_key: Option<String>,
     pub topics: Arc<RwLock<Vec<Topic>>>,
     pub message_tx: Arc<RwLock<async_channel::Sender<SendableMessage>>>,
+    pub cancellation_token: Arc<RwLock<CancellationToken>>,
 }
 
 #[derive(Debug)]
 struct Inner {
-    pub auth_to_listen_key_map: HashMap<WebsocketAuth, String>,
     pub websockets: Vec<Arc<WebsocketEntry>>,
 }
 
@@ -36,17 +56,29 @@ pub struct MexcSpotWebsocketClient {
     ws_endpoint: Arc<MexcWebsocketEndpoint>,
     spot_api_endpoint: Arc<MexcSpotApiEndpoint>,
     broadcast_tx: tokio::sync::broadcast::Sender<Arc<message::Message>>,
+    topic_routes: Arc<TopicRoutes>,
+    topic_channel_capacity: usize,
+    bounded_subscribers: Arc<BoundedSubscribers>,
+    lifecycle_tx: tokio::sync::broadcast::Sender<lifecycle::LifecycleEvent>,
+    pending_replies: Arc<PendingReplies>,
+    listen_keys: Arc<ListenKeyManager>,
+    recorder: Option<Arc<Recorder>>,
+    /// The background tasks of all websockets
+    tasks: TaskTracker,
+    /// Shuts the client down when the last handle is dropped, `None` for the
+    /// handles of the background tasks.
+    _drop_guard: Option<Arc<DropGuard>>,
 }
 
 impl MexcSpotWebsocketClient {
     pub fn new_with_endpoints(ws_endpoint: MexcWebsocketEndpoint, spot_api_endpoint: MexcSpotApiEndpoint) -> Self {
-        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(1024);
+        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(DEFAULT_BROADCAST_CAPACITY);
+        let (lifecycle_tx, _lifecycle_rx) = tokio::sync::broadcast::channel(256);
 
-        Self {
+        let client = Self {
             inner: Arc::new(
                 RwLock::new(
                     Inner {
-                        auth_to_listen_key_map: HashMap::new(),
                         websockets: Vec::new(),
                     },
                 ),
@@ -54,12 +86,57 @@ impl MexcSpotWebsocketClient {
             ws_endpoint: Arc::new(ws_endpoint),
             spot_api_endpoint: Arc::new(spot_api_endpoint),
             broadcast_tx,
+            topic_routes: Arc::new(TopicRoutes::default()),
+            topic_channel_capacity: DEFAULT_TOPIC_CHANNEL_CAPACITY,
+            bounded_subscribers: Arc::new(BoundedSubscribers::default()),
+            lifecycle_tx,
+            pending_replies: Arc::new(PendingReplies::default()),
+            listen_keys: Arc::new(ListenKeyManager::default()),
+            recorder: None,
+            tasks: TaskTracker::new(),
+            _drop_guard: None,
+        };
+        Self {
+            _drop_guard: Some(Arc::new(DropGuard::new(client.task_handle()))),
+            ..client
         }
     }
 
+    /// Amount of messages [`stream::Stream::stream`] subscribers can lag behind
+    /// before they start missing messages. Defaults to 1024.
+    pub fn with_broadcast_capacity(mut self, capacity: usize) -> Self {
+        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel(capacity);
+        self.broadcast_tx = broadcast_tx;
+        self
+    }
+
+    /// Amount of messages a [`topic_stream::TopicStream`] can lag behind before
+    /// it starts missing messages. Defaults to 1024.
+    pub fn with_topic_channel_capacity(mut self, capacity: usize) -> Self {
+        self.topic_channel_capacity = capacity;
+        self
+    }
+
+    /// Records every frame received by the websockets, see [`crate::recording`].
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
     pub fn into_arc(self) -> Arc<Self> {
         Arc::new(self)
     }
+
+    /// A handle for background tasks, which doesn't keep the client from being
+    /// shut down on drop.
+    pub(crate) fn task_handle(&self) -> Arc<Self> {
+        Arc::new(
+            Self {
+                _drop_guard: None,
+                ..self.clone()
+            },
+        )
+    }
 }
 
 impl Default for MexcSpotWebsocketClient {
@@ -71,14 +148,39 @@ impl Default for MexcSpotWebsocketClient {
     }
 }
 
+/// The `id` is echoed in the reply of the server, see [`ack`].
 #[derive(Debug, serde::Serialize)]
 #[serde(
     tag = "method",
-    content = "params",
     rename_all = "SCREAMING_SNAKE_CASE"
 )]
 pub enum SendableMessage {
-    Subscription(Vec<String>),
-    Unsubscription(Vec<String>),
+    Subscription { params: Vec<String>, id: i32 },
+    Unsubscription { params: Vec<String>, id: i32 },
     Ping,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_sendable_message() {
+        let message = SendableMessage::Subscription {
+            params: vec!["spot@public.deals.v3.api@BTCUSDT".to_string()],
+            id: 1,
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "method": "SUBSCRIPTION",
+                "params": ["spot@public.deals.v3.api@BTCUSDT"],
+                "id": 1
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&SendableMessage::Ping).unwrap(),
+            serde_json::json!({ "method": "PING" })
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    recording::{self, RecordedPayload, ReplayError, ReplayOutput, ReplayParams},
+    spot::ws::{
+        acquire_websocket,
+        message::{Message, RawMessage},
+        MexcSpotWebsocketClient,
+    },
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait Replay {
+    /// Feeds a recording through the message decoding into the streams of the
+    /// client, without connecting to MEXC. Streams have to be created before
+    /// the replay to receive all messages. Private topic streams don't receive
+    /// replayed messages, as a recording doesn't contain api keys.
+    async fn replay(self: Arc<Self>, params: ReplayParams) -> Result<ReplayOutput, ReplayError>;
+}
+
+#[async_trait]
+impl Replay for MexcSpotWebsocketClient {
+    async fn replay(self: Arc<Self>, params: ReplayParams) -> Result<ReplayOutput, ReplayError> {
+        recording::replay_frames(
+            params,
+            |frame| {
+                let this = self.clone();
+                async move {
+                    let Some((channel, message)) = decode_payload(&frame.payload) else {
+                        return false;
+                    };
+                    acquire_websocket::dispatch_message(
+                        &this, &channel, None, message,
+                    )
+                    .await;
+                    true
+                }
+            },
+        )
+        .await
+    }
+}
+
+/// Replies to (un)subscriptions are skipped
+fn decode_payload(
+    payload: &RecordedPayload,
+) -> Option<(
+    String,
+    Message,
+)> {
+    match payload {
+        RecordedPayload::Text(text) => {
+            let raw_message = serde_json::from_str::<RawMessage>(text).ok()?;
+            let message = Message::try_from(&raw_message).ok()?;
+            let RawMessage::ChannelMessage(raw_channel_message) = raw_message else {
+                return None;
+            };
+            Some(
+                (
+                    raw_channel_message.channel,
+                    message,
+                ),
+            )
+        }
+        RecordedPayload::Binary(proto) => Message::from_proto_with_channel(proto).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        recording::{RecordedFrame, Recorder, ReplaySpeed},
+        spot::ws::stream::Stream,
+    };
+    use chrono::Utc;
+    use futures::StreamExt;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn replays_recorded_frames_into_stream() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "mexc-rs-replay-{}.rec",
+                Uuid::new_v4()
+            ),
+        );
+        let recorder = Recorder::create(&path).unwrap();
+        let websocket_id = Uuid::new_v4();
+        for text in [
+            r#"{"id":0,"code":0,"msg":"spot@public.kline.v3.api@BTCUSDT@Min1"}"#,
+            r#"{"d":{"e":"spot@public.kline.v3.api","k":{"t":1695680400,"o":"26288.47","c":"26289.11","h":"26289.12","l":"26288.46","v":"1.579991","a":"41535.11","T":1695680460,"i":"Min1"}},"c":"spot@public.kline.v3.api@BTCUSDT@Min1","t":1695680458622,"s":"BTCUSDT"}"#,
+        ] {
+            recorder
+                .record_frame(
+                    &RecordedFrame {
+                        received_at: Utc::now(),
+                        websocket_id,
+                        payload: RecordedPayload::Text(text.to_string()),
+                    },
+                )
+                .unwrap();
+        }
+        drop(recorder);
+
+        let client = MexcSpotWebsocketClient::default().into_arc();
+        let mut stream = client
+            .clone()
+            .stream();
+        let output = client
+            .replay(ReplayParams::new(&path).with_speed(ReplaySpeed::AsFastAsPossible))
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            output.frames,
+            2
+        );
+        assert_eq!(
+            output.skipped_frames,
+            1
+        );
+        assert!(
+            matches!(
+                *stream
+                    .next()
+                    .await
+                    .unwrap(),
+                Message::Kline(_)
+            )
+        );
+    }
+}
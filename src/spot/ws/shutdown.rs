@@ -0,0 +1,178 @@
+use crate::spot::ws::{lifecycle::LifecycleEventKind, MexcSpotWebsocketClient, SendableMessage};
+use async_trait::async_trait;
+use std::{sync::Arc, time::Duration};
+
+/// How long a shutdown waits for the websockets to close.
+pub(crate) const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShutdownError {
+    #[error("Timed out waiting for the websocket tasks to finish")]
+    TimedOut,
+}
+
+#[async_trait]
+pub trait Shutdown {
+    /// Unsubscribes from all topics, closes all websockets, deletes the listen
+    /// keys and waits for the background tasks to finish. The client can't be
+    /// used anymore afterwards.
+    async fn shutdown(self: Arc<Self>) -> Result<(), ShutdownError>;
+}
+
+#[async_trait]
+impl Shutdown for MexcSpotWebsocketClient {
+    async fn shutdown(self: Arc<Self>) -> Result<(), ShutdownError> {
+        tokio::time::timeout(
+            SHUTDOWN_TIMEOUT,
+            shutdown(&self),
+        )
+        .await
+        .map_err(|_| ShutdownError::TimedOut)
+    }
+}
+
+async fn shutdown(this: &MexcSpotWebsocketClient) {
+    // Stops new websockets from being created and disconnected ones from
+    // reconnecting
+    this.tasks
+        .close();
+
+    let websocket_entries = std::mem::take(
+        &mut this
+            .inner
+            .write()
+            .await
+            .websockets,
+    );
+    for websocket_entry in websocket_entries {
+        let topics = websocket_entry
+            .topics
+            .read()
+            .await
+            .clone();
+        if !topics.is_empty() {
+            let sendable_message = SendableMessage::Unsubscription {
+                params: topics
+                    .iter()
+                    .map(|topic| topic.to_topic_subscription_string())
+                    .collect(),
+                id: this
+                    .pending_replies
+                    .next_id(),
+            };
+            // The websocket may already be gone
+            let _ = websocket_entry
+                .message_tx
+                .read()
+                .await
+                .send(sendable_message)
+                .await;
+        }
+        // The sender task flushes the unsubscription before sending the close
+        // frame
+        websocket_entry
+            .cancellation_token
+            .read()
+            .await
+            .cancel();
+        this.emit_lifecycle_event(
+            websocket_entry.id,
+            topics,
+            LifecycleEventKind::Disconnected,
+        );
+        if let Some(auth) = &websocket_entry.auth {
+            this.delete_listen_key(
+                websocket_entry.id,
+                auth,
+            )
+            .await;
+        }
+    }
+
+    // Ends the topic and bounded streams
+    this.topic_routes
+        .close();
+    this.bounded_subscribers
+        .close();
+
+    this.tasks
+        .wait()
+        .await;
+    tracing::debug!("Spot websocket client shut down");
+}
+
+/// Shuts the client down in the background once the last handle of the user is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct DropGuard {
+    client: Arc<MexcSpotWebsocketClient>,
+}
+
+impl DropGuard {
+    pub(crate) fn new(client: Arc<MexcSpotWebsocketClient>) -> Self {
+        Self {
+            client,
+        }
+    }
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        if self
+            .client
+            .tasks
+            .is_closed()
+        {
+            return;
+        }
+        self.client
+            .tasks
+            .close();
+        // Without a runtime the tasks are gone already
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let client = self
+            .client
+            .clone();
+        handle.spawn(
+            async move {
+                if let Err(err) = client
+                    .shutdown()
+                    .await
+                {
+                    tracing::warn!(
+                        "Failed to shut down dropped spot websocket client: {}",
+                        err
+                    );
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dropping_last_handle_shuts_down() {
+        let client = MexcSpotWebsocketClient::default().into_arc();
+        let task_handle = client.task_handle();
+        let other_handle = client.clone();
+
+        drop(client);
+        assert!(
+            !task_handle
+                .tasks
+                .is_closed()
+        );
+
+        drop(other_handle);
+        assert!(
+            task_handle
+                .tasks
+                .is_closed()
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::spot::{
     v3::ApiError,
     ws::{
+        ack::{RejectedTopic, TopicRequestError},
         acquire_websocket::{AcquireWebsocketForTopicsError, AcquireWebsocketsForTopics, AcquireWebsocketsForTopicsParams},
         auth::WebsocketAuth,
         topic::Topic,
@@ -15,28 +16,26 @@ use std::sync::Arc;
 pub struct SubscribeParams {
     pub auth: Option<WebsocketAuth>,
     pub topics: Vec<Topic>,
-    // pub wait_for_confirmation: bool,
+    /// Wait for the server to accept or reject the topics before returning
+    pub wait_for_confirmation: bool,
 }
 
 impl Default for SubscribeParams {
     fn default() -> Self {
         Self::new(
             None,
-            Vec::new(), /* , true */
+            Vec::new(),
+            true,
         )
     }
 }
 
 impl SubscribeParams {
-    pub fn new(
-        auth: Option<WebsocketAuth>,
-        topics: Vec<Topic>,
-        // wait_for_confirmation: bool,
-    ) -> Self {
+    pub fn new(auth: Option<WebsocketAuth>, topics: Vec<Topic>, wait_for_confirmation: bool) -> Self {
         Self {
             auth,
             topics,
-            // wait_for_confirmation,
+            wait_for_confirmation,
         }
     }
 
@@ -57,14 +56,20 @@ impl SubscribeParams {
         self
     }
 
-    // pub fn with_wait_for_confirmation(mut self, wait_for_confirmation: bool) ->
-    // Self {     self.wait_for_confirmation = wait_for_confirmation;
-    //     self
-    // }
+    pub fn with_wait_for_confirmation(mut self, wait_for_confirmation: bool) -> Self {
+        self.wait_for_confirmation = wait_for_confirmation;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct SubscribeOutput {}
+pub struct SubscribeOutput {
+    /// Without waiting for confirmation all topics are considered accepted
+    pub accepted_topics: Vec<Topic>,
+    /// Topics without a reply stay subscribed and are resubscribed on
+    /// reconnect, unsubscribe them to give up on them
+    pub rejected_topics: Vec<RejectedTopic>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum SubscribeError {
@@ -88,6 +93,12 @@ pub enum SubscribeError {
 
     #[error("Failed to send message through channel: {0}")]
     SendError(#[from] SendError<SendableMessage>),
+
+    #[error("Topics were rejected: {0:?}")]
+    TopicsRejected(Vec<RejectedTopic>),
+
+    #[error("The client has been shut down")]
+    ClientShutDown,
 }
 
 #[async_trait]
@@ -98,6 +109,7 @@ pub trait Subscribe {
 #[async_trait]
 impl Subscribe for MexcSpotWebsocketClient {
     async fn subscribe(self: Arc<Self>, params: SubscribeParams) -> Result<SubscribeOutput, SubscribeError> {
+        let wait_for_confirmation = params.wait_for_confirmation;
         let mut acquire_websocket_params = AcquireWebsocketsForTopicsParams::default().for_topics(params.topics);
         if let Some(auth) = params.auth {
             acquire_websocket_params = acquire_websocket_params.with_auth(auth);
@@ -121,9 +133,14 @@ impl Subscribe for MexcSpotWebsocketClient {
                 AcquireWebsocketForTopicsError::CouldNotCreateDataStream(err) => {
                     return Err(SubscribeError::CouldNotCreateDataStream(err));
                 }
+                AcquireWebsocketForTopicsError::ClientShutDown => {
+                    return Err(SubscribeError::ClientShutDown);
+                }
             },
         };
 
+        let mut accepted_topics = vec![];
+        let mut waiting_for_replies = vec![];
         for acquired_ws in acquire_output
             .websockets
             .into_iter()
@@ -133,15 +150,34 @@ impl Subscribe for MexcSpotWebsocketClient {
                 .iter()
                 .map(|topic| topic.to_topic_subscription_string())
                 .collect::<Vec<String>>();
-            let sendable_message = SendableMessage::Subscription(params);
+            let id = self
+                .pending_replies
+                .next_id();
+            let reply_rx = match wait_for_confirmation {
+                true => Some(
+                    self.pending_replies
+                        .register(id),
+                ),
+                false => None,
+            };
+            let sendable_message = SendableMessage::Subscription {
+                params,
+                id,
+            };
 
             let tx = acquired_ws
                 .websocket_entry
                 .message_tx
                 .read()
                 .await;
-            tx.send(sendable_message)
-                .await?;
+            if let Err(err) = tx
+                .send(sendable_message)
+                .await
+            {
+                self.pending_replies
+                    .forget(id);
+                return Err(err.into());
+            }
 
             let mut topics = acquired_ws
                 .websocket_entry
@@ -150,12 +186,86 @@ impl Subscribe for MexcSpotWebsocketClient {
                 .await;
             let topics_websocket_entry_does_not_have = acquired_ws
                 .for_topics
-                .into_iter()
+                .iter()
                 .filter(|topic| !topics.contains(topic))
+                .cloned()
                 .collect::<Vec<Topic>>();
             topics.extend(topics_websocket_entry_does_not_have);
+            drop(topics);
+            drop(tx);
+
+            match reply_rx {
+                Some(reply_rx) => waiting_for_replies.push(
+                    (
+                        id,
+                        reply_rx,
+                        acquired_ws,
+                    ),
+                ),
+                None => accepted_topics.extend(acquired_ws.for_topics),
+            }
+        }
+
+        let replies = futures::future::join_all(
+            waiting_for_replies
+                .into_iter()
+                .map(
+                    |(id, reply_rx, acquired_ws)| {
+                        let this = self.clone();
+                        async move {
+                            let (accepted, rejected) = this
+                                .pending_replies
+                                .wait_for_topics(
+                                    id,
+                                    reply_rx,
+                                    acquired_ws
+                                        .for_topics
+                                        .clone(),
+                                )
+                                .await;
+                            // Rejected topics must not be resubscribed on reconnect.
+                            // Without a reply the subscription may be live, so
+                            // the topic is kept.
+                            acquired_ws
+                                .websocket_entry
+                                .topics
+                                .write()
+                                .await
+                                .retain(
+                                    |topic| {
+                                        !rejected
+                                            .iter()
+                                            .any(
+                                                |rejected_topic| {
+                                                    &rejected_topic.topic == topic
+                                                        && matches!(
+                                                            rejected_topic.error,
+                                                            TopicRequestError::Rejected { .. }
+                                                        )
+                                                },
+                                            )
+                                    },
+                                );
+                            (
+                                accepted, rejected,
+                            )
+                        }
+                    },
+                ),
+        )
+        .await;
+
+        let mut rejected_topics = vec![];
+        for (accepted, rejected) in replies {
+            accepted_topics.extend(accepted);
+            rejected_topics.extend(rejected);
         }
 
-        Ok(SubscribeOutput {})
+        Ok(
+            SubscribeOutput {
+                accepted_topics,
+                rejected_topics,
+            },
+        )
     }
 }
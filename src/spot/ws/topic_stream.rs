@@ -0,0 +1,590 @@
+use crate::{
+    proto::PublicAggreDepthsV3Api,
+    spot::ws::{
+        auth::WebsocketAuth,
+        message::{
+            account_deals::AccountDealsMessage,
+            account_orders::AccountOrdersMessage,
+            account_update::AccountUpdateMessage,
+            deals::SpotDealsMessage,
+            kline::{KlineIntervalTopic, SpotKlineMessage},
+            Message,
+        },
+        stream::StreamEvent,
+        subscribe::{Subscribe, SubscribeError, SubscribeParams},
+        topic::{DealsTopic, DepthTopic, DepthTopicFrequency, KlineTopic, Topic},
+        unsubscribe::{Unsubscribe, UnsubscribeParams},
+        MexcSpotWebsocketClient,
+    },
+};
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+pub(crate) const DEFAULT_TOPIC_CHANNEL_CAPACITY: usize = 1024;
+
+/// Routes incoming messages to the typed streams of the topic they were pushed
+/// on.
+#[derive(Debug, Default)]
+pub(crate) struct TopicRoutes {
+    routes: std::sync::RwLock<HashMap<TopicRouteKey, TopicRoute>>,
+    /// Serializes acquiring and releasing of topic streams, so a release can
+    /// never unsubscribe a topic that was just acquired again.
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct TopicRouteKey {
+    channel: String,
+    /// Private channels are named the same for every user, so they are routed
+    /// per api key.
+    api_key: Option<String>,
+}
+
+impl TopicRouteKey {
+    fn new(channel: &str, api_key: Option<&str>) -> Self {
+        let api_key = match channel.starts_with("spot@private") {
+            true => api_key.map(|api_key| api_key.to_string()),
+            false => None,
+        };
+        Self {
+            channel: channel.to_string(),
+            api_key,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TopicRoute {
+    tx: broadcast::Sender<Arc<Message>>,
+    handle: Weak<TopicHandle>,
+}
+
+impl TopicRoutes {
+    /// Drops all routes, which ends their streams
+    pub(crate) fn close(&self) {
+        self.routes
+            .write()
+            .expect("Topic routes lock poisoned")
+            .clear();
+    }
+
+    pub(crate) fn route(&self, channel: &str, api_key: Option<&str>, message: &Arc<Message>) {
+        let routes = self
+            .routes
+            .read()
+            .expect("Topic routes lock poisoned");
+        let Some(route) = routes.get(
+            &TopicRouteKey::new(
+                channel, api_key,
+            ),
+        ) else {
+            return;
+        };
+        // No receivers means the last stream is being dropped right now
+        let _ = route
+            .tx
+            .send(message.clone());
+    }
+}
+
+/// Keeps the topic subscribed for as long as a [`TopicStream`] of it is alive.
+#[derive(Debug)]
+struct TopicHandle {
+    client: Arc<MexcSpotWebsocketClient>,
+    auth: Option<WebsocketAuth>,
+    topic: Topic,
+    key: TopicRouteKey,
+}
+
+impl Drop for TopicHandle {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::debug!(
+                "No runtime available to unsubscribe from topic {:?}",
+                self.topic
+            );
+            return;
+        };
+        let client = self
+            .client
+            .clone();
+        let auth = self
+            .auth
+            .take();
+        let topic = self
+            .topic
+            .clone();
+        let key = self
+            .key
+            .clone();
+        runtime.spawn(
+            async move {
+                release_topic(
+                    client, auth, topic, key,
+                )
+                .await;
+            },
+        );
+    }
+}
+
+async fn release_topic(client: Arc<MexcSpotWebsocketClient>, auth: Option<WebsocketAuth>, topic: Topic, key: TopicRouteKey) {
+    let _guard = client
+        .topic_routes
+        .lock
+        .lock()
+        .await;
+
+    {
+        let mut routes = client
+            .topic_routes
+            .routes
+            .write()
+            .expect("Topic routes lock poisoned");
+        let still_in_use = routes
+            .get(&key)
+            .is_some_and(
+                |route| {
+                    route
+                        .handle
+                        .strong_count()
+                        > 0
+                },
+            );
+        if still_in_use {
+            return;
+        }
+        routes.remove(&key);
+    }
+
+    let params = UnsubscribeParams::new(
+        auth,
+        vec![topic.clone()],
+    );
+    if let Err(err) = client
+        .clone()
+        .unsubscribe(params)
+        .await
+    {
+        tracing::error!(
+            "Failed to unsubscribe from topic {:?}: {}",
+            topic,
+            err
+        );
+    }
+}
+
+/// A stream of the messages of a single topic, with a [`StreamEvent::Lagged`]
+/// event wherever messages were missed.
+///
+/// The topic is unsubscribed once the last stream for it is dropped.
+pub struct TopicStream<T> {
+    stream: BoxStream<'static, StreamEvent<T>>,
+    _handle: Arc<TopicHandle>,
+}
+
+impl<T> futures::Stream for TopicStream<T> {
+    type Item = StreamEvent<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .stream
+            .poll_next_unpin(cx)
+    }
+}
+
+impl<T> std::fmt::Debug for TopicStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicStream")
+            .field(
+                "topic",
+                &self
+                    ._handle
+                    .topic,
+            )
+            .finish()
+    }
+}
+
+#[async_trait]
+pub trait TopicStreams {
+    async fn subscribe_deals(self: Arc<Self>, symbol: String) -> Result<TopicStream<SpotDealsMessage>, SubscribeError>;
+
+    async fn subscribe_kline(self: Arc<Self>, symbol: String, interval: KlineIntervalTopic) -> Result<TopicStream<SpotKlineMessage>, SubscribeError>;
+
+    async fn subscribe_depth(self: Arc<Self>, symbol: String, frequency: DepthTopicFrequency) -> Result<TopicStream<PublicAggreDepthsV3Api>, SubscribeError>;
+
+    async fn subscribe_account_deals(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountDealsMessage>, SubscribeError>;
+
+    async fn subscribe_account_orders(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountOrdersMessage>, SubscribeError>;
+
+    async fn subscribe_account_update(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountUpdateMessage>, SubscribeError>;
+}
+
+#[async_trait]
+impl TopicStreams for MexcSpotWebsocketClient {
+    async fn subscribe_deals(self: Arc<Self>, symbol: String) -> Result<TopicStream<SpotDealsMessage>, SubscribeError> {
+        let topic = Topic::Deals(DealsTopic::new(symbol));
+        subscribe_topic(
+            self,
+            None,
+            topic,
+            |message| match message {
+                Message::Deals(deals) => Some(deals.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_kline(self: Arc<Self>, symbol: String, interval: KlineIntervalTopic) -> Result<TopicStream<SpotKlineMessage>, SubscribeError> {
+        let topic = Topic::Kline(
+            KlineTopic::new(
+                symbol, interval,
+            ),
+        );
+        subscribe_topic(
+            self,
+            None,
+            topic,
+            |message| match message {
+                Message::Kline(kline) => Some(kline.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_depth(self: Arc<Self>, symbol: String, frequency: DepthTopicFrequency) -> Result<TopicStream<PublicAggreDepthsV3Api>, SubscribeError> {
+        let topic = Topic::Depth(
+            DepthTopic {
+                symbol,
+                frequency,
+            },
+        );
+        subscribe_topic(
+            self,
+            None,
+            topic,
+            |message| match message {
+                Message::PublicAggreDepthsV3Api(depth) => Some(depth.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_account_deals(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountDealsMessage>, SubscribeError> {
+        subscribe_topic(
+            self,
+            Some(auth),
+            Topic::AccountDeals,
+            |message| match message {
+                Message::AccountDeals(account_deals) => Some(account_deals.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_account_orders(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountOrdersMessage>, SubscribeError> {
+        subscribe_topic(
+            self,
+            Some(auth),
+            Topic::AccountOrders,
+            |message| match message {
+                Message::AccountOrders(account_orders) => Some(account_orders.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn subscribe_account_update(self: Arc<Self>, auth: WebsocketAuth) -> Result<TopicStream<AccountUpdateMessage>, SubscribeError> {
+        subscribe_topic(
+            self,
+            Some(auth),
+            Topic::AccountUpdate,
+            |message| match message {
+                Message::AccountUpdate(account_update) => Some(account_update.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+}
+
+async fn subscribe_topic<T, F>(client: Arc<MexcSpotWebsocketClient>, auth: Option<WebsocketAuth>, topic: Topic, extract: F) -> Result<TopicStream<T>, SubscribeError>
+where
+    T: Send + 'static,
+    F: Fn(&Message) -> Option<T> + Send + 'static,
+{
+    let _guard = client
+        .topic_routes
+        .lock
+        .lock()
+        .await;
+
+    let key = TopicRouteKey::new(
+        &topic.to_topic_subscription_string(),
+        auth.as_ref()
+            .map(
+                |auth| {
+                    auth.api_key
+                        .as_str()
+                },
+            ),
+    );
+
+    let existing = {
+        let routes = client
+            .topic_routes
+            .routes
+            .read()
+            .expect("Topic routes lock poisoned");
+        routes
+            .get(&key)
+            .and_then(
+                |route| {
+                    route
+                        .handle
+                        .upgrade()
+                        .map(
+                            |handle| {
+                                (
+                                    route
+                                        .tx
+                                        .subscribe(),
+                                    handle,
+                                )
+                            },
+                        )
+                },
+            )
+    };
+
+    let (rx, handle) = match existing {
+        Some(x) => x,
+        None => {
+            // Register the route before subscribing so no messages are missed
+            let (tx, rx) = broadcast::channel(client.topic_channel_capacity);
+            client
+                .topic_routes
+                .routes
+                .write()
+                .expect("Topic routes lock poisoned")
+                .insert(
+                    key.clone(),
+                    TopicRoute {
+                        tx,
+                        handle: Weak::new(),
+                    },
+                );
+
+            let params = SubscribeParams::new(
+                auth.clone(),
+                vec![topic.clone()],
+                true,
+            );
+            let result = match client
+                .clone()
+                .subscribe(params)
+                .await
+            {
+                Ok(output)
+                    if !output
+                        .rejected_topics
+                        .is_empty() =>
+                {
+                    Err(SubscribeError::TopicsRejected(output.rejected_topics))
+                }
+                Ok(_) => Ok(()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                client
+                    .topic_routes
+                    .routes
+                    .write()
+                    .expect("Topic routes lock poisoned")
+                    .remove(&key);
+                return Err(err);
+            }
+
+            let handle = Arc::new(
+                TopicHandle {
+                    client: client.clone(),
+                    auth,
+                    topic,
+                    key: key.clone(),
+                },
+            );
+            if let Some(route) = client
+                .topic_routes
+                .routes
+                .write()
+                .expect("Topic routes lock poisoned")
+                .get_mut(&key)
+            {
+                route.handle = Arc::downgrade(&handle);
+            }
+            (
+                rx, handle,
+            )
+        }
+    };
+
+    Ok(
+        TopicStream {
+            stream: typed_stream(
+                rx, extract,
+            ),
+            _handle: handle,
+        },
+    )
+}
+
+fn typed_stream<T, F>(mut rx: broadcast::Receiver<Arc<Message>>, extract: F) -> BoxStream<'static, StreamEvent<T>>
+where
+    T: Send + 'static,
+    F: Fn(&Message) -> Option<T> + Send + 'static,
+{
+    let stream = async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(message) => {
+                    if let Some(item) = extract(&message) {
+                        yield StreamEvent::Message(item);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => yield StreamEvent::Lagged { skipped },
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+    stream.boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_channels_are_routed_per_api_key() {
+        let key = TopicRouteKey::new(
+            "spot@private.deals.v3.api",
+            Some("api_key"),
+        );
+        assert_eq!(
+            key.api_key
+                .as_deref(),
+            Some("api_key")
+        );
+
+        let key = TopicRouteKey::new(
+            "spot@public.deals.v3.api@BTCUSDT",
+            Some("api_key"),
+        );
+        assert_eq!(
+            key.api_key,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_only_matching_channel() {
+        let client = MexcSpotWebsocketClient::default().into_arc();
+        let (tx, mut rx) = broadcast::channel(DEFAULT_TOPIC_CHANNEL_CAPACITY);
+        client
+            .topic_routes
+            .routes
+            .write()
+            .unwrap()
+            .insert(
+                TopicRouteKey::new(
+                    "spot@public.deals.v3.api@BTCUSDT",
+                    None,
+                ),
+                TopicRoute {
+                    tx,
+                    handle: Weak::new(),
+                },
+            );
+
+        let message = Arc::new(Message::PublicAggreDepthsV3Api(PublicAggreDepthsV3Api::default()));
+        client
+            .topic_routes
+            .route(
+                "spot@public.deals.v3.api@ETHUSDT",
+                None,
+                &message,
+            );
+        assert!(
+            rx.try_recv()
+                .is_err()
+        );
+
+        client
+            .topic_routes
+            .route(
+                "spot@public.deals.v3.api@BTCUSDT",
+                None,
+                &message,
+            );
+        assert!(
+            rx.try_recv()
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn typed_streams_report_lag() {
+        let (tx, rx) = broadcast::channel(1);
+        let mut stream = typed_stream(
+            rx,
+            |message| match message {
+                Message::PublicAggreDepthsV3Api(depth) => Some(depth.clone()),
+                _ => None,
+            },
+        );
+        for _ in 0..3 {
+            tx.send(Arc::new(Message::PublicAggreDepthsV3Api(PublicAggreDepthsV3Api::default())))
+                .unwrap();
+        }
+        drop(tx);
+
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(
+                    StreamEvent::Lagged {
+                        skipped: 2
+                    }
+                )
+            )
+        );
+        assert!(
+            matches!(
+                stream
+                    .next()
+                    .await,
+                Some(StreamEvent::Message(_))
+            )
+        );
+        assert!(
+            stream
+                .next()
+                .await
+                .is_none()
+        );
+    }
+}
@@ -1 +1,232 @@
+use crate::spot::ws::{ack::RejectedTopic, auth::WebsocketAuth, lifecycle::LifecycleEventKind, topic::Topic, MexcSpotWebsocketClient, SendableMessage};
+use async_channel::SendError;
+use async_trait::async_trait;
+use std::sync::Arc;
 
+#[derive(Debug)]
+pub struct UnsubscribeParams {
+    /// Required to unsubscribe from private topics, as those are kept per user
+    pub auth: Option<WebsocketAuth>,
+    pub topics: Vec<Topic>,
+    /// Wait for the server to confirm the unsubscription
+    pub wait_for_confirmation: bool,
+}
+
+impl Default for UnsubscribeParams {
+    fn default() -> Self {
+        Self::new(
+            None,
+            Vec::new(),
+        )
+    }
+}
+
+impl UnsubscribeParams {
+    pub fn new(auth: Option<WebsocketAuth>, topics: Vec<Topic>) -> Self {
+        Self {
+            auth,
+            topics,
+            wait_for_confirmation: true,
+        }
+    }
+
+    pub fn with_wait_for_confirmation(mut self, wait_for_confirmation: bool) -> Self {
+        self.wait_for_confirmation = wait_for_confirmation;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: WebsocketAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn with_topic(mut self, topic: Topic) -> Self {
+        self.topics
+            .push(topic);
+        self
+    }
+
+    pub fn with_topics(mut self, topics: Vec<Topic>) -> Self {
+        self.topics
+            .extend(topics);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnsubscribeOutput {
+    pub accepted_topics: Vec<Topic>,
+    pub rejected_topics: Vec<RejectedTopic>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("Failed to send message through channel: {0}")]
+    SendError(#[from] SendError<SendableMessage>),
+}
+
+#[async_trait]
+pub trait Unsubscribe {
+    async fn unsubscribe(self: Arc<Self>, params: UnsubscribeParams) -> Result<UnsubscribeOutput, UnsubscribeError>;
+}
+
+#[async_trait]
+impl Unsubscribe for MexcSpotWebsocketClient {
+    async fn unsubscribe(self: Arc<Self>, params: UnsubscribeParams) -> Result<UnsubscribeOutput, UnsubscribeError> {
+        let mut inner = self
+            .inner
+            .write()
+            .await;
+
+        let mut accepted_topics = vec![];
+        let mut waiting_for_replies = vec![];
+        let mut websocket_ids_to_close = vec![];
+        for websocket_entry in inner
+            .websockets
+            .iter()
+        {
+            let mut topics = websocket_entry
+                .topics
+                .write()
+                .await;
+            let topics_to_unsubscribe = params
+                .topics
+                .iter()
+                .filter(|topic| topics.contains(topic))
+                .filter(
+                    |topic| {
+                        !topic.requires_auth()
+                            || websocket_entry
+                                .auth
+                                .as_ref()
+                                == params
+                                    .auth
+                                    .as_ref()
+                    },
+                )
+                .cloned()
+                .collect::<Vec<Topic>>();
+            if topics_to_unsubscribe.is_empty() {
+                continue;
+            }
+
+            let topic_strs = topics_to_unsubscribe
+                .iter()
+                .map(|topic| topic.to_topic_subscription_string())
+                .collect::<Vec<String>>();
+            let id = self
+                .pending_replies
+                .next_id();
+            let reply_rx = match params.wait_for_confirmation {
+                true => Some(
+                    self.pending_replies
+                        .register(id),
+                ),
+                false => None,
+            };
+            let tx = websocket_entry
+                .message_tx
+                .read()
+                .await;
+            if let Err(err) = tx
+                .send(
+                    SendableMessage::Unsubscription {
+                        params: topic_strs,
+                        id,
+                    },
+                )
+                .await
+            {
+                self.pending_replies
+                    .forget(id);
+                return Err(err.into());
+            }
+            topics.retain(|topic| !topics_to_unsubscribe.contains(topic));
+
+            if topics.is_empty() {
+                websocket_ids_to_close.push(
+                    (
+                        websocket_entry.id,
+                        topics_to_unsubscribe.clone(),
+                    ),
+                );
+            }
+            match reply_rx {
+                Some(reply_rx) => waiting_for_replies.push(
+                    (
+                        id,
+                        reply_rx,
+                        topics_to_unsubscribe,
+                    ),
+                ),
+                None => accepted_topics.extend(topics_to_unsubscribe),
+            }
+        }
+
+        let mut websocket_entries_to_close = vec![];
+        for (websocket_id, unsubscribed_topics) in websocket_ids_to_close {
+            let Some(index) = inner
+                .websockets
+                .iter()
+                .position(|websocket_entry| websocket_entry.id == websocket_id)
+            else {
+                continue;
+            };
+            websocket_entries_to_close.push(
+                (
+                    inner
+                        .websockets
+                        .remove(index),
+                    unsubscribed_topics,
+                ),
+            );
+        }
+        // The receiver tasks need the lock to handle the replies
+        drop(inner);
+
+        let mut rejected_topics = vec![];
+        for (id, reply_rx, topics) in waiting_for_replies {
+            let (accepted, rejected) = self
+                .pending_replies
+                .wait_for_topics(
+                    id, reply_rx, topics,
+                )
+                .await;
+            accepted_topics.extend(accepted);
+            rejected_topics.extend(rejected);
+        }
+
+        // Websockets without any topics left are of no use, so close them. The
+        // sender task flushes the unsubscription before sending the close frame.
+        for (websocket_entry, unsubscribed_topics) in websocket_entries_to_close {
+            websocket_entry
+                .cancellation_token
+                .read()
+                .await
+                .cancel();
+            self.emit_lifecycle_event(
+                websocket_entry.id,
+                unsubscribed_topics,
+                LifecycleEventKind::Disconnected,
+            );
+            if let Some(auth) = &websocket_entry.auth {
+                self.delete_listen_key(
+                    websocket_entry.id,
+                    auth,
+                )
+                .await;
+            }
+            tracing::debug!(
+                "Closed spot websocket with id {} as it has no topics left",
+                websocket_entry.id
+            );
+        }
+
+        Ok(
+            UnsubscribeOutput {
+                accepted_topics,
+                rejected_topics,
+            },
+        )
+    }
+}